@@ -0,0 +1,112 @@
+//! Aggregation of replica runs into a band around their mean, shared by the
+//! ensemble modes of both simulations.
+
+/// One replica's infected fraction, each value at the simulated time in the
+/// same position of `times`.
+#[derive(Clone, Debug, Default)]
+pub struct Curve {
+    pub times: Vec<f32>,
+    pub infected: Vec<f32>,
+}
+
+impl Curve {
+    pub fn push(&mut self, time: f32, infected: f32) {
+        self.times.push(time);
+        self.infected.push(infected);
+    }
+
+    /// The value at the sample nearest `time`; `None` more than half a
+    /// sample past the end.
+    pub fn at(&self, time: f32) -> Option<f32> {
+        nearest(&self.times, time).map(|k| self.infected[k])
+    }
+}
+
+/// Cross-replica statistics of the infected fraction at one time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Band {
+    pub mean: f32,
+    pub q25: f32,
+    pub q75: f32,
+}
+
+/// Mean and inter-quartile range of `curves` at simulated `time`, each read
+/// at its sample nearest that time. Curves that haven't reached `time` are
+/// left out; `None` if none has.
+pub fn band_at<'a>(curves: impl IntoIterator<Item = &'a Curve>, time: f32) -> Option<Band> {
+    let mut values: Vec<f32> = curves
+        .into_iter()
+        .filter_map(|curve| curve.at(time))
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    Some(Band {
+        mean,
+        q25: quantile(&values, 0.25),
+        q75: quantile(&values, 0.75),
+    })
+}
+
+/// Index of the entry of ascending `times` nearest `time`, or `None` more
+/// than half a step past the last.
+fn nearest(times: &[f32], time: f32) -> Option<usize> {
+    let n = times.len();
+    let i = times.partition_point(|&t| t < time);
+    if i == n {
+        let half_step = if n >= 2 {
+            (times[n - 1] - times[n - 2]) / 2.0
+        } else {
+            0.0
+        };
+        return (n > 0 && time - times[n - 1] <= half_step).then(|| n - 1);
+    }
+    if i > 0 && time - times[i - 1] < times[i] - time {
+        Some(i - 1)
+    } else {
+        Some(i)
+    }
+}
+
+/// Linear-interpolated quantile of an ascending, non-empty slice.
+pub fn quantile(sorted: &[f32], q: f32) -> f32 {
+    let pos = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f32;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    let t = pos - lo as f32;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_sample_by_time() {
+        let times = [0.1, 0.2, 0.3];
+        assert_eq!(nearest(&times, 0.0), Some(0));
+        assert_eq!(nearest(&times, 0.17), Some(1));
+        assert_eq!(nearest(&times, 0.34), Some(2));
+        assert_eq!(nearest(&times, 0.36), None);
+        assert_eq!(nearest(&[], 0.0), None);
+    }
+
+    #[test]
+    fn band_skips_curves_that_have_not_reached_the_time() {
+        let mut short = Curve::default();
+        let mut long = Curve::default();
+        for k in 1..=4 {
+            long.push(k as f32, 0.5);
+            if k <= 2 {
+                short.push(k as f32, 0.1);
+            }
+        }
+        let both = band_at([&short, &long], 1.0).unwrap();
+        assert!((both.mean - 0.3).abs() < 1e-6);
+        let band = band_at([&short, &long], 4.0).unwrap();
+        assert_eq!((band.mean, band.q25, band.q75), (0.5, 0.5, 0.5));
+        assert_eq!(band_at([&short, &long], 5.0), None);
+    }
+}
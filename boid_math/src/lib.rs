@@ -0,0 +1,181 @@
+//! Vector math shared by the boid simulations: a plain 2D vector for the
+//! renderer-independent `rust_src` model, and free functions that also work
+//! on glam's `Vec2` (with the `glam` feature) for the macroquad crates;
+//! the `serde` feature makes the plain vector serializable.
+//! `timeseries` holds the CSV time-series exporter both simulations use,
+//! `rng` the seeded random number generator that keeps their runs
+//! reproducible, `params` the parameter rules they share, `ensemble` the
+//! band their ensemble modes draw around replica runs, and `spatial` the
+//! spatial hash both use for neighbour queries.
+
+pub mod ensemble;
+pub mod params;
+pub mod rng;
+pub mod spatial;
+pub mod timeseries;
+
+/// A 2D vector with the operations the simulations need and nothing else.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[allow(clippy::should_implement_trait)]
+impl Vec2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+
+    pub fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+
+    pub fn mul(self, s: f32) -> Vec2 {
+        Vec2::new(self.x * s, self.y * s)
+    }
+
+    pub fn div(self, s: f32) -> Vec2 {
+        Vec2::new(self.x / s, self.y / s)
+    }
+
+    pub fn length(self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    pub fn normalize_or_zero(self) -> Vec2 {
+        normalize_or_zero(self)
+    }
+
+    pub fn limit(self, max: f32) -> Vec2 {
+        limit(self, max)
+    }
+}
+
+/// Anything with an x and a y the free functions can work on.
+pub trait Planar: Copy {
+    fn xy(self) -> (f32, f32);
+    fn from_xy(x: f32, y: f32) -> Self;
+}
+
+impl Planar for Vec2 {
+    fn xy(self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    fn from_xy(x: f32, y: f32) -> Self {
+        Vec2::new(x, y)
+    }
+}
+
+#[cfg(feature = "glam")]
+impl Planar for glam::Vec2 {
+    fn xy(self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    fn from_xy(x: f32, y: f32) -> Self {
+        glam::Vec2::new(x, y)
+    }
+}
+
+fn length(x: f32, y: f32) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+/// `v` scaled down to length `max` if it is longer.
+pub fn limit<V: Planar>(v: V, max: f32) -> V {
+    let (x, y) = v.xy();
+    let len = length(x, y);
+    if len > max {
+        let s = max / len;
+        V::from_xy(x * s, y * s)
+    } else {
+        v
+    }
+}
+
+/// `v` at unit length, or zero if `v` is zero.
+pub fn normalize_or_zero<V: Planar>(v: V) -> V {
+    let (x, y) = v.xy();
+    let len = length(x, y);
+    if len > 0.0 {
+        V::from_xy(x / len, y / len)
+    } else {
+        V::from_xy(0.0, 0.0)
+    }
+}
+
+/// Shortest signed distance `d` on a circle of circumference `extent`.
+pub fn wrap_delta(d: f32, extent: f32) -> f32 {
+    if d > extent / 2.0 {
+        d - extent
+    } else if d < -extent / 2.0 {
+        d + extent
+    } else {
+        d
+    }
+}
+
+/// Displacement from `from` to `to` on a torus of size `world`, taking the
+/// short way round on each axis.
+pub fn torus_delta<V: Planar>(from: V, to: V, world: V) -> V {
+    let ((fx, fy), (tx, ty), (wx, wy)) = (from.xy(), to.xy(), world.xy());
+    V::from_xy(wrap_delta(tx - fx, wx), wrap_delta(ty - fy, wy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        (a.x - b.x).abs() < 1e-5 && (a.y - b.y).abs() < 1e-5
+    }
+
+    #[test]
+    fn limit_shortens_only_long_vectors() {
+        assert!(close(Vec2::new(3.0, 4.0).limit(10.0), Vec2::new(3.0, 4.0)));
+        assert!(close(Vec2::new(3.0, 4.0).limit(1.0), Vec2::new(0.6, 0.8)));
+        assert_eq!(Vec2::default().limit(0.0), Vec2::default());
+    }
+
+    #[test]
+    fn normalize_or_zero_handles_the_zero_vector() {
+        assert!(close(
+            Vec2::new(0.0, -2.0).normalize_or_zero(),
+            Vec2::new(0.0, -1.0)
+        ));
+        assert_eq!(Vec2::default().normalize_or_zero(), Vec2::default());
+    }
+
+    #[test]
+    fn torus_delta_takes_the_short_way_round() {
+        let world = Vec2::new(100.0, 50.0);
+        let d = torus_delta(Vec2::new(95.0, 2.0), Vec2::new(3.0, 48.0), world);
+        assert!(close(d, Vec2::new(8.0, -4.0)));
+        // Without crossing a seam it is the plain difference.
+        let d = torus_delta(Vec2::new(10.0, 10.0), Vec2::new(30.0, 5.0), world);
+        assert!(close(d, Vec2::new(20.0, -5.0)));
+    }
+
+    #[test]
+    fn wrap_delta_stays_within_half_the_extent() {
+        for k in -14..=14 {
+            assert!(wrap_delta(k as f32 * 7.0, 100.0).abs() <= 50.0);
+        }
+        assert_eq!(wrap_delta(60.0, 100.0), -40.0);
+        assert_eq!(wrap_delta(-60.0, 100.0), 40.0);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn free_functions_work_on_glam_vectors() {
+        let v = limit(glam::Vec2::new(3.0, 4.0), 1.0);
+        assert!((v - glam::Vec2::new(0.6, 0.8)).length() < 1e-5);
+    }
+}
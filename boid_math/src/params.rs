@@ -0,0 +1,108 @@
+//! Parameter rules shared by the simulations, so the same radii behave the
+//! same in every front end.
+
+use std::fmt;
+
+/// Smallest perception (neighbor) radius, in world units.
+pub const MIN_PERCEPTION_RADIUS: f32 = 1.0;
+/// Smallest separation radius, in world units.
+pub const MIN_SEPARATION_RADIUS: f32 = 0.5;
+/// Smallest infection radius, in world units.
+pub const MIN_INFECTION_RADIUS: f32 = 1.0;
+
+/// A parameter `Radii::normalize` changed: what was asked for and what the
+/// simulation uses instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjustment {
+    pub name: &'static str,
+    pub requested: f32,
+    pub used: f32,
+}
+
+impl fmt::Display for Adjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} adjusted to {}",
+            self.name, self.requested, self.used
+        )
+    }
+}
+
+/// The radii every flocking parameter set has, in world units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Radii {
+    pub perception: f32,
+    pub separation: f32,
+    pub infection: f32,
+}
+
+impl Radii {
+    /// The radii the simulations run with, and each one that had to change.
+    /// Perception and infection radii have a floor; the separation radius is
+    /// kept between its floor and the perception radius, since separation
+    /// only considers neighbors already perceived.
+    pub fn normalize(self) -> (Radii, Vec<Adjustment>) {
+        let perception = self.perception.max(MIN_PERCEPTION_RADIUS);
+        let used = Radii {
+            perception,
+            separation: clamp_separation(self.separation, perception),
+            infection: self.infection.max(MIN_INFECTION_RADIUS),
+        };
+        let adjustments = [
+            ("perception radius", self.perception, used.perception),
+            ("separation radius", self.separation, used.separation),
+            ("infection radius", self.infection, used.infection),
+        ]
+        .into_iter()
+        .filter(|(_, requested, used)| requested != used)
+        .map(|(name, requested, used)| Adjustment {
+            name,
+            requested,
+            used,
+        })
+        .collect();
+        (used, adjustments)
+    }
+}
+
+/// The separation radius rule of `Radii::normalize`, for a perception radius
+/// that varies per boid.
+pub fn clamp_separation(separation: f32, perception: f32) -> f32 {
+    separation.min(perception).max(MIN_SEPARATION_RADIUS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separation_is_capped_at_perception_and_reported() {
+        let (used, adjustments) = Radii {
+            perception: 20.0,
+            separation: 30.0,
+            infection: 10.0,
+        }
+        .normalize();
+        assert_eq!(used.separation, 20.0);
+        assert_eq!(
+            adjustments,
+            [Adjustment {
+                name: "separation radius",
+                requested: 30.0,
+                used: 20.0
+            }]
+        );
+    }
+
+    #[test]
+    fn valid_radii_pass_unchanged() {
+        let radii = Radii {
+            perception: 50.0,
+            separation: 25.0,
+            infection: 15.0,
+        };
+        assert_eq!(radii.normalize(), (radii, Vec::new()));
+        assert_eq!(clamp_separation(0.1, 0.2), MIN_SEPARATION_RADIUS);
+    }
+}
@@ -0,0 +1,296 @@
+//! Uniform spatial hash for neighbour queries, shared by both simulations.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use crate::{Planar, Vec2, torus_delta};
+
+type Cell = (i32, i32);
+
+/// Uniform spatial hash over points of vector type `V` carrying a payload
+/// `T`.
+///
+/// On an open plane cells are `cell_size` wide. When the grid wraps, each
+/// axis is split into a whole number of cells at least `cell_size` wide so
+/// neighbouring cells line up across the seam, and distances are measured
+/// the short way round the torus.
+#[derive(Clone, Debug)]
+pub struct Grid<T, V = Vec2> {
+    cell_size: f32,
+    wrap: Option<V>,
+    buckets: HashMap<Cell, Vec<(V, T)>>,
+}
+
+impl<T, V: Planar> Grid<T, V> {
+    /// A grid over the open plane.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size: cell_size.max(1.0),
+            wrap: None,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// A grid over a torus of size `world`.
+    pub fn wrapping(cell_size: f32, world: V) -> Self {
+        Self {
+            wrap: Some(world),
+            ..Self::new(cell_size)
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Change the cell size. Points already stored stay in their old cells,
+    /// so call this between `clear` and the next round of inserts.
+    pub fn set_cell_size(&mut self, cell_size: f32) {
+        self.cell_size = cell_size.max(1.0);
+    }
+
+    /// Wrap over a torus of size `world`, or stop wrapping with `None`. Like
+    /// `set_cell_size`, call this between `clear` and the next inserts.
+    pub fn set_wrap(&mut self, world: Option<V>) {
+        self.wrap = world;
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.values().all(Vec::is_empty)
+    }
+
+    pub fn insert(&mut self, pos: V, item: T) {
+        let cell = self.cell_of(pos);
+        self.buckets.entry(cell).or_default().push((pos, item));
+    }
+
+    /// Everything in the 3x3 block of cells around `pos`: a superset of the
+    /// points within `cell_size`, in a fixed order (row by row, then
+    /// insertion order) that does not depend on hashing.
+    pub fn candidates(&self, pos: V) -> impl Iterator<Item = (V, &T)> + '_ {
+        self.cells_around(pos, 1)
+            .into_iter()
+            .filter_map(|cell| self.buckets.get(&cell))
+            .flatten()
+            .map(|(p, item)| (*p, item))
+    }
+
+    /// Every point strictly within `radius` of `pos`, with its offset from
+    /// `pos` (wrapped on a torus) and distance. `radius` may exceed the cell
+    /// size; more rings of cells are scanned.
+    pub fn within(&self, pos: V, radius: f32) -> impl Iterator<Item = (&T, V, f32)> + '_ {
+        let rings = (radius / self.cell_size).ceil().max(1.0) as i32;
+        self.cells_around(pos, rings)
+            .into_iter()
+            .filter_map(|cell| self.buckets.get(&cell))
+            .flatten()
+            .filter_map(move |(p, item)| {
+                let offset = self.offset(pos, *p);
+                let (dx, dy) = offset.xy();
+                let dist = (dx * dx + dy * dy).sqrt();
+                (dist < radius).then_some((item, offset, dist))
+            })
+    }
+
+    /// Displacement from `from` to `to`, the short way round when wrapping.
+    pub fn offset(&self, from: V, to: V) -> V {
+        match self.wrap {
+            Some(world) => torus_delta(from, to, world),
+            None => {
+                let ((fx, fy), (tx, ty)) = (from.xy(), to.xy());
+                V::from_xy(tx - fx, ty - fy)
+            }
+        }
+    }
+
+    /// Bytes held on the heap by the table and the buckets.
+    pub fn heap_bytes(&self) -> usize {
+        // One control byte per table slot on top of the entries.
+        self.buckets.capacity() * (size_of::<(Cell, Vec<(V, T)>)>() + 1)
+            + self
+                .buckets
+                .values()
+                .map(|items| items.capacity() * size_of::<(V, T)>())
+                .sum::<usize>()
+    }
+
+    /// Expected `heap_bytes` for `items` points spread over `cells`
+    /// occupied cells.
+    pub fn estimate_heap_bytes(cells: usize, items: usize) -> usize {
+        // Table slots at hashbrown's 7/8 load factor, rounded up to a power
+        // of two, each with one control byte; bucket vectors grow by
+        // doubling, so hold on average 1.5x their contents.
+        let slots = (cells * 8 / 7).next_power_of_two();
+        slots * (size_of::<(Cell, Vec<(V, T)>)>() + 1) + items * size_of::<(V, T)>() * 3 / 2
+    }
+
+    /// Number of cells along each axis of a wrapping grid.
+    fn wrapped_counts(&self, world: V) -> (i32, i32) {
+        let count = |extent: f32| ((extent / self.cell_size).floor() as i32).max(1);
+        let (wx, wy) = world.xy();
+        (count(wx), count(wy))
+    }
+
+    fn cell_of(&self, pos: V) -> Cell {
+        let (x, y) = pos.xy();
+        match self.wrap {
+            Some(world) => {
+                let (nx, ny) = self.wrapped_counts(world);
+                let (wx, wy) = world.xy();
+                let index = |v: f32, extent: f32, n: i32| {
+                    ((v.rem_euclid(extent) / extent * n as f32).floor() as i32).clamp(0, n - 1)
+                };
+                (index(x, wx, nx), index(y, wy, ny))
+            }
+            None => (
+                (x / self.cell_size).floor() as i32,
+                (y / self.cell_size).floor() as i32,
+            ),
+        }
+    }
+
+    /// Cells within `rings` of the one holding `pos`, each listed once.
+    fn cells_around(&self, pos: V, rings: i32) -> Vec<Cell> {
+        let (cx, cy) = self.cell_of(pos);
+        let mut cells = Vec::with_capacity(((2 * rings + 1) * (2 * rings + 1)) as usize);
+        match self.wrap {
+            Some(world) => {
+                let (nx, ny) = self.wrapped_counts(world);
+                // Past half the grid the rings meet themselves round the back.
+                let (rx, ry) = (rings.min(nx / 2), rings.min(ny / 2));
+                let xs: Vec<i32> = (-rx..=rx).map(|dx| (cx + dx).rem_euclid(nx)).collect();
+                for dy in -ry..=ry {
+                    let y = (cy + dy).rem_euclid(ny);
+                    for &x in &xs {
+                        if !cells.contains(&(x, y)) {
+                            cells.push((x, y));
+                        }
+                    }
+                }
+            }
+            None => {
+                for dy in -rings..=rings {
+                    for dx in -rings..=rings {
+                        cells.push((cx + dx, cy + dy));
+                    }
+                }
+            }
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::wrap_delta;
+
+    fn points(seed: u64, n: usize, world: Vec2) -> Vec<Vec2> {
+        let mut rng = Rng::new(seed);
+        (0..n)
+            .map(|_| Vec2::new(rng.next_f32() * world.x, rng.next_f32() * world.y))
+            .collect()
+    }
+
+    fn brute_force(pts: &[Vec2], pos: Vec2, radius: f32, wrap: Option<Vec2>) -> Vec<usize> {
+        (0..pts.len())
+            .filter(|&k| {
+                let mut d = pts[k].sub(pos);
+                if let Some(world) = wrap {
+                    d = Vec2::new(wrap_delta(d.x, world.x), wrap_delta(d.y, world.y));
+                }
+                d.length() < radius
+            })
+            .collect()
+    }
+
+    fn query(grid: &Grid<usize>, pos: Vec2, radius: f32) -> Vec<usize> {
+        let mut found: Vec<usize> = grid.within(pos, radius).map(|(&k, _, _)| k).collect();
+        found.sort_unstable();
+        found
+    }
+
+    fn check(grid: &mut Grid<usize>, pts: &[Vec2], wrap: Option<Vec2>) {
+        for (k, &p) in pts.iter().enumerate() {
+            grid.insert(p, k);
+        }
+        assert_eq!(grid.len(), pts.len());
+        for &radius in &[5.0, 17.0, 40.0, 95.0] {
+            for &pos in &pts[..40] {
+                assert_eq!(
+                    query(grid, pos, radius),
+                    brute_force(pts, pos, radius, wrap)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn radius_queries_match_brute_force_on_the_plane() {
+        let world = Vec2::new(300.0, 200.0);
+        let pts = points(7, 500, world);
+        check(&mut Grid::new(20.0), &pts, None);
+    }
+
+    #[test]
+    fn radius_queries_match_brute_force_on_the_torus() {
+        // 250 is not a multiple of the cell size, so the seam cells stretch.
+        let world = Vec2::new(250.0, 130.0);
+        let pts = points(11, 500, world);
+        check(&mut Grid::wrapping(20.0, world), &pts, Some(world));
+    }
+
+    #[test]
+    fn radius_larger_than_half_the_torus_lists_each_point_once() {
+        let world = Vec2::new(60.0, 60.0);
+        let pts = points(3, 80, world);
+        check(&mut Grid::wrapping(25.0, world), &pts, Some(world));
+    }
+
+    #[test]
+    fn candidates_cover_everything_within_a_cell() {
+        let world = Vec2::new(300.0, 200.0);
+        let pts = points(5, 400, world);
+        let mut grid = Grid::new(30.0);
+        for (k, &p) in pts.iter().enumerate() {
+            grid.insert(p, k);
+        }
+        for &pos in &pts[..40] {
+            let near: Vec<usize> = grid.candidates(pos).map(|(_, &k)| k).collect();
+            for k in brute_force(&pts, pos, 30.0, None) {
+                assert!(near.contains(&k));
+            }
+        }
+    }
+
+    #[test]
+    fn wrapped_offsets_take_the_short_way_round() {
+        let grid: Grid<()> = Grid::wrapping(10.0, Vec2::new(100.0, 50.0));
+        let d = grid.offset(Vec2::new(95.0, 2.0), Vec2::new(3.0, 48.0));
+        assert!((d.x - 8.0).abs() < 1e-4 && (d.y + 4.0).abs() < 1e-4);
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn grids_hold_glam_vectors() {
+        let mut grid = Grid::new(10.0);
+        grid.insert(glam::Vec2::new(1.0, 1.0), 0);
+        grid.insert(glam::Vec2::new(4.0, 5.0), 1);
+        grid.insert(glam::Vec2::new(30.0, 1.0), 2);
+        let mut near: Vec<usize> = grid
+            .within(glam::Vec2::new(2.0, 2.0), 5.0)
+            .map(|(&k, _, _)| k)
+            .collect();
+        near.sort_unstable();
+        assert_eq!(near, [0, 1]);
+    }
+}
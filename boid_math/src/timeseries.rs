@@ -0,0 +1,174 @@
+//! Per-tick epidemic time series as CSV, shared by the simulations so their
+//! exports load the same way in pandas or R.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Column names, in row order. Compartments a model lacks are written as 0,
+/// and `r_eff` is empty until an estimate exists.
+pub const HEADER: &str = "time,susceptible,exposed,infected,recovered,dead,r_eff,mean_speed";
+
+/// Simulated seconds `REffEstimator` looks back over by default.
+pub const R_EFF_WINDOW: f32 = 2.0;
+
+/// One row of the export: compartment counts, the reproduction number
+/// estimate and the flock's mean speed at `time`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TickRow {
+    pub time: f32,
+    pub susceptible: usize,
+    pub exposed: usize,
+    pub infected: usize,
+    pub recovered: usize,
+    pub dead: usize,
+    pub r_eff: Option<f32>,
+    pub mean_speed: f32,
+}
+
+/// Effective reproduction number from the flow through the infected
+/// compartments: infections started over the last `window` seconds divided
+/// by infections that ended over it (recovered, died or otherwise left).
+/// Each case is replaced by that many new ones while the ratio holds, so it
+/// is R_eff in any model where cases end at a steady rate.
+#[derive(Clone, Debug)]
+pub struct REffEstimator {
+    window: f32,
+    /// `(time, infections so far, infections ended so far)`, oldest first.
+    samples: VecDeque<(f32, usize, usize)>,
+}
+
+impl REffEstimator {
+    pub fn new(window: f32) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Record the state at `time`: `cumulative_infections` every infection
+    /// so far, seeded cases included, and `active` the boids currently
+    /// exposed or infected. Returns the estimate, or `None` while no
+    /// infection has ended within the window.
+    pub fn record(
+        &mut self,
+        time: f32,
+        cumulative_infections: usize,
+        active: usize,
+    ) -> Option<f32> {
+        let ended = cumulative_infections.saturating_sub(active);
+        self.samples.push_back((time, cumulative_infections, ended));
+        // Keep the newest sample at or before the window start as the base.
+        while self.samples.len() > 2 && self.samples[1].0 <= time - self.window {
+            self.samples.pop_front();
+        }
+        let (_, base_infections, base_ended) = self.samples[0];
+        let ended = ended.saturating_sub(base_ended);
+        (ended > 0)
+            .then(|| cumulative_infections.saturating_sub(base_infections) as f32 / ended as f32)
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl Default for REffEstimator {
+    fn default() -> Self {
+        Self::new(R_EFF_WINDOW)
+    }
+}
+
+/// Streams `TickRow`s as CSV, header first, to any writer.
+pub struct TimeSeriesWriter<W: Write> {
+    out: W,
+    rows: usize,
+}
+
+impl TimeSeriesWriter<BufWriter<File>> {
+    /// Create (or truncate) the file at `path` and write the header.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> TimeSeriesWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        writeln!(out, "{HEADER}")?;
+        Ok(Self { out, rows: 0 })
+    }
+
+    pub fn write(&mut self, row: &TickRow) -> io::Result<()> {
+        write!(
+            self.out,
+            "{:.4},{},{},{},{},{},",
+            row.time, row.susceptible, row.exposed, row.infected, row.recovered, row.dead
+        )?;
+        if let Some(r) = row.r_eff {
+            write!(self.out, "{r:.4}")?;
+        }
+        writeln!(self.out, ",{:.3}", row.mean_speed)?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Rows written so far, not counting the header.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Flush and hand back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn r_eff_is_new_infections_per_ended_infection() {
+        let mut r = REffEstimator::new(1.0);
+        // Nothing has ended yet.
+        assert_eq!(r.record(0.0, 10, 10), None);
+        assert_eq!(r.record(0.5, 14, 14), None);
+        // 6 new infections against 2 ended since t = 0.
+        assert_eq!(r.record(1.0, 16, 14), Some(3.0));
+        // The window moves on to t = 0.5: 8 new against 4 ended.
+        assert_eq!(r.record(1.5, 22, 18), Some(2.0));
+    }
+
+    #[test]
+    fn r_eff_falls_below_one_as_the_epidemic_wanes() {
+        let mut r = REffEstimator::default();
+        r.record(0.0, 100, 50);
+        assert_eq!(r.record(1.0, 102, 42), Some(0.2));
+    }
+
+    #[test]
+    fn writer_leaves_missing_estimates_empty() {
+        let mut w = TimeSeriesWriter::new(Vec::new()).unwrap();
+        let row = TickRow {
+            time: 0.5,
+            susceptible: 90,
+            infected: 10,
+            mean_speed: 120.0,
+            ..TickRow::default()
+        };
+        w.write(&row).unwrap();
+        w.write(&TickRow {
+            r_eff: Some(1.5),
+            ..row
+        })
+        .unwrap();
+        assert_eq!(w.rows(), 2);
+        let csv = String::from_utf8(w.finish().unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], HEADER);
+        assert_eq!(lines[1], "0.5000,90,0,10,0,0,,120.000");
+        assert_eq!(lines[2], "0.5000,90,0,10,0,0,1.5000,120.000");
+    }
+}
@@ -2,16 +2,108 @@ use macroquad::prelude::*;
 
 mod my_boid;
 mod my_boid_ui;
+mod my_flock;
+mod net;
+mod pressure;
+mod versus;
 
+use std::thread;
+
+use boid_math::rng::Rng;
 use boid_simulation::constants::*;
-use boid_simulation::sir::{count_disease_states, process_infections, DiseaseModel, DiseaseState};
-use boid_simulation::simulation::{SimParams, initialize_boids};
+use boid_simulation::clock::SimClock;
+use boid_simulation::sir::{count_disease_states, process_infections, remove_corpses, DiseaseModel, DiseaseState};
+use boid_simulation::simulation::{SimParams, initialize_boids, resize_boids};
 use boid_simulation::visualization::PopulationHistory;
 use boid_simulation::spatial::SpatialGrid;
-use boid_simulation::ui::{UIState, render_parameter_panel, render_graph_toggle, render_collapsed_params_button};
+use boid_simulation::keybindings::{Action, Keybindings};
+use boid_simulation::ui::{UIState, render_parameter_panel, render_graph_toggle, render_collapsed_params_button, render_help_panel};
 
 use my_boid::{MyBoid, MyBoidParams};
 use my_boid_ui::{MyBoidUIState, render_my_boid_panel, render_collapsed_my_boid_button};
+use my_flock::MyFlock;
+use net::{Peer, decode_params, decode_snapshot, encode_params, encode_snapshot};
+use pressure::{InfectionPressure, draw_pressure_gauge};
+use versus::{Versus, draw_player_tag, draw_scoreboard};
+
+const DEFAULT_ADDR: &str = "0.0.0.0:7878";
+
+const ACTIONS: [Action; 7] = [
+    Action::Restart,
+    Action::Pause,
+    Action::ToggleParams,
+    Action::ToggleGraph,
+    Action::ToggleMyBoid,
+    Action::ToggleRings,
+    Action::Help,
+];
+
+/// The host owns the simulation, so a client can only change what it sees.
+const CLIENT_ACTIONS: [Action; 4] = [Action::ToggleGraph, Action::ToggleMyBoid, Action::ToggleRings, Action::Help];
+
+/// How this instance takes part: alone, as the authoritative host of a
+/// two-player match, or as a client joining one.
+enum Mode {
+    Solo,
+    Host(String),
+    Join(String),
+}
+
+/// `--host [addr]` or `--join addr`; anything else plays solo.
+fn parse_mode() -> Mode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--host") => Mode::Host(args.get(1).cloned().unwrap_or_else(|| DEFAULT_ADDR.to_owned())),
+        Some("--join") => match args.get(1) {
+            Some(addr) => Mode::Join(addr.clone()),
+            None => {
+                eprintln!("--join needs an address, e.g. --join 192.168.1.5:7878");
+                std::process::exit(2);
+            }
+        },
+        _ => Mode::Solo,
+    }
+}
+
+/// Connect on a background thread, keeping the window responsive meanwhile.
+async fn connect(message: String, open: impl FnOnce() -> std::io::Result<Peer> + Send + 'static) -> Peer {
+    let pending = thread::spawn(open);
+    while !pending.is_finished() {
+        clear_background(BLACK);
+        let dims = measure_text(&message, None, 28, 1.0);
+        draw_text(&message, (SCREEN_WIDTH - dims.width) / 2.0, SCREEN_HEIGHT / 2.0, 28.0, WHITE);
+        next_frame().await
+    }
+    match pending.join().expect("connection thread panicked") {
+        Ok(peer) => peer,
+        Err(err) => {
+            eprintln!("connection failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `corpses_removed` adds dead boids no longer in `boids` to the death toll.
+fn draw_status(boids: &[boid_simulation::boid::Boid], model: DiseaseModel, corpses_removed: usize) {
+    let (s, e, i, r, d) = count_disease_states(boids);
+    let status_text = match model {
+        DiseaseModel::SIR | DiseaseModel::SIS => {
+            format!("S: {} | I: {} | R: {}", s, i, r)
+        }
+        DiseaseModel::SEIR => {
+            format!("S: {} | E: {} | I: {} | R: {}", s, e, i, r)
+        }
+    };
+    let deaths = d + corpses_removed;
+    let status_text = if deaths > 0 { format!("{} | D: {}", status_text, deaths) } else { status_text };
+    draw_text(
+        &status_text,
+        20.0,
+        SCREEN_HEIGHT - 20.0,
+        24.0,
+        WHITE,
+    );
+}
 
 fn window_conf() -> Conf {
     Conf {
@@ -46,6 +138,7 @@ fn draw_boid_dimmed(boid: &boid_simulation::boid::Boid) {
         DiseaseState::Exposed => (255, 200, 0),
         DiseaseState::Infected => (255, 0, 0),
         DiseaseState::Recovered => (0, 0, 255),
+        DiseaseState::Dead => (110, 110, 110),
     };
 
     draw_triangle(p1, p2, p3, Color::from_rgba(r, g, b, 130));
@@ -53,35 +146,72 @@ fn draw_boid_dimmed(boid: &boid_simulation::boid::Boid) {
 
 #[macroquad::main(window_conf)]
 async fn main() {
+    let bindings = Keybindings::load();
+    let mut peer = match parse_mode() {
+        Mode::Solo => None,
+        Mode::Host(addr) => {
+            let message = format!("Waiting for a player to join on {}...", addr);
+            Some(connect(message, move || Peer::host(&addr)).await)
+        }
+        Mode::Join(addr) => {
+            let message = format!("Joining {}...", addr);
+            let peer = connect(message, move || Peer::join(&addr)).await;
+            run_client(peer, &bindings).await;
+            return;
+        }
+    };
+
     let mut params = SimParams::default();
-    let mut boids = initialize_boids(params.num_boids, params.initial_infected);
+    // One stream for the whole run, the player's boids included, so a run
+    // is fixed by `params.seed`
+    let mut rng = Rng::new(params.seed);
+    println!("seed {}", params.seed);
+    let mut boids = initialize_boids(&params, &mut rng);
     let mut spatial_grid = SpatialGrid::new(50.0);
     let mut history = PopulationHistory::new();
     let mut ui_state = UIState::default();
-    let mut frame_counter = 0;
+    let mut clock = SimClock::default();
+    let mut corpses_removed = 0;
 
-    let mut my_boid = MyBoid::new();
+    let mut my_flock = MyFlock::new(1, &mut rng);
     let mut my_boid_params = MyBoidParams::default();
     let mut my_boid_ui_state = MyBoidUIState::default();
 
+    // Two-player state, only used while hosting
+    let mut rival = MyBoid::new(&mut rng);
+    let mut rival_params = MyBoidParams::default();
+    let mut versus = Versus::default();
+    let mut pressure = InfectionPressure::default();
+    let mut paused = false;
+
     loop {
         clear_background(BLACK);
         let dt = get_frame_time();
 
         let mut should_restart = false;
+        let mut replay_seed = false;
         let mut boid_count_changed = false;
         let mut model_changed = false;
 
         // Keyboard shortcuts
-        if is_key_pressed(KeyCode::P) {
+        if bindings.pressed(Action::ToggleParams) {
             ui_state.params_collapsed = !ui_state.params_collapsed;
         }
-        if is_key_pressed(KeyCode::G) {
+        if bindings.pressed(Action::ToggleGraph) {
             ui_state.show_graph = !ui_state.show_graph;
         }
-        if is_key_pressed(KeyCode::M) {
+        if bindings.pressed(Action::ToggleMyBoid) {
             my_boid_ui_state.collapsed = !my_boid_ui_state.collapsed;
         }
+        if bindings.pressed(Action::ToggleRings) {
+            ui_state.show_rings = !ui_state.show_rings;
+        }
+        if bindings.pressed(Action::Help) {
+            ui_state.show_help = !ui_state.show_help;
+        }
+        if bindings.pressed(Action::Pause) {
+            paused = !paused;
+        }
 
         let graph_x = SCREEN_WIDTH - GRAPH_WIDTH - 10.0;
         let graph_y = SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0;
@@ -90,62 +220,117 @@ async fn main() {
             render_graph_toggle(egui_ctx, &mut ui_state, graph_x, graph_y);
             let controls = render_parameter_panel(egui_ctx, &mut params, &mut ui_state);
             render_collapsed_params_button(egui_ctx, &mut ui_state);
-            render_my_boid_panel(egui_ctx, &mut my_boid_params, &mut my_boid_ui_state, params.model);
+            // A two-player game is one boid each
+            let flock_stats = peer.is_none().then(|| my_flock.stats());
+            render_my_boid_panel(egui_ctx, &mut my_boid_params, &mut my_boid_ui_state, params.model, flock_stats.as_ref());
             render_collapsed_my_boid_button(egui_ctx, &mut my_boid_ui_state);
+            render_help_panel(egui_ctx, &mut ui_state, &bindings, &ACTIONS);
             should_restart = controls.should_restart;
+            replay_seed = controls.replay_seed;
             boid_count_changed = controls.boid_count_changed;
             model_changed = controls.model_changed;
         });
 
-        if is_key_pressed(KeyCode::Enter) || should_restart || boid_count_changed || model_changed {
-            boids = initialize_boids(params.num_boids, params.initial_infected);
-            my_boid = MyBoid::new();
+        if bindings.pressed(Action::Restart) || should_restart || replay_seed || model_changed {
+            if !replay_seed {
+                params.seed = rng.next_u64();
+            }
+            rng = Rng::new(params.seed);
+            println!("restart: seed {}", params.seed);
+            boids = initialize_boids(&params, &mut rng);
+            my_flock = MyFlock::new(my_flock.len(), &mut rng);
+            rival = MyBoid::new(&mut rng);
+            versus = Versus::default();
+            pressure = InfectionPressure::default();
+            history.set_sample_interval(params.sample_interval);
             history.clear();
-            frame_counter = 0;
+            clock.reset();
+            corpses_removed = 0;
+        } else if boid_count_changed {
+            // Keep the epidemic going; only add or remove boids
+            resize_boids(&mut boids, &params, &mut rng);
         }
-
-        // Build spatial grid
-        spatial_grid.clear();
-        for (i, boid) in boids.iter().enumerate() {
-            spatial_grid.insert(i, boid.position);
+        if peer.is_none() {
+            my_flock.resize(my_boid_ui_state.flock_size, &mut rng);
         }
 
-        process_infections(&mut boids, &params, &spatial_grid);
+        // While paused nothing moves, but the rival still sees the frozen state
+        if !paused {
+            clock.advance(dt);
+
+            // Build spatial grid
+            spatial_grid.clear();
+            for (i, boid) in boids.iter().enumerate() {
+                spatial_grid.insert(i, boid.position);
+            }
 
-        // My Boid disease: catch from / spread to regular boids
-        my_boid.process_infection(&mut boids, &spatial_grid, &params);
-        my_boid.update_disease_state(&params, dt);
+            process_infections(&mut boids, &params, &spatial_grid, &mut rng);
 
-        // Update regular boids
-        for i in 0..boids.len() {
-            let neighbors = spatial_grid.query_nearby(
-                boids[i].position,
-                params.perception_radius,
-                &boids,
-            );
-            boids[i].update(&neighbors, &params);
-            boids[i].update_disease_state(&params, dt);
+            // My Boid disease: catch from / spread to regular boids
+            my_flock.process_infection(&mut boids, &spatial_grid, &params, dt, &mut rng);
+            if peer.is_some() {
+                rival.process_infection(&mut boids, &spatial_grid, &params, &mut rng);
+                rival.update_disease_state(&params, dt);
+            }
+
+            // Update regular boids
+            for i in 0..boids.len() {
+                let neighbors = spatial_grid.query_nearby(
+                    boids[i].position,
+                    params.perception_radius_for(boids[i].disease_state),
+                    &boids,
+                );
+                boids[i].update(&neighbors, &params);
+                boids[i].update_disease_state(&params, dt, &mut rng);
+            }
+            corpses_removed += remove_corpses(&mut boids, &params).iter().filter(|&&r| r).count();
+
+            // Update My Boid flocking
+            my_flock.update(&boids, &spatial_grid, &my_boid_params);
         }
+        pressure.update(my_flock.leader(), &boids, &spatial_grid, &params, !paused);
 
-        // Update My Boid flocking
-        my_boid.update(&boids, &spatial_grid, &my_boid_params);
+        if let Some(link) = peer.as_mut() {
+            if let Some(remote) = link.latest().as_deref().and_then(decode_params) {
+                rival_params = remote;
+            }
+            if !paused {
+                rival.update(&boids, &spatial_grid, &rival_params);
+                versus.update([my_flock.leader(), &rival], dt);
+            }
+            if let Err(err) = link.send(&encode_snapshot(params.model, &boids, [my_flock.leader(), &rival], &versus)) {
+                eprintln!("player disconnected: {}", err);
+                peer = None;
+            }
+        }
 
         // Draw regular boids (dimmed)
         for boid in &boids {
             draw_boid_dimmed(boid);
         }
+        if ui_state.show_rings {
+            for boid in &boids {
+                boid.draw_ring(ui_state.palette);
+            }
+        }
 
         // Draw My Boid (bright, with circle)
-        my_boid.draw();
+        my_flock.draw();
+        if peer.is_some() {
+            rival.draw();
+            draw_player_tag(my_flock.leader(), "You");
+            draw_player_tag(&rival, "Rival");
+            draw_scoreboard(&versus, 0);
+        }
 
         // Population tracking
-        frame_counter += 1;
-        if frame_counter % 10 == 0 {
-            let (s, e, i, r) = count_disease_states(&boids);
-            history.add(s, e, i, r);
+        if !paused && clock.crossed(history.sample_interval()) {
+            let (s, e, i, r, d) = count_disease_states(&boids);
+            history.add(s, e, i, r, d + corpses_removed);
         }
 
         if ui_state.show_graph {
+            history.set_window(ui_state.graph_window);
             history.draw(
                 SCREEN_WIDTH - GRAPH_WIDTH - 10.0,
                 SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0,
@@ -154,22 +339,106 @@ async fn main() {
             );
         }
 
-        let (s, e, i, r) = count_disease_states(&boids);
-        let status_text = match params.model {
-            DiseaseModel::SIR | DiseaseModel::SIS => {
-                format!("S: {} | I: {} | R: {}", s, i, r)
+        draw_status(&boids, params.model, corpses_removed);
+        draw_pressure_gauge(&pressure, my_flock.leader());
+        if paused {
+            draw_text("PAUSED", SCREEN_WIDTH / 2.0 - 40.0, 30.0, 32.0, WHITE);
+        }
+
+        egui_macroquad::draw();
+
+        next_frame().await
+    }
+}
+
+/// Client side of a two-player match: send My Boid parameters to the host
+/// and draw whatever state it sends back. The host owns the simulation, so
+/// the simulation parameter panel is not shown here.
+async fn run_client(mut peer: Peer, bindings: &Keybindings) {
+    let mut history = PopulationHistory::new();
+    let mut ui_state = UIState::default();
+    // Local, since the host doesn't send its clock; only paces the graph
+    let mut clock = SimClock::default();
+
+    let mut my_boid_params = MyBoidParams::default();
+    let mut my_boid_ui_state = MyBoidUIState::default();
+    let mut snapshot = None;
+    let mut connected = true;
+
+    loop {
+        clear_background(BLACK);
+
+        if bindings.pressed(Action::ToggleGraph) {
+            ui_state.show_graph = !ui_state.show_graph;
+        }
+        if bindings.pressed(Action::ToggleMyBoid) {
+            my_boid_ui_state.collapsed = !my_boid_ui_state.collapsed;
+        }
+        if bindings.pressed(Action::ToggleRings) {
+            ui_state.show_rings = !ui_state.show_rings;
+        }
+        if bindings.pressed(Action::Help) {
+            ui_state.show_help = !ui_state.show_help;
+        }
+
+        let graph_x = SCREEN_WIDTH - GRAPH_WIDTH - 10.0;
+        let graph_y = SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0;
+        let model = snapshot.as_ref().map_or(DiseaseModel::SIR, |s: &net::Snapshot| s.model);
+
+        egui_macroquad::ui(|egui_ctx| {
+            render_graph_toggle(egui_ctx, &mut ui_state, graph_x, graph_y);
+            render_my_boid_panel(egui_ctx, &mut my_boid_params, &mut my_boid_ui_state, model, None);
+            render_collapsed_my_boid_button(egui_ctx, &mut my_boid_ui_state);
+            render_help_panel(egui_ctx, &mut ui_state, bindings, &CLIENT_ACTIONS);
+        });
+
+        if connected && let Err(err) = peer.send(&encode_params(&my_boid_params)) {
+            eprintln!("lost connection to host: {}", err);
+            connected = false;
+        }
+        if let Some(next) = peer.latest().as_deref().and_then(decode_snapshot) {
+            // A survival clock running backwards means the host restarted
+            if snapshot.as_ref().is_some_and(|s: &net::Snapshot| next.versus.survival[1] < s.versus.survival[1]) {
+                history.clear();
+                clock.reset();
+            }
+            snapshot = Some(next);
+        }
+
+        if let Some(state) = &snapshot {
+            for boid in &state.boids {
+                draw_boid_dimmed(boid);
             }
-            DiseaseModel::SEIR => {
-                format!("S: {} | E: {} | I: {} | R: {}", s, e, i, r)
+            if ui_state.show_rings {
+                for boid in &state.boids {
+                    boid.draw_ring(ui_state.palette);
+                }
             }
-        };
-        draw_text(
-            &status_text,
-            20.0,
-            SCREEN_HEIGHT - 20.0,
-            24.0,
-            WHITE,
-        );
+            let [rival, me] = &state.players;
+            rival.draw();
+            me.draw();
+            draw_player_tag(me, "You");
+            draw_player_tag(rival, "Rival");
+            draw_scoreboard(&state.versus, 1);
+
+            clock.advance(get_frame_time());
+            if clock.crossed(history.sample_interval()) {
+                // Only the host knows about corpses already removed
+                let (s, e, i, r, d) = count_disease_states(&state.boids);
+                history.add(s, e, i, r, d);
+            }
+            if ui_state.show_graph {
+                history.set_window(ui_state.graph_window);
+                history.draw(graph_x, graph_y, state.boids.len() as f32, state.model);
+            }
+            draw_status(&state.boids, state.model, 0);
+        }
+
+        if !connected {
+            let text = "Connection to host lost";
+            let dims = measure_text(text, None, 28, 1.0);
+            draw_text(text, (SCREEN_WIDTH - dims.width) / 2.0, SCREEN_HEIGHT / 2.0, 28.0, RED);
+        }
 
         egui_macroquad::draw();
 
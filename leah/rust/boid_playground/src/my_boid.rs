@@ -1,5 +1,7 @@
 use macroquad::prelude::*;
-use macroquad::prelude::rand;
+use boid_math::limit;
+use boid_math::params::{clamp_separation, MIN_PERCEPTION_RADIUS};
+use boid_math::rng::Rng;
 use boid_simulation::boid::Boid;
 use boid_simulation::constants::{SCREEN_WIDTH, SCREEN_HEIGHT};
 use boid_simulation::sir::{DiseaseState, DiseaseModel};
@@ -39,6 +41,14 @@ impl Default for MyBoidParams {
     }
 }
 
+impl MyBoidParams {
+    /// Apply the same radius rules as `SimParams::normalize`.
+    pub fn normalize(&mut self) {
+        self.perception_radius = self.perception_radius.max(MIN_PERCEPTION_RADIUS);
+        self.separation_radius = clamp_separation(self.separation_radius, self.perception_radius);
+    }
+}
+
 pub struct MyBoid {
     pub position: Vec2,
     pub velocity: Vec2,
@@ -47,11 +57,11 @@ pub struct MyBoid {
 }
 
 impl MyBoid {
-    pub fn new() -> Self {
-        let x = rand::gen_range(100.0, SCREEN_WIDTH - 100.0);
-        let y = rand::gen_range(100.0, SCREEN_HEIGHT - 100.0);
-        let angle = rand::gen_range(0.0, std::f32::consts::TAU);
-        let speed = rand::gen_range(1.5, 2.5);
+    pub fn new(rng: &mut Rng) -> Self {
+        let x = rng.range(100.0, SCREEN_WIDTH - 100.0);
+        let y = rng.range(100.0, SCREEN_HEIGHT - 100.0);
+        let angle = rng.range(0.0, std::f32::consts::TAU);
+        let speed = rng.range(1.5, 2.5);
         Self {
             position: vec2(x, y),
             velocity: vec2(angle.cos() * speed, angle.sin() * speed),
@@ -61,36 +71,34 @@ impl MyBoid {
     }
 
     /// Check nearby regular boids for infection, and also infect nearby susceptible boids if we're infected.
-    pub fn process_infection(&mut self, boids: &mut [Boid], spatial_grid: &SpatialGrid, params: &SimParams) {
-        let nearby_indices = spatial_grid.query_nearby_indices(self.position, params.infection_radius);
+    pub fn process_infection(&mut self, boids: &mut [Boid], spatial_grid: &SpatialGrid, params: &SimParams, rng: &mut Rng) {
+        let nearby_indices = spatial_grid.query_nearby_indices(self.position, params.max_infection_radius());
 
         for idx in nearby_indices {
             let dist = (self.position - boids[idx].position).length();
-            if dist < params.infection_radius {
+            if dist < params.infection_radius_between(self.disease_state, boids[idx].disease_state) {
                 // We can catch it from infected boids
                 if self.disease_state == DiseaseState::Susceptible
                     && boids[idx].disease_state == DiseaseState::Infected
+                    && rng.chance(params.infection_probability)
                 {
-                    if rand::gen_range(0.0, 1.0) < params.infection_probability {
-                        self.disease_state = match params.model {
-                            DiseaseModel::SEIR => DiseaseState::Exposed,
-                            DiseaseModel::SIR | DiseaseModel::SIS => DiseaseState::Infected,
-                        };
-                        self.state_timer = 0.0;
-                    }
+                    self.disease_state = match params.model {
+                        DiseaseModel::SEIR => DiseaseState::Exposed,
+                        DiseaseModel::SIR | DiseaseModel::SIS => DiseaseState::Infected,
+                    };
+                    self.state_timer = 0.0;
                 }
 
                 // We can spread it to susceptible boids
                 if self.disease_state == DiseaseState::Infected
                     && boids[idx].disease_state == DiseaseState::Susceptible
+                    && rng.chance(params.infection_probability)
                 {
-                    if rand::gen_range(0.0, 1.0) < params.infection_probability {
-                        boids[idx].disease_state = match params.model {
-                            DiseaseModel::SEIR => DiseaseState::Exposed,
-                            DiseaseModel::SIR | DiseaseModel::SIS => DiseaseState::Infected,
-                        };
-                        boids[idx].state_timer = 0.0;
-                    }
+                    boids[idx].disease_state = match params.model {
+                        DiseaseModel::SEIR => DiseaseState::Exposed,
+                        DiseaseModel::SIR | DiseaseModel::SIS => DiseaseState::Infected,
+                    };
+                    boids[idx].state_timer = 0.0;
                 }
             }
         }
@@ -131,6 +139,7 @@ impl MyBoid {
             DiseaseState::Exposed => params.affinity_exposed,
             DiseaseState::Infected => params.affinity_infected,
             DiseaseState::Recovered => params.affinity_recovered,
+            DiseaseState::Dead => 0.0,
         }
     }
 
@@ -182,25 +191,25 @@ impl MyBoid {
         if separation_count > 0 {
             separation /= separation_count as f32;
             separation = separation.normalize_or_zero() * params.max_speed - self.velocity;
-            separation = limit_vec(separation, params.max_force);
+            separation = limit(separation, params.max_force);
         }
 
         if alignment_count > 0 {
             alignment /= alignment_count as f32;
             alignment = alignment.normalize_or_zero() * params.max_speed - self.velocity;
-            alignment = limit_vec(alignment, params.max_force);
+            alignment = limit(alignment, params.max_force);
         }
 
         if cohesion_count > 0 {
             cohesion /= cohesion_count as f32;
             cohesion = (cohesion - self.position).normalize_or_zero() * params.max_speed - self.velocity;
-            cohesion = limit_vec(cohesion, params.max_force);
+            cohesion = limit(cohesion, params.max_force);
         }
 
         if affinity_count > 0 {
             affinity_force /= affinity_count as f32;
             affinity_force = affinity_force.normalize_or_zero() * params.max_speed - self.velocity;
-            affinity_force = limit_vec(affinity_force, params.max_force);
+            affinity_force = limit(affinity_force, params.max_force);
         }
 
         let mut acceleration = vec2(0.0, 0.0);
@@ -210,7 +219,7 @@ impl MyBoid {
         acceleration += affinity_force;
 
         self.velocity += acceleration;
-        self.velocity = limit_vec(self.velocity, params.max_speed);
+        self.velocity = limit(self.velocity, params.max_speed);
         self.position += self.velocity;
 
         // Wrap around screen
@@ -234,6 +243,7 @@ impl MyBoid {
             DiseaseState::Exposed => (255, 200, 0),
             DiseaseState::Infected => (255, 0, 0),
             DiseaseState::Recovered => (80, 130, 255),
+            DiseaseState::Dead => (110, 110, 110),
         };
 
         // Bright glowing circle (outer, faint)
@@ -263,11 +273,3 @@ impl MyBoid {
         draw_triangle(p1, p2, p3, Color::from_rgba(r, g, b, 255));
     }
 }
-
-fn limit_vec(v: Vec2, max: f32) -> Vec2 {
-    if v.length() > max {
-        v.normalize() * max
-    } else {
-        v
-    }
-}
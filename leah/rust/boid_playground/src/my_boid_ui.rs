@@ -1,14 +1,19 @@
 use egui_macroquad::egui;
 use boid_simulation::sir::DiseaseModel;
 use crate::my_boid::MyBoidParams;
+use crate::my_flock::{FlockStats, MAX_FLOCK_SIZE};
 
 pub struct MyBoidUIState {
     pub collapsed: bool,
+    pub flock_size: usize,
 }
 
 impl Default for MyBoidUIState {
     fn default() -> Self {
-        Self { collapsed: false }
+        Self {
+            collapsed: false,
+            flock_size: 1,
+        }
     }
 }
 
@@ -17,6 +22,7 @@ pub fn render_my_boid_panel(
     params: &mut MyBoidParams,
     ui_state: &mut MyBoidUIState,
     disease_model: DiseaseModel,
+    flock: Option<&FlockStats>,
 ) {
     if ui_state.collapsed {
         return;
@@ -39,6 +45,22 @@ pub fn render_my_boid_panel(
 
             ui.separator();
 
+            // Squad size and how it is faring (not available in two-player games)
+            if let Some(stats) = flock {
+                ui.horizontal(|ui| {
+                    ui.label("Flock size");
+                    ui.add(egui::Slider::new(&mut ui_state.flock_size, 1..=MAX_FLOCK_SIZE));
+                });
+                let mut states = format!("S {}", stats.susceptible);
+                if disease_model == DiseaseModel::SEIR {
+                    states.push_str(&format!("  E {}", stats.exposed));
+                }
+                states.push_str(&format!("  I {}  R {}", stats.infected, stats.recovered));
+                ui.label(states);
+                ui.label(format!("Infected so far: {} of {}", stats.ever_infected, stats.size));
+                ui.add_space(4.0);
+            }
+
             egui::Frame::new()
                 .fill(egui::Color32::from_rgb(40, 50, 80))
                 .inner_margin(egui::Margin::same(8))
@@ -141,6 +163,8 @@ pub fn render_my_boid_panel(
                     });
                 });
         });
+
+    params.normalize();
 }
 
 pub fn render_collapsed_my_boid_button(
@@ -0,0 +1,100 @@
+use boid_math::rng::Rng;
+use boid_simulation::boid::Boid;
+use boid_simulation::sir::DiseaseState;
+use boid_simulation::simulation::SimParams;
+use boid_simulation::spatial::SpatialGrid;
+
+use crate::my_boid::{MyBoid, MyBoidParams};
+
+pub const MAX_FLOCK_SIZE: usize = 20;
+
+/// A squad of MyBoids that all steer by the same `MyBoidParams`.
+pub struct MyFlock {
+    pub members: Vec<MyBoid>,
+    /// Per member: has it caught the disease at any point this run
+    ever_infected: Vec<bool>,
+}
+
+/// Aggregate disease state of the squad.
+#[derive(Default)]
+pub struct FlockStats {
+    pub size: usize,
+    pub susceptible: usize,
+    pub exposed: usize,
+    pub infected: usize,
+    pub recovered: usize,
+    pub ever_infected: usize,
+}
+
+impl MyFlock {
+    pub fn new(size: usize, rng: &mut Rng) -> Self {
+        let mut flock = Self {
+            members: Vec::new(),
+            ever_infected: Vec::new(),
+        };
+        flock.resize(size, rng);
+        flock
+    }
+
+    /// Add or remove members to reach `size` (at least 1, at most
+    /// `MAX_FLOCK_SIZE`) without resetting the ones that stay.
+    pub fn resize(&mut self, size: usize, rng: &mut Rng) {
+        let size = size.clamp(1, MAX_FLOCK_SIZE);
+        self.members.truncate(size);
+        self.ever_infected.truncate(size);
+        while self.members.len() < size {
+            self.members.push(MyBoid::new(rng));
+            self.ever_infected.push(false);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// The member that stands in for the player where only one counts
+    pub fn leader(&self) -> &MyBoid {
+        &self.members[0]
+    }
+
+    pub fn process_infection(&mut self, boids: &mut [Boid], spatial_grid: &SpatialGrid, params: &SimParams, dt: f32, rng: &mut Rng) {
+        for (member, ever) in self.members.iter_mut().zip(&mut self.ever_infected) {
+            member.process_infection(boids, spatial_grid, params, rng);
+            member.update_disease_state(params, dt);
+            if member.disease_state != DiseaseState::Susceptible {
+                *ever = true;
+            }
+        }
+    }
+
+    pub fn update(&mut self, boids: &[Boid], spatial_grid: &SpatialGrid, params: &MyBoidParams) {
+        for member in &mut self.members {
+            member.update(boids, spatial_grid, params);
+        }
+    }
+
+    pub fn draw(&self) {
+        for member in &self.members {
+            member.draw();
+        }
+    }
+
+    pub fn stats(&self) -> FlockStats {
+        let mut stats = FlockStats {
+            size: self.members.len(),
+            ever_infected: self.ever_infected.iter().filter(|&&e| e).count(),
+            ..FlockStats::default()
+        };
+        for member in &self.members {
+            match member.disease_state {
+                DiseaseState::Susceptible => stats.susceptible += 1,
+                DiseaseState::Exposed => stats.exposed += 1,
+                DiseaseState::Infected => stats.infected += 1,
+                DiseaseState::Recovered => stats.recovered += 1,
+                // My Boids always recover
+                DiseaseState::Dead => {}
+            }
+        }
+        stats
+    }
+}
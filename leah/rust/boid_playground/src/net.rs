@@ -0,0 +1,225 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use macroquad::prelude::*;
+use boid_math::rng::Rng;
+use boid_simulation::boid::Boid;
+use boid_simulation::sir::{DiseaseModel, DiseaseState};
+
+use crate::my_boid::{MyBoid, MyBoidParams};
+use crate::versus::Versus;
+
+/// One end of a two-player session. The host runs the authoritative
+/// simulation and sends a `Snapshot` every frame; the client sends its
+/// `MyBoidParams` and only renders.
+///
+/// Messages are single text lines. A background thread reads lines so the
+/// game loop never blocks on the socket.
+pub struct Peer {
+    stream: TcpStream,
+    incoming: Receiver<String>,
+}
+
+impl Peer {
+    /// Wait for one client on `addr`.
+    pub fn host(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        println!("Waiting for a player to join on {}...", listener.local_addr()?);
+        let (stream, remote) = listener.accept()?;
+        println!("Player joined from {}", remote);
+        Self::from_stream(stream)
+    }
+
+    pub fn join(addr: &str) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(addr)?)
+    }
+
+    fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { stream, incoming })
+    }
+
+    pub fn send(&mut self, line: &str) -> io::Result<()> {
+        self.stream.write_all(line.as_bytes())?;
+        self.stream.write_all(b"\n")
+    }
+
+    /// The newest message received since the last call, dropping older ones;
+    /// only the latest state matters.
+    pub fn latest(&self) -> Option<String> {
+        self.incoming.try_iter().last()
+    }
+}
+
+fn state_code(state: DiseaseState) -> char {
+    match state {
+        DiseaseState::Susceptible => 'S',
+        DiseaseState::Exposed => 'E',
+        DiseaseState::Infected => 'I',
+        DiseaseState::Recovered => 'R',
+        DiseaseState::Dead => 'D',
+    }
+}
+
+fn parse_state(code: &str) -> Option<DiseaseState> {
+    match code {
+        "S" => Some(DiseaseState::Susceptible),
+        "E" => Some(DiseaseState::Exposed),
+        "I" => Some(DiseaseState::Infected),
+        "R" => Some(DiseaseState::Recovered),
+        "D" => Some(DiseaseState::Dead),
+        _ => None,
+    }
+}
+
+fn model_code(model: DiseaseModel) -> &'static str {
+    match model {
+        DiseaseModel::SIR => "SIR",
+        DiseaseModel::SIS => "SIS",
+        DiseaseModel::SEIR => "SEIR",
+    }
+}
+
+fn parse_model(code: &str) -> Option<DiseaseModel> {
+    match code {
+        "SIR" => Some(DiseaseModel::SIR),
+        "SIS" => Some(DiseaseModel::SIS),
+        "SEIR" => Some(DiseaseModel::SEIR),
+        _ => None,
+    }
+}
+
+/// `P` followed by every `MyBoidParams` field.
+pub fn encode_params(p: &MyBoidParams) -> String {
+    format!(
+        "P {} {} {} {} {} {} {} {} {} {} {}",
+        p.perception_radius, p.separation_radius, p.separation_weight, p.alignment_weight,
+        p.cohesion_weight, p.max_speed, p.max_force, p.affinity_susceptible, p.affinity_exposed,
+        p.affinity_infected, p.affinity_recovered,
+    )
+}
+
+pub fn decode_params(line: &str) -> Option<MyBoidParams> {
+    let mut fields = line.strip_prefix("P ")?.split(' ').map(|f| f.parse::<f32>().ok());
+    let mut next = || fields.next().flatten();
+    let mut params = MyBoidParams {
+        perception_radius: next()?,
+        separation_radius: next()?,
+        separation_weight: next()?,
+        alignment_weight: next()?,
+        cohesion_weight: next()?,
+        max_speed: next()?,
+        max_force: next()?,
+        affinity_susceptible: next()?,
+        affinity_exposed: next()?,
+        affinity_infected: next()?,
+        affinity_recovered: next()?,
+    };
+    params.normalize();
+    Some(params)
+}
+
+/// Everything the client needs to draw a frame.
+pub struct Snapshot {
+    pub model: DiseaseModel,
+    pub boids: Vec<Boid>,
+    /// Host's boid first, client's second.
+    pub players: [MyBoid; 2],
+    pub versus: Versus,
+}
+
+fn push_agent(out: &mut String, position: Vec2, velocity: Vec2, state: DiseaseState) {
+    out.push_str(&format!(
+        " {:.1} {:.1} {:.2} {:.2} {}",
+        position.x, position.y, velocity.x, velocity.y, state_code(state)
+    ));
+}
+
+/// `S model survival0 survival1 caught0 caught1 n` followed by five fields
+/// (x y vx vy state) per regular boid, then per player.
+pub fn encode_snapshot(model: DiseaseModel, boids: &[Boid], players: [&MyBoid; 2], versus: &Versus) -> String {
+    let mut out = format!(
+        "S {} {:.2} {:.2} {} {} {}",
+        model_code(model), versus.survival[0], versus.survival[1],
+        versus.caught[0] as u8, versus.caught[1] as u8, boids.len()
+    );
+    for b in boids {
+        push_agent(&mut out, b.position, b.velocity, b.disease_state);
+    }
+    for p in players {
+        push_agent(&mut out, p.position, p.velocity, p.disease_state);
+    }
+    out
+}
+
+/// Most boids a snapshot may carry, well above the panel's limit. The count
+/// comes from the peer, so anything larger is rejected rather than trusted.
+const MAX_SNAPSHOT_BOIDS: usize = 10_000;
+
+pub fn decode_snapshot(line: &str) -> Option<Snapshot> {
+    let mut fields = line.strip_prefix("S ")?.split(' ');
+    let model = parse_model(fields.next()?)?;
+    let survival = [fields.next()?.parse().ok()?, fields.next()?.parse().ok()?];
+    let caught = [fields.next()? == "1", fields.next()? == "1"];
+    let count: usize = fields.next()?.parse().ok().filter(|&n| n <= MAX_SNAPSHOT_BOIDS)?;
+
+    let mut agent = || -> Option<(Vec2, Vec2, DiseaseState)> {
+        let mut num = || fields.next()?.parse::<f32>().ok();
+        let position = vec2(num()?, num()?);
+        let velocity = vec2(num()?, num()?);
+        Some((position, velocity, parse_state(fields.next()?)?))
+    };
+    let mut boids = Vec::new();
+    for _ in 0..count {
+        let (position, velocity, state) = agent()?;
+        // The random heading is replaced by the host's at once
+        let mut boid = Boid::new(position.x, position.y, state, &mut Rng::new(0));
+        boid.velocity = velocity;
+        boids.push(boid);
+    }
+    let mut player = || -> Option<MyBoid> {
+        let (position, velocity, disease_state) = agent()?;
+        Some(MyBoid { position, velocity, disease_state, state_timer: 0.0 })
+    };
+    let players = [player()?, player()?];
+    Some(Snapshot { model, boids, players, versus: Versus { survival, caught } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_a_snapshot_line() {
+        let players = " 1.0 2.0 0.50 0.00 S 3.0 4.0 0.00 0.50 I";
+        let snapshot = decode_snapshot(&format!("S SEIR 1.50 0.00 0 1 1 10.0 20.0 1.00 -1.00 E{}", players)).unwrap();
+        assert_eq!(snapshot.model, DiseaseModel::SEIR);
+        assert_eq!(snapshot.boids.len(), 1);
+        assert_eq!(snapshot.boids[0].disease_state, DiseaseState::Exposed);
+        assert_eq!(snapshot.players[1].disease_state, DiseaseState::Infected);
+        assert_eq!(snapshot.versus.caught, [false, true]);
+    }
+
+    #[test]
+    fn rejects_malformed_snapshots() {
+        // A count no allocation could hold
+        assert!(decode_snapshot("S SIR 0 0 0 0 18446744073709551615").is_none());
+        // Over the limit, and more boids claimed than sent
+        assert!(decode_snapshot(&format!("S SIR 0 0 0 0 {}", MAX_SNAPSHOT_BOIDS + 1)).is_none());
+        assert!(decode_snapshot("S SIR 0 0 0 0 5 1.0 2.0 0.0 0.0 S").is_none());
+        assert!(decode_snapshot("S XYZ 0 0 0 0 0").is_none());
+        assert!(decode_snapshot("").is_none());
+    }
+}
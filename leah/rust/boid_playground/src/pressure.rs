@@ -0,0 +1,74 @@
+use macroquad::prelude::*;
+use boid_simulation::boid::Boid;
+use boid_simulation::constants::SCREEN_HEIGHT;
+use boid_simulation::sir::DiseaseState;
+use boid_simulation::simulation::SimParams;
+use boid_simulation::spatial::SpatialGrid;
+
+use crate::my_boid::MyBoid;
+
+const GAUGE_WIDTH: f32 = 220.0;
+const GAUGE_HEIGHT: f32 = 12.0;
+
+/// How risky My Boid's current position is. Infection is rolled once per
+/// frame against every infected boid in range, so the hazard is the number
+/// of those boids times the infection probability.
+#[derive(Clone, Copy, Default)]
+pub struct InfectionPressure {
+    /// Infected boids within infection range right now
+    pub infected_nearby: usize,
+    /// Expected infecting contacts this frame (`infected_nearby` × probability)
+    pub hazard: f32,
+    /// Chance of catching it this frame: 1 − (1 − p)^infected_nearby
+    pub chance: f32,
+    /// Sum of `hazard` over the frames spent susceptible this run
+    pub exposure: f32,
+}
+
+impl InfectionPressure {
+    /// Measure the pressure on `me`; only accumulate exposure when the
+    /// simulation actually advanced.
+    pub fn update(&mut self, me: &MyBoid, boids: &[Boid], spatial_grid: &SpatialGrid, params: &SimParams, advanced: bool) {
+        let radius = params.infection_radius_between(me.disease_state, DiseaseState::Infected);
+        self.infected_nearby = spatial_grid
+            .query_nearby_indices(me.position, radius)
+            .into_iter()
+            .filter(|&idx| boids[idx].disease_state == DiseaseState::Infected)
+            .filter(|&idx| (boids[idx].position - me.position).length() < radius)
+            .count();
+        let p = params.infection_probability;
+        self.hazard = self.infected_nearby as f32 * p;
+        self.chance = 1.0 - (1.0 - p).powi(self.infected_nearby as i32);
+        if advanced && me.disease_state == DiseaseState::Susceptible {
+            self.exposure += self.hazard;
+        }
+    }
+}
+
+/// Gauge above the status line: bar fill is this frame's chance of infection,
+/// shading from green to red.
+pub fn draw_pressure_gauge(pressure: &InfectionPressure, me: &MyBoid) {
+    let x = 20.0;
+    let y = SCREEN_HEIGHT - 72.0;
+    let label = match me.disease_state {
+        DiseaseState::Susceptible => format!(
+            "Infection pressure: {} infected in range, hazard {:.2}",
+            pressure.infected_nearby, pressure.hazard
+        ),
+        _ => "Infection pressure: already caught".to_owned(),
+    };
+    draw_text(&label, x, y - 6.0, 18.0, WHITE);
+
+    let fill = if me.disease_state == DiseaseState::Susceptible { pressure.chance.clamp(0.0, 1.0) } else { 0.0 };
+    let color = Color::new(fill.sqrt(), 1.0 - fill.sqrt(), 0.1, 0.9);
+    draw_rectangle(x, y, GAUGE_WIDTH, GAUGE_HEIGHT, Color::new(0.2, 0.2, 0.2, 0.8));
+    draw_rectangle(x, y, GAUGE_WIDTH * fill, GAUGE_HEIGHT, color);
+    draw_rectangle_lines(x, y, GAUGE_WIDTH, GAUGE_HEIGHT, 1.0, GRAY);
+    draw_text(
+        &format!("{:.0}% this frame   exposure {:.1}", fill * 100.0, pressure.exposure),
+        x + GAUGE_WIDTH + 10.0,
+        y + GAUGE_HEIGHT - 1.0,
+        18.0,
+        WHITE,
+    );
+}
@@ -0,0 +1,55 @@
+use macroquad::prelude::*;
+use boid_simulation::constants::SCREEN_WIDTH;
+use boid_simulation::sir::DiseaseState;
+
+use crate::my_boid::MyBoid;
+
+/// Two-player scoring: how long each player's boid has stayed susceptible.
+/// A player is caught the moment their boid leaves `Susceptible`, even if it
+/// later recovers.
+#[derive(Clone, Copy, Default)]
+pub struct Versus {
+    pub survival: [f32; 2],
+    pub caught: [bool; 2],
+}
+
+impl Versus {
+    pub fn update(&mut self, players: [&MyBoid; 2], dt: f32) {
+        for (k, player) in players.iter().enumerate() {
+            if self.caught[k] {
+                continue;
+            }
+            if player.disease_state == DiseaseState::Susceptible {
+                self.survival[k] += dt;
+            } else {
+                self.caught[k] = true;
+            }
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.caught.iter().all(|&c| c)
+    }
+}
+
+/// Scoreboard across the top of the screen, from the point of view of player
+/// `me` (0 on the host, 1 on the client).
+pub fn draw_scoreboard(versus: &Versus, me: usize) {
+    let label = |k: usize| if k == me { "You" } else { "Rival" };
+    let status = |k: usize| if versus.caught[k] { " (caught)" } else { "" };
+    let mut text = format!(
+        "{}: {:.1}s{}   |   {}: {:.1}s{}",
+        label(0), versus.survival[0], status(0), label(1), versus.survival[1], status(1)
+    );
+    if versus.is_over() {
+        let winner = if versus.survival[0] >= versus.survival[1] { 0 } else { 1 };
+        text.push_str(&format!("   —   {} wins! (Enter on host to restart)", label(winner)));
+    }
+    let dims = measure_text(&text, None, 24, 1.0);
+    draw_text(&text, (SCREEN_WIDTH - dims.width) / 2.0, 30.0, 24.0, YELLOW);
+}
+
+/// Name tag above a player's boid.
+pub fn draw_player_tag(player: &MyBoid, label: &str) {
+    draw_text(label, player.position.x - 12.0, player.position.y - 26.0, 18.0, YELLOW);
+}
@@ -14,6 +14,8 @@ fi
 TARGET_DIR="../../../../leahchilders-portfolio/web_app/public/wasm"
 mkdir -p "$TARGET_DIR"
 cp target/wasm32-unknown-unknown/release/boid_simulation.wasm "$TARGET_DIR/"
+# Share-link plugin; the page must load it after mq_js_bundle.js
+cp web/share_plugin.js "$TARGET_DIR/"
 
 echo "Build complete! File copied to $TARGET_DIR"
 echo "File size: $(du -h target/wasm32-unknown-unknown/release/boid_simulation.wasm | cut -f1)"
@@ -0,0 +1,401 @@
+//! The app's frame loop, shared by the native binary and the WASM build so
+//! both run the same simulation, panels and overlays.
+
+use macroquad::prelude::*;
+use boid_math::rng::Rng;
+use boid_math::timeseries::{REffEstimator, TickRow, TimeSeriesWriter};
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::{boid, settings, share, warnings};
+use crate::constants::*;
+use crate::clock::SimClock;
+use crate::sir::{count_disease_states, DiseaseModel};
+use crate::simulation::{SimParams, assign_apps, initialize_boids, resize_boids, step};
+use crate::visualization::PopulationHistory;
+use crate::spatial::SpatialGrid;
+use crate::density::DensityStats;
+use crate::ensemble::Ensemble;
+use crate::calibration::{Calibration, draw_cursor_overlay};
+use crate::exposure::ExposureNotifier;
+use crate::experiment::{Experiment, GroupHistory, GROUP_GRAPH_HEIGHT};
+use crate::keybindings::{Action, Keybindings};
+use crate::settings::UserSettings;
+use crate::ui::{UIState, render_parameter_panel, render_graph_toggle, render_collapsed_params_button, render_density_panel, render_help_panel, render_experiment_panel, render_calibration_panel};
+
+const ACTIONS: [Action; 11] = [
+    Action::Restart,
+    Action::Pause,
+    Action::ToggleParams,
+    Action::ToggleGraph,
+    Action::ToggleDensity,
+    Action::ToggleRings,
+    Action::ToggleExperiment,
+    Action::ToggleCalibration,
+    Action::ToggleExport,
+    Action::ToggleEnsemble,
+    Action::Help,
+];
+
+/// Per-tick CSV export in progress: the writer, where it goes, and the
+/// R_eff estimate fed from the same ticks
+struct TickExport {
+    writer: TimeSeriesWriter<BufWriter<File>>,
+    path: String,
+    r_eff: REffEstimator,
+}
+
+impl TickExport {
+    /// Start `ticks-<unix time>.csv` in the working directory
+    fn start() -> Result<Self, String> {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = format!("ticks-{}.csv", stamp);
+        let writer = TimeSeriesWriter::create(std::path::Path::new(&path))
+            .map_err(|err| format!("failed to create {}: {}", path, err))?;
+        Ok(Self { writer, path, r_eff: REffEstimator::default() })
+    }
+
+    /// `cumulative_infections` and `corpses_removed` as kept by the main loop
+    fn record(&mut self, boids: &[boid::Boid], time: f32, cumulative_infections: usize, corpses_removed: usize) -> std::io::Result<()> {
+        let (s, e, i, r, d) = count_disease_states(boids);
+        let speed: f32 = boids.iter().map(|b| b.velocity.length()).sum();
+        self.writer.write(&TickRow {
+            time,
+            susceptible: s,
+            exposed: e,
+            infected: i,
+            recovered: r,
+            dead: d + corpses_removed,
+            r_eff: self.r_eff.record(time, cumulative_infections, e + i),
+            mean_speed: speed / boids.len().max(1) as f32,
+        })
+    }
+
+    /// Flush the file; the status message to show
+    fn finish(self) -> String {
+        let rows = self.writer.rows();
+        match self.writer.finish() {
+            Ok(_) => format!("Saved {} ({} rows)", self.path, rows),
+            Err(err) => format!("Failed to write {}: {}", self.path, err),
+        }
+    }
+}
+
+/// What differs between the builds that run the app.
+pub struct AppConfig {
+    /// Parameters of the first run, e.g. from a share link
+    pub params: SimParams,
+    pub ui_state: UIState,
+    /// Keep the workspace in `settings::SETTINGS_FILE` and offer CSV export;
+    /// off where there is no file system (WASM)
+    pub files: bool,
+}
+
+/// The frame loop of the app, shared by the native binary (`main.rs`) and
+/// the WASM entry point (`lib.rs`). Never returns.
+pub async fn run(config: AppConfig) {
+    let AppConfig { mut params, mut ui_state, files } = config;
+    // Every random draw of a run comes from this stream, so a run is fixed
+    // by `params.seed`
+    let mut rng = Rng::new(params.seed);
+    println!("seed {}", params.seed);
+    let mut boids = initialize_boids(&params, &mut rng);
+    let mut spatial_grid = SpatialGrid::new(50.0); // Cell size based on perception radius
+    let mut history = PopulationHistory::new();
+    // Last workspace written to the settings file, to save only on change
+    let mut saved_settings = UserSettings::capture(&ui_state);
+    if files {
+        saved_settings = UserSettings::load(saved_settings);
+        saved_settings.restore(&mut ui_state);
+    }
+    // A link may set the interval too
+    history.set_sample_interval(params.sample_interval);
+    let mut density_stats = DensityStats::default();
+    let mut calibration = Calibration::default();
+    let mut notifier = ExposureNotifier::default();
+    let mut app_uptake = params.app_uptake;
+    let mut experiment = Experiment::default();
+    let mut group_history = GroupHistory::default();
+    let mut clock = SimClock::default();
+    let mut cumulative_infections = count_disease_states(&boids).2;
+    // Dead boids already faded out, which `count_disease_states` no longer sees
+    let mut corpses_removed = 0;
+    let bindings = Keybindings::load();
+    // Export needs somewhere to write
+    let actions: Vec<Action> = ACTIONS.into_iter().filter(|&a| files || a != Action::ToggleExport).collect();
+    let mut paused = false;
+    let mut export: Option<TickExport> = None;
+    // Outcome of the last export, shown in the status line until the next
+    let mut export_message: Option<String> = None;
+    // Replicas of the current run for the graph's spread band, while on
+    let mut ensemble: Option<Ensemble> = None;
+
+    loop {
+        clear_background(BLACK);
+        let dt = get_frame_time();
+
+        // Render UI and get controls
+        let mut should_restart = false;
+        let mut replay_seed = false;
+        let mut boid_count_changed = false;
+        let mut model_changed = false;
+        let mut groups_changed = false;
+        let mut share_requested = false;
+
+        // Handle keyboard shortcuts
+        if bindings.pressed(Action::ToggleParams) {
+            ui_state.params_collapsed = !ui_state.params_collapsed;
+        }
+        if bindings.pressed(Action::ToggleGraph) {
+            ui_state.show_graph = !ui_state.show_graph;
+        }
+        if bindings.pressed(Action::ToggleDensity) {
+            ui_state.show_density = !ui_state.show_density;
+        }
+        if bindings.pressed(Action::ToggleRings) {
+            ui_state.show_rings = !ui_state.show_rings;
+        }
+        if bindings.pressed(Action::ToggleExperiment) {
+            ui_state.show_experiment = !ui_state.show_experiment;
+        }
+        if bindings.pressed(Action::ToggleCalibration) {
+            ui_state.show_calibration = !ui_state.show_calibration;
+        }
+        if bindings.pressed(Action::Help) {
+            ui_state.show_help = !ui_state.show_help;
+        }
+        if bindings.pressed(Action::Pause) {
+            paused = !paused;
+        }
+        if files && bindings.pressed(Action::ToggleExport) {
+            export = match export.take() {
+                Some(done) => {
+                    export_message = Some(done.finish());
+                    None
+                }
+                None => match TickExport::start() {
+                    Ok(started) => Some(started),
+                    Err(err) => {
+                        export_message = Some(err);
+                        None
+                    }
+                },
+            };
+        }
+        if bindings.pressed(Action::ToggleEnsemble) {
+            ensemble = match ensemble {
+                Some(_) => None,
+                None => Some(Ensemble::new(&params, &experiment, params.seed)),
+            };
+        }
+
+        let graph_x = SCREEN_WIDTH - GRAPH_WIDTH - 10.0;
+        let graph_y = SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0;
+
+        egui_macroquad::ui(|egui_ctx| {
+            egui_ctx.set_visuals(ui_state.theme.visuals());
+            render_graph_toggle(egui_ctx, &mut ui_state, graph_x, graph_y);
+            let controls = render_parameter_panel(egui_ctx, &mut params, &mut ui_state);
+            render_collapsed_params_button(egui_ctx, &mut ui_state);
+            render_density_panel(egui_ctx, &mut ui_state, &density_stats);
+            render_help_panel(egui_ctx, &mut ui_state, &bindings, &actions);
+            groups_changed = render_experiment_panel(egui_ctx, &mut ui_state, &mut experiment);
+            let mean_neighbors = (!boids.is_empty()).then_some(density_stats.mean_neighbors);
+            let prevalence = count_disease_states(&boids).2 as f32 / boids.len().max(1) as f32;
+            render_calibration_panel(egui_ctx, &mut ui_state, &calibration, &params, mean_neighbors, prevalence);
+            should_restart = controls.should_restart;
+            replay_seed = controls.replay_seed;
+            boid_count_changed = controls.boid_count_changed;
+            model_changed = controls.model_changed;
+            share_requested = controls.share_requested;
+            ui_state.remember_panel_positions(egui_ctx);
+        });
+
+        if share_requested {
+            share::copy_link(&share::to_query(&params));
+        }
+
+        // Save the workspace once a change settles, not on every frame of a drag
+        let settings = UserSettings::capture(&ui_state);
+        let dragging = [MouseButton::Left, MouseButton::Right, MouseButton::Middle].into_iter().any(is_mouse_button_down);
+        if files && settings != saved_settings && !dragging {
+            if let Err(err) = settings.save() {
+                eprintln!("failed to save {}: {}", settings::SETTINGS_FILE, err);
+            }
+            saved_settings = settings;
+        }
+
+        if bindings.pressed(Action::Restart) || should_restart || replay_seed || model_changed || groups_changed {
+            // A fresh run unless the seed was asked for; printed so any run can be replayed
+            if !replay_seed {
+                params.seed = rng.next_u64();
+            }
+            rng = Rng::new(params.seed);
+            println!("restart: seed {}", params.seed);
+            boids = initialize_boids(&params, &mut rng);
+            experiment.assign(&mut boids, &mut rng);
+            history.set_sample_interval(params.sample_interval);
+            history.clear();
+            group_history.clear();
+            notifier.clear();
+            clock.reset();
+            cumulative_infections = count_disease_states(&boids).2;
+            corpses_removed = 0;
+            // An export covers one run
+            if let Some(done) = export.take() {
+                export_message = Some(done.finish());
+            }
+            // Replacing the ensemble drops the old replicas with it
+            if ensemble.is_some() {
+                ensemble = Some(Ensemble::new(&params, &experiment, params.seed));
+            }
+        } else if boid_count_changed {
+            // Keep the epidemic going; only add or remove boids
+            let old_len = boids.len();
+            resize_boids(&mut boids, &params, &mut rng);
+            // New boids are appended; deal only them into groups
+            let kept = old_len.min(boids.len());
+            experiment.assign(&mut boids[kept..], &mut rng);
+            notifier.clear();
+        }
+        if params.app_uptake != app_uptake {
+            // Hand the app out again mid-run rather than restarting
+            assign_apps(&mut boids, &params, &mut rng);
+            app_uptake = params.app_uptake;
+        }
+
+        // While paused the boids hold still but panels and hotkeys keep working
+        if !paused {
+            clock.advance(dt);
+
+            let group_params = experiment.params_for(&params);
+            let (new_infections, gone) = step(&mut boids, &params, &group_params, &mut spatial_grid, &mut notifier, &clock, &mut rng);
+            cumulative_infections += new_infections;
+            corpses_removed += gone;
+
+            if let Some(running) = &mut export
+                && let Err(err) = running.record(&boids, clock.time(), cumulative_infections, corpses_removed)
+            {
+                export_message = Some(format!("Failed to write {}: {}", running.path, err));
+                export = None;
+            }
+
+            if clock.crossed(history.sample_interval()) {
+                let (s, e, i, r, d) = count_disease_states(&boids);
+                history.add(s, e, i, r, d + corpses_removed);
+                history.add_cumulative(cumulative_infections);
+                if experiment.enabled {
+                    group_history.add(&boids, experiment.groups.len());
+                }
+                if ui_state.show_density || ui_state.show_calibration {
+                    density_stats = DensityStats::compute(&boids, &params, &spatial_grid);
+                }
+            }
+
+            if let Some(ensemble) = &mut ensemble {
+                ensemble.advance();
+            }
+        }
+
+        for boid in &boids {
+            boid.draw(&params, ui_state.palette);
+        }
+        if experiment.enabled {
+            for boid in &boids {
+                if let Some(group) = experiment.groups.get(boid.group) {
+                    boid.draw_outline(group.color());
+                }
+            }
+        }
+        if ui_state.show_rings {
+            for boid in &boids {
+                boid.draw_ring(ui_state.palette);
+            }
+        }
+        if ui_state.show_calibration {
+            let cursor = Vec2::from(mouse_position());
+            calibration.update(dt, cursor, &boids, &params, &spatial_grid);
+            draw_cursor_overlay(&calibration, cursor, &params);
+        }
+
+        // Only draw graph if visible
+        if ui_state.show_graph {
+            history.set_window(ui_state.graph_window);
+            history.set_palette(ui_state.palette);
+            history.set_time_unit(params.time_unit);
+            history.draw(
+                SCREEN_WIDTH - GRAPH_WIDTH - 10.0,
+                SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0,
+                params.num_boids as f32,
+                params.model,
+            );
+            if let Some(ensemble) = &ensemble {
+                history.draw_band(SCREEN_WIDTH - GRAPH_WIDTH - 10.0, SCREEN_HEIGHT - GRAPH_HEIGHT - 10.0, ensemble);
+            }
+            if experiment.enabled {
+                group_history.draw(
+                    SCREEN_WIDTH - GRAPH_WIDTH - 10.0,
+                    SCREEN_HEIGHT - GRAPH_HEIGHT - GROUP_GRAPH_HEIGHT - 20.0,
+                    &experiment.groups,
+                );
+            }
+        }
+
+        let (s, e, i, r, d) = count_disease_states(&boids);
+        // In SIS boids can be counted more than once, so the rate can exceed 100%
+        let attack_rate = cumulative_infections as f32 / boids.len().max(1) as f32 * 100.0;
+        let status_text = match params.model {
+            DiseaseModel::SIR | DiseaseModel::SIS => {
+                format!("S: {} | I: {} | R: {} | Attack rate: {:.1}%", s, i, r, attack_rate)
+            }
+            DiseaseModel::SEIR => {
+                format!("S: {} | E: {} | I: {} | R: {} | Attack rate: {:.1}%", s, e, i, r, attack_rate)
+            }
+        };
+        let deaths = d + corpses_removed;
+        let status_text = if params.death_probability > 0.0 || deaths > 0 {
+            format!("{} | D: {}", status_text, deaths)
+        } else {
+            status_text
+        };
+        let status_text = format!("t = {} | {}", params.time_unit.format(clock.time()), status_text);
+        let status_text = if params.app_uptake > 0.0 {
+            format!(
+                "{} | Diagnosed: {} | Notified: {} | Quarantined: {}",
+                status_text, notifier.diagnosed, notifier.notified, notifier.quarantined
+            )
+        } else {
+            status_text
+        };
+        let status_text = match &ensemble {
+            Some(ensemble) => format!("{} | Ensemble: {}/{}", status_text, ensemble.completed(), ensemble.replicas()),
+            None => status_text,
+        };
+        let status_text = if paused { format!("{} | PAUSED", status_text) } else { status_text };
+        let status_text = match (&export, &export_message) {
+            (Some(running), _) => format!("{} | CSV: {} rows", status_text, running.writer.rows()),
+            (None, Some(message)) => format!("{} | {}", status_text, message),
+            (None, None) => status_text,
+        };
+        // Degenerate parameters stack above the status line, problem then fix
+        let warning_color = Color::from_rgba(255, 170, 60, 255);
+        for (k, warning) in warnings::check(&params).iter().rev().enumerate() {
+            let y = SCREEN_HEIGHT - 50.0 - k as f32 * 40.0;
+            draw_text(&format!("Warning: {}", warning.problem), 20.0, y - 18.0, 18.0, warning_color);
+            draw_text(&format!("  Fix: {}", warning.fix), 20.0, y, 18.0, warning_color);
+        }
+        draw_text(
+            &status_text,
+            20.0,
+            SCREEN_HEIGHT - 20.0,
+            24.0,
+            WHITE,
+        );
+
+        egui_macroquad::draw();
+
+        next_frame().await
+    }
+}
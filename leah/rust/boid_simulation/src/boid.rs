@@ -1,7 +1,10 @@
 use macroquad::prelude::*;
-use macroquad::prelude::rand;
+use boid_math::limit;
+use boid_math::rng::Rng;
 use crate::constants::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use boid_math::params::clamp_separation;
 use crate::simulation::SimParams;
+use crate::settings::Palette;
 use crate::sir::{DiseaseState, DiseaseModel};
 
 #[derive(Clone)]
@@ -10,35 +13,73 @@ pub struct Boid {
     pub velocity: Vec2,
     pub disease_state: DiseaseState,
     pub state_timer: f32,
+    /// Carries the exposure notification app
+    pub has_app: bool,
+    /// Diagnosed during the current infection; cleared on reinfection
+    pub diagnosed: bool,
+    /// Seconds of quarantine left; while positive the boid holds still and
+    /// neither catches nor spreads infection
+    pub quarantine_timer: f32,
+    /// Experiment group, an index into `Experiment::groups`; 0 when no
+    /// experiment is running
+    pub group: usize,
 }
 
 impl Boid {
-    pub fn new(x: f32, y: f32, disease_state: DiseaseState) -> Self {
-        let angle = rand::gen_range(0.0, std::f32::consts::TAU);
-        let speed = rand::gen_range(1.5, 2.5);
+    pub fn new(x: f32, y: f32, disease_state: DiseaseState, rng: &mut Rng) -> Self {
+        let angle = rng.range(0.0, std::f32::consts::TAU);
+        let speed = rng.range(1.5, 2.5);
         Self {
             position: vec2(x, y),
             velocity: vec2(angle.cos() * speed, angle.sin() * speed),
             disease_state,
             state_timer: 0.0,
+            has_app: false,
+            diagnosed: false,
+            quarantine_timer: 0.0,
+            group: 0,
         }
     }
 
-    pub fn update(&mut self, neighbors: &[(Vec2, Vec2)], params: &SimParams) {
+    pub fn quarantined(&self) -> bool {
+        self.quarantine_timer > 0.0
+    }
+
+    /// Fraction of a corpse still visible, from 1 at death to 0 when
+    /// `corpse_fade_time` has passed; always 1 while corpses are kept.
+    pub fn corpse_fade(&self, params: &SimParams) -> f32 {
+        if !params.remove_corpses {
+            return 1.0;
+        }
+        (1.0 - self.state_timer / params.corpse_fade_time.max(0.01)).max(0.0)
+    }
+
+    pub fn update(&mut self, neighbors: &[(Vec2, Vec2, DiseaseState)], params: &SimParams) {
+        if self.disease_state == DiseaseState::Dead {
+            return;
+        }
+        let perception_radius = params.perception_radius_for(self.disease_state);
+        let separation_radius = clamp_separation(params.separation_radius, perception_radius);
+
         let mut separation = vec2(0.0, 0.0);
         let mut alignment = vec2(0.0, 0.0);
         let mut cohesion = vec2(0.0, 0.0);
+        let mut affinity_force = vec2(0.0, 0.0);
 
         let mut separation_count = 0;
         let mut alignment_count = 0;
         let mut cohesion_count = 0;
+        let mut affinity_count = 0;
 
-        for &(other_pos, other_vel) in neighbors {
+        for &(other_pos, other_vel, other_state) in neighbors {
+            if other_state == DiseaseState::Dead {
+                continue;
+            }
             let diff = self.position - other_pos;
             let dist = diff.length();
 
-            if dist > 0.1 && dist < params.perception_radius {
-                if dist < params.separation_radius {
+            if dist > 0.1 && dist < perception_radius {
+                if dist < separation_radius {
                     separation += diff / dist;
                     separation_count += 1;
                 }
@@ -48,34 +89,49 @@ impl Boid {
 
                 cohesion += other_pos;
                 cohesion_count += 1;
+
+                // Affinity: positive attracts (steer toward), negative repels (steer away)
+                let a = params.affinity_for(other_state);
+                if a.abs() > 0.001 {
+                    let toward = -diff / dist;
+                    affinity_force += toward * a / dist.max(1.0);
+                    affinity_count += 1;
+                }
             }
         }
 
         if separation_count > 0 {
             separation /= separation_count as f32;
             separation = separation.normalize_or_zero() * params.max_speed - self.velocity;
-            separation = limit_vec(separation, params.max_force);
+            separation = limit(separation, params.max_force);
         }
 
         if alignment_count > 0 {
             alignment /= alignment_count as f32;
             alignment = alignment.normalize_or_zero() * params.max_speed - self.velocity;
-            alignment = limit_vec(alignment, params.max_force);
+            alignment = limit(alignment, params.max_force);
         }
 
         if cohesion_count > 0 {
             cohesion /= cohesion_count as f32;
             cohesion = (cohesion - self.position).normalize_or_zero() * params.max_speed - self.velocity;
-            cohesion = limit_vec(cohesion, params.max_force);
+            cohesion = limit(cohesion, params.max_force);
+        }
+
+        if affinity_count > 0 {
+            affinity_force /= affinity_count as f32;
+            affinity_force = affinity_force.normalize_or_zero() * params.max_speed - self.velocity;
+            affinity_force = limit(affinity_force, params.max_force);
         }
 
         let mut acceleration = vec2(0.0, 0.0);
         acceleration += separation * params.separation_weight;
         acceleration += alignment * params.alignment_weight;
         acceleration += cohesion * params.cohesion_weight;
+        acceleration += affinity_force;
 
         self.velocity += acceleration;
-        self.velocity = limit_vec(self.velocity, params.max_speed);
+        self.velocity = limit(self.velocity, params.max_speed);
         self.position += self.velocity;
 
         // Wrap around entire screen (boids go behind UI elements)
@@ -93,48 +149,31 @@ impl Boid {
         }
     }
 
-    pub fn update_disease_state(&mut self, params: &SimParams, dt: f32) {
+    pub fn update_disease_state(&mut self, params: &SimParams, dt: f32, rng: &mut Rng) {
         self.state_timer += dt;
 
         match params.model {
             DiseaseModel::SIR => {
                 // SIR: Susceptible -> Infected -> Recovered
-                match self.disease_state {
-                    DiseaseState::Infected => {
-                        if self.state_timer >= params.recovery_time {
-                            self.disease_state = DiseaseState::Recovered;
-                            self.state_timer = 0.0;
-                        }
-                    }
-                    _ => {}
+                if self.disease_state == DiseaseState::Infected && self.state_timer >= params.recovery_time {
+                    self.end_infection(params, DiseaseState::Recovered, rng);
                 }
             }
             DiseaseModel::SIS => {
                 // SIS: Susceptible -> Infected -> Susceptible
-                match self.disease_state {
-                    DiseaseState::Infected => {
-                        if self.state_timer >= params.recovery_time {
-                            self.disease_state = DiseaseState::Susceptible;
-                            self.state_timer = 0.0;
-                        }
-                    }
-                    _ => {}
+                if self.disease_state == DiseaseState::Infected && self.state_timer >= params.recovery_time {
+                    self.end_infection(params, DiseaseState::Susceptible, rng);
                 }
             }
             DiseaseModel::SEIR => {
                 // SEIR: Susceptible -> Exposed -> Infected -> Recovered
                 match self.disease_state {
-                    DiseaseState::Exposed => {
-                        if self.state_timer >= params.incubation_time {
-                            self.disease_state = DiseaseState::Infected;
-                            self.state_timer = 0.0;
-                        }
+                    DiseaseState::Exposed if self.state_timer >= params.incubation_time => {
+                        self.disease_state = DiseaseState::Infected;
+                        self.state_timer = 0.0;
                     }
-                    DiseaseState::Infected => {
-                        if self.state_timer >= params.recovery_time {
-                            self.disease_state = DiseaseState::Recovered;
-                            self.state_timer = 0.0;
-                        }
+                    DiseaseState::Infected if self.state_timer >= params.recovery_time => {
+                        self.end_infection(params, DiseaseState::Recovered, rng);
                     }
                     _ => {}
                 }
@@ -142,7 +181,17 @@ impl Boid {
         }
     }
 
-    pub fn draw(&self) {
+    /// Leave the infected state: dead with `death_probability`, otherwise
+    /// `survived`.
+    fn end_infection(&mut self, params: &SimParams, survived: DiseaseState, rng: &mut Rng) {
+        // Only roll when deaths are on, so runs without them draw the same
+        // random numbers as before
+        let dies = params.death_probability > 0.0 && rng.chance(params.death_probability);
+        self.disease_state = if dies { DiseaseState::Dead } else { survived };
+        self.state_timer = 0.0;
+    }
+
+    fn triangle(&self) -> (Vec2, Vec2, Vec2) {
         let angle = self.velocity.y.atan2(self.velocity.x);
         let size = 8.0;
 
@@ -158,22 +207,34 @@ impl Boid {
             self.position.x + (angle - 2.5).cos() * size * 0.5,
             self.position.y + (angle - 2.5).sin() * size * 0.5,
         );
+        (p1, p2, p3)
+    }
 
+    pub fn draw(&self, params: &SimParams, palette: Palette) {
+        let (p1, p2, p3) = self.triangle();
         let color = match self.disease_state {
-            DiseaseState::Susceptible => WHITE,
-            DiseaseState::Exposed => Color::from_rgba(255, 200, 0, 255), // Orangey-yellow
-            DiseaseState::Infected => RED,
-            DiseaseState::Recovered => BLUE,
+            DiseaseState::Dead => Color::from_rgba(110, 110, 110, (255.0 * self.corpse_fade(params)) as u8),
+            state => palette.color(state),
         };
 
         draw_triangle(p1, p2, p3, color);
+        if self.quarantined() {
+            draw_rectangle_lines(self.position.x - 7.0, self.position.y - 7.0, 14.0, 14.0, 1.0, GRAY);
+        }
+    }
+
+    /// Outline in `color`, e.g. the boid's experiment group.
+    pub fn draw_outline(&self, color: Color) {
+        let (p1, p2, p3) = self.triangle();
+        draw_triangle_lines(p1, p2, p3, 1.5, color);
     }
-}
 
-fn limit_vec(v: Vec2, max: f32) -> Vec2 {
-    if v.length() > max {
-        v.normalize() * max
-    } else {
-        v
+    /// Thin ring in the state's color around exposed and infected boids.
+    pub fn draw_ring(&self, palette: Palette) {
+        let color = match self.disease_state {
+            DiseaseState::Exposed | DiseaseState::Infected => palette.color(self.disease_state),
+            DiseaseState::Susceptible | DiseaseState::Recovered | DiseaseState::Dead => return,
+        };
+        draw_circle_lines(self.position.x, self.position.y, 12.0, 1.0, Color { a: 160.0 / 255.0, ..color });
     }
 }
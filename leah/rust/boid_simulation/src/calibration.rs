@@ -0,0 +1,121 @@
+use std::f32::consts::PI;
+use macroquad::prelude::*;
+use crate::boid::Boid;
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::simulation::SimParams;
+use crate::sir::DiseaseState;
+use crate::spatial::SpatialGrid;
+
+/// What the current parameters mean at one density of boids.
+#[derive(Clone, Copy, Debug)]
+pub struct DensityCase {
+    pub label: &'static str,
+    /// Boids per 100×100 px
+    pub density: f32,
+    /// Boids inside a boid's infection radius at any moment
+    pub contacts: f32,
+    /// Boids entering a boid's infection radius per second, assuming random
+    /// headings; an aligned flock meets far fewer
+    pub new_contacts_per_second: f32,
+    /// Expected seconds for a susceptible boid to be infected if every
+    /// contact were infectious; `None` when it has no contacts
+    pub time_to_infection: Option<f32>,
+}
+
+/// Expected contact and infection rates under the current parameters, so
+/// slider changes can be judged before a restart. Both movement and the
+/// infection roll happen once per frame, so per-second figures depend on the
+/// frame rate; a smoothed frame length keeps them steady.
+pub struct Calibration {
+    frame_dt: f32,
+    /// Boids within infection radius of the cursor and how many are infected
+    pub under_cursor: (usize, usize),
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self { frame_dt: 1.0 / 60.0, under_cursor: (0, 0) }
+    }
+}
+
+impl Calibration {
+    /// Fold in this frame's length and count the boids around `cursor`.
+    pub fn update(&mut self, dt: f32, cursor: Vec2, boids: &[Boid], params: &SimParams, spatial_grid: &SpatialGrid) {
+        if dt > 0.0 {
+            self.frame_dt += (dt - self.frame_dt) * 0.05;
+        }
+        let radius = params.infection_radius_between(DiseaseState::Infected, DiseaseState::Susceptible);
+        // The grid is only rebuilt on unpaused frames, so it can name boids
+        // removed since
+        let inside: Vec<&Boid> = spatial_grid
+            .query_nearby_indices(cursor, radius)
+            .into_iter()
+            .filter_map(|j| boids.get(j))
+            .filter(|boid| (boid.position - cursor).length() < radius)
+            .collect();
+        let infected = inside.iter().filter(|boid| boid.disease_state == DiseaseState::Infected).count();
+        self.under_cursor = (inside.len(), infected);
+    }
+
+    pub fn frames_per_second(&self) -> f32 {
+        1.0 / self.frame_dt.max(f32::EPSILON)
+    }
+
+    /// Infection hazard per second from one infectious contact: the
+    /// per-frame probability compounded over a second of frames.
+    pub fn hazard_per_contact(&self, params: &SimParams) -> f32 {
+        -(1.0 - params.infection_probability.min(0.999_999)).ln() * self.frames_per_second()
+    }
+
+    /// Expected seconds until a susceptible boid with `infectious` infected
+    /// contacts is infected.
+    pub fn time_to_infection(&self, params: &SimParams, infectious: f32) -> Option<f32> {
+        let rate = infectious * self.hazard_per_contact(params);
+        (rate > 0.0).then(|| 1.0 / rate)
+    }
+
+    fn case(&self, label: &'static str, per_px: f32, params: &SimParams) -> DensityCase {
+        let radius = params.infection_radius_between(DiseaseState::Infected, DiseaseState::Susceptible);
+        let contacts = per_px * PI * radius * radius;
+        // Mean relative speed of two boids at full speed on random headings
+        let relative_speed = 4.0 / PI * params.max_speed * self.frames_per_second();
+        DensityCase {
+            label,
+            density: per_px * 10_000.0,
+            contacts,
+            new_contacts_per_second: per_px * 2.0 * radius * relative_speed,
+            time_to_infection: self.time_to_infection(params, contacts),
+        }
+    }
+
+    /// Typical densities: the flock spread evenly over the screen, the
+    /// density the contact panel last measured (if it has), and boids packed
+    /// hexagonally at separation distance as in a tight flock.
+    pub fn cases(&self, params: &SimParams, mean_neighbors: Option<f32>) -> Vec<DensityCase> {
+        let mut cases = vec![self.case("Spread evenly", params.num_boids as f32 / (SCREEN_WIDTH * SCREEN_HEIGHT), params)];
+        if let Some(neighbors) = mean_neighbors {
+            let area = PI * params.perception_radius * params.perception_radius;
+            cases.push(self.case("Measured", neighbors / area.max(1.0), params));
+        }
+        let spacing = params.separation_radius.max(1.0);
+        cases.push(self.case("Packed flock", 2.0 / (3.0f32.sqrt() * spacing * spacing), params));
+        cases
+    }
+}
+
+/// Infection and perception radii around the cursor, with what a susceptible
+/// boid standing there would face.
+pub fn draw_cursor_overlay(calibration: &Calibration, cursor: Vec2, params: &SimParams) {
+    let radius = params.infection_radius_between(DiseaseState::Infected, DiseaseState::Susceptible);
+    draw_circle(cursor.x, cursor.y, radius, Color::from_rgba(255, 80, 80, 40));
+    draw_circle_lines(cursor.x, cursor.y, radius, 1.5, Color::from_rgba(255, 80, 80, 220));
+    draw_circle_lines(cursor.x, cursor.y, params.perception_radius, 1.0, Color::from_rgba(100, 150, 255, 160));
+
+    let (inside, infected) = calibration.under_cursor;
+    let wait = match calibration.time_to_infection(params, infected as f32) {
+        Some(t) => params.time_unit.format(t),
+        None => "never".to_owned(),
+    };
+    let label = format!("{} in range, {} infected: infection in ~{}", inside, infected, wait);
+    draw_text(&label, cursor.x + radius + 6.0, cursor.y - 4.0, 16.0, WHITE);
+}
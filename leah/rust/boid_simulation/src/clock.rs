@@ -0,0 +1,92 @@
+/// Default simulated seconds between population graph samples; see
+/// `SimParams::sample_interval`.
+pub const SAMPLE_INTERVAL: f32 = 1.0 / 6.0;
+/// Range `SimParams::sample_interval` is held to: one frame at 60 FPS at the
+/// finest, so every sample sees a new state.
+pub const MIN_SAMPLE_INTERVAL: f32 = 1.0 / 60.0;
+pub const MAX_SAMPLE_INTERVAL: f32 = 2.0;
+
+/// What one simulated second stands for in labels. Purely a display choice:
+/// every duration in `SimParams` is in simulated seconds, which read as one
+/// of these units each.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum TimeUnit {
+    #[default]
+    Seconds,
+    Hours,
+    Days,
+    Weeks,
+}
+
+impl TimeUnit {
+    pub const ALL: [TimeUnit; 4] = [TimeUnit::Seconds, TimeUnit::Hours, TimeUnit::Days, TimeUnit::Weeks];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeUnit::Seconds => "seconds",
+            TimeUnit::Hours => "hours",
+            TimeUnit::Days => "days",
+            TimeUnit::Weeks => "weeks",
+        }
+    }
+
+    pub fn short(self) -> &'static str {
+        match self {
+            TimeUnit::Seconds => "s",
+            TimeUnit::Hours => "h",
+            TimeUnit::Days => "d",
+            TimeUnit::Weeks => "wk",
+        }
+    }
+
+    /// `time` simulated seconds as e.g. `12.5 d`.
+    pub fn format(self, time: f32) -> String {
+        format!("{:.1} {}", time, self.short())
+    }
+}
+
+/// Simulated time since the last restart. Advanced once per simulated frame
+/// by that frame's `dt`, so everything timed off it (disease timers, graph
+/// samples, exposure notifications) stays in step however fast frames come.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SimClock {
+    time: f32,
+    /// `time` before the last step
+    prev_time: f32,
+    steps: u64,
+    dt: f32,
+}
+
+impl SimClock {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        self.prev_time = self.time;
+        self.time += dt;
+        self.steps += 1;
+        self.dt = dt;
+    }
+
+    /// Simulated seconds since the last restart.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Simulated frames since the last restart.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Length of the last step.
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Whether the last step crossed a multiple of `interval` seconds, for
+    /// doing something every `interval` of simulated time.
+    pub fn crossed(&self, interval: f32) -> bool {
+        (self.time / interval).floor() > (self.prev_time / interval).floor()
+    }
+}
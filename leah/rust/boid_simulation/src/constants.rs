@@ -3,4 +3,5 @@ pub const SCREEN_HEIGHT: f32 = 800.0;
 pub const UI_HEIGHT: f32 = 160.0;
 pub const GRAPH_HEIGHT: f32 = 150.0;
 pub const GRAPH_WIDTH: f32 = 400.0;
-pub const GRAPH_HISTORY: usize = 300;
+/// Graph samples kept before the history is thinned to every other one.
+pub const GRAPH_MAX_SAMPLES: usize = 20_000;
@@ -0,0 +1,54 @@
+use crate::boid::Boid;
+use crate::simulation::SimParams;
+use crate::spatial::SpatialGrid;
+
+/// Counts at or above this value share the last histogram bin.
+pub const HISTOGRAM_BINS: usize = 25;
+
+/// Distribution of per-boid neighbor counts (within perception radius) and
+/// contacts (within infection radius) — the effective contact rate that drives
+/// spread.
+#[derive(Default)]
+pub struct DensityStats {
+    pub neighbor_histogram: [usize; HISTOGRAM_BINS],
+    pub contact_histogram: [usize; HISTOGRAM_BINS],
+    pub mean_neighbors: f32,
+    pub mean_contacts: f32,
+}
+
+impl DensityStats {
+    pub fn compute(boids: &[Boid], params: &SimParams, spatial_grid: &SpatialGrid) -> Self {
+        let mut stats = Self::default();
+        if boids.is_empty() {
+            return stats;
+        }
+
+        let radius = params.perception_radius.max(params.infection_radius);
+        let mut total_neighbors = 0;
+        let mut total_contacts = 0;
+        for (i, boid) in boids.iter().enumerate() {
+            let mut neighbors = 0;
+            let mut contacts = 0;
+            for j in spatial_grid.query_nearby_indices(boid.position, radius) {
+                if i == j {
+                    continue;
+                }
+                let dist = (boid.position - boids[j].position).length();
+                if dist < params.perception_radius {
+                    neighbors += 1;
+                }
+                if dist < params.infection_radius {
+                    contacts += 1;
+                }
+            }
+            stats.neighbor_histogram[neighbors.min(HISTOGRAM_BINS - 1)] += 1;
+            stats.contact_histogram[contacts.min(HISTOGRAM_BINS - 1)] += 1;
+            total_neighbors += neighbors;
+            total_contacts += contacts;
+        }
+
+        stats.mean_neighbors = total_neighbors as f32 / boids.len() as f32;
+        stats.mean_contacts = total_contacts as f32 / boids.len() as f32;
+        stats
+    }
+}
@@ -0,0 +1,112 @@
+//! Replicas of the current run with other seeds, for the spread band on the
+//! population graph.
+
+use macroquad::prelude::get_time;
+use boid_math::ensemble::{Band, Curve, band_at};
+use boid_math::rng::Rng;
+use crate::boid::Boid;
+use crate::clock::SimClock;
+use crate::exposure::ExposureNotifier;
+use crate::experiment::Experiment;
+use crate::sir::count_disease_states;
+use crate::simulation::{SimParams, initialize_boids, step};
+use crate::spatial::SpatialGrid;
+
+/// Replicas per ensemble.
+pub const ENSEMBLE_REPLICAS: usize = 8;
+/// Simulated seconds each replica runs for.
+pub const ENSEMBLE_DURATION: f32 = 120.0;
+/// Fixed step of the replicas; the visible run steps by frame time.
+const ENSEMBLE_DT: f32 = 1.0 / 60.0;
+/// Wall-clock seconds per frame the replicas may take, so they don't drag
+/// the frame rate down.
+const FRAME_BUDGET: f64 = 0.004;
+
+/// One replica: the same state the visible run keeps.
+struct Replica {
+    boids: Vec<Boid>,
+    spatial_grid: SpatialGrid,
+    notifier: ExposureNotifier,
+    clock: SimClock,
+    rng: Rng,
+    curve: Curve,
+}
+
+/// `ENSEMBLE_REPLICAS` runs of one set of parameters, each with its own
+/// seed, and their infected fraction at every graph sample.
+///
+/// Replicas are stepped from the frame loop by `advance` rather than on
+/// threads, which the WASM build doesn't have; dropping the ensemble stops
+/// them.
+pub struct Ensemble {
+    params: SimParams,
+    /// Experiment group parameters, indexed by `Boid::group`
+    group_params: Vec<SimParams>,
+    replicas: Vec<Replica>,
+    /// Replica `advance` steps next
+    next: usize,
+}
+
+impl Ensemble {
+    /// Replicas of `params` with `experiment`'s groups, seeded
+    /// `base_seed + 1 ..= base_seed + ENSEMBLE_REPLICAS`.
+    pub fn new(params: &SimParams, experiment: &Experiment, base_seed: u64) -> Self {
+        let replicas = (0..ENSEMBLE_REPLICAS)
+            .map(|k| {
+                let mut rng = Rng::new(base_seed.wrapping_add(k as u64 + 1));
+                let mut boids = initialize_boids(params, &mut rng);
+                experiment.assign(&mut boids, &mut rng);
+                Replica {
+                    boids,
+                    spatial_grid: SpatialGrid::new(50.0),
+                    notifier: ExposureNotifier::default(),
+                    clock: SimClock::default(),
+                    rng,
+                    curve: Curve::default(),
+                }
+            })
+            .collect();
+        Self {
+            params: params.clone(),
+            group_params: experiment.params_for(params),
+            replicas,
+            next: 0,
+        }
+    }
+
+    /// Step the replicas in turn, one step each, until this frame's budget
+    /// is spent or all have finished.
+    pub fn advance(&mut self) {
+        let start = get_time();
+        while self.completed() < self.replicas.len() && get_time() - start < FRAME_BUDGET {
+            let k = self.next;
+            self.next = (k + 1) % self.replicas.len();
+            let replica = &mut self.replicas[k];
+            if replica.clock.time() >= ENSEMBLE_DURATION {
+                continue;
+            }
+            replica.clock.advance(ENSEMBLE_DT);
+            step(&mut replica.boids, &self.params, &self.group_params, &mut replica.spatial_grid,
+                 &mut replica.notifier, &replica.clock, &mut replica.rng);
+            if replica.clock.crossed(self.params.sample_interval) {
+                let infected = count_disease_states(&replica.boids).2;
+                replica.curve.push(replica.clock.time(), infected as f32 / self.params.num_boids.max(1) as f32);
+            }
+        }
+    }
+
+    /// Replicas that have run `ENSEMBLE_DURATION`.
+    pub fn completed(&self) -> usize {
+        self.replicas.iter().filter(|r| r.clock.time() >= ENSEMBLE_DURATION).count()
+    }
+
+    pub fn replicas(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Mean and inter-quartile range of the infected fraction at simulated
+    /// `time`, over the replicas that have got that far.
+    pub fn band_at(&self, time: f32) -> Option<Band> {
+        band_at(self.replicas.iter().map(|r| &r.curve), time)
+    }
+}
@@ -0,0 +1,190 @@
+use macroquad::prelude::*;
+use boid_math::rng::Rng;
+use crate::boid::Boid;
+use crate::constants::{GRAPH_MAX_SAMPLES, GRAPH_WIDTH};
+use crate::simulation::SimParams;
+use crate::sir::DiseaseState;
+
+/// Most groups an experiment can split the flock into.
+pub const MAX_GROUPS: usize = 4;
+pub const GROUP_GRAPH_HEIGHT: f32 = 110.0;
+
+/// One arm of a within-run experiment: a share of the flock whose flocking
+/// parameters are scaled relative to the panel's. Disease parameters are
+/// shared by every group, so differences in the curves come from behavior.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExperimentGroup {
+    pub name: String,
+    /// Outline color of the group's boids and its curves
+    pub color: [u8; 3],
+    /// Relative weight when boids are dealt out at the start of a run
+    pub share: f32,
+    /// Multiplier on the perception and separation radii
+    pub distance_scale: f32,
+    pub cohesion_scale: f32,
+    pub speed_scale: f32,
+}
+
+impl ExperimentGroup {
+    fn new(name: &str, color: [u8; 3]) -> Self {
+        Self {
+            name: name.to_owned(),
+            color,
+            share: 1.0,
+            distance_scale: 1.0,
+            cohesion_scale: 1.0,
+            speed_scale: 1.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        Color::from_rgba(self.color[0], self.color[1], self.color[2], 255)
+    }
+
+    /// `base` with this group's overrides applied.
+    pub fn params(&self, base: &SimParams) -> SimParams {
+        let mut params = base.clone();
+        params.perception_radius *= self.distance_scale;
+        params.separation_radius *= self.distance_scale;
+        params.cohesion_weight *= self.cohesion_scale;
+        params.max_speed *= self.speed_scale;
+        params
+    }
+}
+
+const GROUP_COLORS: [[u8; 3]; MAX_GROUPS] = [[0, 200, 255], [255, 0, 200], [120, 255, 80], [255, 150, 40]];
+
+/// User-defined experiment groups, e.g. a control group and a treatment group
+/// that keeps half the distance. Off by default; while off every boid is in
+/// group 0 and uses the panel's parameters unchanged.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Experiment {
+    pub enabled: bool,
+    pub groups: Vec<ExperimentGroup>,
+}
+
+impl Default for Experiment {
+    fn default() -> Self {
+        let mut treatment = ExperimentGroup::new("Treatment", GROUP_COLORS[1]);
+        treatment.distance_scale = 0.5;
+        Self {
+            enabled: false,
+            groups: vec![ExperimentGroup::new("Control", GROUP_COLORS[0]), treatment],
+        }
+    }
+}
+
+impl Experiment {
+    /// Add another group with no overrides, up to `MAX_GROUPS`.
+    pub fn add_group(&mut self) {
+        let n = self.groups.len();
+        if n < MAX_GROUPS {
+            self.groups.push(ExperimentGroup::new(&format!("Group {}", n + 1), GROUP_COLORS[n]));
+        }
+    }
+
+    /// Deal `boids` into groups at random in proportion to the shares. Draws
+    /// no random numbers while the experiment is off.
+    pub fn assign(&self, boids: &mut [Boid], rng: &mut Rng) {
+        let total: f32 = self.groups.iter().map(|g| g.share.max(0.0)).sum();
+        for boid in boids {
+            boid.group = 0;
+            if !self.enabled || total <= 0.0 {
+                continue;
+            }
+            let mut pick = rng.range(0.0, total);
+            for (k, group) in self.groups.iter().enumerate() {
+                pick -= group.share.max(0.0);
+                boid.group = k;
+                if pick < 0.0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Parameters for each group, indexed by `Boid::group`.
+    pub fn params_for(&self, base: &SimParams) -> Vec<SimParams> {
+        if self.enabled {
+            self.groups.iter().map(|g| g.params(base)).collect()
+        } else {
+            vec![base.clone()]
+        }
+    }
+}
+
+/// Per-group infected and recovered fractions over the run, one sample per
+/// population graph sample. Fractions of each group's own size, so groups of
+/// different shares compare directly.
+#[derive(Default)]
+pub struct GroupHistory {
+    /// Per sample, per group: (exposed + infected, recovered or dead) fractions
+    samples: Vec<Vec<(f32, f32)>>,
+}
+
+impl GroupHistory {
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn add(&mut self, boids: &[Boid], groups: usize) {
+        let mut counts = vec![(0usize, 0usize, 0usize); groups];
+        for boid in boids {
+            let Some(c) = counts.get_mut(boid.group) else {
+                continue;
+            };
+            c.0 += 1;
+            match boid.disease_state {
+                DiseaseState::Exposed | DiseaseState::Infected => c.1 += 1,
+                DiseaseState::Recovered | DiseaseState::Dead => c.2 += 1,
+                DiseaseState::Susceptible => {}
+            }
+        }
+        let fractions = counts
+            .into_iter()
+            .map(|(n, i, r)| {
+                let n = n.max(1) as f32;
+                (i as f32 / n, r as f32 / n)
+            })
+            .collect();
+        self.samples.push(fractions);
+        if self.samples.len() > GRAPH_MAX_SAMPLES {
+            let mut k = 0;
+            self.samples.retain(|_| { k += 1; k % 2 == 1 });
+        }
+    }
+
+    /// Whole-run curves per group: infected (thick) and recovered (thin).
+    pub fn draw(&self, x: f32, y: f32, groups: &[ExperimentGroup]) {
+        draw_rectangle(x, y, GRAPH_WIDTH, GROUP_GRAPH_HEIGHT, Color::from_rgba(20, 20, 20, 255));
+        draw_rectangle_lines(x, y, GRAPH_WIDTH, GROUP_GRAPH_HEIGHT, 2.0, GRAY);
+        draw_text("Infected / recovered share by group", x + 10.0, y + 18.0, 16.0, WHITE);
+        let mut legend_x = x + 10.0;
+        for group in groups {
+            draw_text(&group.name, legend_x, y + 34.0, 14.0, group.color());
+            legend_x += measure_text(&group.name, None, 14, 1.0).width + 12.0;
+        }
+
+        if self.samples.len() < 2 {
+            return;
+        }
+        let top = y + 40.0;
+        let height = GROUP_GRAPH_HEIGHT - 44.0;
+        let step = self.samples.len().div_ceil(GRAPH_WIDTH as usize).max(1);
+        let points: Vec<usize> = (0..self.samples.len()).step_by(step).collect();
+        let span = (self.samples.len() - 1) as f32;
+        let px = |k: usize| x + k as f32 / span * GRAPH_WIDTH;
+        let py = |v: f32| top + height - v.clamp(0.0, 1.0) * height;
+        for pair in points.windows(2) {
+            let (a, b) = (&self.samples[pair[0]], &self.samples[pair[1]]);
+            for (k, group) in groups.iter().enumerate() {
+                let (Some(&(ia, ra)), Some(&(ib, rb))) = (a.get(k), b.get(k)) else {
+                    continue;
+                };
+                let color = group.color();
+                draw_line(px(pair[0]), py(ia), px(pair[1]), py(ib), 2.0, color);
+                draw_line(px(pair[0]), py(ra), px(pair[1]), py(rb), 1.0, Color { a: 0.6, ..color });
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use boid_math::rng::Rng;
+use crate::boid::Boid;
+use crate::clock::SimClock;
+use crate::simulation::SimParams;
+use crate::sir::DiseaseState;
+use crate::spatial::SpatialGrid;
+
+/// App-based exposure notification. App carriers log each other whenever they
+/// come within infection radius; once an infected carrier has been infectious
+/// for `diagnosis_delay` it is diagnosed, and every carrier it met in the last
+/// `contact_window` seconds is notified `notification_delay` later and
+/// quarantines with probability `quarantine_compliance`. Boids without the app
+/// are invisible to it, so low uptake misses most contact pairs.
+///
+/// Contacts are stored by boid index, so call `clear` whenever the boid list
+/// is rebuilt or resized, or `remove` when boids are taken out of it.
+#[derive(Default)]
+pub struct ExposureNotifier {
+    /// Last time each pair of carriers (lower index first) was in contact
+    contacts: HashMap<(usize, usize), f32>,
+    /// Notifications on their way: boid index and time of arrival
+    pending: Vec<(usize, f32)>,
+    pub diagnosed: usize,
+    pub notified: usize,
+    /// Notified boids that went into quarantine
+    pub quarantined: usize,
+}
+
+impl ExposureNotifier {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Follow the boid list through the removal of every index marked in
+    /// `removed`: their contacts and notifications are dropped and the rest
+    /// renumbered. Counters are kept.
+    pub fn remove(&mut self, removed: &[bool]) {
+        if !removed.contains(&true) {
+            return;
+        }
+        let mut next = 0;
+        let new_index: Vec<Option<usize>> = removed
+            .iter()
+            .map(|&gone| {
+                (!gone).then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect();
+        let renumber = |i: usize| new_index.get(i).copied().flatten();
+        self.contacts = self
+            .contacts
+            .drain()
+            .filter_map(|((a, b), seen)| Some(((renumber(a)?, renumber(b)?), seen)))
+            .collect();
+        self.pending = self
+            .pending
+            .drain(..)
+            .filter_map(|(i, arrival)| Some((renumber(i)?, arrival)))
+            .collect();
+    }
+
+    /// Catch up with `clock`'s last step: count down quarantines, log this
+    /// frame's contacts, diagnose and deliver due notifications. Uses the grid
+    /// built for this frame's infections.
+    pub fn update(&mut self, boids: &mut [Boid], params: &SimParams, spatial_grid: &SpatialGrid, clock: &SimClock, rng: &mut Rng) {
+        let now = clock.time();
+        for boid in boids.iter_mut() {
+            boid.quarantine_timer = (boid.quarantine_timer - clock.dt()).max(0.0);
+        }
+        if params.app_uptake <= 0.0 {
+            return;
+        }
+
+        let tracing = |boid: &Boid| boid.has_app && !boid.quarantined() && boid.disease_state != DiseaseState::Dead;
+        for i in 0..boids.len() {
+            if !tracing(&boids[i]) {
+                continue;
+            }
+            for j in spatial_grid.query_nearby_indices(boids[i].position, params.infection_radius) {
+                if j > i
+                    && tracing(&boids[j])
+                    && (boids[i].position - boids[j].position).length() < params.infection_radius
+                {
+                    self.contacts.insert((i, j), now);
+                }
+            }
+        }
+        let since = now - params.contact_window;
+        self.contacts.retain(|_, &mut seen| seen >= since);
+
+        for (i, boid) in boids.iter_mut().enumerate() {
+            if !boid.has_app
+                || boid.diagnosed
+                || boid.disease_state != DiseaseState::Infected
+                || boid.state_timer < params.diagnosis_delay
+            {
+                continue;
+            }
+            boid.diagnosed = true;
+            self.diagnosed += 1;
+            let arrival = now + params.notification_delay;
+            // Sorted so the compliance draws don't depend on map order
+            let mut met: Vec<usize> = self
+                .contacts
+                .keys()
+                .filter_map(|&(a, b)| if a == i { Some(b) } else if b == i { Some(a) } else { None })
+                .collect();
+            met.sort_unstable();
+            self.pending.extend(met.into_iter().map(|j| (j, arrival)));
+        }
+
+        let mut k = 0;
+        while k < self.pending.len() {
+            let (idx, arrival) = self.pending[k];
+            if arrival > now {
+                k += 1;
+                continue;
+            }
+            self.pending.swap_remove(k);
+            self.notified += 1;
+            if rng.chance(params.quarantine_compliance) {
+                let boid = &mut boids[idx];
+                boid.quarantine_timer = boid.quarantine_timer.max(params.quarantine_duration);
+                self.quarantined += 1;
+            }
+        }
+    }
+}
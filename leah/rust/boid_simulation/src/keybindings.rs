@@ -0,0 +1,189 @@
+use macroquad::prelude::{KeyCode, is_key_pressed};
+
+/// Default location of the user's bindings, relative to the working directory.
+pub const KEYBINDINGS_FILE: &str = "keybindings.conf";
+
+/// Everything a hotkey can do. Not every app uses every action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Restart,
+    Pause,
+    ToggleParams,
+    ToggleGraph,
+    ToggleDensity,
+    ToggleMyBoid,
+    ToggleRings,
+    ToggleExperiment,
+    ToggleCalibration,
+    ToggleExport,
+    ToggleEnsemble,
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::Restart,
+        Action::Pause,
+        Action::ToggleParams,
+        Action::ToggleGraph,
+        Action::ToggleDensity,
+        Action::ToggleMyBoid,
+        Action::ToggleRings,
+        Action::ToggleExperiment,
+        Action::ToggleCalibration,
+        Action::ToggleExport,
+        Action::ToggleEnsemble,
+        Action::Help,
+    ];
+
+    /// Name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Restart => "restart",
+            Action::Pause => "pause",
+            Action::ToggleParams => "toggle_params",
+            Action::ToggleGraph => "toggle_graph",
+            Action::ToggleDensity => "toggle_density",
+            Action::ToggleMyBoid => "toggle_my_boid",
+            Action::ToggleRings => "toggle_rings",
+            Action::ToggleExperiment => "toggle_experiment",
+            Action::ToggleCalibration => "toggle_calibration",
+            Action::ToggleExport => "toggle_export",
+            Action::ToggleEnsemble => "toggle_ensemble",
+            Action::Help => "help",
+        }
+    }
+
+    /// Description shown in the help overlay.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Restart => "Restart simulation",
+            Action::Pause => "Pause / resume",
+            Action::ToggleParams => "Toggle parameter panel",
+            Action::ToggleGraph => "Toggle population graph",
+            Action::ToggleDensity => "Toggle contact density panel",
+            Action::ToggleMyBoid => "Toggle My Boid panel",
+            Action::ToggleRings => "Ring exposed / infected boids",
+            Action::ToggleExperiment => "Toggle experiment groups panel",
+            Action::ToggleCalibration => "Toggle infection calibration overlay",
+            Action::ToggleExport => "Start / stop per-tick CSV export",
+            Action::ToggleEnsemble => "Start / stop the ensemble band on the graph",
+            Action::Help => "Show / hide this help",
+        }
+    }
+
+    fn default_key(self) -> KeyCode {
+        match self {
+            Action::Restart => KeyCode::Enter,
+            Action::Pause => KeyCode::Space,
+            Action::ToggleParams => KeyCode::P,
+            Action::ToggleGraph => KeyCode::G,
+            Action::ToggleDensity => KeyCode::D,
+            Action::ToggleMyBoid => KeyCode::M,
+            Action::ToggleRings => KeyCode::I,
+            Action::ToggleExperiment => KeyCode::E,
+            Action::ToggleCalibration => KeyCode::C,
+            Action::ToggleExport => KeyCode::X,
+            Action::ToggleEnsemble => KeyCode::N,
+            Action::Help => KeyCode::H,
+        }
+    }
+}
+
+/// Keys that can be bound, by the names accepted in the config file.
+const BINDABLE: [KeyCode; 57] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Escape, KeyCode::Tab, KeyCode::Backspace,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+];
+
+/// Config-file name of `key`: its `KeyCode` name, with digits written bare
+/// (`1` rather than `Key1`).
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    match name.strip_prefix("Key") {
+        Some(digit) if !digit.is_empty() => digit.to_owned(),
+        _ => name,
+    }
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE.into_iter().find(|&k| key_name(k).eq_ignore_ascii_case(name))
+}
+
+/// Action -> key map. Starts from the defaults; a config file only needs to
+/// list the keys it changes.
+pub struct Keybindings {
+    keys: Vec<(Action, KeyCode)>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self {
+            keys: Action::ALL.iter().map(|&a| (a, a.default_key())).collect(),
+        }
+    }
+}
+
+impl Keybindings {
+    /// Defaults overridden by `KEYBINDINGS_FILE`, if it exists. Bad lines are
+    /// reported and skipped so one typo doesn't lose the rest.
+    pub fn load() -> Self {
+        let mut bindings = Self::default();
+        let Ok(text) = std::fs::read_to_string(KEYBINDINGS_FILE) else {
+            return bindings;
+        };
+        for (n, line) in text.lines().enumerate() {
+            if let Err(err) = bindings.apply_line(line) {
+                eprintln!("{}:{}: {}", KEYBINDINGS_FILE, n + 1, err);
+            }
+        }
+        bindings
+    }
+
+    /// Apply one `action = key` line. Blank lines and `#` comments are
+    /// ignored.
+    pub fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (action, key) = line
+            .split_once('=')
+            .ok_or_else(|| format!("expected action = key, got {}", line))?;
+        let action = Action::ALL
+            .into_iter()
+            .find(|a| a.name() == action.trim())
+            .ok_or_else(|| format!("unknown action: {}", action.trim()))?;
+        let key = parse_key(key.trim()).ok_or_else(|| format!("unknown key: {}", key.trim()))?;
+        self.bind(action, key);
+        Ok(())
+    }
+
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        for entry in &mut self.keys {
+            if entry.0 == action {
+                entry.1 = key;
+            }
+        }
+    }
+
+    pub fn key(&self, action: Action) -> KeyCode {
+        self.keys
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(action.default_key(), |&(_, k)| k)
+    }
+
+    /// Whether `action`'s key went down this frame.
+    pub fn pressed(&self, action: Action) -> bool {
+        is_key_pressed(self.key(action))
+    }
+}
@@ -0,0 +1,232 @@
+use egui_macroquad::egui;
+use macroquad::prelude::*;
+use crate::sir::DiseaseState;
+use crate::ui::{Panel, UIState};
+use crate::visualization::GraphWindow;
+
+/// Default location of the user's workspace settings, relative to the
+/// working directory. Simulation parameters are not kept here; keybindings
+/// live in `keybindings::KEYBINDINGS_FILE`.
+pub const SETTINGS_FILE: &str = "settings.conf";
+
+/// Look of the egui panels. The simulation area stays dark either way so
+/// the boid colors keep their contrast.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark panels",
+            Theme::Light => "Light panels",
+        }
+    }
+
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            Theme::Dark => egui::Visuals::dark(),
+            Theme::Light => egui::Visuals::light(),
+        }
+    }
+}
+
+/// Colors for the disease states, on boids and graph lines alike.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Standard,
+    /// Okabe-Ito colors, distinguishable with the common color-vision
+    /// deficiencies
+    ColorblindSafe,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 2] = [Palette::Standard, Palette::ColorblindSafe];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Standard => "standard",
+            Palette::ColorblindSafe => "colorblind",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Standard => "Standard",
+            Palette::ColorblindSafe => "Colorblind-safe",
+        }
+    }
+
+    /// Opaque color for `state`; callers fade corpses themselves.
+    pub fn color(self, state: DiseaseState) -> Color {
+        match (self, state) {
+            (_, DiseaseState::Susceptible) => WHITE,
+            (_, DiseaseState::Dead) => Color::from_rgba(150, 150, 150, 255),
+            (Palette::Standard, DiseaseState::Exposed) => Color::from_rgba(255, 200, 0, 255),
+            (Palette::Standard, DiseaseState::Infected) => RED,
+            (Palette::Standard, DiseaseState::Recovered) => BLUE,
+            (Palette::ColorblindSafe, DiseaseState::Exposed) => Color::from_rgba(240, 228, 66, 255),
+            (Palette::ColorblindSafe, DiseaseState::Infected) => Color::from_rgba(213, 94, 0, 255),
+            (Palette::ColorblindSafe, DiseaseState::Recovered) => Color::from_rgba(86, 180, 233, 255),
+        }
+    }
+}
+
+/// Workspace preferences kept across sessions: how the app looks and which
+/// panels are open where. Separate from `SimParams`, so restoring a
+/// workspace never changes a run and sharing a run never changes anyone's
+/// workspace.
+#[derive(Clone, PartialEq, Debug)]
+pub struct UserSettings {
+    pub theme: Theme,
+    pub palette: Palette,
+    pub show_graph: bool,
+    pub params_collapsed: bool,
+    pub show_density: bool,
+    pub show_experiment: bool,
+    pub show_calibration: bool,
+    pub show_rings: bool,
+    pub graph_window: GraphWindow,
+    /// Top-left corner of each panel the user has moved
+    pub panels: Vec<(Panel, egui::Pos2)>,
+}
+
+impl UserSettings {
+    /// The current workspace, taken from `ui_state`.
+    pub fn capture(ui_state: &UIState) -> Self {
+        Self {
+            theme: ui_state.theme,
+            palette: ui_state.palette,
+            show_graph: ui_state.show_graph,
+            params_collapsed: ui_state.params_collapsed,
+            show_density: ui_state.show_density,
+            show_experiment: ui_state.show_experiment,
+            show_calibration: ui_state.show_calibration,
+            show_rings: ui_state.show_rings,
+            graph_window: ui_state.graph_window,
+            panels: ui_state.panel_positions.clone(),
+        }
+    }
+
+    /// Open the saved panels where they were left.
+    pub fn restore(&self, ui_state: &mut UIState) {
+        ui_state.theme = self.theme;
+        ui_state.palette = self.palette;
+        ui_state.show_graph = self.show_graph;
+        ui_state.params_collapsed = self.params_collapsed;
+        ui_state.show_density = self.show_density;
+        ui_state.show_experiment = self.show_experiment;
+        ui_state.show_calibration = self.show_calibration;
+        ui_state.show_rings = self.show_rings;
+        ui_state.graph_window = self.graph_window;
+        ui_state.panel_positions = self.panels.clone();
+    }
+
+    /// The workspace from `SETTINGS_FILE`, or `defaults` where it is
+    /// missing. Bad lines are reported and skipped so one typo doesn't lose
+    /// the rest.
+    pub fn load(defaults: Self) -> Self {
+        let mut settings = defaults;
+        let Ok(text) = std::fs::read_to_string(SETTINGS_FILE) else {
+            return settings;
+        };
+        for (n, line) in text.lines().enumerate() {
+            if let Err(err) = settings.apply_line(line) {
+                eprintln!("{}:{}: {}", SETTINGS_FILE, n + 1, err);
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(SETTINGS_FILE, self.to_text())
+    }
+
+    /// One `name = value` line per setting, as `load` reads them.
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# Workspace settings, rewritten by the app as they change\n");
+        text.push_str(&format!("theme = {}\n", self.theme.name()));
+        text.push_str(&format!("palette = {}\n", self.palette.name()));
+        for (name, on) in [
+            ("show_graph", self.show_graph),
+            ("params_collapsed", self.params_collapsed),
+            ("show_density", self.show_density),
+            ("show_experiment", self.show_experiment),
+            ("show_calibration", self.show_calibration),
+            ("show_rings", self.show_rings),
+        ] {
+            text.push_str(&format!("{} = {}\n", name, on));
+        }
+        text.push_str(&format!("graph_window = {}\n", self.graph_window.label()));
+        for (panel, pos) in &self.panels {
+            text.push_str(&format!("panel.{} = {:.0},{:.0}\n", panel.name(), pos.x, pos.y));
+        }
+        text
+    }
+
+    /// Apply one `name = value` line. Blank lines and `#` comments are
+    /// ignored.
+    pub fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("expected name = value, got {}", line))?;
+        let (name, value) = (name.trim(), value.trim());
+        let flag = || value.parse::<bool>().map_err(|_| format!("expected true or false, got {}", value));
+        match name {
+            "theme" => {
+                self.theme = Theme::ALL
+                    .into_iter()
+                    .find(|t| t.name() == value)
+                    .ok_or_else(|| format!("unknown theme: {}", value))?;
+            }
+            "palette" => {
+                self.palette = Palette::ALL
+                    .into_iter()
+                    .find(|p| p.name() == value)
+                    .ok_or_else(|| format!("unknown palette: {}", value))?;
+            }
+            "show_graph" => self.show_graph = flag()?,
+            "params_collapsed" => self.params_collapsed = flag()?,
+            "show_density" => self.show_density = flag()?,
+            "show_experiment" => self.show_experiment = flag()?,
+            "show_calibration" => self.show_calibration = flag()?,
+            "show_rings" => self.show_rings = flag()?,
+            "graph_window" => {
+                self.graph_window = GraphWindow::ALL
+                    .into_iter()
+                    .find(|w| w.label() == value)
+                    .ok_or_else(|| format!("unknown graph window: {}", value))?;
+            }
+            _ => {
+                let panel = name
+                    .strip_prefix("panel.")
+                    .and_then(|p| Panel::ALL.into_iter().find(|panel| panel.name() == p))
+                    .ok_or_else(|| format!("unknown setting: {}", name))?;
+                let (x, y) = value
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+                    .ok_or_else(|| format!("expected x,y, got {}", value))?;
+                self.panels.retain(|(p, _)| *p != panel);
+                self.panels.push((panel, egui::pos2(x, y)));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+//! Shareable links for the WASM build: the full parameter set and random
+//! seed round-trip through the page's URL query string.
+//!
+//! The browser side lives in `web/share_plugin.js`, which must be loaded
+//! after `mq_js_bundle.js` on the embedding page.
+
+use crate::clock::TimeUnit;
+use crate::simulation::{InitialLayout, PerState, SimParams};
+use crate::sir::DiseaseModel;
+
+/// Encode `params`, seed included, as a query string (without the leading `?`).
+pub fn to_query(params: &SimParams) -> String {
+    let model = match params.model {
+        DiseaseModel::SIR => "sir",
+        DiseaseModel::SIS => "sis",
+        DiseaseModel::SEIR => "seir",
+    };
+    let layout = match params.layout {
+        InitialLayout::Grid => "grid",
+        InitialLayout::Random => "random",
+        InitialLayout::Clusters => "clusters",
+        InitialLayout::Ring => "ring",
+        InitialLayout::OutbreakCorner => "corner",
+    };
+    format!(
+        "seed={}&n={}&perception={}&separation={}&sep_w={}&align_w={}&coh_w={}&speed={}&force={}\
+         &model={}&initial={}&inf_radius={}&inf_prob={}&recovery={}&incubation={}&layout={}\
+         &perc_scale={}&inf_scale={}&unit={}&death={}&remove_corpses={}&corpse_fade={}&sample={}",
+        params.seed,
+        params.num_boids,
+        params.perception_radius,
+        params.separation_radius,
+        params.separation_weight,
+        params.alignment_weight,
+        params.cohesion_weight,
+        params.max_speed,
+        params.max_force,
+        model,
+        params.initial_infected,
+        params.infection_radius,
+        params.infection_probability,
+        params.recovery_time,
+        params.incubation_time,
+        layout,
+        per_state_to_query(&params.perception_scale),
+        per_state_to_query(&params.infection_scale),
+        params.time_unit.short(),
+        params.death_probability,
+        params.remove_corpses,
+        params.corpse_fade_time,
+        params.sample_interval,
+    )
+}
+
+/// Apply every recognised `key=value` pair in `query` to `params`. Unknown
+/// keys and unparsable values are ignored so a hand-edited or outdated link
+/// still loads.
+pub fn apply_query(query: &str, params: &mut SimParams) {
+    for pair in query.trim_start_matches('?').split('&') {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+        match key {
+            "seed" => set(&mut params.seed, value),
+            "n" => set(&mut params.num_boids, value),
+            "perception" => set(&mut params.perception_radius, value),
+            "separation" => set(&mut params.separation_radius, value),
+            "sep_w" => set(&mut params.separation_weight, value),
+            "align_w" => set(&mut params.alignment_weight, value),
+            "coh_w" => set(&mut params.cohesion_weight, value),
+            "speed" => set(&mut params.max_speed, value),
+            "force" => set(&mut params.max_force, value),
+            "initial" => set(&mut params.initial_infected, value),
+            "inf_radius" => set(&mut params.infection_radius, value),
+            "inf_prob" => set(&mut params.infection_probability, value),
+            "recovery" => set(&mut params.recovery_time, value),
+            "incubation" => set(&mut params.incubation_time, value),
+            "death" => set(&mut params.death_probability, value),
+            "remove_corpses" => set(&mut params.remove_corpses, value),
+            "corpse_fade" => set(&mut params.corpse_fade_time, value),
+            "sample" => set(&mut params.sample_interval, value),
+            "model" => match value {
+                "sir" => params.model = DiseaseModel::SIR,
+                "sis" => params.model = DiseaseModel::SIS,
+                "seir" => params.model = DiseaseModel::SEIR,
+                _ => {}
+            },
+            "perc_scale" => set_per_state(&mut params.perception_scale, value),
+            "inf_scale" => set_per_state(&mut params.infection_scale, value),
+            "layout" => match value {
+                "grid" => params.layout = InitialLayout::Grid,
+                "random" => params.layout = InitialLayout::Random,
+                "clusters" => params.layout = InitialLayout::Clusters,
+                "ring" => params.layout = InitialLayout::Ring,
+                "corner" => params.layout = InitialLayout::OutbreakCorner,
+                _ => {}
+            },
+            "unit" => {
+                if let Some(unit) = TimeUnit::ALL.into_iter().find(|u| u.short() == value) {
+                    params.time_unit = unit;
+                }
+            }
+            _ => {}
+        }
+    }
+    for adjustment in params.normalize() {
+        eprintln!("shared link: {}", adjustment);
+    }
+}
+
+/// `s,e,i,r` multipliers.
+fn per_state_to_query(table: &PerState) -> String {
+    format!("{},{},{},{}", table.susceptible, table.exposed, table.infected, table.recovered)
+}
+
+fn set_per_state(table: &mut PerState, value: &str) {
+    let values: Vec<f32> = value.split(',').filter_map(|v| v.parse().ok()).collect();
+    if let [s, e, i, r] = values[..] {
+        *table = PerState { susceptible: s, exposed: e, infected: i, recovered: r };
+    }
+}
+
+fn set<T: std::str::FromStr>(field: &mut T, value: &str) {
+    if let Ok(v) = value.parse() {
+        *field = v;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+unsafe extern "C" {
+    fn boids_page_query_len() -> u32;
+    fn boids_page_query_copy(ptr: *mut u8, len: u32);
+    fn boids_copy_share_link(ptr: *const u8, len: u32);
+}
+
+/// The page's query string, or `None` natively or when it is empty.
+#[cfg(target_arch = "wasm32")]
+pub fn page_query() -> Option<String> {
+    // SAFETY: the plugin writes exactly `len` bytes into the buffer.
+    let bytes = unsafe {
+        let len = boids_page_query_len();
+        let mut buf = vec![0u8; len as usize];
+        boids_page_query_copy(buf.as_mut_ptr(), len);
+        buf
+    };
+    String::from_utf8(bytes).ok().filter(|q| !q.is_empty())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn page_query() -> Option<String> {
+    None
+}
+
+/// Put a link to this page with `query` on the clipboard and into the
+/// address bar. Natively there is no page, so the query is printed instead.
+#[cfg(target_arch = "wasm32")]
+pub fn copy_link(query: &str) {
+    // SAFETY: the plugin only reads `len` bytes from `ptr`.
+    unsafe { boids_copy_share_link(query.as_ptr(), query.len() as u32) }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy_link(query: &str) {
+    println!("?{}", query);
+}
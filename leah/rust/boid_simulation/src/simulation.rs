@@ -1,8 +1,84 @@
-use macroquad::prelude::rand;
+use macroquad::prelude::{Vec2, vec2};
+use boid_math::params::{Adjustment, Radii};
+use boid_math::rng::Rng;
 use crate::boid::Boid;
+use crate::clock::{SimClock, TimeUnit, SAMPLE_INTERVAL, MIN_SAMPLE_INTERVAL, MAX_SAMPLE_INTERVAL};
 use crate::constants::{SCREEN_WIDTH, SCREEN_HEIGHT};
-use crate::sir::{DiseaseState, DiseaseModel};
+use crate::exposure::ExposureNotifier;
+use crate::sir::{DiseaseState, DiseaseModel, process_infections, remove_corpses};
+use crate::spatial::SpatialGrid;
 
+/// Where boids start out when a run begins.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum InitialLayout {
+    /// Jittered grid covering the screen
+    Grid,
+    /// Uniformly random positions
+    Random,
+    /// A few Gaussian clusters at random centers
+    Clusters,
+    /// A ring around the screen center
+    Ring,
+    /// Uniformly random, but every initially infected boid starts in the top-left corner
+    OutbreakCorner,
+}
+
+impl InitialLayout {
+    pub const ALL: [InitialLayout; 5] = [
+        InitialLayout::Grid,
+        InitialLayout::Random,
+        InitialLayout::Clusters,
+        InitialLayout::Ring,
+        InitialLayout::OutbreakCorner,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InitialLayout::Grid => "Grid",
+            InitialLayout::Random => "Random",
+            InitialLayout::Clusters => "Clusters",
+            InitialLayout::Ring => "Ring",
+            InitialLayout::OutbreakCorner => "Outbreak corner",
+        }
+    }
+}
+
+/// One value per disease state.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PerState {
+    pub susceptible: f32,
+    pub exposed: f32,
+    pub infected: f32,
+    pub recovered: f32,
+}
+
+impl PerState {
+    pub const fn uniform(value: f32) -> Self {
+        Self {
+            susceptible: value,
+            exposed: value,
+            infected: value,
+            recovered: value,
+        }
+    }
+
+    pub fn get(&self, state: DiseaseState) -> f32 {
+        match state {
+            DiseaseState::Susceptible => self.susceptible,
+            DiseaseState::Exposed => self.exposed,
+            DiseaseState::Infected => self.infected,
+            DiseaseState::Recovered => self.recovered,
+            // Corpses neither sense nor transmit
+            DiseaseState::Dead => 0.0,
+        }
+    }
+
+    pub fn max(&self) -> f32 {
+        self.susceptible.max(self.exposed).max(self.infected).max(self.recovered)
+    }
+}
+
+#[derive(Clone)]
 pub struct SimParams {
     pub perception_radius: f32,
     pub separation_radius: f32,
@@ -16,8 +92,49 @@ pub struct SimParams {
     pub infection_probability: f32,
     pub recovery_time: f32,
     pub incubation_time: f32,
+    /// Chance an infection ends in death rather than recovery (or, under
+    /// SIS, susceptibility)
+    pub death_probability: f32,
+    /// Fade dead boids out and remove them from the population
+    pub remove_corpses: bool,
+    /// Seconds a corpse takes to fade out when `remove_corpses` is on
+    pub corpse_fade_time: f32,
     pub initial_infected: usize,
     pub model: DiseaseModel,
+    pub layout: InitialLayout,
+    /// Seed of the run's random stream (placement, headings, infection and
+    /// death rolls); the same seed and parameters draw the same numbers.
+    /// Takes effect on restart
+    pub seed: u64,
+    /// What one simulated second is labelled as; times stay in seconds
+    pub time_unit: TimeUnit,
+    /// Simulated seconds between population graph samples, which also set
+    /// the resolution of phase detection; takes effect on restart
+    pub sample_interval: f32,
+    /// Multiplier on `perception_radius` by the boid's own state, e.g. below 1
+    /// for infected boids to model sickness behavior
+    pub perception_scale: PerState,
+    /// Multiplier on `infection_radius` by state; a contact's radius is the
+    /// mean of both boids' multipliers
+    pub infection_scale: PerState,
+    // Disease-state affinity for every boid: positive = attract, negative = repel
+    pub affinity_susceptible: f32,
+    pub affinity_exposed: f32,
+    pub affinity_infected: f32,
+    pub affinity_recovered: f32,
+    // Exposure notification (digital contact tracing), see exposure.rs
+    /// Fraction of boids carrying the app; 0 turns notification off
+    pub app_uptake: f32,
+    /// Seconds infectious before an infected boid is diagnosed
+    pub diagnosis_delay: f32,
+    /// How far back, in seconds, a diagnosis reaches for contacts to notify
+    pub contact_window: f32,
+    /// Seconds from a diagnosis to its contacts receiving the notification
+    pub notification_delay: f32,
+    /// Chance a notified boid goes into quarantine
+    pub quarantine_compliance: f32,
+    /// Seconds a quarantined boid holds still and stays out of contact
+    pub quarantine_duration: f32,
 }
 
 impl Default for SimParams {
@@ -35,38 +152,262 @@ impl Default for SimParams {
             infection_probability: 0.02,
             recovery_time: 5.0,
             incubation_time: 3.0,
+            death_probability: 0.0,
+            remove_corpses: false,
+            corpse_fade_time: 5.0,
             initial_infected: 3,
             model: DiseaseModel::SIR,
+            layout: InitialLayout::Grid,
+            seed: 1337,
+            time_unit: TimeUnit::Seconds,
+            sample_interval: SAMPLE_INTERVAL,
+            perception_scale: PerState::uniform(1.0),
+            infection_scale: PerState::uniform(1.0),
+            affinity_susceptible: 0.0,
+            affinity_exposed: 0.0,
+            affinity_infected: 0.0,
+            affinity_recovered: 0.0,
+            app_uptake: 0.0,
+            diagnosis_delay: 2.0,
+            contact_window: 10.0,
+            notification_delay: 1.0,
+            quarantine_compliance: 0.7,
+            quarantine_duration: 10.0,
+        }
+    }
+}
+
+impl SimParams {
+    /// Clamp interdependent parameters into a consistent configuration.
+    ///
+    /// - The radii follow the rules rust_src uses too (`boid_math::params::Radii`):
+    ///   `separation_radius` lies between a small floor and `perception_radius`,
+    ///   since separation is only evaluated for neighbors already perceived.
+    /// - `initial_infected` is capped at `num_boids`.
+    /// - `death_probability` is clamped to a probability.
+    ///
+    /// Called by the parameter panel after every edit so the sliders always show
+    /// the values the simulation actually uses. Returns the radii and seeding
+    /// that had to change, for the panel to explain.
+    pub fn normalize(&mut self) -> Vec<Adjustment> {
+        let (radii, mut adjustments) = Radii {
+            perception: self.perception_radius,
+            separation: self.separation_radius,
+            infection: self.infection_radius,
+        }
+        .normalize();
+        self.perception_radius = radii.perception;
+        self.separation_radius = radii.separation;
+        self.infection_radius = radii.infection;
+        if self.initial_infected > self.num_boids {
+            adjustments.push(Adjustment {
+                name: "initial infected",
+                requested: self.initial_infected as f32,
+                used: self.num_boids as f32,
+            });
+            self.initial_infected = self.num_boids;
+        }
+        self.death_probability = self.death_probability.clamp(0.0, 1.0);
+        self.sample_interval = self.sample_interval.clamp(MIN_SAMPLE_INTERVAL, MAX_SAMPLE_INTERVAL);
+        adjustments
+    }
+
+    /// Perception radius of a boid in `state`.
+    pub fn perception_radius_for(&self, state: DiseaseState) -> f32 {
+        self.perception_radius * self.perception_scale.get(state)
+    }
+
+    /// Distance within which boids in states `a` and `b` can transmit.
+    pub fn infection_radius_between(&self, a: DiseaseState, b: DiseaseState) -> f32 {
+        self.infection_radius * (self.infection_scale.get(a) + self.infection_scale.get(b)) / 2.0
+    }
+
+    /// Largest infection radius any pair of states can have, for spatial queries.
+    pub fn max_infection_radius(&self) -> f32 {
+        self.infection_radius * self.infection_scale.max()
+    }
+
+    pub fn affinity_for(&self, state: DiseaseState) -> f32 {
+        match state {
+            DiseaseState::Susceptible => self.affinity_susceptible,
+            DiseaseState::Exposed => self.affinity_exposed,
+            DiseaseState::Infected => self.affinity_infected,
+            DiseaseState::Recovered => self.affinity_recovered,
+            DiseaseState::Dead => 0.0,
         }
     }
 }
 
-pub fn initialize_boids(num_boids: usize, initial_infected: usize) -> Vec<Boid> {
-    let mut boids = Vec::new();
+/// Place `params.num_boids` boids according to `params.layout`; the first
+/// `params.initial_infected` of them start infected.
+pub fn initialize_boids(params: &SimParams, rng: &mut Rng) -> Vec<Boid> {
+    let num_boids = params.num_boids;
+    let initial_infected = params.initial_infected.min(num_boids);
+
+    let positions: Vec<Vec2> = match params.layout {
+        InitialLayout::Grid => grid_positions(num_boids, rng),
+        InitialLayout::Random | InitialLayout::OutbreakCorner => {
+            (0..num_boids).map(|_| random_position(rng)).collect()
+        }
+        InitialLayout::Clusters => {
+            let centers: Vec<Vec2> = (0..CLUSTER_COUNT)
+                .map(|_| vec2(
+                    rng.range(0.2, 0.8) * SCREEN_WIDTH,
+                    rng.range(0.2, 0.8) * SCREEN_HEIGHT,
+                ))
+                .collect();
+            let spread = SCREEN_WIDTH.min(SCREEN_HEIGHT) * 0.08;
+            (0..num_boids)
+                .map(|_| {
+                    let center = centers[rng.next_index(centers.len())];
+                    wrap_position(center + gaussian_offset(spread, rng))
+                })
+                .collect()
+        }
+        InitialLayout::Ring => {
+            let center = vec2(SCREEN_WIDTH / 2.0, SCREEN_HEIGHT / 2.0);
+            let radius = SCREEN_WIDTH.min(SCREEN_HEIGHT) * 0.35;
+            (0..num_boids)
+                .map(|_| {
+                    let angle = rng.range(0.0, std::f32::consts::TAU);
+                    let r = radius + rng.range(-15.0, 15.0);
+                    center + vec2(angle.cos(), angle.sin()) * r
+                })
+                .collect()
+        }
+    };
+
+    positions
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut position)| {
+            let disease_state = if i < initial_infected {
+                if params.layout == InitialLayout::OutbreakCorner {
+                    position = vec2(
+                        rng.range(0.0, SCREEN_WIDTH * CORNER_FRACTION),
+                        rng.range(0.0, SCREEN_HEIGHT * CORNER_FRACTION),
+                    );
+                }
+                DiseaseState::Infected
+            } else {
+                DiseaseState::Susceptible
+            };
+            let mut boid = Boid::new(position.x, position.y, disease_state, rng);
+            boid.has_app = draw_app(params, rng);
+            boid
+        })
+        .collect()
+}
+
+/// Redraw which boids carry the app after `params.app_uptake` changes.
+pub fn assign_apps(boids: &mut [Boid], params: &SimParams, rng: &mut Rng) {
+    for boid in boids {
+        boid.has_app = draw_app(params, rng);
+    }
+}
+
+/// Whether a new boid carries the exposure notification app. Draws no random
+/// number while the app is off, so runs without it replay the same as before.
+fn draw_app(params: &SimParams, rng: &mut Rng) -> bool {
+    params.app_uptake > 0.0 && rng.chance(params.app_uptake)
+}
+
+const CLUSTER_COUNT: usize = 3;
+/// Share of the screen width and height the outbreak corner covers.
+const CORNER_FRACTION: f32 = 0.15;
+
+fn grid_positions(num_boids: usize, rng: &mut Rng) -> Vec<Vec2> {
+    let mut positions = Vec::with_capacity(num_boids);
     let grid_size = (num_boids as f32).sqrt().ceil() as usize;
     let cell_width = SCREEN_WIDTH / grid_size as f32;
     let cell_height = SCREEN_HEIGHT / grid_size as f32;
 
-    let mut count = 0;
     'outer: for i in 0..grid_size {
         for j in 0..grid_size {
-            if count >= num_boids {
+            if positions.len() >= num_boids {
                 break 'outer;
             }
 
-            let x = (i as f32 + rand::gen_range(0.2, 0.8)) * cell_width;
-            let y = (j as f32 + rand::gen_range(0.2, 0.8)) * cell_height;
+            let x = (i as f32 + rng.range(0.2, 0.8)) * cell_width;
+            let y = (j as f32 + rng.range(0.2, 0.8)) * cell_height;
+            positions.push(vec2(x, y));
+        }
+    }
 
-            let disease_state = if count < initial_infected {
-                DiseaseState::Infected
-            } else {
-                DiseaseState::Susceptible
-            };
+    positions
+}
+
+fn random_position(rng: &mut Rng) -> Vec2 {
+    vec2(rng.range(0.0, SCREEN_WIDTH), rng.range(0.0, SCREEN_HEIGHT))
+}
+
+/// Normally distributed offset with standard deviation `sigma` (Box-Muller).
+fn gaussian_offset(sigma: f32, rng: &mut Rng) -> Vec2 {
+    let u1 = rng.range(f32::EPSILON, 1.0);
+    let u2 = rng.range(0.0, std::f32::consts::TAU);
+    let r = (-2.0 * u1.ln()).sqrt() * sigma;
+    vec2(u2.cos(), u2.sin()) * r
+}
+
+/// Wrap a position onto the screen the same way moving boids wrap.
+fn wrap_position(p: Vec2) -> Vec2 {
+    vec2(p.x.rem_euclid(SCREEN_WIDTH), p.y.rem_euclid(SCREEN_HEIGHT))
+}
 
-            boids.push(Boid::new(x, y, disease_state));
-            count += 1;
+/// Grow or shrink `boids` to `params.num_boids` without restarting the run.
+///
+/// Existing boids keep their positions and disease states. New boids are
+/// susceptible, placed uniformly at random and carry the app at the current
+/// uptake; removed boids are chosen at random.
+pub fn resize_boids(boids: &mut Vec<Boid>, params: &SimParams, rng: &mut Rng) {
+    let num_boids = params.num_boids;
+    while boids.len() > num_boids {
+        let idx = rng.next_index(boids.len());
+        boids.swap_remove(idx);
+    }
+    while boids.len() < num_boids {
+        let position = random_position(rng);
+        let mut boid = Boid::new(position.x, position.y, DiseaseState::Susceptible, rng);
+        boid.has_app = draw_app(params, rng);
+        boids.push(boid);
+    }
+}
+
+/// Simulate the step `clock` just advanced by: infections, exposure
+/// notification, then each boid moving with its experiment group's
+/// parameters (`group_params`, indexed by `Boid::group`) and progressing its
+/// disease; quarantined boids hold still. Faded corpses are removed last.
+///
+/// Returns the new infections and how many corpses were removed.
+pub fn step(boids: &mut Vec<Boid>, params: &SimParams, group_params: &[SimParams], spatial_grid: &mut SpatialGrid, notifier: &mut ExposureNotifier, clock: &SimClock, rng: &mut Rng) -> (usize, usize) {
+    // Build spatial grid for efficient neighbor queries
+    spatial_grid.clear();
+    for (i, boid) in boids.iter().enumerate() {
+        spatial_grid.insert(i, boid.position);
+    }
+
+    let new_infections = process_infections(boids, params, spatial_grid, rng);
+    notifier.update(boids, params, spatial_grid, clock, rng);
+
+    for i in 0..boids.len() {
+        let own = &group_params[boids[i].group.min(group_params.len() - 1)];
+        if boids[i].quarantined() {
+            boids[i].update_disease_state(params, clock.dt(), rng);
+            continue;
         }
+        let neighbors = spatial_grid.query_nearby(
+            boids[i].position,
+            own.perception_radius_for(boids[i].disease_state),
+            boids
+        );
+        boids[i].update(&neighbors, own);
+        boids[i].update_disease_state(params, clock.dt(), rng);
     }
 
-    boids
+    let removed = remove_corpses(boids, params);
+    let gone = removed.iter().filter(|&&r| r).count();
+    if gone > 0 {
+        notifier.remove(&removed);
+    }
+    (new_infections, gone)
 }
@@ -1,4 +1,4 @@
-use macroquad::prelude::rand;
+use boid_math::rng::Rng;
 use crate::boid::Boid;
 use crate::simulation::SimParams;
 use crate::spatial::SpatialGrid;
@@ -9,40 +9,51 @@ pub enum DiseaseState {
     Exposed,
     Infected,
     Recovered,
+    /// Died at the end of an infection; holds still and takes no part in
+    /// flocking or transmission until removed
+    Dead,
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum DiseaseModel {
     SIR,
     SIS,
     SEIR,
 }
 
-pub fn process_infections(boids: &mut [Boid], params: &SimParams, spatial_grid: &SpatialGrid) {
+/// Returns the number of susceptible boids newly infected this frame.
+pub fn process_infections(boids: &mut [Boid], params: &SimParams, spatial_grid: &SpatialGrid, rng: &mut Rng) -> usize {
     let mut new_infections = Vec::new();
+    let radius = params.infection_radius_between(DiseaseState::Infected, DiseaseState::Susceptible);
 
     for i in 0..boids.len() {
-        if boids[i].disease_state == DiseaseState::Infected {
+        // Quarantined boids neither spread nor catch infection
+        if boids[i].disease_state == DiseaseState::Infected && !boids[i].quarantined() {
             // Only check nearby boids using spatial grid
             let nearby_indices = spatial_grid.query_nearby_indices(
                 boids[i].position,
-                params.infection_radius
+                params.max_infection_radius()
             );
 
             for j in nearby_indices {
-                if i != j && boids[j].disease_state == DiseaseState::Susceptible {
+                if i != j && boids[j].disease_state == DiseaseState::Susceptible && !boids[j].quarantined() {
                     let dist = (boids[i].position - boids[j].position).length();
-                    if dist < params.infection_radius {
-                        if rand::gen_range(0.0, 1.0) < params.infection_probability {
-                            new_infections.push(j);
-                        }
+                    if dist < radius && rng.chance(params.infection_probability) {
+                        new_infections.push(j);
                     }
                 }
             }
         }
     }
 
+    let mut count = 0;
     for &idx in &new_infections {
+        // A boid within range of several infected boids can be listed twice
+        if boids[idx].disease_state != DiseaseState::Susceptible {
+            continue;
+        }
+        count += 1;
         // For SEIR, newly infected go to Exposed first
         // For SIR and SIS, they go directly to Infected
         boids[idx].disease_state = match params.model {
@@ -51,14 +62,19 @@ pub fn process_infections(boids: &mut [Boid], params: &SimParams, spatial_grid:
         };
         // Reset the timer when changing disease state
         boids[idx].state_timer = 0.0;
+        boids[idx].diagnosed = false;
     }
+    count
 }
 
-pub fn count_disease_states(boids: &[Boid]) -> (usize, usize, usize, usize) {
+/// Boids in each state as (S, E, I, R, D); D counts only corpses not yet
+/// removed.
+pub fn count_disease_states(boids: &[Boid]) -> (usize, usize, usize, usize, usize) {
     let mut s = 0;
     let mut e = 0;
     let mut i = 0;
     let mut r = 0;
+    let mut d = 0;
 
     for boid in boids {
         match boid.disease_state {
@@ -66,8 +82,27 @@ pub fn count_disease_states(boids: &[Boid]) -> (usize, usize, usize, usize) {
             DiseaseState::Exposed => e += 1,
             DiseaseState::Infected => i += 1,
             DiseaseState::Recovered => r += 1,
+            DiseaseState::Dead => d += 1,
         }
     }
 
-    (s, e, i, r)
+    (s, e, i, r, d)
+}
+
+/// With `remove_corpses` on, drop dead boids that have finished fading out.
+/// Returns which of the old indices were removed, for index-keyed state such
+/// as `ExposureNotifier`.
+pub fn remove_corpses(boids: &mut Vec<Boid>, params: &SimParams) -> Vec<bool> {
+    let removed: Vec<bool> = boids
+        .iter()
+        .map(|b| params.remove_corpses && b.disease_state == DiseaseState::Dead && b.corpse_fade(params) <= 0.0)
+        .collect();
+    if removed.contains(&true) {
+        let mut k = 0;
+        boids.retain(|_| {
+            k += 1;
+            !removed[k - 1]
+        });
+    }
+    removed
 }
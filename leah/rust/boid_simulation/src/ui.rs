@@ -1,11 +1,105 @@
 use egui_macroquad::egui;
-use crate::simulation::SimParams;
+use boid_math::params::Adjustment;
+use crate::simulation::{InitialLayout, PerState, SimParams};
 use crate::sir::DiseaseModel;
 use crate::constants::SCREEN_WIDTH;
+use crate::calibration::Calibration;
+use crate::clock::{TimeUnit, MIN_SAMPLE_INTERVAL, MAX_SAMPLE_INTERVAL};
+use crate::density::{DensityStats, HISTOGRAM_BINS};
+use crate::experiment::{Experiment, MAX_GROUPS};
+use crate::keybindings::{Action, Keybindings, key_name};
+use crate::settings::{Palette, Theme};
+use crate::visualization::GraphWindow;
+
+/// Movable panels whose position is remembered across sessions
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Panel {
+    Params,
+    Density,
+    Calibration,
+    Experiment,
+    Help,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 5] = [Panel::Params, Panel::Density, Panel::Calibration, Panel::Experiment, Panel::Help];
+
+    /// Name in the settings file, also the panel's egui id
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Params => "params",
+            Panel::Density => "density",
+            Panel::Calibration => "calibration",
+            Panel::Experiment => "experiment",
+            Panel::Help => "help",
+        }
+    }
+
+    pub fn id(self) -> egui::Id {
+        egui::Id::new(self.name())
+    }
+
+    fn default_pos(self) -> egui::Pos2 {
+        match self {
+            Panel::Params => egui::pos2(10.0, 10.0),
+            Panel::Density => egui::pos2(SCREEN_WIDTH - 310.0, 10.0),
+            Panel::Calibration => egui::pos2(SCREEN_WIDTH - 470.0, 420.0),
+            Panel::Experiment => egui::pos2(SCREEN_WIDTH - 430.0, 200.0),
+            Panel::Help => egui::pos2(SCREEN_WIDTH / 2.0 - 150.0, 100.0),
+        }
+    }
+}
 
 pub struct UIState {
     pub show_graph: bool,
     pub params_collapsed: bool,
+    pub show_density: bool,
+    /// Offer a "Copy share link" button; only the WASM build can share.
+    pub show_share: bool,
+    pub show_help: bool,
+    /// Offer the exposure notification sliders; only loops that run an
+    /// `ExposureNotifier` act on them.
+    pub show_exposure: bool,
+    pub show_experiment: bool,
+    /// Expected contact and infection rates, plus radii at the cursor
+    pub show_calibration: bool,
+    /// Ring exposed and infected boids so they stand out in a dense swarm
+    pub show_rings: bool,
+    pub graph_window: GraphWindow,
+    /// Offer the theme and palette choices; only loops that apply them
+    /// (`app::run`) turn this on.
+    pub show_display: bool,
+    pub theme: Theme,
+    pub palette: Palette,
+    /// Where panels were last seen, once moved or restored from settings
+    pub panel_positions: Vec<(Panel, egui::Pos2)>,
+    /// Parameters the last edit asked for that `SimParams::normalize` changed
+    pub adjustments: Vec<Adjustment>,
+}
+
+impl UIState {
+    /// Where `panel` opens: its remembered spot, or its default one.
+    pub fn panel_pos(&self, panel: Panel) -> egui::Pos2 {
+        self.panel_positions
+            .iter()
+            .find(|(p, _)| *p == panel)
+            .map_or(panel.default_pos(), |&(_, pos)| pos)
+    }
+
+    /// Note where each panel egui has laid out sits now, so moves survive
+    /// being saved. Call after the frame's panels have been shown.
+    pub fn remember_panel_positions(&mut self, egui_ctx: &egui::Context) {
+        for panel in Panel::ALL {
+            let Some(rect) = egui_ctx.memory(|mem| mem.area_rect(panel.id())) else {
+                continue;
+            };
+            if rect.min == panel.default_pos() && !self.panel_positions.iter().any(|(p, _)| *p == panel) {
+                continue;
+            }
+            self.panel_positions.retain(|(p, _)| *p != panel);
+            self.panel_positions.push((panel, rect.min));
+        }
+    }
 }
 
 impl Default for UIState {
@@ -13,24 +107,32 @@ impl Default for UIState {
         Self {
             show_graph: true,
             params_collapsed: false,
+            show_density: false,
+            show_share: false,
+            show_help: false,
+            show_exposure: false,
+            show_experiment: false,
+            show_calibration: false,
+            show_rings: false,
+            graph_window: GraphWindow::default(),
+            show_display: false,
+            theme: Theme::default(),
+            palette: Palette::default(),
+            panel_positions: Vec::new(),
+            adjustments: Vec::new(),
         }
     }
 }
 
+#[derive(Default)]
 pub struct UIControls {
     pub should_restart: bool,
     pub boid_count_changed: bool,
     pub model_changed: bool,
-}
-
-impl Default for UIControls {
-    fn default() -> Self {
-        Self {
-            should_restart: false,
-            boid_count_changed: false,
-            model_changed: false,
-        }
-    }
+    pub share_requested: bool,
+    /// Restart with `params.seed` as it stands; other restarts draw a fresh
+    /// seed
+    pub replay_seed: bool,
 }
 
 pub fn render_parameter_panel(
@@ -39,6 +141,7 @@ pub fn render_parameter_panel(
     ui_state: &mut UIState,
 ) -> UIControls {
     let mut controls = UIControls::default();
+    let unit = params.time_unit.short();
 
     // Only show if not collapsed
     if ui_state.params_collapsed {
@@ -46,8 +149,9 @@ pub fn render_parameter_panel(
     }
 
     egui::Window::new("##params")
+        .id(Panel::Params.id())
         .title_bar(false)
-        .default_pos(egui::pos2(10.0, 10.0))
+        .default_pos(ui_state.panel_pos(Panel::Params))
         .default_width(SCREEN_WIDTH - 20.0)
         .resizable(false)
         .show(egui_ctx, |ui| {
@@ -61,23 +165,45 @@ pub fn render_parameter_panel(
                     if ui.button("Restart").clicked() {
                         controls.should_restart = true;
                     }
+                    if ui_state.show_share && ui.button("Copy share link").clicked() {
+                        controls.share_requested = true;
+                    }
+                    if ui_state.show_display {
+                        egui::ComboBox::from_id_salt("palette_selector")
+                            .selected_text(ui_state.palette.label())
+                            .show_ui(ui, |ui| {
+                                for palette in Palette::ALL {
+                                    ui.selectable_value(&mut ui_state.palette, palette, palette.label());
+                                }
+                            });
+                        egui::ComboBox::from_id_salt("theme_selector")
+                            .selected_text(ui_state.theme.label())
+                            .show_ui(ui, |ui| {
+                                for theme in Theme::ALL {
+                                    ui.selectable_value(&mut ui_state.theme, theme, theme.label());
+                                }
+                            });
+                    }
                 });
             });
 
             ui.separator();
             // Boid Parameters Section with grey background
+            let dark = ui_state.theme == Theme::Dark;
             egui::Frame::new()
-                .fill(egui::Color32::from_rgb(60, 60, 60))
+                .fill(if dark { egui::Color32::from_rgb(60, 60, 60) } else { egui::Color32::from_rgb(225, 225, 225) })
                 .inner_margin(egui::Margin::same(8))
                 .corner_radius(4.0)
                 .show(ui, |ui| {
-                    // Apply custom style for sliders in this section
-                    let mut style = (*ui.ctx().style()).clone();
-                    // Darken the slider rail background
-                    style.visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(30, 30, 30);
-                    style.visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(40, 40, 40);
-                    style.visuals.widgets.active.bg_fill = egui::Color32::from_rgb(50, 50, 50);
-                    ui.ctx().set_style(style);
+                    // Darken the slider rail background; the light theme's
+                    // rails already stand out from the lighter section
+                    if dark {
+                        let mut style = (*ui.ctx().style()).clone();
+                        style.visuals.widgets.inactive.bg_fill = egui::Color32::from_rgb(30, 30, 30);
+                        style.visuals.widgets.hovered.bg_fill = egui::Color32::from_rgb(40, 40, 40);
+                        style.visuals.widgets.active.bg_fill = egui::Color32::from_rgb(50, 50, 50);
+                        ui.ctx().set_style(style);
+                    }
 
                     ui.heading("Boid Parameters");
                     ui.horizontal(|ui| {
@@ -96,11 +222,40 @@ pub fn render_parameter_panel(
                         ui.vertical(|ui| {
                             ui.label("Separation Radius");
                             ui.add(egui::Slider::new(&mut params.separation_radius, 5.0..=50.0));
+                            for adjustment in &ui_state.adjustments {
+                                ui.small(adjustment.to_string());
+                            }
                         });
                         ui.vertical(|ui| {
                             ui.label("Max Speed");
                             ui.add(egui::Slider::new(&mut params.max_speed, 0.5..=5.0));
                         });
+                        ui.vertical(|ui| {
+                            ui.label("Initial Layout");
+                            let old_layout = params.layout;
+                            egui::ComboBox::from_id_salt("layout_selector")
+                                .selected_text(params.layout.label())
+                                .show_ui(ui, |ui| {
+                                    for layout in InitialLayout::ALL {
+                                        ui.selectable_value(&mut params.layout, layout, layout.label());
+                                    }
+                                });
+                            // Only takes effect when a run starts
+                            if params.layout != old_layout {
+                                controls.should_restart = true;
+                            }
+                        });
+                        ui.vertical(|ui| {
+                            ui.label("Seed");
+                            ui.horizontal(|ui| {
+                                if ui.add(egui::DragValue::new(&mut params.seed)).changed() {
+                                    controls.replay_seed = true;
+                                }
+                                if ui.button("Replay").on_hover_text("Restart with this seed").clicked() {
+                                    controls.replay_seed = true;
+                                }
+                            });
+                        });
                     });
                 });
 
@@ -141,22 +296,145 @@ pub fn render_parameter_panel(
                             ui.add(egui::Slider::new(&mut params.infection_probability, 0.001..=0.1).step_by(0.001));
                         });
                         ui.vertical(|ui| {
-                            ui.label("Recovery Time (s)");
+                            ui.label(format!("Recovery Time ({})", unit));
                             ui.add(egui::Slider::new(&mut params.recovery_time, 1.0..=20.0));
                         });
                         if params.model == DiseaseModel::SEIR {
                             ui.vertical(|ui| {
-                                ui.label("Incubation Time (s)");
+                                ui.label(format!("Incubation Time ({})", unit));
                                 ui.add(egui::Slider::new(&mut params.incubation_time, 1.0..=20.0));
                             });
                         }
+                        ui.vertical(|ui| {
+                            ui.label("Death Probability");
+                            ui.add(egui::Slider::new(&mut params.death_probability, 0.0..=1.0).step_by(0.01));
+                        });
+                        if params.death_probability > 0.0 {
+                            ui.vertical(|ui| {
+                                ui.checkbox(&mut params.remove_corpses, "Remove Corpses");
+                                if params.remove_corpses {
+                                    ui.add(egui::Slider::new(&mut params.corpse_fade_time, 1.0..=20.0)
+                                        .text(format!("fade ({})", unit)));
+                                }
+                            });
+                        }
+                        ui.vertical(|ui| {
+                            ui.label(format!("Graph Sample Interval ({}, on restart)", unit));
+                            ui.add(egui::Slider::new(&mut params.sample_interval, MIN_SAMPLE_INTERVAL..=MAX_SAMPLE_INTERVAL)
+                                .logarithmic(true));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label("Time Unit");
+                            egui::ComboBox::from_id_salt("time_unit_selector")
+                                .selected_text(params.time_unit.label())
+                                .show_ui(ui, |ui| {
+                                    for time_unit in TimeUnit::ALL {
+                                        ui.selectable_value(&mut params.time_unit, time_unit, time_unit.label());
+                                    }
+                                });
+                        });
+                    });
+
+                    ui.add_space(4.0);
+                    ui.label("Affinity (+ attract / - repel)");
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.colored_label(egui::Color32::WHITE, "Susceptible");
+                            ui.add(egui::Slider::new(&mut params.affinity_susceptible, -3.0..=3.0));
+                        });
+                        if params.model == DiseaseModel::SEIR {
+                            ui.vertical(|ui| {
+                                ui.colored_label(egui::Color32::from_rgb(255, 200, 0), "Exposed");
+                                ui.add(egui::Slider::new(&mut params.affinity_exposed, -3.0..=3.0));
+                            });
+                        }
+                        ui.vertical(|ui| {
+                            ui.colored_label(egui::Color32::from_rgb(255, 80, 80), "Infected");
+                            ui.add(egui::Slider::new(&mut params.affinity_infected, -3.0..=3.0));
+                        });
+                        ui.vertical(|ui| {
+                            ui.colored_label(egui::Color32::from_rgb(80, 130, 255), "Recovered");
+                            ui.add(egui::Slider::new(&mut params.affinity_recovered, -3.0..=3.0));
+                        });
+                    });
+
+                    ui.add_space(4.0);
+                    ui.label("Perception radius × by state");
+                    render_per_state_sliders(ui, &mut params.perception_scale, params.model);
+                    ui.label("Infection radius × by state (a contact uses the mean of both boids)");
+                    render_per_state_sliders(ui, &mut params.infection_scale, params.model);
+                });
+
+            if !ui_state.show_exposure {
+                return;
+            }
+            ui.add_space(6.0);
+
+            // Exposure notification section with blue background
+            egui::Frame::new()
+                .fill(egui::Color32::from_rgb(40, 50, 80))
+                .inner_margin(egui::Margin::same(8))
+                .corner_radius(4.0)
+                .show(ui, |ui| {
+                    ui.heading("Exposure Notification");
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("App Uptake");
+                            ui.add(egui::Slider::new(&mut params.app_uptake, 0.0..=1.0));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label(format!("Diagnosis Delay ({})", unit));
+                            ui.add(egui::Slider::new(&mut params.diagnosis_delay, 0.0..=20.0));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label(format!("Contact Window ({})", unit));
+                            ui.add(egui::Slider::new(&mut params.contact_window, 1.0..=30.0));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label(format!("Notification Delay ({})", unit));
+                            ui.add(egui::Slider::new(&mut params.notification_delay, 0.0..=10.0));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label("Compliance");
+                            ui.add(egui::Slider::new(&mut params.quarantine_compliance, 0.0..=1.0));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label(format!("Quarantine ({})", unit));
+                            ui.add(egui::Slider::new(&mut params.quarantine_duration, 1.0..=30.0));
+                        });
                     });
                 });
         });
 
+    ui_state.adjustments = params.normalize();
+
     controls
 }
 
+/// One multiplier slider per disease state; Exposed only under SEIR.
+fn render_per_state_sliders(ui: &mut egui::Ui, table: &mut PerState, model: DiseaseModel) {
+    ui.horizontal(|ui| {
+        ui.vertical(|ui| {
+            ui.colored_label(egui::Color32::WHITE, "Susceptible");
+            ui.add(egui::Slider::new(&mut table.susceptible, 0.25..=2.0));
+        });
+        if model == DiseaseModel::SEIR {
+            ui.vertical(|ui| {
+                ui.colored_label(egui::Color32::from_rgb(255, 200, 0), "Exposed");
+                ui.add(egui::Slider::new(&mut table.exposed, 0.25..=2.0));
+            });
+        }
+        ui.vertical(|ui| {
+            ui.colored_label(egui::Color32::from_rgb(255, 80, 80), "Infected");
+            ui.add(egui::Slider::new(&mut table.infected, 0.25..=2.0));
+        });
+        ui.vertical(|ui| {
+            ui.colored_label(egui::Color32::from_rgb(80, 130, 255), "Recovered");
+            ui.add(egui::Slider::new(&mut table.recovered, 0.25..=2.0));
+        });
+    });
+}
+
 pub fn render_collapsed_params_button(
     egui_ctx: &egui::Context,
     ui_state: &mut UIState,
@@ -186,24 +464,204 @@ pub fn render_graph_toggle(
 ) {
     let button_text = if ui_state.show_graph { "X [g]" } else { "≡ [g]" };
 
-    // Position at top of graph when shown, bottom-right corner when hidden
-    let (pos_x, pos_y) = if ui_state.show_graph {
-        (graph_x + 340.0, graph_y + 5.0)
+    // Position at top of graph when shown, bottom-right corner when hidden;
+    // the time window choice only shows with the graph
+    let (pos_x, pos_y, width) = if ui_state.show_graph {
+        (graph_x + 235.0, graph_y + 5.0, 160.0)
     } else {
-        (graph_x + 340.0, graph_y + 115.0) // Bottom right of screen
+        (graph_x + 340.0, graph_y + 115.0, 55.0) // Bottom right of screen
     };
 
     egui::Window::new("##graph_toggle")
         .title_bar(false)
         .fixed_pos(egui::pos2(pos_x, pos_y))
-        .fixed_size(egui::vec2(55.0, 30.0))
+        .fixed_size(egui::vec2(width, 30.0))
         .frame(egui::Frame::new()
             .fill(egui::Color32::from_rgba_unmultiplied(40, 40, 40, 200))
             .corner_radius(4.0))
         .resizable(false)
         .show(egui_ctx, |ui| {
-            if ui.button(button_text).clicked() {
-                ui_state.show_graph = !ui_state.show_graph;
-            }
+            ui.horizontal(|ui| {
+                if ui_state.show_graph {
+                    for window in GraphWindow::ALL {
+                        ui.selectable_value(&mut ui_state.graph_window, window, window.label());
+                    }
+                }
+                if ui.button(button_text).clicked() {
+                    ui_state.show_graph = !ui_state.show_graph;
+                }
+            });
+        });
+}
+
+pub fn render_density_panel(
+    egui_ctx: &egui::Context,
+    ui_state: &mut UIState,
+    stats: &DensityStats,
+) {
+    let pos = ui_state.panel_pos(Panel::Density);
+    egui::Window::new("Contact Density [d]")
+        .id(Panel::Density.id())
+        .open(&mut ui_state.show_density)
+        .default_pos(pos)
+        .resizable(false)
+        .show(egui_ctx, |ui| {
+            ui.label(format!("Neighbors (perception radius): mean {:.1}", stats.mean_neighbors));
+            draw_histogram(ui, &stats.neighbor_histogram, egui::Color32::from_rgb(100, 150, 255));
+            ui.separator();
+            ui.label(format!("Contacts (infection radius): mean {:.2}", stats.mean_contacts));
+            draw_histogram(ui, &stats.contact_histogram, egui::Color32::from_rgb(255, 100, 100));
+            ui.small(format!("Last bin counts {}+", HISTOGRAM_BINS - 1));
+        });
+}
+
+/// Expected contacts and time to infection at typical densities under the
+/// current parameters; recomputed every frame so it follows the sliders.
+/// `prevalence` is the infected share of the flock right now.
+pub fn render_calibration_panel(
+    egui_ctx: &egui::Context,
+    ui_state: &mut UIState,
+    calibration: &Calibration,
+    params: &SimParams,
+    mean_neighbors: Option<f32>,
+    prevalence: f32,
+) {
+    let unit = params.time_unit;
+    let wait = |t: Option<f32>| t.map_or("never".to_owned(), |t| unit.format(t));
+    let pos = ui_state.panel_pos(Panel::Calibration);
+    egui::Window::new("Infection Calibration [c]")
+        .id(Panel::Calibration.id())
+        .open(&mut ui_state.show_calibration)
+        .default_pos(pos)
+        .resizable(false)
+        .show(egui_ctx, |ui| {
+            ui.label(format!(
+                "One infectious contact infects in ~{} (rolled {:.0}× per {})",
+                wait(calibration.time_to_infection(params, 1.0)),
+                calibration.frames_per_second(),
+                unit.short(),
+            ));
+            egui::Grid::new("calibration_cases").num_columns(6).striped(true).show(ui, |ui| {
+                ui.label("Density");
+                ui.label("per 100² px");
+                ui.label("Contacts");
+                ui.label(format!("New contacts/{}", unit.short()));
+                ui.label("Infection if all infected");
+                ui.label(format!("at {:.0}% infected", prevalence * 100.0));
+                ui.end_row();
+                for case in calibration.cases(params, mean_neighbors) {
+                    ui.label(case.label);
+                    ui.label(format!("{:.2}", case.density));
+                    ui.label(format!("{:.2}", case.contacts));
+                    ui.label(format!("{:.1}", case.new_contacts_per_second));
+                    ui.label(wait(case.time_to_infection));
+                    ui.label(wait(calibration.time_to_infection(params, case.contacts * prevalence)));
+                    ui.end_row();
+                }
+            });
+            ui.small("New contacts assume random headings; an aligned flock meets fewer.");
+            ui.small("Hover the swarm to see what a boid under the cursor would face.");
+        });
+}
+
+/// Experiment groups: name, color, share and flocking overrides per group.
+/// Returns true when group membership changed (enabling, shares, adding or
+/// removing a group), which only takes effect on a restart; the overrides
+/// apply immediately.
+pub fn render_experiment_panel(
+    egui_ctx: &egui::Context,
+    ui_state: &mut UIState,
+    experiment: &mut Experiment,
+) -> bool {
+    let before = experiment.clone();
+    let pos = ui_state.panel_pos(Panel::Experiment);
+    egui::Window::new("Experiment Groups [e]")
+        .id(Panel::Experiment.id())
+        .open(&mut ui_state.show_experiment)
+        .default_pos(pos)
+        .resizable(false)
+        .show(egui_ctx, |ui| {
+            ui.checkbox(&mut experiment.enabled, "Split the flock into groups (restarts)");
+            ui.add_enabled_ui(experiment.enabled, |ui| {
+                let mut remove = None;
+                egui::Grid::new("experiment_groups").num_columns(6).show(ui, |ui| {
+                    ui.label("Group");
+                    ui.label("Share");
+                    ui.label("Distance ×");
+                    ui.label("Cohesion ×");
+                    ui.label("Speed ×");
+                    ui.end_row();
+                    for (k, group) in experiment.groups.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.color_edit_button_srgb(&mut group.color);
+                            ui.add(egui::TextEdit::singleline(&mut group.name).desired_width(80.0));
+                        });
+                        ui.add(egui::DragValue::new(&mut group.share).range(0.0..=10.0).speed(0.05));
+                        ui.add(egui::Slider::new(&mut group.distance_scale, 0.25..=2.0));
+                        ui.add(egui::Slider::new(&mut group.cohesion_scale, 0.0..=3.0));
+                        ui.add(egui::Slider::new(&mut group.speed_scale, 0.25..=2.0));
+                        if k > 0 && ui.small_button("✖").clicked() {
+                            remove = Some(k);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(k) = remove {
+                    experiment.groups.remove(k);
+                }
+                if experiment.groups.len() < MAX_GROUPS && ui.button("Add group").clicked() {
+                    experiment.add_group();
+                }
+            });
+            ui.small("Disease parameters are shared; groups differ only in flocking.");
+        });
+
+    experiment.enabled != before.enabled
+        || experiment.groups.len() != before.groups.len()
+        || experiment.groups.iter().zip(&before.groups).any(|(a, b)| a.share != b.share)
+}
+
+fn draw_histogram(ui: &mut egui::Ui, bins: &[usize], color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(280.0, 60.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(30));
+
+    let max = bins.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bar_width = rect.width() / bins.len() as f32;
+    for (k, &count) in bins.iter().enumerate() {
+        let height = count as f32 / max * rect.height();
+        let x = rect.left() + k as f32 * bar_width;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x + 1.0, rect.bottom() - height),
+            egui::pos2(x + bar_width - 1.0, rect.bottom()),
+        );
+        painter.rect_filled(bar, 0.0, color);
+    }
+}
+
+/// Lists the current key for each of `actions`, the ones this app handles.
+pub fn render_help_panel(
+    egui_ctx: &egui::Context,
+    ui_state: &mut UIState,
+    bindings: &Keybindings,
+    actions: &[Action],
+) {
+    let pos = ui_state.panel_pos(Panel::Help);
+    egui::Window::new(format!("Keyboard [{}]", key_name(bindings.key(Action::Help)).to_lowercase()))
+        .id(Panel::Help.id())
+        .open(&mut ui_state.show_help)
+        .default_pos(pos)
+        .resizable(false)
+        .collapsible(false)
+        .show(egui_ctx, |ui| {
+            egui::Grid::new("keybindings").num_columns(2).show(ui, |ui| {
+                for &action in actions {
+                    ui.monospace(key_name(bindings.key(action)));
+                    ui.label(action.description());
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.small(format!("Remap keys in {}", crate::keybindings::KEYBINDINGS_FILE));
         });
 }
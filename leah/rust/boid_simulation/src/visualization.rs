@@ -1,36 +1,264 @@
 use macroquad::prelude::*;
-use std::collections::VecDeque;
-use crate::constants::{GRAPH_HEIGHT, GRAPH_WIDTH, GRAPH_HISTORY};
-use crate::sir::DiseaseModel;
+use crate::constants::{GRAPH_HEIGHT, GRAPH_WIDTH, GRAPH_MAX_SAMPLES};
+use crate::clock::{SAMPLE_INTERVAL, TimeUnit};
+use crate::ensemble::Ensemble;
+use crate::settings::Palette;
+use crate::sir::{DiseaseModel, DiseaseState};
 
+/// How much of the run the population graph shows.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum GraphWindow {
+    Last30s,
+    #[default]
+    Last2Min,
+    /// Everything since the last restart, downsampled to fit
+    FullRun,
+}
+
+impl GraphWindow {
+    pub const ALL: [GraphWindow; 3] = [GraphWindow::Last30s, GraphWindow::Last2Min, GraphWindow::FullRun];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GraphWindow::Last30s => "30s",
+            GraphWindow::Last2Min => "2m",
+            GraphWindow::FullRun => "All",
+        }
+    }
+
+    fn seconds(self) -> Option<f32> {
+        match self {
+            GraphWindow::Last30s => Some(30.0),
+            GraphWindow::Last2Min => Some(120.0),
+            GraphWindow::FullRun => None,
+        }
+    }
+}
+
+/// A turning point of the epidemic, marked on the graph.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    FirstTransmission,
+    /// Incidence over the last few seconds clearly up on the few before
+    Growth,
+    /// Highest prevalence, confirmed once it has fallen a fifth below it
+    Peak,
+    /// No one infected or exposed any more
+    Extinction,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::FirstTransmission => "first case",
+            Phase::Growth => "growth",
+            Phase::Peak => "peak",
+            Phase::Extinction => "extinct",
+        }
+    }
+}
+
+/// Seconds in each of the two incidence windows compared for growth.
+const GROWTH_SECONDS: f32 = 2.0;
+
+/// Finds `Phase`s online from the samples as they arrive. Times are in added
+/// samples, so they survive the history being compacted.
+#[derive(Default)]
+struct PhaseTracker {
+    /// Samples in each growth window, `GROWTH_SECONDS` at the sample interval
+    growth_window: usize,
+    events: Vec<(usize, Phase)>,
+    prev_susceptible: Option<f32>,
+    first_cumulative: Option<f32>,
+    prev_cumulative: f32,
+    /// New infections per sample, last `2 * growth_window` samples
+    incidence: std::collections::VecDeque<f32>,
+    peak: Option<(usize, f32)>,
+    active: bool,
+}
+
+impl PhaseTracker {
+    fn new(sample_interval: f32) -> Self {
+        Self {
+            growth_window: ((GROWTH_SECONDS / sample_interval).round() as usize).max(1),
+            ..Default::default()
+        }
+    }
+
+    fn has(&self, phase: Phase) -> bool {
+        self.events.iter().any(|&(_, p)| p == phase)
+    }
+
+    fn mark(&mut self, sample: usize, phase: Phase) {
+        if !self.has(phase) {
+            self.events.push((sample, phase));
+        }
+    }
+
+    /// Infections since the last sample as far as the counts show them; SIS
+    /// recoveries can hide some, which the cumulative count corrects.
+    fn record(&mut self, sample: usize, s: f32, prevalence: f32) {
+        let new_cases = self.prev_susceptible.map_or(0.0, |prev| (prev - s).max(0.0));
+        self.prev_susceptible = Some(s);
+        self.push_incidence(sample, new_cases);
+
+        if prevalence > 0.0 {
+            self.active = true;
+            if self.peak.is_none_or(|(_, top)| prevalence > top) && !self.has(Phase::Peak) {
+                self.peak = Some((sample, prevalence));
+            }
+        }
+        if let Some((at, top)) = self.peak
+            && top >= 5.0
+            && prevalence <= 0.8 * top
+        {
+            self.mark(at, Phase::Peak);
+        }
+        if self.active && prevalence == 0.0 {
+            self.mark(sample, Phase::Extinction);
+        }
+    }
+
+    fn record_cumulative(&mut self, sample: usize, cumulative: f32) {
+        let first = *self.first_cumulative.get_or_insert(cumulative);
+        let extra = (cumulative - self.prev_cumulative).max(0.0);
+        // Top up this sample's incidence where the counts undercounted
+        if cumulative > first && let Some(last) = self.incidence.back_mut() && extra > *last {
+            *last = extra;
+            self.check_incidence(sample);
+        }
+        self.prev_cumulative = cumulative;
+    }
+
+    fn push_incidence(&mut self, sample: usize, new_cases: f32) {
+        self.incidence.push_back(new_cases);
+        if self.incidence.len() > 2 * self.growth_window {
+            self.incidence.pop_front();
+        }
+        self.check_incidence(sample);
+    }
+
+    fn check_incidence(&mut self, sample: usize) {
+        if self.incidence.back().is_some_and(|&c| c > 0.0) {
+            self.mark(sample, Phase::FirstTransmission);
+        }
+        let window = self.growth_window;
+        if self.incidence.len() == 2 * window {
+            let before: f32 = self.incidence.iter().take(window).sum();
+            let recent: f32 = self.incidence.iter().skip(window).sum();
+            if recent >= 5.0 && recent >= 1.25 * before.max(1.0) {
+                self.mark(sample, Phase::Growth);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct PopulationHistory {
-    susceptible: VecDeque<f32>,
-    exposed: VecDeque<f32>,
-    infected: VecDeque<f32>,
-    recovered: VecDeque<f32>,
+    susceptible: Vec<f32>,
+    exposed: Vec<f32>,
+    infected: Vec<f32>,
+    recovered: Vec<f32>,
+    /// Deaths so far, corpses already removed included
+    dead: Vec<f32>,
+    /// Total infections so far, seeded cases included; SIS reinfections count
+    /// again. Unlike prevalence this only grows, so it shows total burden even
+    /// when `infected` sits at an endemic level.
+    cumulative: Vec<f32>,
+    /// Samples added since the last clear, kept or not
+    added: usize,
+    /// Every `stride`-th added sample is kept; doubles whenever the run
+    /// outgrows `GRAPH_MAX_SAMPLES`
+    stride: usize,
+    window: GraphWindow,
+    /// Zoomed range, in added samples
+    zoom: Option<(usize, usize)>,
+    /// Screen x where a zoom drag started
+    drag_start: Option<f32>,
+    phases: PhaseTracker,
+    time_unit: TimeUnit,
+    /// Simulated seconds between added samples
+    sample_interval: f32,
+    palette: Palette,
 }
 
 impl PopulationHistory {
     pub fn new() -> Self {
         Self {
-            susceptible: VecDeque::new(),
-            exposed: VecDeque::new(),
-            infected: VecDeque::new(),
-            recovered: VecDeque::new(),
+            stride: 1,
+            phases: PhaseTracker::new(SAMPLE_INTERVAL),
+            sample_interval: SAMPLE_INTERVAL,
+            ..Default::default()
         }
     }
 
-    pub fn add(&mut self, s: usize, e: usize, i: usize, r: usize) {
-        self.susceptible.push_back(s as f32);
-        self.exposed.push_back(e as f32);
-        self.infected.push_back(i as f32);
-        self.recovered.push_back(r as f32);
+    /// Colors for the state lines and legend.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
 
-        if self.susceptible.len() > GRAPH_HISTORY {
-            self.susceptible.pop_front();
-            self.exposed.pop_front();
-            self.infected.pop_front();
-            self.recovered.pop_front();
+    /// Simulated seconds between samples: callers `add` whenever the clock
+    /// crosses a multiple of this, and the time axis and phase detection
+    /// read it back.
+    pub fn sample_interval(&self) -> f32 {
+        self.sample_interval
+    }
+
+    /// Change the sample interval. Samples taken at the old interval can't
+    /// share a time axis with new ones, so a change clears the history.
+    pub fn set_sample_interval(&mut self, interval: f32) {
+        if interval != self.sample_interval {
+            self.sample_interval = interval;
+            self.clear();
+        }
+    }
+
+    pub fn set_window(&mut self, window: GraphWindow) {
+        self.window = window;
+    }
+
+    /// Unit the time axis is labelled in.
+    pub fn set_time_unit(&mut self, time_unit: TimeUnit) {
+        self.time_unit = time_unit;
+    }
+
+    /// Record the cumulative infection count alongside the most recent `add`.
+    pub fn add_cumulative(&mut self, cumulative: usize) {
+        // `add` already decided whether this sample is kept
+        if self.cumulative.len() < self.susceptible.len() {
+            self.cumulative.push(cumulative as f32);
+        }
+        self.phases.record_cumulative(self.added.saturating_sub(1), cumulative as f32);
+    }
+
+    /// Epidemic phases found so far, at the added sample where each occurred.
+    pub fn phases(&self) -> &[(usize, Phase)] {
+        &self.phases.events
+    }
+
+    /// Record state counts; `d` is every death so far, not just corpses
+    /// still on screen.
+    pub fn add(&mut self, s: usize, e: usize, i: usize, r: usize, d: usize) {
+        let stride = self.stride.max(1);
+        let index = self.added;
+        self.added += 1;
+        self.phases.record(index, s as f32, (e + i) as f32);
+        if !index.is_multiple_of(stride) {
+            return;
+        }
+        self.susceptible.push(s as f32);
+        self.exposed.push(e as f32);
+        self.infected.push(i as f32);
+        self.recovered.push(r as f32);
+        self.dead.push(d as f32);
+
+        if self.susceptible.len() > GRAPH_MAX_SAMPLES {
+            for series in [&mut self.susceptible, &mut self.exposed, &mut self.infected,
+                           &mut self.recovered, &mut self.dead, &mut self.cumulative] {
+                let mut k = 0;
+                series.retain(|_| { k += 1; k % 2 == 1 });
+            }
+            self.stride = stride * 2;
         }
     }
 
@@ -39,9 +267,93 @@ impl PopulationHistory {
         self.exposed.clear();
         self.infected.clear();
         self.recovered.clear();
+        self.dead.clear();
+        self.cumulative.clear();
+        self.added = 0;
+        self.stride = 1;
+        self.zoom = None;
+        self.drag_start = None;
+        self.phases = PhaseTracker::new(self.sample_interval);
     }
 
-    pub fn draw(&self, x: f32, y: f32, total_boids: f32, model: DiseaseModel) {
+    /// Range of added samples on screen, and how many the x axis spans.
+    fn visible_range(&self) -> (usize, usize, usize) {
+        if let Some((start, end)) = self.zoom {
+            return (start, end, end - start);
+        }
+        match self.window.seconds() {
+            Some(secs) => {
+                let span = (secs / self.sample_interval) as usize;
+                (self.added.saturating_sub(span), self.added, span)
+            }
+            None => (0, self.added, self.added),
+        }
+    }
+
+    /// Drag across the graph to zoom into that time range; right-click resets.
+    fn handle_mouse(&mut self, x: f32, y: f32) {
+        let (mx, my) = mouse_position();
+        let inside = mx >= x && mx <= x + GRAPH_WIDTH && my >= y + 30.0 && my <= y + GRAPH_HEIGHT;
+        if inside && is_mouse_button_pressed(MouseButton::Right) {
+            self.zoom = None;
+        }
+        if inside && is_mouse_button_pressed(MouseButton::Left) {
+            self.drag_start = Some(mx);
+        }
+        if is_mouse_button_released(MouseButton::Left) && let Some(start_x) = self.drag_start.take() {
+            let end_x = mx.clamp(x, x + GRAPH_WIDTH);
+            // Ignore clicks; only a real drag zooms
+            if (end_x - start_x).abs() < 5.0 {
+                return;
+            }
+            let (start, _, span) = self.visible_range();
+            let to_sample = |sx: f32| start + (((sx - x) / GRAPH_WIDTH) * span as f32) as usize;
+            let (a, b) = (to_sample(start_x.min(end_x)), to_sample(start_x.max(end_x)).min(self.added));
+            if b > a + 1 {
+                self.zoom = Some((a, b));
+            }
+        }
+    }
+
+    /// Shade the ensemble's inter-quartile band of infected boids over the
+    /// graph `draw` left at the same `x`, `y`, with its mean dashed. Each
+    /// column reads the replicas at the simulated time of the sample plotted
+    /// there.
+    pub fn draw_band(&self, x: f32, y: f32, ensemble: &Ensemble) {
+        if self.susceptible.is_empty() {
+            return;
+        }
+        let (start, _, span) = self.visible_range();
+        let span = span.max(1) as f32;
+        // Fractions of `num_boids`, the scale `draw` plots counts on
+        let py = |f: f32| y + GRAPH_HEIGHT - f.min(1.0) * (GRAPH_HEIGHT - 30.0);
+        let infected = self.palette.color(DiseaseState::Infected);
+        let shade = Color::new(infected.r, infected.g, infected.b, 0.25);
+        let mut prev_mean: Option<(f32, f32)> = None;
+        let mut col = 0.0;
+        while col < GRAPH_WIDTH {
+            // Sample k is added once the clock passes (k + 1) intervals
+            let sample = start as f32 + col / GRAPH_WIDTH * span;
+            let time = (sample + 1.0) * self.sample_interval;
+            match ensemble.band_at(time) {
+                Some(band) => {
+                    let (top, bottom) = (py(band.q75), py(band.q25));
+                    draw_rectangle(x + col, top, 2.0, (bottom - top).max(1.0), shade);
+                    let mean = (x + col, py(band.mean));
+                    if let Some((px, pm)) = prev_mean && (col as usize / 2).is_multiple_of(2) {
+                        draw_line(px, pm, mean.0, mean.1, 1.0, infected);
+                    }
+                    prev_mean = Some(mean);
+                }
+                None => prev_mean = None,
+            }
+            col += 2.0;
+        }
+    }
+
+    pub fn draw(&mut self, x: f32, y: f32, total_boids: f32, model: DiseaseModel) {
+        self.handle_mouse(x, y);
+
         draw_rectangle(x, y, GRAPH_WIDTH, GRAPH_HEIGHT, Color::from_rgba(20, 20, 20, 255));
         draw_rectangle_lines(x, y, GRAPH_WIDTH, GRAPH_HEIGHT, 2.0, GRAY);
 
@@ -51,57 +363,119 @@ impl PopulationHistory {
             DiseaseModel::SEIR => "SEIR Population Over Time",
         };
         draw_text(title, x + 10.0, y + 20.0, 20.0, WHITE);
+        if self.zoom.is_some() {
+            draw_text("zoomed - right-click to reset", x + 10.0, y + 36.0, 14.0, GRAY);
+        }
+
+        if let Some(start_x) = self.drag_start {
+            let end_x = mouse_position().0.clamp(x, x + GRAPH_WIDTH);
+            draw_rectangle(start_x.min(end_x), y + 30.0, (end_x - start_x).abs(), GRAPH_HEIGHT - 30.0,
+                Color::from_rgba(255, 255, 255, 40));
+        }
 
         if self.susceptible.is_empty() {
             return;
         }
 
+        // Time axis: simulated time at the left and right edges, one sample
+        // every `sample_interval`
+        let (start, end, span) = self.visible_range();
+        let axis = Color::from_rgba(160, 160, 160, 255);
+        let left = self.time_unit.format(start as f32 * self.sample_interval);
+        draw_text(&left, x + 4.0, y + GRAPH_HEIGHT - 4.0, 14.0, axis);
+        let right = self.time_unit.format((start + span) as f32 * self.sample_interval);
+        let width = measure_text(&right, None, 14, 1.0).width;
+        draw_text(&right, x + GRAPH_WIDTH - width - 4.0, y + GRAPH_HEIGHT - 4.0, 14.0, axis);
+
         let max_val = total_boids;
-        let len = self.susceptible.len();
+        let stride = self.stride.max(1);
+        let first = start.div_ceil(stride);
+        let last = (end.div_ceil(stride)).min(self.susceptible.len());
+        if last <= first + 1 {
+            return;
+        }
+        // At most one point per pixel column
+        let step = (last - first).div_ceil(GRAPH_WIDTH as usize).max(1);
+        let mut points: Vec<usize> = (first..last).step_by(step).collect();
+        if points.last() != Some(&(last - 1)) {
+            points.push(last - 1);
+        }
+        let span = span.max(1) as f32;
+        let px = |k: usize| x + ((k * stride - start) as f32 / span) * GRAPH_WIDTH;
+        let py = |v: f32| y + GRAPH_HEIGHT - (v / max_val).min(1.0) * (GRAPH_HEIGHT - 30.0);
+        let has_cumulative = self.cumulative.len() == self.susceptible.len();
+        // Deaths only accumulate, so any at all shows in the last sample
+        let has_deaths = self.dead.last().is_some_and(|&d| d > 0.0);
+        let color = |state| self.palette.color(state);
+        let dead_color = color(DiseaseState::Dead);
 
-        for i in 1..len {
-            let x1 = x + ((i - 1) as f32 / GRAPH_HISTORY as f32) * GRAPH_WIDTH;
-            let x2 = x + (i as f32 / GRAPH_HISTORY as f32) * GRAPH_WIDTH;
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let (x1, x2) = (px(a), px(b));
 
-            let s1 = y + GRAPH_HEIGHT - (self.susceptible[i - 1] / max_val) * (GRAPH_HEIGHT - 30.0);
-            let s2 = y + GRAPH_HEIGHT - (self.susceptible[i] / max_val) * (GRAPH_HEIGHT - 30.0);
-            draw_line(x1, s1, x2, s2, 2.0, WHITE);
+            draw_line(x1, py(self.susceptible[a]), x2, py(self.susceptible[b]), 2.0, color(DiseaseState::Susceptible));
 
             // Only draw Exposed line for SEIR model
             if model == DiseaseModel::SEIR {
-                let e1 = y + GRAPH_HEIGHT - (self.exposed[i - 1] / max_val) * (GRAPH_HEIGHT - 30.0);
-                let e2 = y + GRAPH_HEIGHT - (self.exposed[i] / max_val) * (GRAPH_HEIGHT - 30.0);
-                draw_line(x1, e1, x2, e2, 2.0, Color::from_rgba(255, 200, 0, 255));
+                draw_line(x1, py(self.exposed[a]), x2, py(self.exposed[b]), 2.0, color(DiseaseState::Exposed));
             }
 
-            let i1 = y + GRAPH_HEIGHT - (self.infected[i - 1] / max_val) * (GRAPH_HEIGHT - 30.0);
-            let i2 = y + GRAPH_HEIGHT - (self.infected[i] / max_val) * (GRAPH_HEIGHT - 30.0);
-            draw_line(x1, i1, x2, i2, 2.0, RED);
+            draw_line(x1, py(self.infected[a]), x2, py(self.infected[b]), 2.0, color(DiseaseState::Infected));
 
             // Only draw Recovered line for SIR and SEIR models
             if model != DiseaseModel::SIS {
-                let r1 = y + GRAPH_HEIGHT - (self.recovered[i - 1] / max_val) * (GRAPH_HEIGHT - 30.0);
-                let r2 = y + GRAPH_HEIGHT - (self.recovered[i] / max_val) * (GRAPH_HEIGHT - 30.0);
-                draw_line(x1, r1, x2, r2, 2.0, BLUE);
+                draw_line(x1, py(self.recovered[a]), x2, py(self.recovered[b]), 2.0, color(DiseaseState::Recovered));
+            }
+
+            if has_deaths {
+                draw_line(x1, py(self.dead[a]), x2, py(self.dead[b]), 2.0, dead_color);
+            }
+
+            if has_cumulative {
+                draw_line(x1, py(self.cumulative[a]), x2, py(self.cumulative[b]), 1.0, MAGENTA);
+            }
+        }
+
+        // Phase markers: a dashed line and label at each sample on screen
+        for &(sample, phase) in &self.phases.events {
+            if sample < start || sample >= end {
+                continue;
+            }
+            let mx = x + ((sample - start) as f32 / span) * GRAPH_WIDTH;
+            let mut dy = y + 44.0;
+            while dy < y + GRAPH_HEIGHT {
+                draw_line(mx, dy, mx, (dy + 4.0).min(y + GRAPH_HEIGHT), 1.0, Color::from_rgba(200, 200, 200, 140));
+                dy += 8.0;
             }
+            draw_text(phase.label(), mx + 3.0, y + 54.0, 14.0, Color::from_rgba(200, 200, 200, 220));
         }
 
         // Draw legend based on model
-        let legend_x = x + GRAPH_WIDTH - 250.0;
+        let legend_x = x + GRAPH_WIDTH - 300.0;
         let legend_y = y + 40.0;
-        draw_text("S", legend_x, legend_y, 18.0, WHITE);
+        draw_text("S", legend_x, legend_y, 18.0, color(DiseaseState::Susceptible));
 
         let mut offset = 50.0;
         if model == DiseaseModel::SEIR {
-            draw_text("E", legend_x + offset, legend_y, 18.0, Color::from_rgba(255, 200, 0, 255));
+            draw_text("E", legend_x + offset, legend_y, 18.0, color(DiseaseState::Exposed));
             offset += 50.0;
         }
 
-        draw_text("I", legend_x + offset, legend_y, 18.0, RED);
+        draw_text("I", legend_x + offset, legend_y, 18.0, color(DiseaseState::Infected));
         offset += 50.0;
 
         if model != DiseaseModel::SIS {
-            draw_text("R", legend_x + offset, legend_y, 18.0, BLUE);
+            draw_text("R", legend_x + offset, legend_y, 18.0, color(DiseaseState::Recovered));
+            offset += 50.0;
+        }
+
+        if has_deaths {
+            draw_text("D", legend_x + offset, legend_y, 18.0, dead_color);
+            offset += 50.0;
+        }
+
+        if !self.cumulative.is_empty() {
+            draw_text("Cum. I", legend_x + offset, legend_y, 18.0, MAGENTA);
         }
     }
 }
@@ -0,0 +1,67 @@
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::simulation::SimParams;
+use crate::sir::DiseaseState;
+
+/// A parameter combination that makes the simulation meaningless, with what
+/// to change to fix it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigWarning {
+    pub problem: String,
+    pub fix: String,
+}
+
+/// Typical distance between a boid and its nearest neighbor: separation
+/// holds flocking boids about `separation_radius` apart, and a sparse flock
+/// spread evenly over the screen is never closer than that.
+pub fn typical_spacing(params: &SimParams) -> f32 {
+    let even = (SCREEN_WIDTH * SCREEN_HEIGHT / params.num_boids.max(1) as f32).sqrt();
+    params.separation_radius.min(even)
+}
+
+/// Every degenerate configuration `params` is in. Speeds are per frame,
+/// like movement and the infection roll, so no frame rate is involved.
+pub fn check(params: &SimParams) -> Vec<ConfigWarning> {
+    let mut warnings = Vec::new();
+    let radius = params.infection_radius_between(DiseaseState::Infected, DiseaseState::Susceptible);
+    let spacing = typical_spacing(params);
+
+    // Neighbors rarely come closer than half the spacing, so infection
+    // would need boids to pass right through each other
+    if radius < spacing / 2.0 {
+        warnings.push(ConfigWarning {
+            problem: format!(
+                "infection radius {:.0} px is well below the typical boid spacing of {:.0} px, so almost nothing spreads",
+                radius, spacing
+            ),
+            fix: format!(
+                "raise infection radius to at least {:.0} or lower separation radius to {:.0}",
+                spacing / 2.0,
+                radius * 2.0
+            ),
+        });
+    }
+    // Two boids closing head-on shorten their distance by twice the speed
+    // each frame; once that exceeds the contact diameter they can pass
+    // through each other between two infection rolls
+    if params.max_speed > radius {
+        warnings.push(ConfigWarning {
+            problem: format!(
+                "max speed {:.1} px/frame outruns the infection radius {:.0} px; boids can tunnel through contacts between frames",
+                params.max_speed, radius
+            ),
+            fix: format!("lower max speed below {:.1} or raise infection radius above {:.1}", radius, params.max_speed),
+        });
+    }
+    // `normalize` caps separation at perception, so equal means it was
+    // at or above it
+    if params.separation_radius >= params.perception_radius {
+        warnings.push(ConfigWarning {
+            problem: format!(
+                "separation radius {:.0} px covers the whole perception radius, so every neighbor repels and the flock never forms",
+                params.separation_radius
+            ),
+            fix: format!("lower separation radius to about {:.0}", params.perception_radius / 2.0),
+        });
+    }
+    warnings
+}
@@ -0,0 +1,27 @@
+// Share-link support for boid_simulation.wasm. Load after mq_js_bundle.js.
+miniquad_add_plugin({
+    name: "boids_share",
+    version: 1,
+    register_plugin: function (importObject) {
+        const encoder = new TextEncoder();
+        const decoder = new TextDecoder();
+        const query = () => encoder.encode(window.location.search.replace(/^\?/, ""));
+
+        importObject.env.boids_page_query_len = function () {
+            return query().length;
+        };
+        importObject.env.boids_page_query_copy = function (ptr, len) {
+            new Uint8Array(wasm_memory.buffer, ptr, len).set(query().subarray(0, len));
+        };
+        importObject.env.boids_copy_share_link = function (ptr, len) {
+            const search = decoder.decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+            const url = window.location.origin + window.location.pathname + "?" + search;
+            window.history.replaceState(null, "", url);
+            if (navigator.clipboard) {
+                navigator.clipboard.writeText(url).catch(function (err) {
+                    console.warn("could not copy share link:", err);
+                });
+            }
+        };
+    },
+});
@@ -0,0 +1,1437 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod tui;
+
+use rust_src::calibration::{grid_fit, parse_incidence_csv};
+use rust_src::chart::{ChartFormat, chart_rows, export_chart};
+use rust_src::commute::{Commute, Patch};
+use rust_src::config_file::ConfigFile;
+use rust_src::contacts::ContactNetwork;
+use rust_src::ensemble::OutbreakStats;
+use rust_src::export::TickExport;
+use rust_src::heatmap::write_heatmap_png;
+use rust_src::history::{DEFAULT_SAMPLE_INTERVAL, History, Sampler};
+use rust_src::household::{HouseholdSplit, Households};
+use rust_src::immunization::Immunization;
+use rust_src::incidence::IncidenceGrid;
+use rust_src::intervention::{Scenario, compare};
+use rust_src::oscillation;
+use rust_src::quarantine_zone::QuarantineZone;
+use rust_src::recording::Recorder;
+use rust_src::render::{NullRenderer, draw_frame};
+use rust_src::restriction::TravelRestriction;
+use rust_src::rundb::{Filter, Query, RunRecord, RunStore};
+use rust_src::runner::run_to_end;
+use rust_src::scheduler::Scheduler;
+use rust_src::scripting::Script;
+use rust_src::sensitivity::{ParamRange, RunSettings, run_sensitivity};
+use rust_src::sim::{
+    ActivitySchedule, Boundaries, DiseaseModel, Integrator, MemoryFootprint, MovementModel,
+    SimConfig, Simulation,
+};
+use rust_src::snapshot::{contact_sheet, final_state};
+use rust_src::stats::{
+    EpidemicTracker, herd_immunity_threshold, infected_clustering, mean_field_r0,
+};
+use rust_src::stop::StopConditions;
+use rust_src::sweep::run_phase_diagram;
+use rust_src::tiled::TiledSimulation;
+use rust_src::timestep::AdaptiveDt;
+use rust_src::train::{CemSettings, train};
+use rust_src::trajectory::{TrackSelection, TrajectoryRecorder};
+use rust_src::transmission::{
+    IntervalStats, generation_intervals, intervals_csv, serial_intervals,
+};
+use rust_src::venue::{Venue, VenueIncidence};
+use rust_src::world::WorldPreset;
+
+const DT: f32 = 1.0 / 60.0;
+/// Graph samples the null renderer keeps at full resolution, as the live
+/// view does.
+const GRAPH_RECENT: usize = 360;
+
+struct Args {
+    boids: usize,
+    steps: usize,
+    seed: u64,
+    threads: usize,
+    check_determinism: bool,
+    estimate_memory: bool,
+    report: Option<PathBuf>,
+    contacts: Option<PathBuf>,
+    timeseries: Option<PathBuf>,
+    chart: Option<PathBuf>,
+    chart_format: ChartFormat,
+    /// Simulated seconds between samples of every recorded series.
+    sample_interval: f32,
+    intervals: Option<PathBuf>,
+    sensitivity: bool,
+    params: Vec<ParamRange>,
+    samples: usize,
+    replicates: usize,
+    out: Option<PathBuf>,
+    ensemble: bool,
+    phase_diagram: bool,
+    x: Option<ParamRange>,
+    y: Option<ParamRange>,
+    grid: (usize, usize),
+    heatmap: Option<PathBuf>,
+    /// Sweeps and ensembles: final state of every point or replicate as one
+    /// image, and as one image each in this directory.
+    contact_sheet: Option<PathBuf>,
+    thumbnails: Option<PathBuf>,
+    oscillation: bool,
+    fit: Option<PathBuf>,
+    day: f32,
+    compare: bool,
+    scenarios: Vec<Scenario>,
+    incidence: Option<PathBuf>,
+    incidence_png: Option<PathBuf>,
+    cell_size: f32,
+    immunization: Immunization,
+    boundaries: Boundaries,
+    world: WorldPreset,
+    movement: MovementModel,
+    integrator: Integrator,
+    schedule: Option<ActivitySchedule>,
+    households: Option<Households>,
+    travel_restriction: Option<TravelRestriction>,
+    commute: Option<Commute>,
+    disease_model: DiseaseModel,
+    incubation_period: f32,
+    warmup: f32,
+    script: Option<PathBuf>,
+    metrics: Option<PathBuf>,
+    tui: bool,
+    adaptive: Option<AdaptiveDt>,
+    tiles: Option<(usize, usize)>,
+    venues: Vec<Venue>,
+    quarantine_zones: Vec<QuarantineZone>,
+    db: Option<PathBuf>,
+    track: TrackSelection,
+    trajectories: Option<PathBuf>,
+    /// Where to write a replayable recording of the main run.
+    record: Option<PathBuf>,
+    /// Where to stream a CSV row per step of the main run.
+    tick_csv: Option<PathBuf>,
+    /// Time and state conditions ending the main run; `steps` is its step
+    /// limit.
+    stop: StopConditions,
+    checkpoint: Option<PathBuf>,
+    /// World size and flocking and epidemic parameters from `--config`;
+    /// everything else in it is mirrored into the fields above, so flags
+    /// override the file.
+    base: SimConfig,
+    /// Train a steering policy with CEM, evaluating candidates with the
+    /// run settings above.
+    train: bool,
+    cem: CemSettings,
+    policy_out: Option<PathBuf>,
+    /// Policy file to steer the run with, and to start training from.
+    policy: Option<PathBuf>,
+    /// Draw every step through the null renderer, as the live view would.
+    null_render: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            boids: 2400,
+            steps: 600,
+            seed: 1337,
+            threads: std::thread::available_parallelism().map_or(4, |n| n.get()),
+            check_determinism: false,
+            estimate_memory: false,
+            report: None,
+            contacts: None,
+            timeseries: None,
+            chart: None,
+            chart_format: ChartFormat::VegaLite,
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
+            intervals: None,
+            sensitivity: false,
+            params: Vec::new(),
+            samples: 20,
+            replicates: 3,
+            out: None,
+            ensemble: false,
+            phase_diagram: false,
+            x: None,
+            y: None,
+            grid: (10, 10),
+            heatmap: None,
+            contact_sheet: None,
+            thumbnails: None,
+            oscillation: false,
+            fit: None,
+            day: 1.0,
+            compare: false,
+            scenarios: Vec::new(),
+            incidence: None,
+            incidence_png: None,
+            cell_size: 40.0,
+            immunization: Immunization::None,
+            boundaries: Boundaries::default(),
+            world: WorldPreset::OpenField,
+            movement: MovementModel::Flocking,
+            integrator: Integrator::SemiImplicitEuler,
+            schedule: None,
+            households: None,
+            travel_restriction: None,
+            commute: None,
+            disease_model: DiseaseModel::Sir,
+            incubation_period: SimConfig::default().incubation_period,
+            warmup: 0.0,
+            script: None,
+            metrics: None,
+            tui: false,
+            adaptive: None,
+            tiles: None,
+            venues: Vec::new(),
+            quarantine_zones: Vec::new(),
+            db: None,
+            track: TrackSelection::IndexCases,
+            trajectories: None,
+            record: None,
+            tick_csv: None,
+            stop: StopConditions::default(),
+            checkpoint: None,
+            base: SimConfig::default(),
+            train: false,
+            cem: CemSettings::default(),
+            policy_out: None,
+            policy: None,
+            null_render: false,
+        }
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut stop_on_given = false;
+    // The config file goes first wherever it appears, so flags win.
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let configs: Vec<&String> = argv
+        .windows(2)
+        .filter(|w| w[0] == "--config")
+        .map(|w| &w[1])
+        .collect();
+    if configs.len() > 1 {
+        return Err("--config given more than once".into());
+    }
+    if let Some(path) = configs.first() {
+        let base = ConfigFile {
+            boids: args.boids,
+            config: args.config(),
+            smoothing: 0.0,
+            step_budget: 0.0,
+            sample_interval: args.sample_interval,
+        };
+        args.apply_config_file(&ConfigFile::load(Path::new(path), base)?);
+    }
+    let mut iter = argv.into_iter();
+    while let Some(flag) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .ok_or_else(|| format!("missing value for {name}"))
+        };
+        match flag.as_str() {
+            "--boids" => args.boids = parse_num(&value("--boids")?)?,
+            "--steps" => args.steps = parse_num(&value("--steps")?)?,
+            "--seed" => args.seed = parse_num(&value("--seed")?)?,
+            "--threads" => args.threads = parse_num(&value("--threads")?)?,
+            "--check-determinism" => args.check_determinism = true,
+            "--estimate-memory" => args.estimate_memory = true,
+            "--report" => args.report = Some(value("--report")?.into()),
+            "--contacts" => args.contacts = Some(value("--contacts")?.into()),
+            "--timeseries" => args.timeseries = Some(value("--timeseries")?.into()),
+            "--intervals" => args.intervals = Some(value("--intervals")?.into()),
+            "--chart" => args.chart = Some(value("--chart")?.into()),
+            "--chart-format" => args.chart_format = ChartFormat::parse(&value("--chart-format")?)?,
+            "--sample-interval" => args.sample_interval = parse_num(&value("--sample-interval")?)?,
+            // The older form, in steps of the default step length.
+            "--sample-every" => {
+                args.sample_interval = parse_num::<usize>(&value("--sample-every")?)? as f32 * DT
+            }
+            "--sensitivity" => args.sensitivity = true,
+            "--param" => args.params.push(ParamRange::parse(&value("--param")?)?),
+            "--samples" => args.samples = parse_num(&value("--samples")?)?,
+            "--replicates" => args.replicates = parse_num(&value("--replicates")?)?,
+            "--out" => args.out = Some(value("--out")?.into()),
+            "--ensemble" => args.ensemble = true,
+            "--phase-diagram" => args.phase_diagram = true,
+            "--x" => args.x = Some(ParamRange::parse(&value("--x")?)?),
+            "--y" => args.y = Some(ParamRange::parse(&value("--y")?)?),
+            "--grid" => {
+                let spec = value("--grid")?;
+                let (nx, ny) = spec
+                    .split_once('x')
+                    .ok_or_else(|| format!("expected NxM, got {spec}"))?;
+                args.grid = (parse_num(nx)?, parse_num(ny)?);
+            }
+            "--tiles" => {
+                let spec = value("--tiles")?;
+                let (nx, ny) = spec
+                    .split_once('x')
+                    .ok_or_else(|| format!("expected NxM, got {spec}"))?;
+                args.tiles = Some((parse_num(nx)?, parse_num(ny)?));
+            }
+            "--heatmap" => args.heatmap = Some(value("--heatmap")?.into()),
+            "--contact-sheet" => args.contact_sheet = Some(value("--contact-sheet")?.into()),
+            "--thumbnails" => args.thumbnails = Some(value("--thumbnails")?.into()),
+            "--oscillation" => args.oscillation = true,
+            "--fit" => args.fit = Some(value("--fit")?.into()),
+            "--day" => args.day = parse_num(&value("--day")?)?,
+            "--compare" => args.compare = true,
+            "--incidence" => args.incidence = Some(value("--incidence")?.into()),
+            "--incidence-png" => args.incidence_png = Some(value("--incidence-png")?.into()),
+            "--immunize" => args.immunization = Immunization::parse(&value("--immunize")?)?,
+            "--boundary" => args.boundaries = Boundaries::parse(&value("--boundary")?)?,
+            // Sets the preset's boundaries too; a later --boundary overrides.
+            "--world" => {
+                args.world = WorldPreset::parse(&value("--world")?)?;
+                args.boundaries = args.world.boundaries();
+            }
+            "--movement" => args.movement = MovementModel::parse(&value("--movement")?)?,
+            "--integrator" => args.integrator = Integrator::parse(&value("--integrator")?)?,
+            "--day-night" => args.schedule = Some(ActivitySchedule::parse(&value("--day-night")?)?),
+            "--households" => args.households = Some(Households::parse(&value("--households")?)?),
+            "--restrict-travel" => {
+                args.travel_restriction =
+                    Some(TravelRestriction::parse(&value("--restrict-travel")?)?)
+            }
+            "--commute" => args.commute = Some(Commute::parse(&value("--commute")?)?),
+            "--model" => args.disease_model = DiseaseModel::parse(&value("--model")?)?,
+            "--incubation" => args.incubation_period = parse_num(&value("--incubation")?)?,
+            "--warmup" => args.warmup = parse_num(&value("--warmup")?)?,
+            "--script" => args.script = Some(value("--script")?.into()),
+            "--metrics" => args.metrics = Some(value("--metrics")?.into()),
+            "--tui" => args.tui = true,
+            "--adaptive-dt" => args.adaptive = Some(AdaptiveDt::parse(&value("--adaptive-dt")?)?),
+            "--cell-size" => args.cell_size = parse_num(&value("--cell-size")?)?,
+            "--venue" => args.venues.push(Venue::parse(&value("--venue")?)?),
+            "--quarantine-zone" => args
+                .quarantine_zones
+                .push(QuarantineZone::parse(&value("--quarantine-zone")?)?),
+            "--db" => args.db = Some(value("--db")?.into()),
+            "--track" => args.track = TrackSelection::parse(&value("--track")?)?,
+            "--trajectories" => args.trajectories = Some(value("--trajectories")?.into()),
+            "--record" => args.record = Some(value("--record")?.into()),
+            "--tick-csv" => args.tick_csv = Some(value("--tick-csv")?.into()),
+            "--checkpoint" => args.checkpoint = Some(value("--checkpoint")?.into()),
+            "--max-time" => args.stop.max_time = Some(parse_num(&value("--max-time")?)?),
+            "--stop-on" => {
+                args.stop.parse_events(&value("--stop-on")?)?;
+                stop_on_given = true;
+            }
+            "--scenario" => args.scenarios.push(Scenario::parse(&value("--scenario")?)?),
+            "--train" => args.train = true,
+            "--iterations" => args.cem.iterations = parse_num(&value("--iterations")?)?,
+            "--candidates" => args.cem.candidates = parse_num(&value("--candidates")?)?,
+            "--elite" => args.cem.elite_fraction = parse_num(&value("--elite")?)?,
+            "--sigma" => args.cem.sigma = parse_num(&value("--sigma")?)?,
+            "--sigma-decay" => args.cem.sigma_decay = parse_num(&value("--sigma-decay")?)?,
+            "--patience" => args.cem.patience = parse_num(&value("--patience")?)?,
+            "--tolerance" => args.cem.tolerance = parse_num(&value("--tolerance")?)?,
+            "--null-render" => args.null_render = true,
+            "--policy" => args.policy = Some(value("--policy")?.into()),
+            "--policy-out" => args.policy_out = Some(value("--policy-out")?.into()),
+            "--config" => {
+                value("--config")?;
+            }
+            other => return Err(format!("unknown argument: {other}")),
+        }
+    }
+    if args.adaptive.is_some() && args.oscillation {
+        // The spectral analysis assumes evenly spaced samples.
+        return Err("--oscillation needs a fixed step; drop --adaptive-dt".into());
+    }
+    if args.oscillation && !stop_on_given {
+        // Endemic runs are analysed over their full length.
+        args.stop.extinct = false;
+    }
+    Ok(args)
+}
+
+fn parse_num<T: std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid number: {s}"))
+}
+
+/// Runs the configured scenario inside a 1-thread and an N-thread rayon pool
+/// and compares state hashes after every step.
+fn check_determinism(args: &Args) -> ExitCode {
+    let run = |threads: usize| -> Vec<u64> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .expect("failed to build thread pool");
+        pool.install(|| {
+            let mut sim = Simulation::new(args.boids, args.config(), args.seed);
+            (0..args.steps)
+                .map(|_| {
+                    sim.step(DT);
+                    sim.state_hash()
+                })
+                .collect()
+        })
+    };
+
+    let single = run(1);
+    let multi = run(args.threads);
+    if let Some(step) = single.iter().zip(&multi).position(|(a, b)| a != b) {
+        eprintln!(
+            "determinism check FAILED at step {step}: 1 thread {:016x} vs {} threads {:016x}",
+            single[step], args.threads, multi[step]
+        );
+        return ExitCode::FAILURE;
+    }
+    println!(
+        "determinism check passed: {} steps, 1 vs {} threads, final hash {:016x}",
+        args.steps,
+        args.threads,
+        single.last().copied().unwrap_or_default()
+    );
+    ExitCode::SUCCESS
+}
+
+impl Args {
+    fn config(&self) -> SimConfig {
+        SimConfig {
+            immunization: self.immunization,
+            boundaries: self.boundaries,
+            world: self.world,
+            movement: self.movement,
+            integrator: self.integrator,
+            schedule: self.schedule,
+            households: self.households,
+            travel_restriction: self.travel_restriction,
+            commute: self.commute,
+            disease_model: self.disease_model,
+            incubation_period: self.incubation_period,
+            warmup: self.warmup.max(0.0),
+            ..self.base
+        }
+    }
+
+    fn apply_config_file(&mut self, file: &ConfigFile) {
+        let cfg = file.config;
+        self.boids = file.boids;
+        self.sample_interval = file.sample_interval;
+        self.immunization = cfg.immunization;
+        self.boundaries = cfg.boundaries;
+        self.world = cfg.world;
+        self.movement = cfg.movement;
+        self.integrator = cfg.integrator;
+        self.schedule = cfg.schedule;
+        self.households = cfg.households;
+        self.travel_restriction = cfg.travel_restriction;
+        self.commute = cfg.commute;
+        self.disease_model = cfg.disease_model;
+        self.incubation_period = cfg.incubation_period;
+        self.warmup = cfg.warmup;
+        self.base = cfg;
+    }
+
+    /// Sweeps report progress and, with `--checkpoint`, save each run as
+    /// it completes.
+    fn scheduler(&self) -> Scheduler {
+        Scheduler {
+            checkpoint: self.checkpoint.clone(),
+            progress: true,
+        }
+    }
+
+    fn run_settings(&self) -> RunSettings {
+        RunSettings {
+            base: self.config(),
+            boids: self.boids,
+            replicates: self.replicates,
+            seed: self.seed,
+            max_steps: self.steps,
+            dt: DT,
+        }
+    }
+}
+
+/// Latin hypercube sampling over `--param` ranges, reporting partial rank
+/// correlation coefficients of peak prevalence and final size.
+fn sensitivity(args: &Args) -> ExitCode {
+    let ranges = if args.params.is_empty() {
+        [
+            "infection_radius=8:40",
+            "infection_beta=0.2:3",
+            "infectious_period=2:12",
+        ]
+        .iter()
+        .map(|s| ParamRange::parse(s).expect("valid default range"))
+        .collect()
+    } else {
+        args.params.clone()
+    };
+    let result = match run_sensitivity(
+        &ranges,
+        args.samples,
+        &args.run_settings(),
+        &args.scheduler(),
+    ) {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!(
+        "{:<20} {:>10} {:>10}",
+        "parameter", "PRCC peak", "PRCC size"
+    );
+    for index in &result.indices {
+        println!(
+            "{:<20} {:>10.3} {:>10.3}",
+            index.param.name(),
+            index.peak_prevalence,
+            index.final_size
+        );
+    }
+
+    if let Some(path) = &args.out {
+        let mut csv = String::new();
+        for range in &ranges {
+            let _ = write!(csv, "{},", range.param.name());
+        }
+        csv.push_str("peak_prevalence,final_size\n");
+        for sample in &result.samples {
+            for v in &sample.values {
+                let _ = write!(csv, "{v:.4},");
+            }
+            let _ = writeln!(
+                csv,
+                "{:.4},{:.4}",
+                sample.outcome.peak_prevalence, sample.outcome.final_size
+            );
+        }
+        if let Err(err) = std::fs::write(path, csv) {
+            eprintln!("failed to write {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Train a steering policy with CEM, starting from `--policy` if given,
+/// and write the best one to `--policy-out`.
+fn train_policy(args: &Args) -> ExitCode {
+    let (run, settings) = (args.run_settings(), args.cem);
+    if !(0.0..=1.0).contains(&settings.elite_fraction) || settings.candidates == 0 {
+        eprintln!("--elite must be within 0..1 and --candidates at least 1");
+        return ExitCode::FAILURE;
+    }
+    let mut sim = Simulation::new(run.boids, run.base, run.seed);
+    if let Err(err) = sim.load_policies(&args.policy.iter().cloned().collect::<Vec<_>>()) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    let initial = sim.policy().clone();
+    println!(
+        "training: {} candidates x {} replicates x {} steps, {} elites, {} threads",
+        settings.candidates,
+        run.replicates.max(1),
+        run.max_steps,
+        settings.elites(),
+        args.threads
+    );
+    println!(
+        "{:>4} {:>8} {:>8} {:>8} {:>7}",
+        "iter", "mean", "elite", "best", "sigma"
+    );
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("failed to build thread pool: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = pool.install(|| {
+        train(&initial, &run, &settings, |entry| {
+            println!(
+                "{:>4} {:>8.3} {:>8.3} {:>8.3} {:>7.3}",
+                entry.iteration, entry.mean_score, entry.elite_score, entry.best_score, entry.sigma
+            );
+        })
+    });
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "best score {:.3} (fraction never infected){}",
+        result.best_score,
+        if result.stopped_early {
+            format!(
+                ", stopped after {} iterations without improvement",
+                settings.patience
+            )
+        } else {
+            String::new()
+        }
+    );
+    if let Some(path) = &args.policy_out {
+        if let Err(err) = result.best.save(path) {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+        println!("wrote {}", path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+/// Independent replicates of the default scenario, classified as minor or
+/// major outbreaks.
+fn ensemble(args: &Args) -> ExitCode {
+    use rayon::prelude::*;
+
+    let settings = args.run_settings();
+    let outcomes: Vec<_> = (0..args.replicates.max(1))
+        .into_par_iter()
+        .map(|r| {
+            run_to_end(
+                settings.base,
+                settings.boids,
+                settings.seed.wrapping_add(r as u64),
+                settings.max_steps,
+                settings.dt,
+            )
+        })
+        .collect();
+    print!("{}", OutbreakStats::from_outcomes(&outcomes));
+
+    let major: Vec<_> = outcomes.iter().filter(|o| o.is_major()).collect();
+    if !major.is_empty() {
+        let hit = major.iter().map(|o| o.herd_immunity_threshold).sum::<f32>() / major.len() as f32;
+        let r0 = mean_field_r0(&settings.base, settings.boids);
+        println!(
+            "herd immunity: {:.1}% observed (mean of major outbreaks) vs {:.1}% mean-field (R0 {:.2})",
+            hit * 100.0,
+            herd_immunity_threshold(r0) * 100.0,
+            r0
+        );
+    }
+
+    if let Some(path) = &args.out {
+        let mut csv = String::from("replicate,final_size,peak_prevalence,duration,extinct,major\n");
+        for (r, o) in outcomes.iter().enumerate() {
+            let _ = writeln!(
+                csv,
+                "{r},{:.4},{:.4},{:.3},{},{}",
+                o.final_size,
+                o.peak_prevalence,
+                o.duration,
+                o.extinct,
+                o.is_major()
+            );
+        }
+        if let Err(err) = std::fs::write(path, csv) {
+            eprintln!("failed to write {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    let runs: Vec<(String, SimConfig, usize)> = (0..outcomes.len())
+        .map(|r| (format!("replicate{r}"), settings.base, r))
+        .collect();
+    let columns = (runs.len() as f32).sqrt().ceil() as usize;
+    if let Err(err) = write_snapshots(args, &runs, columns) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// 2D sweep over `--x` and `--y` producing attack rate and extinction
+/// probability per grid cell.
+fn phase_diagram(args: &Args) -> ExitCode {
+    let x = args
+        .x
+        .unwrap_or_else(|| ParamRange::parse("infection_radius=4:40").expect("valid default"));
+    let y = args
+        .y
+        .unwrap_or_else(|| ParamRange::parse("infection_beta=0.1:3").expect("valid default"));
+    let (nx, ny) = args.grid;
+    let diagram = match run_phase_diagram(
+        x,
+        y,
+        nx.max(1),
+        ny.max(1),
+        &args.run_settings(),
+        &args.scheduler(),
+    ) {
+        Ok(diagram) => diagram,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let unfinished: usize = diagram.cells.iter().map(|c| c.unfinished).sum();
+    if unfinished > 0 {
+        eprintln!(
+            "note: {unfinished} runs were still infectious after {} steps; raise --steps to see how they end",
+            args.steps
+        );
+    }
+    let csv = diagram.to_csv();
+    match &args.out {
+        Some(path) => {
+            if let Err(err) = std::fs::write(path, csv) {
+                eprintln!("failed to write {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{csv}"),
+    }
+    if let Some(path) = &args.heatmap
+        && let Err(err) =
+            write_heatmap_png(path, diagram.nx, diagram.ny, &diagram.attack_rates(), 24)
+    {
+        eprintln!("failed to write {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+    // Laid out like the heatmap: x to the right, y upward.
+    let base = args.run_settings().base;
+    let runs: Vec<(String, SimConfig, usize)> = (0..diagram.ny)
+        .rev()
+        .flat_map(|j| (0..diagram.nx).map(move |i| (i, j)))
+        .map(|(i, j)| {
+            let cell = &diagram.cells[j * diagram.nx + i];
+            let mut cfg = base;
+            diagram.x.param.apply(&mut cfg, cell.x);
+            diagram.y.param.apply(&mut cfg, cell.y);
+            (format!("x{i}_y{j}"), cfg, 0)
+        })
+        .collect();
+    if let Err(err) = write_snapshots(args, &runs, diagram.nx) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Width in pixels of each final-state thumbnail.
+const THUMB_WIDTH: usize = 96;
+
+/// Render where each named `(config, replicate)` run ended, in parallel, and
+/// write the images under `--thumbnails` and as one `--contact-sheet`,
+/// `columns` to a row. Does nothing unless one of them is set.
+fn write_snapshots(
+    args: &Args,
+    runs: &[(String, SimConfig, usize)],
+    columns: usize,
+) -> Result<(), String> {
+    use rayon::prelude::*;
+
+    if args.contact_sheet.is_none() && args.thumbnails.is_none() {
+        return Ok(());
+    }
+    let settings = args.run_settings();
+    let tiles: Vec<_> = runs
+        .par_iter()
+        .map(|(_, cfg, r)| final_state(&settings, *cfg, *r, THUMB_WIDTH))
+        .collect();
+    if let Some(dir) = &args.thumbnails {
+        std::fs::create_dir_all(dir)
+            .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+        for ((name, _, _), tile) in runs.iter().zip(&tiles) {
+            let path = dir.join(format!("{name}.png"));
+            tile.write_png(&path)
+                .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+        }
+        println!("thumbnails: {} in {}", tiles.len(), dir.display());
+    }
+    if let Some(path) = &args.contact_sheet {
+        contact_sheet(&tiles, columns)
+            .write_png(path)
+            .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+        println!("contact sheet: {} runs in {}", tiles.len(), path.display());
+    }
+    Ok(())
+}
+
+/// Domain-decomposed run for very large populations: the world is split
+/// into `--tiles` stepped in parallel on `--threads` threads of this one
+/// process, and only aggregate counts are reported. The world grows with `--boids` to keep the default density.
+fn tiled(args: &Args, nx: usize, ny: usize) -> ExitCode {
+    let scale = (args.boids as f32 / Args::default().boids as f32)
+        .sqrt()
+        .max(1.0);
+    let mut cfg = args.config();
+    cfg.world_size = cfg.world_size.mul(scale);
+    let pool = match rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads)
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(err) => {
+            eprintln!("failed to build thread pool: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut sim = match TiledSimulation::new(args.boids, cfg, args.seed, nx, ny) {
+        Ok(sim) => sim,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "{} boids in a {:.0}x{:.0} world, {nx}x{ny} tiles on {} threads",
+        sim.population(),
+        cfg.world_size.x,
+        cfg.world_size.y,
+        args.threads
+    );
+
+    let mut timeseries = String::from("time,susceptible,infected,recovered\n");
+    let mut sampler = Sampler::new(args.sample_interval);
+    let started = std::time::Instant::now();
+    pool.install(|| {
+        for step in 0..args.steps {
+            sim.step(DT);
+            let c = sim.counts();
+            if sampler.due(sim.time()) {
+                let _ = writeln!(
+                    timeseries,
+                    "{:.3},{},{},{}",
+                    sim.time(),
+                    c.susceptible,
+                    c.infected,
+                    c.recovered
+                );
+            }
+            if step % 60 == 0 {
+                println!(
+                    "step {step:>5}  S {:>8}{}  I {:>8}  R {:>8}  halo {:>7}",
+                    c.susceptible,
+                    exposed_count(cfg.disease_model, c.exposed),
+                    c.infected,
+                    c.recovered,
+                    sim.halo_boids()
+                );
+            }
+            if c.infected + c.exposed == 0 {
+                break;
+            }
+        }
+    });
+    let c = sim.counts();
+    println!(
+        "final       S {:>8}{}  I {:>8}  R {:>8}  infections {}  ({:.1}s simulated in {:.1}s)",
+        c.susceptible,
+        exposed_count(cfg.disease_model, c.exposed),
+        c.infected,
+        c.recovered,
+        sim.transitions().infections,
+        sim.time(),
+        started.elapsed().as_secs_f32()
+    );
+    if let Some(path) = &args.timeseries
+        && let Err(err) = std::fs::write(path, &timeseries)
+    {
+        eprintln!("failed to write time series {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Least-squares grid fit of `--x` and `--y` (beta and infection radius by
+/// default) to the daily incidence in the `--fit` CSV.
+fn fit(args: &Args, path: &Path) -> ExitCode {
+    let observed = match std::fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| parse_incidence_csv(&text))
+    {
+        Ok(observed) => observed,
+        Err(err) => {
+            eprintln!("failed to read {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let x = args
+        .x
+        .unwrap_or_else(|| ParamRange::parse("infection_beta=0.1:3").expect("valid default"));
+    let y = args
+        .y
+        .unwrap_or_else(|| ParamRange::parse("infection_radius=4:40").expect("valid default"));
+    let (nx, ny) = args.grid;
+    let best = grid_fit(
+        &observed,
+        x,
+        y,
+        nx.max(1),
+        ny.max(1),
+        &args.run_settings(),
+        args.day,
+    );
+
+    println!(
+        "best fit: {} = {:.4}, {} = {:.4} (SSE {:.2} over {} days)",
+        x.param.name(),
+        best.x_value,
+        y.param.name(),
+        best.y_value,
+        best.sse,
+        observed.len()
+    );
+    let csv = best.trajectory_csv(&observed);
+    match &args.out {
+        Some(path) => {
+            if let Err(err) = std::fs::write(path, csv) {
+                eprintln!("failed to write {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{csv}"),
+    }
+    ExitCode::SUCCESS
+}
+
+/// Baseline plus `--scenario` interventions on matched seeds, tabulated.
+fn compare_interventions(args: &Args) -> ExitCode {
+    let scenarios = if args.scenarios.is_empty() {
+        [
+            "distancing:separation_radius=44",
+            "vaccinate-30:vaccinate=0.3",
+            "degree-30:vaccinate_degree=0.3",
+            "ring-30:vaccinate_ring=0.3",
+            "masks:infection_beta=0.6",
+        ]
+        .iter()
+        .map(|s| Scenario::parse(s).expect("valid default scenario"))
+        .collect()
+    } else {
+        args.scenarios.clone()
+    };
+    let results = compare(&scenarios, &args.run_settings());
+
+    let mut csv = String::from("scenario,peak_infected,final_size,infections_averted\n");
+    println!(
+        "{:<16} {:>10} {:>10} {:>10}",
+        "scenario", "peak", "final", "averted"
+    );
+    for r in &results {
+        println!(
+            "{:<16} {:>10.1} {:>9.1}% {:>10.1}",
+            r.name,
+            r.peak_infected,
+            r.final_size * 100.0,
+            r.infections_averted
+        );
+        let _ = writeln!(
+            csv,
+            "{},{:.2},{:.4},{:.2}",
+            r.name, r.peak_infected, r.final_size, r.infections_averted
+        );
+    }
+    if let Some(path) = &args.out
+        && let Err(err) = std::fs::write(path, csv)
+    {
+        eprintln!("failed to write {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Columns `query` prints unless `--columns` says otherwise.
+const QUERY_COLUMNS: &str = "timestamp,boids,seed,infection_beta,infection_radius,movement,attack_rate,peak_prevalence,peak_time,duration";
+
+/// `headless query DB [--where COND]... [--sort COL] [--desc] [--limit N]
+/// [--columns a,b,...|all]`: list runs recorded with `--db`.
+fn query(mut iter: impl Iterator<Item = String>) -> Result<String, String> {
+    let path: PathBuf = iter
+        .next()
+        .ok_or("usage: headless query DB [--where COND]... [--sort COL] [--desc] [--limit N] [--columns LIST]")?
+        .into();
+    let mut query = Query::default();
+    let mut sort = None;
+    let mut descending = false;
+    let mut columns = QUERY_COLUMNS.to_owned();
+    while let Some(flag) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .ok_or_else(|| format!("missing value for {name}"))
+        };
+        match flag.as_str() {
+            "--where" => query.filters.push(Filter::parse(&value("--where")?)?),
+            "--sort" => sort = Some(value("--sort")?),
+            "--desc" => descending = true,
+            "--limit" => query.limit = Some(parse_num::<usize>(&value("--limit")?)?),
+            "--columns" => columns = value("--columns")?,
+            other => return Err(format!("unknown argument: {other}")),
+        }
+    }
+    if !path.exists() {
+        return Err(format!("no run store at {}", path.display()));
+    }
+    query.sort = sort.map(|column| (column, descending));
+    if columns != "all" {
+        query.columns = columns.split(',').map(str::to_owned).collect();
+    }
+    Ok(RunStore::open(&path)?.query(&query)?.to_string())
+}
+
+fn main() -> ExitCode {
+    if std::env::args().nth(1).as_deref() == Some("query") {
+        return match query(std::env::args().skip(2)) {
+            Ok(table) => {
+                println!("{table}");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.estimate_memory {
+        println!(
+            "estimated memory for {} boids: {}",
+            args.boids,
+            MemoryFootprint::estimate(args.boids, &args.config())
+        );
+        return ExitCode::SUCCESS;
+    }
+    if args.check_determinism {
+        return check_determinism(&args);
+    }
+    if let Some((nx, ny)) = args.tiles {
+        return tiled(&args, nx, ny);
+    }
+    if args.sensitivity {
+        return sensitivity(&args);
+    }
+    if args.ensemble {
+        return ensemble(&args);
+    }
+    if args.phase_diagram {
+        return phase_diagram(&args);
+    }
+    if args.compare {
+        return compare_interventions(&args);
+    }
+    if args.train {
+        return train_policy(&args);
+    }
+    if let Some(path) = &args.fit {
+        return fit(&args, path);
+    }
+
+    for adjustment in args.config().normalized().1 {
+        eprintln!("note: {adjustment}");
+    }
+    let mut sim = Simulation::new(args.boids, args.config(), args.seed);
+    if let Err(err) = sim.load_policies(&args.policy.iter().cloned().collect::<Vec<_>>()) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    sim.set_venues(args.venues.clone());
+    for &zone in &args.quarantine_zones {
+        match sim.add_quarantine_zone(zone) {
+            Ok(confined) => println!(
+                "quarantine zone {:.0},{:.0} to {:.0},{:.0}: {confined} infected confined",
+                zone.min.x, zone.min.y, zone.max.x, zone.max.y
+            ),
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let mut tracker = EpidemicTracker::new(&sim);
+    let mut trajectories = match &args.trajectories {
+        Some(_) => match TrajectoryRecorder::mark(&mut sim, &args.track) {
+            Ok(recorder) => Some(recorder),
+            Err(err) => {
+                eprintln!("failed to mark boids for tracking: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let mut recorder = args
+        .record
+        .as_ref()
+        .map(|_| Recorder::new(sim.config().world_size));
+    let mut tick_csv = match &args.tick_csv {
+        Some(path) => match TickExport::create(path) {
+            Ok(export) => Some(export),
+            Err(err) => {
+                eprintln!("failed to create {}: {err}", path.display());
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let mut contacts = args.contacts.as_ref().map(|_| ContactNetwork::new());
+    let mut timeseries =
+        String::from("time,susceptible,infected,recovered,nn_distance,nn_expected,clark_evans\n");
+    tracker.set_sample_interval(args.sample_interval);
+    let mut sampler = Sampler::new(args.sample_interval);
+    let mut prevalence = Vec::new();
+    let mut history = Vec::new();
+    let mut renderer = args.null_render.then(|| {
+        (
+            NullRenderer::default(),
+            History::sampled(GRAPH_RECENT, args.sample_interval),
+        )
+    });
+    let mut script = match args.script.as_deref().map(Script::load).transpose() {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("failed to load script: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut metrics = String::new();
+    // The live view drives the run itself; the per-step recording below is
+    // skipped, but the end-of-run report still applies.
+    let steps = if args.tui {
+        if let Err(err) = tui::monitor(&mut sim, &mut tracker, args.steps, DT) {
+            eprintln!("terminal error: {err}");
+            return ExitCode::FAILURE;
+        }
+        0
+    } else {
+        args.steps
+    };
+    let mut adaptive = args.adaptive;
+    let stop = StopConditions {
+        max_steps: steps,
+        ..args.stop
+    };
+    let mut steps_taken = 0;
+    let stopped = loop {
+        if let Some(reason) = stop.check(&sim, steps_taken) {
+            break reason;
+        }
+        let step = steps_taken;
+        let dt = match &mut adaptive {
+            Some(adaptive) => adaptive.next_dt(&sim),
+            None => DT,
+        };
+        steps_taken += 1;
+        match &mut script {
+            Some(script) => {
+                if let Err(err) = script.step(&mut sim, dt) {
+                    eprintln!("script error at step {step}: {err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            None => sim.step(dt),
+        }
+        let sample = sampler.due(sim.time());
+        if sample && let Some(script) = &mut script {
+            match script.metrics(&sim) {
+                Ok(values) => {
+                    if metrics.is_empty() && !values.is_empty() {
+                        metrics.push_str("time");
+                        for (name, _) in &values {
+                            let _ = write!(metrics, ",{name}");
+                        }
+                        metrics.push('\n');
+                    }
+                    if !values.is_empty() {
+                        let _ = write!(metrics, "{:.3}", sim.time());
+                        for (_, v) in &values {
+                            let _ = write!(metrics, ",{v:.4}");
+                        }
+                        metrics.push('\n');
+                    }
+                }
+                Err(err) => {
+                    eprintln!("script error at step {step}: {err}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        tracker.record(&sim, dt);
+        if let Some(contacts) = &mut contacts {
+            contacts.record(&sim, dt);
+        }
+        if let Some(trajectories) = &mut trajectories {
+            trajectories.record(&sim);
+        }
+        if let Some(recorder) = &mut recorder {
+            recorder.record(&sim);
+        }
+        if let Some(export) = &mut tick_csv
+            && let Err(err) = export.record(&sim)
+        {
+            eprintln!("failed to write tick csv: {err}");
+            return ExitCode::FAILURE;
+        }
+        if let Some((renderer, graph)) = &mut renderer {
+            graph.push(sim.time(), sim.counts());
+            draw_frame(renderer, &sim, graph, &[format!("step {step}")]);
+        }
+        if args.oscillation && sample {
+            prevalence.push(sim.counts().infected as f32 / sim.boids.len().max(1) as f32);
+        }
+        if args.chart.is_some() && sample {
+            history.push((tracker.time(), sim.counts()));
+        }
+        if args.timeseries.is_some() && sample {
+            let c = sim.counts();
+            let _ = write!(
+                timeseries,
+                "{:.3},{},{},{}",
+                tracker.time(),
+                c.susceptible,
+                c.infected,
+                c.recovered
+            );
+            let _ = match infected_clustering(&sim) {
+                Some(k) => writeln!(
+                    timeseries,
+                    ",{:.3},{:.3},{:.4}",
+                    k.mean_nn_distance, k.expected_nn_distance, k.ratio
+                ),
+                None => writeln!(timeseries, ",,,"),
+            };
+        }
+        if step % 60 == 0 {
+            let c = sim.counts();
+            println!(
+                "step {step:>5}  S {:>5}{}  I {:>5}  R {:>5}{}",
+                c.susceptible,
+                exposed_column(&sim),
+                c.infected,
+                c.recovered,
+                patch_prevalence(&sim)
+            );
+        }
+    };
+    let c = sim.counts();
+    println!(
+        "final       S {:>5}{}  I {:>5}  R {:>5}{}",
+        c.susceptible,
+        exposed_column(&sim),
+        c.infected,
+        c.recovered,
+        patch_prevalence(&sim)
+    );
+    if adaptive.is_some() && steps_taken > 0 {
+        println!(
+            "adaptive dt: {steps_taken} steps over {:.1}s, mean dt {:.4}s",
+            sim.time(),
+            sim.time() / steps_taken as f32
+        );
+    }
+
+    if let Some(path) = &args.metrics
+        && let Err(err) = std::fs::write(path, &metrics)
+    {
+        eprintln!("failed to write metrics {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    if let (Some(trajectories), Some(path)) = (&trajectories, &args.trajectories) {
+        println!("trajectories: {} boids tracked", trajectories.tracked());
+        if let Err(err) = std::fs::write(path, trajectories.csv()) {
+            eprintln!("failed to write trajectories {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(export) = tick_csv {
+        println!("tick csv: {} rows", export.rows());
+        if let Err(err) = export.finish() {
+            eprintln!("failed to write tick csv: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let (Some(recorder), Some(path)) = (&recorder, &args.record) {
+        println!(
+            "recording: {} frames, {} KiB",
+            recorder.frames(),
+            recorder.bytes().len() / 1024
+        );
+        if let Err(err) = recorder.save(path) {
+            eprintln!("failed to write recording {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &args.timeseries
+        && let Err(err) = std::fs::write(path, &timeseries)
+    {
+        eprintln!("failed to write time series {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    if let Some((r, _)) = &renderer {
+        println!(
+            "null renderer: {} frames, {} boids, {} overlay lines, {} graph samples",
+            r.frames, r.boids, r.overlay_lines, r.graph_samples
+        );
+    }
+    let log = sim.infection_log();
+    if !sim.venues().is_empty() {
+        println!("{}", VenueIncidence::from_log(sim.venues(), log));
+    }
+    if sim.config().households.is_some() {
+        println!("{}", HouseholdSplit::from_log(log));
+    }
+    for &(start, end) in sim.restriction_periods() {
+        match end {
+            Some(end) => println!("travel restricted from {start:.1}s to {end:.1}s"),
+            None => println!("travel restricted from {start:.1}s to the end"),
+        }
+    }
+    if let Some(path) = &args.chart {
+        // Close the history at the final state so the last interval counts
+        history.push((tracker.time(), sim.counts()));
+        history.dedup_by(|b, a| b.0 <= a.0);
+        if let Err(err) = export_chart(path, args.chart_format, &chart_rows(&history, log)) {
+            eprintln!("failed to write chart {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+    for (label, values) in [
+        ("generation interval", generation_intervals(log)),
+        ("serial interval", serial_intervals(log)),
+    ] {
+        let st = IntervalStats::from_values(&values);
+        println!(
+            "{label}: n={} mean {:.2}s  p10 {:.2}s  median {:.2}s  p90 {:.2}s",
+            st.count, st.mean, st.p10, st.median, st.p90
+        );
+    }
+    if let Some(path) = &args.intervals
+        && let Err(err) = std::fs::write(path, intervals_csv(log))
+    {
+        eprintln!("failed to write intervals {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    if args.incidence.is_some() || args.incidence_png.is_some() {
+        let grid = IncidenceGrid::from_log(log, sim.config().world_size, args.cell_size, false);
+        if let Some(path) = &args.incidence
+            && let Err(err) = grid.export(path)
+        {
+            eprintln!("failed to write incidence {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+        if let Some(path) = &args.incidence_png
+            && let Err(err) = write_heatmap_png(path, grid.nx, grid.ny, &grid.screen_values(), 8)
+        {
+            eprintln!("failed to write incidence {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let (Some(contacts), Some(path)) = (&contacts, &args.contacts) {
+        let d = contacts.degree_stats();
+        println!(
+            "contacts: {} edges, mean degree {:.2}, max degree {}, {} isolated",
+            d.edges, d.mean, d.max, d.isolated
+        );
+        if let Err(err) = contacts.export(path) {
+            eprintln!("failed to write contacts {}: {err}", path.display());
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut report = match tracker.summary() {
+        Some(summary) => format!("{summary}\n"),
+        None => format!(
+            "epidemic still active after {:.1}s; no summary\n",
+            tracker.time()
+        ),
+    };
+    // The live view decides for itself when to stop.
+    if !args.tui {
+        let _ = writeln!(
+            report,
+            "stopped: {stopped} after {steps_taken} steps, {:.1}s",
+            sim.time()
+        );
+    }
+    if args.oscillation {
+        let analysis = oscillation::analyze(&prevalence, args.sample_interval.max(DT));
+        let _ = writeln!(report, "{analysis}");
+    }
+    print!("{report}");
+    if let Some(path) = &args.report
+        && let Err(err) = std::fs::write(path, &report)
+    {
+        eprintln!("failed to write report {}: {err}", path.display());
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(path) = &args.db {
+        let record = RunRecord {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            boids: args.boids,
+            steps: args.steps,
+            seed: args.seed,
+            config: sim.config(),
+            venues: sim.venues().len(),
+            time: sim.time(),
+            counts: sim.counts(),
+            summary: tracker.summary(),
+        };
+        if let Err(err) = RunStore::open(path).and_then(|store| store.insert(&record)) {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// The exposed count for SEIR runs; empty otherwise, so SIR and SIS output
+/// keeps its columns.
+fn exposed_column(sim: &Simulation) -> String {
+    match sim.config().disease_model {
+        DiseaseModel::Seir => format!("  E {:>5}", sim.counts().exposed),
+        _ => String::new(),
+    }
+}
+
+/// `exposed_column` at the width of the tiled run's counts.
+fn exposed_count(model: DiseaseModel, exposed: usize) -> String {
+    match model {
+        DiseaseModel::Seir => format!("  E {exposed:>8}"),
+        _ => String::new(),
+    }
+}
+
+/// Share of each patch currently infected, for the progress lines of a
+/// commuting run; empty otherwise.
+fn patch_prevalence(sim: &Simulation) -> String {
+    let Some(counts) = sim.patch_counts() else {
+        return String::new();
+    };
+    let mut out = String::new();
+    for (patch, c) in Patch::ALL.into_iter().zip(counts) {
+        let share = c.infected as f32 / c.total().max(1) as f32;
+        let _ = write!(out, "  {} {:>5.1}%", patch.name(), share * 100.0);
+    }
+    out
+}
@@ -0,0 +1,144 @@
+use std::io;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Points};
+use ratatui::widgets::{Block, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+
+use rust_src::sim::{HealthState, Simulation};
+use rust_src::stats::EpidemicTracker;
+
+/// Steps between redraws; drawing every step would dominate the run time.
+const STEPS_PER_FRAME: usize = 10;
+/// History kept for the sparklines, in frames.
+const HISTORY: usize = 400;
+
+#[derive(Default)]
+struct History {
+    susceptible: Vec<u64>,
+    infected: Vec<u64>,
+    recovered: Vec<u64>,
+}
+
+impl History {
+    fn push(&mut self, sim: &Simulation) {
+        let c = sim.counts();
+        for (series, value) in [
+            (&mut self.susceptible, c.susceptible),
+            (&mut self.infected, c.infected),
+            (&mut self.recovered, c.recovered),
+        ] {
+            series.push(value as u64);
+            if series.len() > HISTORY {
+                series.remove(0);
+            }
+        }
+    }
+}
+
+/// Run up to `steps` steps of `dt` with a live braille map of the boids and
+/// S/I/R sparklines. Stops early once the epidemic ends or on `q`/Esc.
+pub fn monitor(
+    sim: &mut Simulation,
+    tracker: &mut EpidemicTracker,
+    steps: usize,
+    dt: f32,
+) -> io::Result<()> {
+    ratatui::run(|terminal| run(terminal, sim, tracker, steps, dt))
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    sim: &mut Simulation,
+    tracker: &mut EpidemicTracker,
+    steps: usize,
+    dt: f32,
+) -> io::Result<()> {
+    let mut history = History::default();
+    let mut step = 0;
+    while step < steps && tracker.summary().is_none() {
+        for _ in 0..STEPS_PER_FRAME.min(steps - step) {
+            sim.step(dt);
+            tracker.record(sim, dt);
+            step += 1;
+        }
+        history.push(sim);
+        terminal.draw(|frame| draw(frame, sim, &history, step))?;
+
+        if event::poll(Duration::ZERO)?
+            && let Event::Key(key) = event::read()?
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn draw(frame: &mut Frame, sim: &Simulation, history: &History, step: usize) {
+    let [map_area, charts] =
+        Layout::vertical([Constraint::Min(10), Constraint::Length(12)]).areas(frame.area());
+    let [s_area, i_area, r_area] = Layout::vertical([Constraint::Length(4); 3]).areas(charts);
+
+    let world = sim.config().world_size;
+    let points = |state: HealthState| -> Vec<(f64, f64)> {
+        sim.boids
+            .iter()
+            .filter(|b| b.state == state)
+            // Canvas y points up; the world's y points down.
+            .map(|b| (b.pos.x as f64, (world.y - b.pos.y) as f64))
+            .collect()
+    };
+    let (susceptible, exposed, infected, recovered) = (
+        points(HealthState::Susceptible),
+        points(HealthState::Exposed),
+        points(HealthState::Infected),
+        points(HealthState::Recovered),
+    );
+    let title = format!(" t={:.1}s  step {step}  q to quit ", sim.time());
+    let map = Canvas::default()
+        .block(Block::bordered().title(title))
+        .marker(Marker::Braille)
+        .x_bounds([0.0, world.x as f64])
+        .y_bounds([0.0, world.y as f64])
+        .paint(|ctx| {
+            ctx.draw(&Points {
+                coords: &susceptible,
+                color: Color::White,
+            });
+            ctx.draw(&Points {
+                coords: &recovered,
+                color: Color::Green,
+            });
+            ctx.draw(&Points {
+                coords: &exposed,
+                color: Color::Yellow,
+            });
+            ctx.draw(&Points {
+                coords: &infected,
+                color: Color::Red,
+            });
+        });
+    frame.render_widget(map, map_area);
+
+    let population = sim.boids.len() as u64;
+    for (area, label, data, color) in [
+        (s_area, "S", &history.susceptible, Color::White),
+        (i_area, "I", &history.infected, Color::Red),
+        (r_area, "R", &history.recovered, Color::Green),
+    ] {
+        let latest = data.last().copied().unwrap_or(0);
+        // Show the most recent samples that fit inside the borders.
+        let width = area.width.saturating_sub(2) as usize;
+        let spark = Sparkline::default()
+            .block(Block::bordered().title(format!(" {label} {latest} ")))
+            .data(&data[data.len().saturating_sub(width)..])
+            .max(population)
+            .style(Style::default().fg(color));
+        frame.render_widget(spark, area);
+    }
+}
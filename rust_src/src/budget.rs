@@ -0,0 +1,97 @@
+use crate::sim::Quality;
+
+/// Quality steps from full to most degraded. Off-screen boids are never
+/// drawn in the first place, so only the simulation side degrades.
+const LEVELS: [Quality; 4] = [
+    Quality {
+        neighbor_cap: None,
+        policy_interval: 1,
+    },
+    Quality {
+        neighbor_cap: Some(24),
+        policy_interval: 1,
+    },
+    Quality {
+        neighbor_cap: Some(24),
+        policy_interval: 2,
+    },
+    Quality {
+        neighbor_cap: Some(12),
+        policy_interval: 4,
+    },
+];
+
+/// Seconds to wait after a change before degrading further.
+const DEGRADE_HOLD: f32 = 1.0;
+/// Seconds to wait after a change before restoring quality; longer than
+/// `DEGRADE_HOLD` so the controller does not oscillate.
+const RESTORE_HOLD: f32 = 3.0;
+
+/// Keeps the simulation step within a time budget by stepping `Quality`
+/// down when the smoothed step time runs over, and back up once it has
+/// fallen well below.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameBudget {
+    /// Target step time in seconds; 0 disables the controller.
+    pub budget: f32,
+    average: f32,
+    level: usize,
+    hold: f32,
+}
+
+impl FrameBudget {
+    pub fn new(budget: f32) -> Self {
+        Self {
+            budget: budget.max(0.0),
+            average: 0.0,
+            level: 0,
+            hold: 0.0,
+        }
+    }
+
+    /// Record a step that took `step_secs` of wall time, `dt` seconds after
+    /// the last one. Returns true when the quality level changed.
+    pub fn record(&mut self, step_secs: f32, dt: f32) -> bool {
+        if self.budget <= 0.0 {
+            return false;
+        }
+        self.average += 0.1 * (step_secs - self.average);
+        self.hold -= dt;
+        if self.hold > 0.0 {
+            return false;
+        }
+        if self.average > self.budget && self.level + 1 < LEVELS.len() {
+            self.level += 1;
+            self.hold = DEGRADE_HOLD;
+            return true;
+        }
+        if self.average < 0.5 * self.budget && self.level > 0 {
+            self.level -= 1;
+            self.hold = RESTORE_HOLD;
+            return true;
+        }
+        false
+    }
+
+    pub fn quality(&self) -> Quality {
+        LEVELS[self.level]
+    }
+
+    /// Smoothed step time in seconds.
+    pub fn average(&self) -> f32 {
+        self.average
+    }
+
+    /// What is currently degraded, for display; `None` at full quality.
+    pub fn degraded(&self) -> Option<String> {
+        let q = self.quality();
+        let mut parts = Vec::new();
+        if let Some(cap) = q.neighbor_cap {
+            parts.push(format!("neighbours capped at {cap}"));
+        }
+        if q.policy_interval > 1 {
+            parts.push(format!("steering every {} steps", q.policy_interval));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
@@ -0,0 +1,107 @@
+use std::fmt::Write as _;
+
+use rayon::prelude::*;
+
+use crate::sensitivity::{ParamRange, RunSettings};
+use crate::sim::{SimConfig, Simulation};
+use crate::sweep::grid_value;
+
+/// Best grid point of a least-squares fit to an observed incidence curve.
+pub struct Fit {
+    pub x: ParamRange,
+    pub y: ParamRange,
+    pub x_value: f32,
+    pub y_value: f32,
+    /// Sum of squared differences between observed and fitted incidence.
+    pub sse: f32,
+    /// Mean simulated incidence per day at the best point.
+    pub fitted: Vec<f32>,
+}
+
+/// Read daily incidence from CSV text: the last column of every row, skipping
+/// rows (such as a header) whose last field is not a number.
+pub fn parse_incidence_csv(text: &str) -> Result<Vec<f32>, String> {
+    let values: Vec<f32> = text
+        .lines()
+        .filter_map(|line| line.rsplit(',').next()?.trim().parse().ok())
+        .collect();
+    if values.is_empty() {
+        return Err("no incidence values found".to_string());
+    }
+    Ok(values)
+}
+
+/// New infections per day of `day_length` seconds, averaged over the
+/// replicates in `settings`. Seeded cases are not counted.
+pub fn simulate_incidence(
+    cfg: SimConfig,
+    settings: &RunSettings,
+    days: usize,
+    day_length: f32,
+) -> Vec<f32> {
+    let steps_per_day = (day_length / settings.dt).round().max(1.0) as usize;
+    let n = settings.replicates.max(1);
+    let mut mean = vec![0.0; days];
+    for r in 0..n {
+        let mut sim = Simulation::new(settings.boids, cfg, settings.seed.wrapping_add(r as u64));
+        let mut before = sim.transitions().infections;
+        for day in mean.iter_mut() {
+            for _ in 0..steps_per_day {
+                sim.step(settings.dt);
+            }
+            let after = sim.transitions().infections;
+            *day += (after - before) as f32 / n as f32;
+            before = after;
+        }
+    }
+    mean
+}
+
+/// Grid search over `x` and `y` minimizing the squared error between
+/// `observed` and simulated daily incidence. Every grid point uses the same
+/// replicate seeds.
+pub fn grid_fit(
+    observed: &[f32],
+    x: ParamRange,
+    y: ParamRange,
+    nx: usize,
+    ny: usize,
+    settings: &RunSettings,
+    day_length: f32,
+) -> Fit {
+    (0..nx * ny)
+        .into_par_iter()
+        .map(|k| {
+            let (xv, yv) = (grid_value(&x, k % nx, nx), grid_value(&y, k / nx, ny));
+            let mut cfg = settings.base;
+            x.param.apply(&mut cfg, xv);
+            y.param.apply(&mut cfg, yv);
+            let fitted = simulate_incidence(cfg, settings, observed.len(), day_length);
+            let sse = observed
+                .iter()
+                .zip(&fitted)
+                .map(|(o, f)| (o - f) * (o - f))
+                .sum();
+            Fit {
+                x,
+                y,
+                x_value: xv,
+                y_value: yv,
+                sse,
+                fitted,
+            }
+        })
+        .min_by(|a, b| a.sse.total_cmp(&b.sse))
+        .expect("grid has at least one point")
+}
+
+impl Fit {
+    /// `day,observed,fitted` CSV with a header row.
+    pub fn trajectory_csv(&self, observed: &[f32]) -> String {
+        let mut out = String::from("day,observed,fitted\n");
+        for (day, (o, f)) in observed.iter().zip(&self.fitted).enumerate() {
+            let _ = writeln!(out, "{day},{o:.4},{f:.4}");
+        }
+        out
+    }
+}
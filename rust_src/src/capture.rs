@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Levels per channel of the fixed 6x6x6 colour cube every frame is
+/// quantized to, so frames can be stored as palette indices and written as
+/// GIF without a per-frame palette search.
+const LEVELS: usize = 6;
+
+/// Rolling buffer of the most recent frames, downscaled and quantized as
+/// they arrive, that can be written out as an animated GIF at any time.
+pub struct FrameRing {
+    width: usize,
+    height: usize,
+    capacity: usize,
+    /// Delay between frames in hundredths of a second, GIF's unit.
+    delay_cs: u16,
+    frames: VecDeque<Vec<u8>>,
+}
+
+impl FrameRing {
+    /// Keep `seconds` of `width x height` frames captured `fps` times per
+    /// second.
+    pub fn new(width: usize, height: usize, seconds: f32, fps: f32) -> Self {
+        let capacity = (seconds * fps).ceil().max(1.0) as usize;
+        Self {
+            width: width.max(1),
+            height: height.max(1),
+            capacity,
+            delay_cs: (100.0 / fps).round().clamp(1.0, u16::MAX as f32) as u16,
+            frames: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Add an RGBA frame of `src_width x src_height`, sampled down to the
+    /// ring's size. `bottom_up` flips frames read back from OpenGL, whose
+    /// first row is the bottom of the screen. Drops the oldest frame when
+    /// full.
+    pub fn push_rgba(&mut self, rgba: &[u8], src_width: usize, src_height: usize, bottom_up: bool) {
+        if src_width == 0 || src_height == 0 || rgba.len() < src_width * src_height * 4 {
+            return;
+        }
+        let mut frame = Vec::with_capacity(self.width * self.height);
+        for y in 0..self.height {
+            let sy = (y * src_height / self.height).min(src_height - 1);
+            let sy = if bottom_up { src_height - 1 - sy } else { sy };
+            for x in 0..self.width {
+                let sx = (x * src_width / self.width).min(src_width - 1);
+                let at = (sy * src_width + sx) * 4;
+                frame.push(palette_index(rgba[at], rgba[at + 1], rgba[at + 2]));
+            }
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Write the buffered frames, oldest first, as a looping GIF.
+    pub fn save_gif(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_gif(&mut out)?;
+        out.flush()
+    }
+
+    pub fn write_gif(&self, out: &mut impl Write) -> io::Result<()> {
+        let (w, h) = (self.width as u16, self.height as u16);
+        out.write_all(b"GIF89a")?;
+        out.write_all(&w.to_le_bytes())?;
+        out.write_all(&h.to_le_bytes())?;
+        // Global colour table of 2^8 entries, 8 bits per channel.
+        out.write_all(&[0xf7, 0, 0])?;
+        for k in 0..256 {
+            out.write_all(&palette_color(k))?;
+        }
+        // Loop forever (NETSCAPE2.0 application extension).
+        out.write_all(&[0x21, 0xff, 11])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[3, 1, 0, 0, 0])?;
+
+        for frame in &self.frames {
+            // Graphic control extension carrying the frame delay.
+            out.write_all(&[0x21, 0xf9, 4, 0])?;
+            out.write_all(&self.delay_cs.to_le_bytes())?;
+            out.write_all(&[0, 0])?;
+            // Full-size image descriptor, no local colour table.
+            out.write_all(&[0x2c, 0, 0, 0, 0])?;
+            out.write_all(&w.to_le_bytes())?;
+            out.write_all(&h.to_le_bytes())?;
+            out.write_all(&[0, 8])?;
+            for block in lzw_encode(frame).chunks(255) {
+                out.write_all(&[block.len() as u8])?;
+                out.write_all(block)?;
+            }
+            out.write_all(&[0])?;
+        }
+        out.write_all(&[0x3b])
+    }
+}
+
+fn palette_index(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| (c as usize * (LEVELS - 1) + 127) / 255;
+    (level(r) * LEVELS * LEVELS + level(g) * LEVELS + level(b)) as u8
+}
+
+/// Colour of palette entry `k`; entries past the cube are black.
+fn palette_color(k: usize) -> [u8; 3] {
+    if k >= LEVELS * LEVELS * LEVELS {
+        return [0, 0, 0];
+    }
+    let value = |level: usize| (level * 255 / (LEVELS - 1)) as u8;
+    [
+        value(k / (LEVELS * LEVELS)),
+        value(k / LEVELS % LEVELS),
+        value(k % LEVELS),
+    ]
+}
+
+/// GIF-flavoured LZW with 8-bit minimum code size: variable-width codes
+/// packed least significant bit first, and a clear code whenever the 12-bit
+/// table fills up.
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODE: u16 = 4095;
+
+    let mut out = Vec::new();
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut emit = |code: u16, width: u32, out: &mut Vec<u8>| {
+        acc |= (code as u32) << bits;
+        bits += width;
+        while bits >= 8 {
+            out.push(acc as u8);
+            acc >>= 8;
+            bits -= 8;
+        }
+    };
+
+    // (prefix code, next byte) -> code
+    let mut table = std::collections::HashMap::new();
+    let mut next_code = END + 1;
+    let mut width = 9;
+    emit(CLEAR, width, &mut out);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        emit(END, width, &mut out);
+        if bits > 0 {
+            out.push(acc as u8);
+        }
+        return out;
+    };
+    let mut prefix = first as u16;
+    for &byte in rest {
+        if let Some(&code) = table.get(&(prefix, byte)) {
+            prefix = code;
+            continue;
+        }
+        emit(prefix, width, &mut out);
+        if next_code <= MAX_CODE {
+            table.insert((prefix, byte), next_code);
+            next_code += 1;
+            // The decoder widens one code later than the encoder adds it.
+            if next_code > (1 << width) && width < 12 {
+                width += 1;
+            }
+        } else {
+            emit(CLEAR, width, &mut out);
+            table.clear();
+            next_code = END + 1;
+            width = 9;
+        }
+        prefix = byte as u16;
+    }
+    emit(prefix, width, &mut out);
+    emit(END, width, &mut out);
+    if bits > 0 {
+        out.push(acc as u8);
+    }
+    out
+}
@@ -0,0 +1,364 @@
+use std::fmt;
+
+use crate::immunization::Immunization;
+use crate::intervention::Intervention;
+use crate::sensitivity::Param;
+use crate::sim::{SimConfig, Simulation, Vec2f};
+
+/// A player action available during a challenge, usable a limited number of
+/// times.
+#[derive(Clone, Copy, Debug)]
+pub struct Tool {
+    pub name: &'static str,
+    pub intervention: Intervention,
+    pub uses: u32,
+}
+
+/// What the player must achieve. Every objective is judged on the whole
+/// population, seeded cases included.
+#[derive(Clone, Copy, Debug)]
+pub enum Objective {
+    /// Keep the fraction ever infected at or below `max` until `duration`
+    /// seconds have passed or infections die out.
+    CapAttackRate { max: f32, duration: f32 },
+    /// Keep prevalence at or below `max` at every moment for `duration`
+    /// seconds.
+    CapPrevalence { max: f32, duration: f32 },
+    /// Clear every infection before `deadline` seconds without the fraction
+    /// ever infected passing `max`, so letting the epidemic burn out doesn't
+    /// count.
+    Eradicate { deadline: f32, max: f32 },
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Objective::CapAttackRate { max, duration } => write!(
+                f,
+                "keep total infections under {:.0}% for {:.0}s",
+                max * 100.0,
+                duration
+            ),
+            Objective::CapPrevalence { max, duration } => write!(
+                f,
+                "keep prevalence under {:.0}% for {:.0}s",
+                max * 100.0,
+                duration
+            ),
+            Objective::Eradicate { deadline, max } => write!(
+                f,
+                "clear all cases within {:.0}s, under {:.0}% infected",
+                deadline,
+                max * 100.0
+            ),
+        }
+    }
+}
+
+/// A predefined scenario with fixed parameters: the player can only change
+/// its course through `tools`.
+#[derive(Clone, Debug)]
+pub struct Challenge {
+    pub name: &'static str,
+    pub brief: &'static str,
+    pub config: SimConfig,
+    /// Population for `config.world_size`; scaled with the world area so a
+    /// different window keeps the same density.
+    pub boids: usize,
+    pub objective: Objective,
+    pub tools: Vec<Tool>,
+}
+
+impl Challenge {
+    /// Fresh simulation for an attempt in a world of `world_size`.
+    pub fn simulation(&self, world_size: Vec2f, seed: u64) -> Simulation {
+        let area = |v: Vec2f| (v.x * v.y).max(1.0);
+        let scale = area(world_size) / area(self.config.world_size);
+        let boids = (self.boids as f32 * scale).round().max(1.0) as usize;
+        let cfg = SimConfig {
+            world_size,
+            ..self.config
+        };
+        Simulation::new(boids, cfg, seed)
+    }
+}
+
+/// The built-in challenges, roughly in order of difficulty.
+pub fn challenges() -> Vec<Challenge> {
+    let distancing = Tool {
+        name: "Distancing",
+        intervention: Intervention::Set(Param::SeparationRadius, 44.0),
+        uses: 1,
+    };
+    let masks = |beta: f32| Tool {
+        name: "Masks",
+        intervention: Intervention::Set(Param::InfectionBeta, beta),
+        uses: 1,
+    };
+    let lockdown = Tool {
+        name: "Lockdown",
+        intervention: Intervention::Set(Param::MaxSpeed, 50.0),
+        uses: 1,
+    };
+    let vaccinate = |fraction: f32, uses: u32| Tool {
+        name: "Vaccinate",
+        intervention: Intervention::Immunize(Immunization::Random(fraction)),
+        uses,
+    };
+    let ring = |fraction: f32, uses: u32| Tool {
+        name: "Ring vaccinate",
+        intervention: Intervention::Immunize(Immunization::Ring(fraction)),
+        uses,
+    };
+
+    vec![
+        Challenge {
+            name: "Flatten the curve",
+            brief: "A fast strain. Keep hospitals under capacity.",
+            config: SimConfig {
+                infection_beta: 0.3,
+                infection_radius: 12.0,
+                ..SimConfig::default()
+            },
+            boids: 2400,
+            objective: Objective::CapPrevalence {
+                max: 0.15,
+                duration: 90.0,
+            },
+            tools: vec![distancing, masks(0.15), vaccinate(0.15, 2)],
+        },
+        Challenge {
+            name: "Containment",
+            brief: "Few cases so far. Keep it that way.",
+            config: SimConfig {
+                infection_beta: 0.15,
+                infection_radius: 12.0,
+                ..SimConfig::default()
+            },
+            boids: 2400,
+            objective: Objective::CapAttackRate {
+                max: 0.3,
+                duration: 180.0,
+            },
+            tools: vec![ring(0.05, 3), masks(0.08)],
+        },
+        Challenge {
+            name: "Eradication",
+            brief: "A lingering strain. Stamp it out in time.",
+            config: SimConfig {
+                infection_beta: 0.2,
+                infection_radius: 10.0,
+                infectious_period: 8.0,
+                ..SimConfig::default()
+            },
+            boids: 2400,
+            objective: Objective::Eradicate {
+                deadline: 120.0,
+                max: 0.5,
+            },
+            tools: vec![lockdown, vaccinate(0.25, 2), ring(0.1, 1)],
+        },
+    ]
+}
+
+/// Apply `intervention` to a running simulation. Parameter changes last for
+/// the rest of the run; immunization acts on the current susceptibles, and
+/// travel restrictions start at once whatever their threshold.
+/// Returns how many boids were immunized.
+pub fn apply(intervention: Intervention, sim: &mut Simulation) -> usize {
+    match intervention {
+        Intervention::Set(param, value) => {
+            let mut cfg = *sim.config();
+            param.apply(&mut cfg, value);
+            sim.set_motion_params(
+                cfg.neighbor_radius,
+                cfg.separation_radius,
+                cfg.max_speed,
+                cfg.max_force,
+            );
+            sim.set_infection_params(
+                cfg.infection_radius,
+                cfg.infection_beta,
+                cfg.infectious_period,
+            );
+            0
+        }
+        Intervention::Immunize(strategy) => {
+            sim.set_immunization(strategy);
+            crate::immunization::apply(sim)
+        }
+        Intervention::RestrictTravel(spec) => {
+            // Only fails when every tag value is taken by other features.
+            let _ = sim.restrict_travel(spec);
+            0
+        }
+    }
+}
+
+/// An attempt at a challenge: tracks tool uses and the objective, and
+/// settles the verdict once the objective is met or broken.
+#[derive(Clone, Debug)]
+pub struct Attempt {
+    objective: Objective,
+    tools: Vec<Tool>,
+    uses_left: Vec<u32>,
+    time: f32,
+    initial_population: usize,
+    initial_infected: usize,
+    peak_prevalence: f32,
+    attack_rate: f32,
+    /// `(time, tool name)` for every tool used.
+    used: Vec<(f32, &'static str)>,
+    result: Option<ChallengeResult>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChallengeResult {
+    pub won: bool,
+    pub reason: String,
+    pub time: f32,
+    pub attack_rate: f32,
+    pub peak_prevalence: f32,
+    pub tools_used: Vec<(f32, &'static str)>,
+}
+
+impl Attempt {
+    pub fn new(challenge: &Challenge, sim: &Simulation) -> Self {
+        let infected = sim.counts().infected;
+        let population = sim.boids.len();
+        Self {
+            objective: challenge.objective,
+            tools: challenge.tools.clone(),
+            uses_left: challenge.tools.iter().map(|t| t.uses).collect(),
+            time: 0.0,
+            initial_population: population,
+            initial_infected: infected,
+            peak_prevalence: infected as f32 / population.max(1) as f32,
+            attack_rate: infected as f32 / population.max(1) as f32,
+            used: Vec::new(),
+            result: None,
+        }
+    }
+
+    /// Use tool `k`. Returns false if it doesn't exist, is used up, or the
+    /// attempt is already over.
+    pub fn use_tool(&mut self, k: usize, sim: &mut Simulation) -> bool {
+        if self.result.is_some() || self.uses_left.get(k).is_none_or(|&n| n == 0) {
+            return false;
+        }
+        self.uses_left[k] -= 1;
+        apply(self.tools[k].intervention, sim);
+        self.used.push((self.time, self.tools[k].name));
+        true
+    }
+
+    /// Record the state after a step of length `dt` and check the objective.
+    /// Does nothing once the attempt is over.
+    pub fn record(&mut self, sim: &Simulation, dt: f32) {
+        if self.result.is_some() {
+            return;
+        }
+        self.time += dt;
+        let counts = sim.counts();
+        let transitions = sim.transitions();
+        let population = (self.initial_population + transitions.arrivals).max(1) as f32;
+        self.attack_rate = (self.initial_infected + transitions.infections) as f32 / population;
+        self.peak_prevalence = self
+            .peak_prevalence
+            .max(counts.infected as f32 / sim.boids.len().max(1) as f32);
+        let extinct = counts.infected == 0;
+
+        let verdict = match self.objective {
+            Objective::CapAttackRate { max, duration } => {
+                if self.attack_rate > max {
+                    Some((
+                        false,
+                        format!("more than {:.0}% of the flock infected", max * 100.0),
+                    ))
+                } else if extinct || self.time >= duration {
+                    Some((true, "infections held below the cap".to_string()))
+                } else {
+                    None
+                }
+            }
+            Objective::CapPrevalence { max, duration } => {
+                if self.peak_prevalence > max {
+                    Some((false, format!("prevalence passed {:.0}%", max * 100.0)))
+                } else if extinct || self.time >= duration {
+                    Some((true, "prevalence stayed under the cap".to_string()))
+                } else {
+                    None
+                }
+            }
+            Objective::Eradicate { deadline, max } => {
+                if self.attack_rate > max {
+                    Some((
+                        false,
+                        format!("more than {:.0}% of the flock infected", max * 100.0),
+                    ))
+                } else if extinct {
+                    Some((true, "the last infection cleared".to_string()))
+                } else if self.time >= deadline {
+                    Some((false, format!("{} still infected", counts.infected)))
+                } else {
+                    None
+                }
+            }
+        };
+        if let Some((won, reason)) = verdict {
+            self.result = Some(ChallengeResult {
+                won,
+                reason,
+                time: self.time,
+                attack_rate: self.attack_rate,
+                peak_prevalence: self.peak_prevalence,
+                tools_used: self.used.clone(),
+            });
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn tools(&self) -> &[Tool] {
+        &self.tools
+    }
+
+    pub fn uses_left(&self, k: usize) -> u32 {
+        self.uses_left.get(k).copied().unwrap_or(0)
+    }
+
+    pub fn attack_rate(&self) -> f32 {
+        self.attack_rate
+    }
+
+    pub fn peak_prevalence(&self) -> f32 {
+        self.peak_prevalence
+    }
+
+    /// `Some` once the objective has been met or broken.
+    pub fn result(&self) -> Option<&ChallengeResult> {
+        self.result.as_ref()
+    }
+}
+
+impl fmt::Display for ChallengeResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.won { "success" } else { "failed" };
+        writeln!(f, "result:          {verdict}: {}", self.reason)?;
+        writeln!(f, "time:            {:.1}s", self.time)?;
+        writeln!(f, "total infected:  {:.1}%", self.attack_rate * 100.0)?;
+        writeln!(f, "peak prevalence: {:.1}%", self.peak_prevalence * 100.0)?;
+        if self.tools_used.is_empty() {
+            write!(f, "tools used:      none")
+        } else {
+            let used: Vec<String> = self
+                .tools_used
+                .iter()
+                .map(|(t, name)| format!("{name} at {t:.0}s"))
+                .collect();
+            write!(f, "tools used:      {}", used.join(", "))
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use crate::sim::{InfectionEvent, SirCounts};
+
+/// Which plotting library the exported spec targets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChartFormat {
+    #[default]
+    VegaLite,
+    Plotly,
+}
+
+impl ChartFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "vega-lite" | "vegalite" => Ok(ChartFormat::VegaLite),
+            "plotly" => Ok(ChartFormat::Plotly),
+            other => Err(format!(
+                "unknown chart format: {other} (expected vega-lite or plotly)"
+            )),
+        }
+    }
+}
+
+/// One sample of the population history together with what happened since
+/// the previous sample.
+#[derive(Clone, Copy, Debug)]
+pub struct ChartRow {
+    pub time: f32,
+    pub counts: SirCounts,
+    /// New infections per second since the previous sample; `None` for the
+    /// first sample.
+    pub incidence: Option<f32>,
+    /// Case reproduction number of the boids infected since the previous
+    /// sample: how many others each went on to infect. `None` when nobody
+    /// was infected in the interval. Cases near the end of a run that is
+    /// still active have not finished transmitting, so the tail reads low.
+    pub rt: Option<f32>,
+}
+
+/// Attach incidence and Rt from `log` to `(time, counts)` samples taken in
+/// ascending time order.
+pub fn chart_rows(samples: &[(f32, SirCounts)], log: &[InfectionEvent]) -> Vec<ChartRow> {
+    let mut offspring = vec![0u32; log.len()];
+    for event in log {
+        if let Some(infector) = event.infector {
+            offspring[infector] += 1;
+        }
+    }
+
+    let mut rows = Vec::with_capacity(samples.len());
+    let mut prev_time = None;
+    for &(time, counts) in samples {
+        let (incidence, rt) = match prev_time {
+            Some(start) if time > start => {
+                let (cases, secondary) = log
+                    .iter()
+                    .zip(&offspring)
+                    .filter(|(e, _)| e.time > start && e.time <= time)
+                    .fold((0u32, 0u32), |(n, s), (_, &o)| (n + 1, s + o));
+                let rt = (cases > 0).then(|| secondary as f32 / cases as f32);
+                (Some(cases as f32 / (time - start)), rt)
+            }
+            _ => (None, None),
+        };
+        rows.push(ChartRow {
+            time,
+            counts,
+            incidence,
+            rt,
+        });
+        prev_time = Some(time);
+    }
+    rows
+}
+
+/// A self-contained chart spec in `format`, with the data inlined: the
+/// S/I/R curves on top, incidence and Rt stacked underneath on the same
+/// time axis.
+pub fn chart_json(format: ChartFormat, rows: &[ChartRow]) -> String {
+    match format {
+        ChartFormat::VegaLite => vega_lite(rows),
+        ChartFormat::Plotly => plotly(rows),
+    }
+}
+
+/// Write `chart_json` to `path`.
+pub fn export_chart(path: &Path, format: ChartFormat, rows: &[ChartRow]) -> io::Result<()> {
+    std::fs::write(path, chart_json(format, rows))
+}
+
+fn number(value: Option<f32>) -> String {
+    match value {
+        Some(v) if v.is_finite() => format!("{v:.4}"),
+        _ => "null".to_owned(),
+    }
+}
+
+fn vega_lite(rows: &[ChartRow]) -> String {
+    let mut values = String::new();
+    for (k, row) in rows.iter().enumerate() {
+        let sep = if k == 0 { "" } else { "," };
+        let _ = write!(
+            values,
+            "{sep}\n      {{\"time\": {:.3}, \"susceptible\": {}, \"infected\": {}, \"recovered\": {}, \"incidence\": {}, \"rt\": {}}}",
+            row.time,
+            row.counts.susceptible,
+            row.counts.infected,
+            row.counts.recovered,
+            number(row.incidence),
+            number(row.rt)
+        );
+    }
+    format!(
+        r##"{{
+  "$schema": "https://vega.github.io/schema/vega-lite/v5.json",
+  "description": "Boid epidemic: population by compartment, incidence and Rt",
+  "data": {{"values": [{values}
+  ]}},
+  "vconcat": [
+    {{
+      "width": 600,
+      "height": 240,
+      "title": "Population",
+      "transform": [{{"fold": ["susceptible", "infected", "recovered"], "as": ["compartment", "boids"]}}],
+      "mark": "line",
+      "encoding": {{
+        "x": {{"field": "time", "type": "quantitative", "title": "Time (s)"}},
+        "y": {{"field": "boids", "type": "quantitative", "title": "Boids"}},
+        "color": {{
+          "field": "compartment",
+          "type": "nominal",
+          "sort": ["susceptible", "infected", "recovered"],
+          "scale": {{"domain": ["susceptible", "infected", "recovered"], "range": ["#3b82f6", "#ef4444", "#22c55e"]}}
+        }}
+      }}
+    }},
+    {{
+      "width": 600,
+      "height": 120,
+      "title": "Incidence",
+      "transform": [{{"filter": "datum.incidence != null"}}],
+      "mark": "bar",
+      "encoding": {{
+        "x": {{"field": "time", "type": "quantitative", "title": "Time (s)"}},
+        "y": {{"field": "incidence", "type": "quantitative", "title": "New infections / s"}}
+      }}
+    }},
+    {{
+      "width": 600,
+      "height": 120,
+      "title": "Rt",
+      "layer": [
+        {{
+          "transform": [{{"filter": "datum.rt != null"}}],
+          "mark": {{"type": "line", "point": true}},
+          "encoding": {{
+            "x": {{"field": "time", "type": "quantitative", "title": "Time (s)"}},
+            "y": {{"field": "rt", "type": "quantitative", "title": "Rt"}}
+          }}
+        }},
+        {{
+          "mark": {{"type": "rule", "strokeDash": [4, 4]}},
+          "encoding": {{"y": {{"datum": 1}}}}
+        }}
+      ]
+    }}
+  ]
+}}
+"##
+    )
+}
+
+fn plotly(rows: &[ChartRow]) -> String {
+    let column =
+        |f: &dyn Fn(&ChartRow) -> String| rows.iter().map(f).collect::<Vec<_>>().join(", ");
+    let time = column(&|r| format!("{:.3}", r.time));
+    let trace = |name: &str, color: &str, y: String, kind: &str, axis: &str| {
+        format!(
+            r#"    {{"type": "{kind}", "name": "{name}", "x": [{time}], "y": [{y}], "xaxis": "x", "yaxis": "{axis}", "marker": {{"color": "{color}"}}}}"#
+        )
+    };
+    let traces = [
+        trace(
+            "susceptible",
+            "#3b82f6",
+            column(&|r| r.counts.susceptible.to_string()),
+            "scatter",
+            "y",
+        ),
+        trace(
+            "infected",
+            "#ef4444",
+            column(&|r| r.counts.infected.to_string()),
+            "scatter",
+            "y",
+        ),
+        trace(
+            "recovered",
+            "#22c55e",
+            column(&|r| r.counts.recovered.to_string()),
+            "scatter",
+            "y",
+        ),
+        trace(
+            "incidence",
+            "#f97316",
+            column(&|r| number(r.incidence)),
+            "bar",
+            "y2",
+        ),
+        trace("Rt", "#a855f7", column(&|r| number(r.rt)), "scatter", "y3"),
+    ];
+    format!(
+        r#"{{
+  "data": [
+{}
+  ],
+  "layout": {{
+    "title": {{"text": "Boid epidemic"}},
+    "grid": {{"rows": 3, "columns": 1, "subplots": [["xy"], ["xy2"], ["xy3"]], "roworder": "top to bottom"}},
+    "xaxis": {{"title": {{"text": "Time (s)"}}}},
+    "yaxis": {{"title": {{"text": "Boids"}}}},
+    "yaxis2": {{"title": {{"text": "New infections / s"}}}},
+    "yaxis3": {{"title": {{"text": "Rt"}}}},
+    "shapes": [{{"type": "line", "xref": "paper", "x0": 0, "x1": 1, "yref": "y3", "y0": 1, "y1": 1, "line": {{"dash": "dash"}}}}],
+    "height": 720
+  }}
+}}
+"#,
+        traces.join(",\n")
+    )
+}
@@ -0,0 +1,134 @@
+use boid_math::torus_delta;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::Vec2f;
+
+/// Name of the tag flag marking boids that commute.
+pub const COMMUTER_TAG: &str = "commuter";
+
+/// Width of the band inside each patch edge over which the push back
+/// builds up.
+const MARGIN: f32 = 30.0;
+/// Cap on the push, as a multiple of `strength`, for boids well outside
+/// their patch.
+const MAX_PUSH: f32 = 3.0;
+
+/// Which side of a two-patch world a boid is in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Patch {
+    Home,
+    Work,
+}
+
+impl Patch {
+    pub const ALL: [Patch; 2] = [Patch::Home, Patch::Work];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Patch::Home => "home",
+            Patch::Work => "work",
+        }
+    }
+}
+
+/// Two-patch commuting: the world splits into a wide home patch on the left
+/// and a narrower shared work patch on the right. Everyone starts at home;
+/// for the first `work_fraction` of every day, commuters travel to the work
+/// patch and mix there at a higher density, then return. The rest stay home
+/// all day, so the two patches only meet through commuters.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Commute {
+    /// Length of a full day in seconds.
+    pub day_length: f32,
+    /// Share of each day spent at work, at the start of the day.
+    pub work_fraction: f32,
+    /// Share of the world width taken by the home patch.
+    pub home_share: f32,
+    /// Share of boids that commute.
+    pub commuters: f32,
+    /// Wall push at full depth, as a multiple of `max_force`.
+    pub strength: f32,
+}
+
+impl Default for Commute {
+    fn default() -> Self {
+        Self {
+            day_length: 30.0,
+            work_fraction: 0.4,
+            home_share: 0.7,
+            commuters: 0.6,
+            strength: 4.0,
+        }
+    }
+}
+
+impl Commute {
+    /// Parse `day_length[:work_fraction[:home_share[:commuters]]]`, e.g.
+    /// `30:0.4:0.7:0.6`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec
+            .split(':')
+            .map(|s| s.parse::<f32>().map_err(|_| format!("invalid number: {s}")));
+        let mut out = Self::default();
+        if let Some(day_length) = parts.next().transpose()? {
+            out.day_length = day_length.max(0.1);
+        }
+        if let Some(work_fraction) = parts.next().transpose()? {
+            out.work_fraction = work_fraction.clamp(0.0, 1.0);
+        }
+        if let Some(home_share) = parts.next().transpose()? {
+            out.home_share = home_share.clamp(0.05, 0.95);
+        }
+        if let Some(commuters) = parts.next().transpose()? {
+            out.commuters = commuters.clamp(0.0, 1.0);
+        }
+        Ok(out)
+    }
+
+    pub fn is_work_time(&self, time: f32) -> bool {
+        (time / self.day_length).fract() < self.work_fraction
+    }
+
+    /// Left and right edge of `patch`.
+    pub fn bounds(&self, patch: Patch, world: Vec2f) -> (f32, f32) {
+        let split = world.x * self.home_share;
+        match patch {
+            Patch::Home => (0.0, split),
+            Patch::Work => (split, world.x),
+        }
+    }
+
+    pub fn patch_of(&self, pos: Vec2f, world: Vec2f) -> Patch {
+        if pos.x.rem_euclid(world.x) < world.x * self.home_share {
+            Patch::Home
+        } else {
+            Patch::Work
+        }
+    }
+
+    /// Where a boid belongs at `time`.
+    pub fn target(&self, commuter: bool, time: f32) -> Patch {
+        if commuter && self.is_work_time(time) {
+            Patch::Work
+        } else {
+            Patch::Home
+        }
+    }
+
+    /// Acceleration keeping a boid at `pos` inside `patch`, or bringing it
+    /// there, given the simulation's `max_force`. Zero away from the patch
+    /// edges.
+    pub fn push(&self, pos: Vec2f, patch: Patch, world: Vec2f, max_force: f32) -> Vec2f {
+        let (left, right) = self.bounds(patch, world);
+        let half = (right - left) / 2.0;
+        let center = Vec2f::new(left + half, pos.y);
+        let u = torus_delta(center, pos, world).x;
+        let inner = (half - MARGIN).max(0.0);
+        if u.abs() <= inner {
+            return Vec2f::default();
+        }
+        let depth = ((u.abs() - inner) / MARGIN).min(MAX_PUSH);
+        Vec2f::new(-u.signum() * depth * self.strength * max_force, 0.0)
+    }
+}
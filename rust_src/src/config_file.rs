@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::sim::SimConfig;
+
+/// Run settings read from a TOML or JSON file. Run-level keys sit at the
+/// top; the `[sim]` table holds `SimConfig` fields under their own names.
+/// Only the keys a file gives change anything.
+///
+/// ```toml
+/// boids = 3000
+/// smoothing = 0.5
+/// step_budget = 0.02
+///
+/// [sim]
+/// world_size = [1600, 900]
+/// infection_radius = 14
+/// infection_beta = 0.8
+/// movement = { levy = { exponent = 2.0, min_flight = 10 } }
+/// integrator = "rk2"
+/// households = { size = 4, beta = 0.3, radius = 30 }
+/// ```
+///
+/// A file starting with `{` is read as JSON with the same structure, e.g.
+/// `{"boids": 3000, "sim": {"movement": "brownian"}}`.
+///
+/// `sim.world_size` only applies to headless runs; the live view sizes the
+/// world to its window. `sim.world` picks a `WorldPreset` and, unless the
+/// file also gives `sim.boundaries`, its boundaries.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub boids: usize,
+    #[serde(rename = "sim")]
+    pub config: SimConfig,
+    /// Time constant in seconds with which live parameter changes ease in;
+    /// 0 applies them at once.
+    pub smoothing: f32,
+    /// Wall time in seconds one simulation step may take before quality is
+    /// degraded; 0 never degrades.
+    pub step_budget: f32,
+    /// Simulated seconds between samples of the population history, its
+    /// CSV export and the growth-rate R0 fit; 0 samples every step.
+    pub sample_interval: f32,
+}
+
+impl ConfigFile {
+    /// `base` with every key in `text` applied on top.
+    pub fn parse(text: &str, base: ConfigFile) -> Result<Self, String> {
+        let given: Value = if text.trim_start().starts_with('{') {
+            serde_json::from_str(text).map_err(|err| err.to_string())?
+        } else {
+            toml::from_str(text).map_err(|err| err.to_string())?
+        };
+        let Value::Object(given) = given else {
+            return Err("expected a table of settings".into());
+        };
+        let sim_given = |key: &str| {
+            given
+                .get("sim")
+                .and_then(Value::as_object)
+                .is_some_and(|sim| sim.contains_key(key))
+        };
+        let preset_boundaries = sim_given("world") && !sim_given("boundaries");
+
+        let mut merged = serde_json::to_value(base).map_err(|err| err.to_string())?;
+        overlay(&mut merged, given);
+        let mut file: ConfigFile =
+            serde_path_to_error::deserialize(merged).map_err(|err| {
+                match err.path().to_string().as_str() {
+                    "." => err.inner().to_string(),
+                    path => format!("{path}: {}", err.inner()),
+                }
+            })?;
+        let cfg = &mut file.config;
+        if preset_boundaries {
+            cfg.boundaries = cfg.world.boundaries();
+        }
+        cfg.world_size.x = cfg.world_size.x.max(1.0);
+        cfg.world_size.y = cfg.world_size.y.max(1.0);
+        file.smoothing = file.smoothing.max(0.0);
+        file.step_budget = file.step_budget.max(0.0);
+        file.sample_interval = file.sample_interval.max(0.0);
+        Ok(file)
+    }
+
+    pub fn load(path: &Path, base: ConfigFile) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        Self::parse(&text, base).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Whether going from `self` to `next` changes something a running
+    /// simulation can't pick up: how the population is set up and moves, or
+    /// how it is sampled. Radii, speeds, beta and the infectious period can
+    /// all be applied live, and the boid count through `Simulation::resize`.
+    pub fn needs_restart(&self, next: &ConfigFile) -> bool {
+        let (a, b) = (&self.config, &next.config);
+        self.sample_interval != next.sample_interval
+            || a.initial_infected != b.initial_infected
+            || a.warmup != b.warmup
+            || a.movement != b.movement
+            || a.integrator != b.integrator
+            || a.boundaries != b.boundaries
+            || a.world != b.world
+            || a.immunization != b.immunization
+            || a.households != b.households
+            || a.travel_restriction != b.travel_restriction
+            || a.commute != b.commute
+            || a.disease_model != b.disease_model
+    }
+}
+
+/// Replace members of `base` with those in `given`, going one level into
+/// the `sim` table so a file only has to name the fields it changes. Deeper
+/// values, like a whole `households` table, replace the base's as a unit.
+fn overlay(base: &mut Value, given: Map<String, Value>) {
+    let Value::Object(base) = base else {
+        return;
+    };
+    for (key, value) in given {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Object(sim)), Value::Object(fields)) if key == "sim" => {
+                sim.extend(fields);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Notices when a file has been saved by polling its modification time.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    since_check: f32,
+}
+
+/// Seconds between modification-time checks.
+const POLL_INTERVAL: f32 = 0.5;
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified(&path);
+        Self {
+            path,
+            modified,
+            since_check: 0.0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Advance by `dt` seconds; true when the file changed since the last
+    /// time this returned true (or since the watcher was created).
+    pub fn poll(&mut self, dt: f32) -> bool {
+        self.since_check += dt;
+        if self.since_check < POLL_INTERVAL {
+            return false;
+        }
+        self.since_check = 0.0;
+        let now = modified(&self.path);
+        if now.is_some() && now != self.modified {
+            self.modified = now;
+            return true;
+        }
+        false
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::household::Households;
+    use crate::sim::{Boundary, MovementModel};
+    use crate::world::WorldPreset;
+
+    fn base() -> ConfigFile {
+        ConfigFile {
+            boids: 100,
+            config: SimConfig::default(),
+            smoothing: 0.4,
+            step_budget: 0.0,
+            sample_interval: 1.0,
+        }
+    }
+
+    #[test]
+    fn toml_and_json_files_agree() {
+        let toml = r#"
+            boids = 3000
+            [sim]
+            world_size = [1600, 900]
+            infection_beta = 0.8
+            movement = { crw = { turn_sd = 0.5 } }
+            households = { size = 4, beta = 0.3, radius = 30 }
+        "#;
+        let json = r#"{
+            "boids": 3000,
+            "sim": {
+                "world_size": {"x": 1600, "y": 900},
+                "infection_beta": 0.8,
+                "movement": {"crw": {"turn_sd": 0.5}},
+                "households": {"size": 4, "beta": 0.3, "radius": 30}
+            }
+        }"#;
+        for text in [toml, json] {
+            let file = ConfigFile::parse(text, base()).unwrap();
+            let cfg = file.config;
+            assert_eq!(file.boids, 3000);
+            assert_eq!(file.smoothing, 0.4, "keys not given keep the base value");
+            assert_eq!((cfg.world_size.x, cfg.world_size.y), (1600.0, 900.0));
+            assert_eq!(cfg.infection_beta, 0.8);
+            assert_eq!(cfg.movement, MovementModel::CorrelatedWalk { turn_sd: 0.5 });
+            assert_eq!(
+                cfg.households,
+                Some(Households {
+                    size: 4,
+                    beta: 0.3,
+                    radius: 30.0
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        for text in [
+            "{\"boids\": 10,}",
+            "{\"sim\": {\"infection_beta\": \"high\"}}",
+            "boidz = 10",
+            "[sim]\nradius = 3",
+            "[sim]\nmovement = \"sideways\"",
+        ] {
+            assert!(ConfigFile::parse(text, base()).is_err(), "{text}");
+        }
+        let err = ConfigFile::parse("[sim]\ninfection_beta = \"x\"", base()).unwrap_err();
+        assert!(err.starts_with("sim.infection_beta:"), "{err}");
+    }
+
+    #[test]
+    fn a_world_preset_brings_its_boundaries_unless_given() {
+        let file = ConfigFile::parse("[sim]\nworld = \"rooms\"", base()).unwrap();
+        assert_eq!(file.config.world, WorldPreset::FourRooms);
+        assert_eq!(file.config.boundaries, WorldPreset::FourRooms.boundaries());
+
+        let text = "[sim]\nworld = \"rooms\"\nboundaries = { left = \"remove\" }";
+        let file = ConfigFile::parse(text, base()).unwrap();
+        assert_eq!(file.config.boundaries.left, Boundary::Remove);
+        assert_eq!(file.config.boundaries.right, Boundary::Wrap);
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use crate::sim::Simulation;
+
+/// Undirected contact graph accumulated over a run. An edge joins two boids
+/// that were within the infection radius of each other; its weight is the
+/// total time they spent in contact.
+///
+/// Nodes are boid indices, so the graph is only meaningful for runs whose
+/// population is not resized.
+#[derive(Default)]
+pub struct ContactNetwork {
+    nodes: usize,
+    weights: HashMap<(usize, usize), f32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DegreeStats {
+    pub mean: f32,
+    pub max: usize,
+    /// Nodes with no recorded contact at all.
+    pub isolated: usize,
+    pub edges: usize,
+}
+
+impl ContactNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `dt` of contact time to every pair currently within the infection
+    /// radius.
+    pub fn record(&mut self, sim: &Simulation, dt: f32) {
+        self.nodes = self.nodes.max(sim.boids.len());
+        for pair in sim.contact_pairs(sim.config().infection_radius) {
+            *self.weights.entry(pair).or_insert(0.0) += dt;
+        }
+    }
+
+    pub fn weight(&self, a: usize, b: usize) -> f32 {
+        let key = if a < b { (a, b) } else { (b, a) };
+        self.weights.get(&key).copied().unwrap_or(0.0)
+    }
+
+    pub fn degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.nodes];
+        for &(a, b) in self.weights.keys() {
+            degrees[a] += 1;
+            degrees[b] += 1;
+        }
+        degrees
+    }
+
+    pub fn degree_stats(&self) -> DegreeStats {
+        let degrees = self.degrees();
+        if degrees.is_empty() {
+            return DegreeStats::default();
+        }
+        DegreeStats {
+            mean: degrees.iter().sum::<usize>() as f32 / degrees.len() as f32,
+            max: degrees.iter().copied().max().unwrap_or(0),
+            isolated: degrees.iter().filter(|&&d| d == 0).count(),
+            edges: self.weights.len(),
+        }
+    }
+
+    /// Edges sorted by endpoint for stable output.
+    fn sorted_edges(&self) -> Vec<((usize, usize), f32)> {
+        let mut edges: Vec<_> = self.weights.iter().map(|(&k, &w)| (k, w)).collect();
+        edges.sort_by_key(|&(k, _)| k);
+        edges
+    }
+
+    /// `source,target,weight` CSV with a header row.
+    pub fn to_edge_list(&self) -> String {
+        let mut out = String::from("source,target,weight\n");
+        for ((a, b), w) in self.sorted_edges() {
+            let _ = writeln!(out, "{a},{b},{w:.4}");
+        }
+        out
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+            "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n",
+            "  <graph id=\"contacts\" edgedefault=\"undirected\">\n",
+        ));
+        for n in 0..self.nodes {
+            let _ = writeln!(out, "    <node id=\"n{n}\"/>");
+        }
+        for ((a, b), w) in self.sorted_edges() {
+            let _ = writeln!(
+                out,
+                "    <edge source=\"n{a}\" target=\"n{b}\"><data key=\"weight\">{w:.4}</data></edge>"
+            );
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+
+    /// Write GraphML if `path` ends in `.graphml`, an edge-list CSV otherwise.
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        let body = if path.extension().is_some_and(|e| e == "graphml") {
+            self.to_graphml()
+        } else {
+            self.to_edge_list()
+        };
+        std::fs::write(path, body)
+    }
+}
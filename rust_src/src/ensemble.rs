@@ -0,0 +1,185 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use boid_math::ensemble::{Band, Curve, band_at, quantile};
+
+use crate::runner::{RunOutcome, outcome_of};
+use crate::sim::{SimConfig, Simulation};
+use crate::stats::EpidemicTracker;
+
+/// Runs replicas of one configuration with different seeds on background
+/// threads and aggregates their infected-fraction curves. Dropping it stops
+/// replicas still running, so replacing an ensemble doesn't leave the old one
+/// using CPU.
+pub struct Ensemble {
+    receiver: Receiver<(Curve, RunOutcome)>,
+    replicas: usize,
+    runs: Vec<Curve>,
+    outcomes: Vec<RunOutcome>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Ensemble {
+    /// Start `replicas` runs of `steps` fixed steps of length `dt`, seeded
+    /// `base_seed + 1 ..= base_seed + replicas`.
+    pub fn spawn(
+        cfg: SimConfig,
+        boids: usize,
+        replicas: usize,
+        base_seed: u64,
+        steps: usize,
+        dt: f32,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        for k in 0..replicas {
+            let sender = sender.clone();
+            let cancel = Arc::clone(&cancel);
+            let seed = base_seed.wrapping_add(k as u64 + 1);
+            thread::spawn(move || {
+                let mut sim = Simulation::new(boids, cfg, seed);
+                let mut tracker = EpidemicTracker::new(&sim);
+                let total = boids.max(1) as f32;
+                let mut curve = Curve::default();
+                for _ in 0..steps {
+                    if cancel.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    sim.step(dt);
+                    tracker.record(&sim, dt);
+                    curve.push(sim.time(), sim.counts().infected as f32 / total);
+                }
+                // The receiver is gone if the ensemble was discarded; that's fine.
+                let _ = sender.send((curve, outcome_of(&sim, &tracker)));
+            });
+        }
+        Self {
+            receiver,
+            replicas,
+            runs: Vec::with_capacity(replicas),
+            outcomes: Vec::with_capacity(replicas),
+            cancel,
+        }
+    }
+
+    /// Collect any replicas that have finished since the last call.
+    pub fn poll(&mut self) {
+        for (curve, outcome) in self.receiver.try_iter() {
+            self.runs.push(curve);
+            self.outcomes.push(outcome);
+        }
+    }
+
+    pub fn outbreak_stats(&self) -> OutbreakStats {
+        OutbreakStats::from_outcomes(&self.outcomes)
+    }
+
+    pub fn completed(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn replicas(&self) -> usize {
+        self.replicas
+    }
+
+    /// Mean and inter-quartile range over completed replicas at simulated
+    /// `time`. `None` if no replica has finished or `time` is past their end.
+    pub fn band_at(&self, time: f32) -> Option<Band> {
+        band_at(&self.runs, time)
+    }
+}
+
+impl Drop for Ensemble {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Minor/major outbreak classification across replicates. Spatial runs seeded
+/// with few infections often fade out stochastically, so a single run says
+/// little about whether an outbreak is likely.
+#[derive(Clone, Debug, Default)]
+pub struct OutbreakStats {
+    pub replicates: usize,
+    pub major: usize,
+    /// Fraction of replicates that became major outbreaks.
+    pub outbreak_probability: f32,
+    /// Ascending extinction times of minor outbreaks that ended.
+    pub minor_extinction_times: Vec<f32>,
+    /// Ascending extinction times of major outbreaks that ended.
+    pub major_extinction_times: Vec<f32>,
+    /// Replicates still active when they hit the step cap.
+    pub unfinished: usize,
+}
+
+impl OutbreakStats {
+    pub fn from_outcomes(outcomes: &[RunOutcome]) -> Self {
+        let mut stats = OutbreakStats {
+            replicates: outcomes.len(),
+            ..Default::default()
+        };
+        for o in outcomes {
+            if o.is_major() {
+                stats.major += 1;
+            }
+            match (o.extinct, o.is_major()) {
+                (false, _) => stats.unfinished += 1,
+                (true, false) => stats.minor_extinction_times.push(o.duration),
+                (true, true) => stats.major_extinction_times.push(o.duration),
+            }
+        }
+        stats.minor_extinction_times.sort_by(f32::total_cmp);
+        stats.major_extinction_times.sort_by(f32::total_cmp);
+        stats.outbreak_probability = stats.major as f32 / stats.replicates.max(1) as f32;
+        stats
+    }
+}
+
+impl fmt::Display for OutbreakStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "outbreak probability: {:.2} ({} of {} major, {} unfinished)",
+            self.outbreak_probability, self.major, self.replicates, self.unfinished
+        )?;
+        for (label, times) in [
+            ("minor", &self.minor_extinction_times),
+            ("major", &self.major_extinction_times),
+        ] {
+            if times.is_empty() {
+                writeln!(f, "{label} time to extinction: n=0")?;
+            } else {
+                writeln!(
+                    f,
+                    "{label} time to extinction: n={} p10 {:.1}s  median {:.1}s  p90 {:.1}s",
+                    times.len(),
+                    quantile(times, 0.1),
+                    quantile(times, 0.5),
+                    quantile(times, 0.9)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bands_follow_the_replicas_clock() {
+        let dt = 1.0 / 30.0;
+        let mut ensemble = Ensemble::spawn(SimConfig::default(), 40, 2, 9, 30, dt);
+        while ensemble.completed() < 2 {
+            thread::sleep(std::time::Duration::from_millis(5));
+            ensemble.poll();
+        }
+        assert!(ensemble.band_at(0.5 * dt).is_some());
+        assert!(ensemble.band_at(30.0 * dt).is_some());
+        assert!(ensemble.band_at(32.0 * dt).is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::ensemble::OutbreakStats;
+use crate::runner::{RunOutcome, run_to_end};
+use crate::sim::{Rng, SimConfig, Vec2f};
+
+/// Population of each pre-simulated run. The world is shrunk to match, so
+/// boids meet as often as they would in the full-size run.
+const PREVIEW_BOIDS: usize = 400;
+/// Replicates per candidate; enough to tell a coin-flip outbreak from a
+/// sure one.
+const REPLICATES: usize = 4;
+const MAX_STEPS: usize = 3600;
+const DT: f32 = 1.0 / 60.0;
+
+/// Ranges the explorer samples from, matching the live view's knobs (beta
+/// starts above 0, which never spreads).
+const RANGES: [(f32, f32); 6] = [
+    (20.0, 140.0), // neighbor_radius
+    (5.0, 80.0),   // separation_radius
+    (40.0, 320.0), // max_speed
+    (4.0, 60.0),   // infection_radius
+    (0.05, 5.0),   // infection_beta
+    (1.0, 20.0),   // infectious_period
+];
+
+/// `base` with the flocking and infection parameters drawn from their knob
+/// ranges. Infection radius and beta are drawn on a log scale: most of
+/// their linear range sweeps the whole population, so uniform draws would
+/// rarely land near the threshold.
+pub fn sample(base: &SimConfig, rng: &mut Rng) -> SimConfig {
+    let mut draw = |(lo, hi): (f32, f32), log: bool| {
+        let u = rng.next_f32();
+        if log {
+            lo * (hi / lo).powf(u)
+        } else {
+            lo + u * (hi - lo)
+        }
+    };
+    let neighbor_radius = draw(RANGES[0], false);
+    SimConfig {
+        neighbor_radius,
+        separation_radius: draw(RANGES[1], false).min(neighbor_radius),
+        max_speed: draw(RANGES[2], false),
+        infection_radius: draw(RANGES[3], true),
+        infection_beta: draw(RANGES[4], true),
+        infectious_period: draw(RANGES[5], false),
+        ..*base
+    }
+}
+
+/// How close replicate outcomes sit to the epidemic threshold, from 0 to 1.
+/// Half the score is outcome uncertainty, highest when half the replicates
+/// take off; the other half rewards mid-sized epidemics over ones that
+/// fizzle or sweep the whole population.
+pub fn interest(outcomes: &[RunOutcome]) -> f32 {
+    if outcomes.is_empty() {
+        return 0.0;
+    }
+    let p = OutbreakStats::from_outcomes(outcomes).outbreak_probability;
+    let final_size = outcomes.iter().map(|o| o.final_size).sum::<f32>() / outcomes.len() as f32;
+    0.5 * 4.0 * p * (1.0 - p) + 0.5 * (1.0 - (2.0 * final_size - 1.0).abs())
+}
+
+/// A sampled configuration and how its pre-simulated replicates went.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub config: SimConfig,
+    pub stats: OutbreakStats,
+    /// Mean fraction ever infected.
+    pub final_size: f32,
+    pub score: f32,
+}
+
+/// Samples configurations and pre-simulates them on a background thread,
+/// keeping the most interesting ones.
+pub struct Explorer {
+    receiver: Receiver<Candidate>,
+    total: usize,
+    completed: usize,
+    shortlist: Vec<Candidate>,
+    keep: usize,
+}
+
+impl Explorer {
+    /// Try `count` random variations of `base` for a population of `boids`,
+    /// keeping the best `keep`. Candidates come back with `base`'s world.
+    pub fn spawn(base: SimConfig, boids: usize, count: usize, keep: usize, seed: u64) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let scale = (PREVIEW_BOIDS as f32 / boids.max(1) as f32).sqrt();
+        thread::spawn(move || {
+            let mut rng = Rng::new(seed ^ 0x5eed_f00d);
+            for k in 0..count {
+                let config = sample(&base, &mut rng);
+                let preview = SimConfig {
+                    world_size: Vec2f::new(base.world_size.x * scale, base.world_size.y * scale),
+                    ..config
+                };
+                let outcomes: Vec<RunOutcome> = (0..REPLICATES)
+                    .map(|r| {
+                        let run_seed = seed.wrapping_add((k * REPLICATES + r) as u64 + 1);
+                        run_to_end(preview, PREVIEW_BOIDS, run_seed, MAX_STEPS, DT)
+                    })
+                    .collect();
+                let candidate = Candidate {
+                    config,
+                    stats: OutbreakStats::from_outcomes(&outcomes),
+                    final_size: outcomes.iter().map(|o| o.final_size).sum::<f32>()
+                        / REPLICATES as f32,
+                    score: interest(&outcomes),
+                };
+                // Stop early if the explorer was discarded.
+                if sender.send(candidate).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            receiver,
+            total: count,
+            completed: 0,
+            shortlist: Vec::with_capacity(keep + 1),
+            keep,
+        }
+    }
+
+    /// Collect any candidates finished since the last call.
+    pub fn poll(&mut self) {
+        for candidate in self.receiver.try_iter() {
+            self.completed += 1;
+            let at = self
+                .shortlist
+                .iter()
+                .position(|c| c.score < candidate.score)
+                .unwrap_or(self.shortlist.len());
+            self.shortlist.insert(at, candidate);
+            self.shortlist.truncate(self.keep);
+        }
+    }
+
+    /// The best candidates so far, most interesting first.
+    pub fn shortlist(&self) -> &[Candidate] {
+        &self.shortlist
+    }
+
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
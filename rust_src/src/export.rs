@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use boid_math::timeseries::{REffEstimator, TickRow, TimeSeriesWriter};
+
+use crate::sim::Simulation;
+
+/// Per-tick CSV of a run through the exporter shared with the `leah`
+/// simulation: compartment counts, an R_eff estimate and mean speed. The
+/// model has no deaths, so `dead` stays 0.
+pub struct TickExport {
+    writer: TimeSeriesWriter<BufWriter<File>>,
+    r_eff: REffEstimator,
+}
+
+impl TickExport {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: TimeSeriesWriter::create(path)?,
+            r_eff: REffEstimator::default(),
+        })
+    }
+
+    /// Append a row for the current state of `sim`.
+    pub fn record(&mut self, sim: &Simulation) -> io::Result<()> {
+        let c = sim.counts();
+        let speed: f32 = sim.boids.iter().map(|b| b.vel.length()).sum();
+        let row = TickRow {
+            time: sim.time(),
+            susceptible: c.susceptible,
+            exposed: c.exposed,
+            infected: c.infected,
+            recovered: c.recovered,
+            dead: 0,
+            r_eff: self.r_eff.record(
+                sim.time(),
+                sim.infection_log().len(),
+                c.exposed + c.infected,
+            ),
+            mean_speed: speed / sim.boids.len().max(1) as f32,
+        };
+        self.writer.write(&row)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.writer.rows()
+    }
+
+    /// Flush what is buffered to the file.
+    pub fn finish(self) -> io::Result<()> {
+        self.writer.finish().map(drop)
+    }
+}
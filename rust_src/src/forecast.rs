@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+
+use crate::sim::{Simulation, SirCounts};
+use crate::stats::mean_field_r0;
+
+/// Seconds of recent history the contact rate is estimated from.
+const WINDOW: f32 = 10.0;
+/// Infections needed in the window before the estimate replaces the
+/// mean-field rate.
+const MIN_CASES: usize = 5;
+/// Integration step of the projection, in simulated seconds.
+const STEP: f32 = 0.1;
+
+/// Estimates the transmission rate `beta` of the mean-field model
+/// `dS/dt = -beta S I / N` from what the run has actually done: infections
+/// over the last `WINDOW` seconds divided by the `S I / N` exposure over the
+/// same period. Flocking keeps boids closer than uniform mixing would, so
+/// the observed rate is usually well above the mean-field one.
+#[derive(Clone, Debug, Default)]
+pub struct ContactRate {
+    /// `(dt, S I / N * dt, new infections)` per recorded step.
+    samples: VecDeque<(f32, f32, usize)>,
+    span: f32,
+    exposure: f32,
+    cases: usize,
+    logged: usize,
+}
+
+impl ContactRate {
+    pub fn new(sim: &Simulation) -> Self {
+        Self {
+            logged: sim.infection_log().len(),
+            ..Self::default()
+        }
+    }
+
+    /// Record the step that just advanced `sim` by `dt`.
+    pub fn record(&mut self, sim: &Simulation, dt: f32) {
+        let counts = sim.counts();
+        let total = counts.total();
+        let exposure = if total == 0 {
+            0.0
+        } else {
+            counts.susceptible as f32 * counts.infected as f32 / total as f32 * dt
+        };
+        let logged = sim.infection_log().len();
+        let cases = logged.saturating_sub(self.logged);
+        self.logged = logged;
+
+        self.samples.push_back((dt, exposure, cases));
+        self.span += dt;
+        self.exposure += exposure;
+        self.cases += cases;
+        while self.span > WINDOW
+            && let Some((dt, exposure, cases)) = self.samples.pop_front()
+        {
+            self.span -= dt;
+            self.exposure -= exposure;
+            self.cases -= cases;
+        }
+    }
+
+    /// The observed rate, or `None` until the window holds enough infections.
+    pub fn observed(&self) -> Option<f32> {
+        (self.cases >= MIN_CASES && self.exposure > 0.0).then(|| self.cases as f32 / self.exposure)
+    }
+
+    /// The observed rate, falling back to the mean-field one from `sim`'s
+    /// configuration.
+    pub fn estimate(&self, sim: &Simulation) -> f32 {
+        self.observed().unwrap_or_else(|| {
+            let cfg = sim.config();
+            mean_field_r0(cfg, sim.boids.len()) / cfg.infectious_period.max(f32::EPSILON)
+        })
+    }
+}
+
+/// A mean-field SIR trajectory projected forward from the current counts,
+/// as population fractions sampled every `STEP` seconds from now.
+#[derive(Clone, Debug)]
+pub struct Forecast {
+    pub beta: f32,
+    pub horizon: f32,
+    points: Vec<[f32; 3]>,
+}
+
+impl Forecast {
+    /// Integrate `dS = -beta S I`, `dI = beta S I - I / period`,
+    /// `dR = I / period` (fractions) from `counts` for `horizon` seconds.
+    pub fn project(counts: SirCounts, beta: f32, infectious_period: f32, horizon: f32) -> Self {
+        let total = counts.total().max(1) as f32;
+        let gamma = 1.0 / infectious_period.max(f32::EPSILON);
+        let deriv = |[s, i, _]: [f32; 3]| {
+            let infection = beta * s * i;
+            [-infection, infection - gamma * i, gamma * i]
+        };
+        let mut state = [
+            counts.susceptible as f32 / total,
+            counts.infected as f32 / total,
+            counts.recovered as f32 / total,
+        ];
+        let steps = (horizon / STEP).ceil() as usize;
+        let mut points = Vec::with_capacity(steps + 1);
+        points.push(state);
+        for _ in 0..steps {
+            let add = |a: [f32; 3], b: [f32; 3], h: f32| {
+                [a[0] + h * b[0], a[1] + h * b[1], a[2] + h * b[2]]
+            };
+            let k1 = deriv(state);
+            let k2 = deriv(add(state, k1, STEP / 2.0));
+            let k3 = deriv(add(state, k2, STEP / 2.0));
+            let k4 = deriv(add(state, k3, STEP));
+            for c in 0..3 {
+                state[c] += STEP / 6.0 * (k1[c] + 2.0 * k2[c] + 2.0 * k3[c] + k4[c]);
+            }
+            points.push(state);
+        }
+        Self {
+            beta,
+            horizon,
+            points,
+        }
+    }
+
+    /// Projected `[S, I, R]` fractions `t` seconds from now, interpolated
+    /// between integration steps and held at the horizon.
+    pub fn at(&self, t: f32) -> [f32; 3] {
+        let x = (t.max(0.0) / STEP).min((self.points.len() - 1) as f32);
+        let k = (x.floor() as usize).min(self.points.len() - 1);
+        let next = (k + 1).min(self.points.len() - 1);
+        let f = x - k as f32;
+        let (a, b) = (self.points[k], self.points[next]);
+        [0, 1, 2].map(|c| a[c] + f * (b[c] - a[c]))
+    }
+
+    /// `(seconds from now, infected fraction)` at the projected peak; time 0
+    /// when prevalence is already falling.
+    pub fn peak(&self) -> (f32, f32) {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(k, p)| (k as f32 * STEP, p[1]))
+            .fold(
+                (0.0, 0.0),
+                |best, cur| if cur.1 > best.1 { cur } else { best },
+            )
+    }
+
+    /// Projected recovered fraction at the horizon.
+    pub fn final_recovered(&self) -> f32 {
+        self.points.last().map_or(0.0, |p| p[2])
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// Write a row-major `width x height` grid of values as a PNG heatmap, each
+/// cell drawn as a `scale x scale` block. Row 0 is drawn at the bottom so the
+/// image reads like a plot with y increasing upward. NaN cells are grey.
+pub fn write_heatmap_png(
+    path: &Path,
+    width: usize,
+    height: usize,
+    values: &[f32],
+    scale: usize,
+) -> io::Result<()> {
+    let (lo, hi) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    let span = if hi > lo { hi - lo } else { 1.0 };
+
+    let (pw, ph) = (width * scale, height * scale);
+    let mut pixels = vec![0u8; pw * ph * 3];
+    for py in 0..ph {
+        let row = height - 1 - py / scale;
+        for px in 0..pw {
+            let v = values[row * width + px / scale];
+            let rgb = if v.is_finite() {
+                colormap((v - lo) / span)
+            } else {
+                [90, 90, 90]
+            };
+            let at = (py * pw + px) * 3;
+            pixels[at..at + 3].copy_from_slice(&rgb);
+        }
+    }
+
+    write_rgb_png(path, pw, ph, &pixels)
+}
+
+/// Write `pixels`, `width x height` 8-bit RGB triples row by row from the
+/// top, as a PNG.
+pub fn write_rgb_png(path: &Path, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut w| w.write_image_data(pixels))
+        .map_err(io::Error::other)
+}
+
+/// Dark blue -> red -> yellow ramp over `t` in [0, 1], matching the app's
+/// dark background palette.
+pub fn colormap(t: f32) -> [u8; 3] {
+    const STOPS: [[f32; 3]; 4] = [
+        [10.0, 12.0, 40.0],
+        [120.0, 30.0, 110.0],
+        [255.0, 90.0, 90.0],
+        [255.0, 230.0, 120.0],
+    ];
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (t.floor() as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    let mut out = [0u8; 3];
+    for (c, o) in out.iter_mut().enumerate() {
+        *o = (STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f) as u8;
+    }
+    out
+}
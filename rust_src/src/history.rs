@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+
+/// Simulated seconds between statistics samples unless configured
+/// otherwise.
+pub const DEFAULT_SAMPLE_INTERVAL: f32 = 0.1;
+
+/// Picks which moments of a run are sampled: the first time it is asked,
+/// then once per `interval` of simulated time on a fixed grid, so samples
+/// stay evenly spaced whatever the step size. An interval of 0 samples
+/// every time. Everything that samples a run goes through one of these, so
+/// graphs, exports and estimates all see the same moments.
+#[derive(Clone, Copy, Debug)]
+pub struct Sampler {
+    interval: f32,
+    /// Earliest time the next sample is due.
+    next: f32,
+}
+
+impl Sampler {
+    pub fn new(interval: f32) -> Self {
+        Self {
+            interval: interval.max(0.0),
+            next: f32::NEG_INFINITY,
+        }
+    }
+
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    /// Whether to sample at `time`. Times within a thousandth of an interval
+    /// of a grid point count as on it, so accumulated rounding in the clock
+    /// neither skips nor repeats a sample.
+    pub fn due(&mut self, time: f32) -> bool {
+        if self.interval <= 0.0 {
+            return true;
+        }
+        let slack = self.interval * 1e-3;
+        if time < self.next - slack {
+            return false;
+        }
+        self.next = ((time + slack) / self.interval).floor() * self.interval + self.interval;
+        true
+    }
+}
+
+/// One entry of a `History`.
+#[derive(Clone, Copy, Debug)]
+pub struct Sample<T> {
+    /// Position in the sequence of everything pushed, from 0.
+    pub index: usize,
+    pub time: f32,
+    pub value: T,
+}
+
+/// A time series that keeps the whole run in memory logarithmic in its
+/// length: the newest `recent` samples at full resolution, then levels that
+/// each keep every other sample of the level before, so resolution halves
+/// with every `recent` samples of age. Nothing is ever dropped outright, so
+/// the full run can always be drawn or exported.
+#[derive(Clone, Debug)]
+pub struct History<T> {
+    recent: usize,
+    /// `levels[k]` holds samples at a stride of `2^k`, oldest first; higher
+    /// levels are older.
+    levels: Vec<VecDeque<Sample<T>>>,
+    pushed: usize,
+    sampler: Sampler,
+}
+
+impl<T: Copy> History<T> {
+    /// A history keeping everything pushed to it.
+    pub fn new(recent: usize) -> Self {
+        Self::sampled(recent, 0.0)
+    }
+
+    /// A history keeping one push per `interval` of simulated time and
+    /// ignoring the rest, so callers can push every step.
+    pub fn sampled(recent: usize, interval: f32) -> Self {
+        Self {
+            recent: recent.max(2),
+            levels: vec![VecDeque::new()],
+            pushed: 0,
+            sampler: Sampler::new(interval),
+        }
+    }
+
+    /// Simulated seconds between kept pushes; 0 if every push is kept.
+    pub fn interval(&self) -> f32 {
+        self.sampler.interval()
+    }
+
+    pub fn push(&mut self, time: f32, value: T) {
+        if !self.sampler.due(time) {
+            return;
+        }
+        self.levels[0].push_back(Sample {
+            index: self.pushed,
+            time,
+            value,
+        });
+        self.pushed += 1;
+        let mut k = 0;
+        while self.levels[k].len() >= self.recent + 2 {
+            // Demote the oldest pair as its earlier sample.
+            let kept = self.levels[k].pop_front();
+            self.levels[k].pop_front();
+            if k + 1 == self.levels.len() {
+                self.levels.push(VecDeque::new());
+            }
+            self.levels[k + 1].extend(kept);
+            k += 1;
+        }
+    }
+
+    /// Samples taken so far, including any since thinned out.
+    pub fn pushed(&self) -> usize {
+        self.pushed
+    }
+
+    /// Samples currently kept.
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pushed == 0
+    }
+
+    /// The newest `recent` samples, all at full resolution, oldest first.
+    pub fn recent(&self) -> impl Iterator<Item = &Sample<T>> {
+        let level = &self.levels[0];
+        level.iter().skip(level.len().saturating_sub(self.recent))
+    }
+
+    /// Every kept sample, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Sample<T>> {
+        self.levels.iter().rev().flatten()
+    }
+
+    /// The kept samples as CSV, with `header` naming the columns `row`
+    /// writes after `index,time`.
+    pub fn csv(&self, header: &str, row: impl Fn(&T) -> String) -> String {
+        let mut out = format!("index,time,{header}\n");
+        for sample in self.iter() {
+            let _ = writeln!(
+                out,
+                "{},{:.4},{}",
+                sample.index,
+                sample.time,
+                row(&sample.value)
+            );
+        }
+        out
+    }
+}
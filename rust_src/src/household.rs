@@ -0,0 +1,91 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sim::InfectionEvent;
+
+/// Name of the tag value holding each boid's household: 1-based id, 0 for
+/// none.
+pub const HOUSEHOLD_TAG: &str = "household";
+
+/// Boids grouped into households that share a home anchor. Housemates
+/// transmit to each other at their own rate on top of community contacts:
+/// all of them at night, when everyone is home, and by day only within
+/// `radius` of each other.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Households {
+    /// Members per household; the last one may be smaller.
+    pub size: usize,
+    /// Infection rate per second from each infectious housemate in contact.
+    pub beta: f32,
+    /// Daytime contact radius between housemates; 0 limits household
+    /// transmission to the night.
+    pub radius: f32,
+}
+
+impl Households {
+    /// Parse `size[:beta[:radius]]`, e.g. `4:0.3:30`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(':');
+        let size = parts
+            .next()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("invalid household size in {spec}"))?;
+        let mut num = |default: f32| {
+            parts.next().map_or(Ok(default), |s| {
+                s.parse::<f32>().map_err(|_| format!("invalid number: {s}"))
+            })
+        };
+        let beta = num(0.3)?;
+        let radius = num(30.0)?;
+        Ok(Self {
+            size,
+            beta: beta.max(0.0),
+            radius: radius.max(0.0),
+        })
+    }
+}
+
+/// How transmitted (non-seeded) cases split between households and the
+/// community.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HouseholdSplit {
+    pub household: usize,
+    pub community: usize,
+}
+
+impl HouseholdSplit {
+    pub fn from_log(log: &[InfectionEvent]) -> Self {
+        let mut split = Self::default();
+        for event in log.iter().filter(|e| e.infector.is_some()) {
+            if event.household {
+                split.household += 1;
+            } else {
+                split.community += 1;
+            }
+        }
+        split
+    }
+}
+
+impl fmt::Display for HouseholdSplit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = (self.household + self.community).max(1) as f32;
+        writeln!(
+            f,
+            "{:<18} {:>6} infections ({:.1}%)",
+            "household",
+            self.household,
+            self.household as f32 / total * 100.0
+        )?;
+        write!(
+            f,
+            "{:<18} {:>6} infections ({:.1}%)",
+            "community",
+            self.community,
+            self.community as f32 / total * 100.0
+        )
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use crate::contacts::ContactNetwork;
+use crate::sim::{HealthState, SimConfig, Simulation};
+
+/// Burn-in used to build the contact graph for degree-targeted immunization
+/// when none is given.
+pub const DEFAULT_BURN_IN: f32 = 5.0;
+
+/// Pre-run immunization strategy. Every strategy targets the same share of
+/// the initially susceptible population, so they can be compared at equal
+/// coverage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Immunization {
+    #[default]
+    None,
+    /// Each susceptible boid independently with probability `fraction`.
+    Random(f32),
+    /// The best-connected susceptible boids of a contact graph recorded over
+    /// `burn_in` seconds of infection-free motion from the same start.
+    #[serde(rename = "degree")]
+    HighestDegree { fraction: f32, burn_in: f32 },
+    /// The susceptible boids closest to any seeded case.
+    Ring(f32),
+}
+
+impl Immunization {
+    /// Parse `random:F`, `degree:F[:burn_in]` or `ring:F`, where `F` is the
+    /// fraction of susceptibles to immunize.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let mut num = |what: &str| -> Result<Option<f32>, String> {
+            parts
+                .next()
+                .map(|s| s.parse().map_err(|_| format!("invalid {what}: {s}")))
+                .transpose()
+        };
+        let fraction = num("fraction")?;
+        let strategy = match (kind, fraction) {
+            ("none", None) => Immunization::None,
+            ("random", Some(f)) => Immunization::Random(f),
+            ("degree", Some(fraction)) => Immunization::HighestDegree {
+                fraction,
+                burn_in: num("burn-in")?.unwrap_or(DEFAULT_BURN_IN),
+            },
+            ("ring", Some(f)) => Immunization::Ring(f),
+            _ => return Err(format!("expected random|degree|ring:FRACTION, got {spec}")),
+        };
+        Ok(strategy)
+    }
+}
+
+/// Apply `sim.config().immunization`, moving the chosen susceptible boids to
+/// `Recovered`. Returns how many were immunized.
+pub fn apply(sim: &mut Simulation) -> usize {
+    let susceptible: Vec<usize> = (0..sim.boids.len())
+        .filter(|&i| sim.boids[i].state == HealthState::Susceptible)
+        .collect();
+    let quota =
+        |fraction: f32| (fraction.clamp(0.0, 1.0) * susceptible.len() as f32).round() as usize;
+
+    let chosen: Vec<usize> = match sim.config().immunization {
+        Immunization::None => return 0,
+        Immunization::Random(fraction) => return sim.immunize_random(fraction),
+        Immunization::HighestDegree { fraction, burn_in } => {
+            let degrees = burn_in_degrees(sim, burn_in);
+            let mut ranked = susceptible.clone();
+            ranked.sort_by_key(|&i| std::cmp::Reverse(degrees.get(i).copied().unwrap_or(0)));
+            ranked.truncate(quota(fraction));
+            ranked
+        }
+        Immunization::Ring(fraction) => {
+            let cfg = sim.config();
+            let seeds: Vec<_> = sim
+                .boids
+                .iter()
+                .filter(|b| b.state == HealthState::Infected)
+                .map(|b| b.pos)
+                .collect();
+            let distance = |i: usize| {
+                seeds
+                    .iter()
+                    .map(|&s| cfg.offset(s, sim.boids[i].pos).length())
+                    .fold(f32::INFINITY, f32::min)
+            };
+            let mut ranked: Vec<(f32, usize)> =
+                susceptible.iter().map(|&i| (distance(i), i)).collect();
+            ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+            ranked
+                .into_iter()
+                .take(quota(fraction))
+                .map(|(_, i)| i)
+                .collect()
+        }
+    };
+    for &i in &chosen {
+        sim.boids[i].state = HealthState::Recovered;
+    }
+    chosen.len()
+}
+
+/// Contact degrees over `burn_in` seconds of a twin run with the same seed
+/// and transmission switched off.
+fn burn_in_degrees(sim: &Simulation, burn_in: f32) -> Vec<usize> {
+    const DT: f32 = 1.0 / 60.0;
+    let cfg = SimConfig {
+        infection_beta: 0.0,
+        immunization: Immunization::None,
+        ..*sim.config()
+    };
+    let mut twin = Simulation::new(sim.boids.len(), cfg, sim.seed());
+    let mut network = ContactNetwork::new();
+    for _ in 0..(burn_in / DT).round() as usize {
+        twin.step(DT);
+        network.record(&twin, DT);
+    }
+    network.degrees()
+}
@@ -0,0 +1,96 @@
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use crate::sim::{InfectionEvent, Vec2f};
+
+/// Infection events binned by where they happened. Row-major (y outer,
+/// x inner), row 0 at y = 0.
+pub struct IncidenceGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub cell_size: f32,
+    pub counts: Vec<u32>,
+}
+
+impl IncidenceGrid {
+    /// Bin every event in `log` into square cells of `cell_size` covering
+    /// `world`. Seeded cases are included unless `transmitted_only`.
+    pub fn from_log(
+        log: &[InfectionEvent],
+        world: Vec2f,
+        cell_size: f32,
+        transmitted_only: bool,
+    ) -> Self {
+        let cell_size = cell_size.max(1.0);
+        let nx = (world.x / cell_size).ceil().max(1.0) as usize;
+        let ny = (world.y / cell_size).ceil().max(1.0) as usize;
+        let mut counts = vec![0; nx * ny];
+        for event in log {
+            if transmitted_only && event.infector.is_none() {
+                continue;
+            }
+            let cx = ((event.pos.x / cell_size) as usize).min(nx - 1);
+            let cy = ((event.pos.y / cell_size) as usize).min(ny - 1);
+            counts[cy * nx + cx] += 1;
+        }
+        Self {
+            nx,
+            ny,
+            cell_size,
+            counts,
+        }
+    }
+
+    /// One CSV row per grid row, no header.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in self.counts.chunks(self.nx) {
+            let line: Vec<String> = row.iter().map(u32::to_string).collect();
+            let _ = writeln!(out, "{}", line.join(","));
+        }
+        out
+    }
+
+    /// NumPy `.npy` (format 1.0) encoding of the `ny x nx` matrix as
+    /// little-endian `u32`.
+    pub fn to_npy(&self) -> Vec<u8> {
+        let mut header = format!(
+            "{{'descr': '<u4', 'fortran_order': False, 'shape': ({}, {}), }}",
+            self.ny, self.nx
+        );
+        // Magic, version and length take 10 bytes; pad so data is 64-aligned.
+        let total = (10 + header.len() + 1).div_ceil(64) * 64;
+        header.push_str(&" ".repeat(total - 10 - header.len() - 1));
+        header.push('\n');
+
+        let mut out = Vec::with_capacity(total + self.counts.len() * 4);
+        out.extend_from_slice(b"\x93NUMPY\x01\x00");
+        out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        out.extend_from_slice(header.as_bytes());
+        for c in &self.counts {
+            out.extend_from_slice(&c.to_le_bytes());
+        }
+        out
+    }
+
+    /// Write `.npy` if `path` ends in `.npy`, CSV otherwise.
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        if path.extension().is_some_and(|e| e == "npy") {
+            std::fs::write(path, self.to_npy())
+        } else {
+            std::fs::write(path, self.to_csv())
+        }
+    }
+
+    /// Counts with rows reversed, so `write_heatmap_png` (which puts row 0 at
+    /// the bottom) draws the grid the way the world appears on screen.
+    pub fn screen_values(&self) -> Vec<f32> {
+        self.counts
+            .chunks(self.nx)
+            .rev()
+            .flatten()
+            .map(|&c| c as f32)
+            .collect()
+    }
+}
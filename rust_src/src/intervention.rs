@@ -0,0 +1,139 @@
+use rayon::prelude::*;
+
+use crate::immunization::{DEFAULT_BURN_IN, Immunization};
+use crate::restriction::TravelRestriction;
+use crate::runner::{RunOutcome, run_sim_to_end};
+use crate::sensitivity::{Param, RunSettings};
+use crate::sim::Simulation;
+
+/// One change applied to a scenario before it runs.
+#[derive(Clone, Copy, Debug)]
+pub enum Intervention {
+    /// Override a config parameter, e.g. a larger separation radius for
+    /// distancing or a lower beta for masks.
+    Set(Param, f32),
+    /// Immunize part of the susceptible population before the run.
+    Immunize(Immunization),
+    /// Confine boids to their quadrant once prevalence reaches a threshold.
+    RestrictTravel(TravelRestriction),
+}
+
+impl Intervention {
+    /// Parse `key=value`, where `key` is a parameter name or one of
+    /// `vaccinate`, `vaccinate_degree` and `vaccinate_ring` (random,
+    /// highest-degree and ring immunization of that fraction) or
+    /// `restrict_travel` (quadrant confinement from that prevalence on).
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (key, value) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got {spec}"))?;
+        let value: f32 = value
+            .parse()
+            .map_err(|_| format!("invalid number: {value}"))?;
+        match key {
+            "vaccinate" => Ok(Intervention::Immunize(Immunization::Random(value))),
+            "vaccinate_degree" => Ok(Intervention::Immunize(Immunization::HighestDegree {
+                fraction: value,
+                burn_in: DEFAULT_BURN_IN,
+            })),
+            "vaccinate_ring" => Ok(Intervention::Immunize(Immunization::Ring(value))),
+            "restrict_travel" => Ok(Intervention::RestrictTravel(TravelRestriction {
+                threshold: Some(value.clamp(0.0, 1.0)),
+                ..TravelRestriction::default()
+            })),
+            _ => Param::from_name(key)
+                .map(|p| Intervention::Set(p, value))
+                .ok_or_else(|| format!("unknown intervention: {key}")),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    pub interventions: Vec<Intervention>,
+}
+
+impl Scenario {
+    pub fn baseline() -> Self {
+        Self {
+            name: "baseline".to_string(),
+            interventions: Vec::new(),
+        }
+    }
+
+    /// Parse `name:key=value[,key=value...]`, e.g.
+    /// `distancing:separation_radius=44,vaccinate=0.3`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, list) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("expected name:key=value,..., got {spec}"))?;
+        let interventions = list
+            .split(',')
+            .map(Intervention::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: name.to_string(),
+            interventions,
+        })
+    }
+
+    /// Build replicate `r` of this scenario. All scenarios share the seeds of
+    /// `settings`, so they differ only by their interventions.
+    pub fn build(&self, settings: &RunSettings, r: usize) -> Simulation {
+        let mut cfg = settings.base;
+        for intervention in &self.interventions {
+            match *intervention {
+                Intervention::Set(param, value) => param.apply(&mut cfg, value),
+                Intervention::Immunize(strategy) => cfg.immunization = strategy,
+                Intervention::RestrictTravel(spec) => cfg.travel_restriction = Some(spec),
+            }
+        }
+        Simulation::new(settings.boids, cfg, settings.seed.wrapping_add(r as u64))
+    }
+}
+
+/// Mean outcomes of one scenario, relative to the baseline.
+#[derive(Clone, Debug)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub peak_infected: f32,
+    /// Fraction ever infected, seeded cases included.
+    pub final_size: f32,
+    /// Mean infections prevented compared with the baseline. There is no
+    /// mortality model, so this stands in for deaths averted.
+    pub infections_averted: f32,
+}
+
+/// Run the baseline followed by each of `scenarios` over the replicates in
+/// `settings`. The first result is always the baseline.
+pub fn compare(scenarios: &[Scenario], settings: &RunSettings) -> Vec<ScenarioResult> {
+    let all: Vec<Scenario> = std::iter::once(Scenario::baseline())
+        .chain(scenarios.iter().cloned())
+        .collect();
+    let n = settings.replicates.max(1);
+    let population = settings.boids as f32;
+    let means: Vec<RunOutcome> = all
+        .par_iter()
+        .map(|scenario| {
+            let mut mean = RunOutcome::default();
+            for r in 0..n {
+                let o =
+                    run_sim_to_end(scenario.build(settings, r), settings.max_steps, settings.dt);
+                mean.peak_prevalence += o.peak_prevalence / n as f32;
+                mean.final_size += o.final_size / n as f32;
+            }
+            mean
+        })
+        .collect();
+    let baseline_infected = means[0].final_size * population;
+    all.into_iter()
+        .zip(means)
+        .map(|(scenario, mean)| ScenarioResult {
+            name: scenario.name,
+            peak_infected: mean.peak_prevalence * population,
+            final_size: mean.final_size,
+            infections_averted: baseline_infected - mean.final_size * population,
+        })
+        .collect()
+}
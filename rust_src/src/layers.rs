@@ -0,0 +1,139 @@
+/// Something the live view draws over the world. World layers are drawn in
+/// world coordinates under the camera, then screen layers on top of them,
+/// each group in its own order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    /// Borders of travel restriction zones.
+    Zones,
+    /// Crowd pressure per cell, over-compressed cells outlined.
+    Pressure,
+    Boids,
+    /// Rings around quarantined boids.
+    Quarantine,
+    /// Transmission chain of the selected boid.
+    Chain,
+    /// Ring around the selected boid.
+    Selection,
+    /// Night-time tint while the activity schedule has boids at home.
+    Night,
+    /// Boid index labels.
+    Labels,
+    Minimap,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 9] = [
+        Layer::Zones,
+        Layer::Pressure,
+        Layer::Boids,
+        Layer::Quarantine,
+        Layer::Chain,
+        Layer::Selection,
+        Layer::Night,
+        Layer::Labels,
+        Layer::Minimap,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Zones => "zones",
+            Layer::Pressure => "pressure",
+            Layer::Boids => "boids",
+            Layer::Quarantine => "quarantine",
+            Layer::Chain => "chain",
+            Layer::Selection => "selection",
+            Layer::Night => "night",
+            Layer::Labels => "labels",
+            Layer::Minimap => "minimap",
+        }
+    }
+
+    /// Whether the layer is drawn in world coordinates.
+    pub fn in_world(self) -> bool {
+        !matches!(self, Layer::Night | Layer::Labels | Layer::Minimap)
+    }
+
+    /// Whether the layer is shown before anyone toggles it.
+    fn shown_by_default(self) -> bool {
+        !matches!(self, Layer::Chain | Layer::Labels | Layer::Pressure)
+    }
+}
+
+/// Draw order and visibility of every `Layer`, back to front.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layers {
+    order: Vec<(Layer, bool)>,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self {
+            order: Layer::ALL
+                .iter()
+                .map(|&layer| (layer, layer.shown_by_default()))
+                .collect(),
+        }
+    }
+}
+
+impl Layers {
+    /// Visible layers back to front, world layers first.
+    pub fn visible(&self) -> impl Iterator<Item = Layer> + '_ {
+        let world = self.order.iter().filter(|(l, _)| l.in_world());
+        let screen = self.order.iter().filter(|(l, _)| !l.in_world());
+        world.chain(screen).filter(|(_, on)| *on).map(|&(l, _)| l)
+    }
+
+    /// Every layer back to front with whether it is shown, world layers
+    /// first.
+    pub fn all(&self) -> impl Iterator<Item = (Layer, bool)> + '_ {
+        let world = self.order.iter().filter(|(l, _)| l.in_world());
+        let screen = self.order.iter().filter(|(l, _)| !l.in_world());
+        world.chain(screen).copied()
+    }
+
+    pub fn is_shown(&self, layer: Layer) -> bool {
+        self.order.iter().any(|&(l, on)| l == layer && on)
+    }
+
+    pub fn set_shown(&mut self, layer: Layer, shown: bool) {
+        for entry in &mut self.order {
+            if entry.0 == layer {
+                entry.1 = shown;
+            }
+        }
+    }
+
+    pub fn toggle(&mut self, layer: Layer) {
+        self.set_shown(layer, !self.is_shown(layer));
+    }
+
+    /// Move `layer` one step towards the front, past the next layer drawn in
+    /// the same space. Does nothing if it is already frontmost there.
+    pub fn raise(&mut self, layer: Layer) {
+        self.shift(layer, true);
+    }
+
+    /// Move `layer` one step towards the back.
+    pub fn lower(&mut self, layer: Layer) {
+        self.shift(layer, false);
+    }
+
+    fn shift(&mut self, layer: Layer, forward: bool) {
+        let Some(at) = self.order.iter().position(|&(l, _)| l == layer) else {
+            return;
+        };
+        let same_space = |&(l, _): &(Layer, bool)| l.in_world() == layer.in_world();
+        let other = if forward {
+            self.order[at + 1..]
+                .iter()
+                .position(same_space)
+                .map(|k| at + 1 + k)
+        } else {
+            self.order[..at].iter().rposition(same_space)
+        };
+        if let Some(other) = other {
+            self.order.swap(at, other);
+        }
+    }
+}
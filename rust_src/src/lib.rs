@@ -0,0 +1,47 @@
+pub mod budget;
+pub mod calibration;
+pub mod capture;
+pub mod challenge;
+pub mod chart;
+pub mod commute;
+pub mod config_file;
+pub mod contacts;
+pub mod ensemble;
+pub mod explore;
+pub mod export;
+pub mod forecast;
+pub mod heatmap;
+pub mod history;
+pub mod household;
+pub mod immunization;
+pub mod incidence;
+pub mod intervention;
+pub mod layers;
+pub mod oscillation;
+pub mod policy;
+pub mod quarantine_zone;
+pub mod recording;
+pub mod render;
+pub mod restriction;
+pub mod risk;
+pub mod rundb;
+pub mod runner;
+pub mod scheduler;
+pub mod scripting;
+pub mod sensitivity;
+pub mod sim;
+pub mod snapshot;
+pub mod sonify;
+pub mod stats;
+pub mod stop;
+pub mod sweep;
+pub mod tags;
+pub mod tiled;
+pub mod timeline;
+pub mod timestep;
+pub mod train;
+pub mod trajectory;
+pub mod transmission;
+pub mod tutorial;
+pub mod venue;
+pub mod world;
@@ -1,9 +1,41 @@
+use macroquad::audio::{
+    PlaySoundParams, Sound, load_sound_from_bytes, play_sound, set_sound_volume, stop_sound,
+};
 use macroquad::prelude::*;
+use std::collections::VecDeque;
 use std::f32::consts::PI;
 
-mod sim;
-
-use sim::{HealthState, SimConfig, Simulation, SirCounts, Vec2f};
+use boid_math::ensemble::Band;
+use rust_src::budget::FrameBudget;
+use rust_src::capture::FrameRing;
+use rust_src::challenge::{Attempt, Challenge, ChallengeResult, challenges};
+use rust_src::commute::{Commute, Patch};
+use rust_src::config_file::{ConfigFile, ConfigWatcher};
+use rust_src::ensemble::Ensemble;
+use rust_src::explore::Explorer;
+use rust_src::export::TickExport;
+use rust_src::forecast::{ContactRate, Forecast};
+use rust_src::heatmap::colormap;
+use rust_src::history::{DEFAULT_SAMPLE_INTERVAL, History, Sample};
+use rust_src::layers::{Layer, Layers};
+use rust_src::policy::{INIT_SCALE, Policy};
+use rust_src::quarantine_zone::QuarantineZone;
+use rust_src::recording::{Recorder, Recording, Replay};
+use rust_src::render::Renderer;
+use rust_src::restriction::TravelRestriction;
+use rust_src::risk::{RISK_WINDOW, RiskScores};
+use rust_src::sim::{
+    ActivitySchedule, AreaAction, Boid, DiseaseModel, HealthState, Rng, SimConfig, Simulation,
+    SirCounts, TICK, Vec2f,
+};
+use rust_src::sonify::{self, Sonifier};
+use rust_src::stats::{
+    CrowdPressure, EpidemicSummary, EpidemicTracker, OVERCOMPRESSED, crowd_pressure,
+};
+use rust_src::timeline::StateTimeline;
+use rust_src::transmission::{ancestry, descendants};
+use rust_src::tutorial::Tutorial;
+use rust_src::world::{Obstacle, WorldPreset};
 
 struct Knob {
     label: &'static str,
@@ -100,99 +132,1726 @@ impl Knob {
     }
 }
 
+const ENSEMBLE_REPLICAS: usize = 8;
+const ENSEMBLE_STEPS: usize = 3600;
+const ENSEMBLE_DT: f32 = 1.0 / 60.0;
+
+/// Samples the graph keeps at full resolution, one per sample interval: the
+/// window it shows unless the whole run is asked for.
+const GRAPH_RECENT: usize = 360;
+
+/// Earlier runs whose curves stay on the graph, faded, while comparing
+/// restarts.
+const GHOST_RUNS: usize = 3;
+
+/// A finished run's curves, kept to draw behind the live ones.
+struct GhostRun {
+    history: History<SirCounts>,
+    /// Population at the end of the run, to draw it as shares like the live
+    /// curves.
+    total: usize,
+}
+
 struct SirGraph {
-    history: Vec<SirCounts>,
-    max_len: usize,
+    history: History<SirCounts>,
+    /// Who was in which state, for brushing a time on the graph.
+    states: StateTimeline,
+    /// Show the whole run, older parts downsampled, instead of the recent
+    /// window.
+    whole_run: bool,
 }
 
 impl SirGraph {
-    fn new(max_len: usize) -> Self {
+    /// A graph sampling the run every `interval` simulated seconds.
+    fn new(whole_run: bool, interval: f32) -> Self {
         Self {
-            history: Vec::with_capacity(max_len),
-            max_len,
+            history: History::sampled(GRAPH_RECENT, interval),
+            states: StateTimeline::new(),
+            whole_run,
         }
     }
 
-    fn push(&mut self, counts: SirCounts) {
-        if self.history.len() == self.max_len {
-            self.history.remove(0);
+    fn push(&mut self, sim: &Simulation) {
+        self.history.push(sim.time(), sim.counts());
+        self.states.record(sim);
+    }
+
+    /// Time and screen column of the shown sample nearest column `x` of a
+    /// graph drawn at `origin`, if `x` is over it.
+    fn sample_at(&self, x: f32, origin: Vec2f, size: Vec2f) -> Option<(f32, f32)> {
+        if x < origin.x || x > origin.x + size.x {
+            return None;
         }
-        self.history.push(counts);
+        let (samples, first, span) = self.visible();
+        samples
+            .into_iter()
+            .map(|s| (s.time, Self::x_of(s.index, first, span, origin, size)))
+            .min_by(|a, b| (a.1 - x).abs().total_cmp(&(b.1 - x).abs()))
+    }
+
+    fn visible(&self) -> (Vec<&Sample<SirCounts>>, usize, usize) {
+        graph_window(&self.history, self.whole_run)
+    }
+
+    fn x_of(index: usize, first: usize, span: usize, origin: Vec2f, size: Vec2f) -> f32 {
+        origin.x + (index - first) as f32 / (span.saturating_sub(1).max(1) as f32) * size.x
+    }
+
+    /// Shade the ensemble's inter-quartile band of the infected fraction and
+    /// draw its mean, aligned by time: each sample of the live run sits over
+    /// the replicas' samples nearest its time, whatever steps either took.
+    fn draw_band(&self, ensemble: &Ensemble, origin: Vec2f, size: Vec2f) {
+        let (samples, first, span) = self.visible();
+        let y_of = |v: f32| origin.y + size.y - v * size.y;
+        let mut prev: Option<(f32, Band)> = None;
+        for sample in samples {
+            let Some(band) = ensemble.band_at(sample.time) else {
+                break;
+            };
+            let x = Self::x_of(sample.index, first, span, origin, size);
+            if let Some((px, pb)) = prev {
+                let fill = Color::from_rgba(255, 90, 90, 45);
+                draw_triangle(
+                    Vec2::new(px, y_of(pb.q25)),
+                    Vec2::new(px, y_of(pb.q75)),
+                    Vec2::new(x, y_of(band.q75)),
+                    fill,
+                );
+                draw_triangle(
+                    Vec2::new(px, y_of(pb.q25)),
+                    Vec2::new(x, y_of(band.q75)),
+                    Vec2::new(x, y_of(band.q25)),
+                    fill,
+                );
+                draw_line(
+                    px,
+                    y_of(pb.mean),
+                    x,
+                    y_of(band.mean),
+                    1.5,
+                    Color::from_rgba(255, 170, 120, 200),
+                );
+            }
+            prev = Some((x, band));
+        }
+    }
+
+    /// Faded S/I/R curves of earlier runs, newest brightest, aligned with
+    /// the live run by time and spanning the whole axis rather than stopping
+    /// at the live run's present.
+    fn draw_ghosts(&self, ghosts: &VecDeque<GhostRun>, origin: Vec2f, size: Vec2f) {
+        let (_, first, span) = self.visible();
+        let interval = self.history.interval();
+        let last = (first + span).saturating_sub(1) as f32;
+        for (age, ghost) in ghosts.iter().enumerate() {
+            if ghost.total == 0 {
+                continue;
+            }
+            let alpha = 110 / (age as u8 + 1);
+            let colors = [
+                Color::from_rgba(200, 220, 255, alpha),
+                Color::from_rgba(255, 90, 90, alpha),
+                Color::from_rgba(120, 220, 140, alpha),
+            ];
+            let mut prev: Option<[Vec2f; 3]> = None;
+            for sample in ghost.history.iter() {
+                let at = if interval > 0.0 {
+                    sample.time / interval
+                } else {
+                    sample.index as f32
+                };
+                if at < first as f32 || at > last {
+                    prev = None;
+                    continue;
+                }
+                let x = origin.x
+                    + (at - first as f32) / (span.saturating_sub(1).max(1) as f32) * size.x;
+                let c = sample.value;
+                let cur = [c.susceptible, c.infected, c.recovered].map(|v| {
+                    Vec2f::new(
+                        x,
+                        origin.y + size.y - v as f32 / ghost.total as f32 * size.y,
+                    )
+                });
+                if let Some(prev) = prev {
+                    for ((p, c), color) in prev.iter().zip(&cur).zip(colors) {
+                        draw_line(p.x, p.y, c.x, c.y, 1.5, color);
+                    }
+                }
+                prev = Some(cur);
+            }
+        }
+    }
+
+    /// The kept history as CSV, downsampled parts included.
+    fn csv(&self) -> String {
+        self.history
+            .csv("susceptible,exposed,infected,recovered", |c| {
+                format!(
+                    "{},{},{},{}",
+                    c.susceptible, c.exposed, c.infected, c.recovered
+                )
+            })
+    }
+}
+
+/// Samples of `history` on screen, and the first sample index and number
+/// of samples the x axis spans.
+fn graph_window(
+    history: &History<SirCounts>,
+    whole_run: bool,
+) -> (Vec<&Sample<SirCounts>>, usize, usize) {
+    if whole_run {
+        (history.iter().collect(), 0, history.pushed())
+    } else {
+        let first = history.pushed().saturating_sub(GRAPH_RECENT);
+        (history.recent().collect(), first, GRAPH_RECENT)
+    }
+}
+
+/// Draws into the window: boids inside `view` under the world camera,
+/// overlay lines down from `overlay_at`, and the population graph into the
+/// screen rectangle at `graph_origin`, with an exposed curve if `exposed`.
+struct MacroquadRenderer {
+    view: Rect,
+    overlay_at: Vec2f,
+    graph_origin: Vec2f,
+    graph_size: Vec2f,
+    whole_run: bool,
+    exposed: bool,
+}
+
+impl Renderer for MacroquadRenderer {
+    fn draw_boids(&mut self, sim: &Simulation, shown: &[bool]) {
+        draw_boids(sim, self.view, shown);
     }
 
-    fn draw(&self, origin: Vec2f, size: Vec2f, total: usize) {
-        if self.history.len() < 2 || total == 0 {
+    fn draw_overlay(&mut self, lines: &[String]) {
+        for (k, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                self.overlay_at.x,
+                self.overlay_at.y + k as f32 * 20.0,
+                16.0,
+                Color::from_rgba(120, 200, 255, 255),
+            );
+        }
+    }
+
+    fn draw_graph(&mut self, history: &History<SirCounts>, total: usize) {
+        let (samples, first, span) = graph_window(history, self.whole_run);
+        let (origin, size) = (self.graph_origin, self.graph_size);
+        if samples.len() < 2 || total == 0 {
             return;
         }
         let total_f = total as f32;
-        let mut prev_s = self.point(0, origin, size, total_f, |c| c.susceptible as f32);
-        let mut prev_i = self.point(0, origin, size, total_f, |c| c.infected as f32);
-        let mut prev_r = self.point(0, origin, size, total_f, |c| c.recovered as f32);
-        for idx in 1..self.history.len() {
-            let cur_s = self.point(idx, origin, size, total_f, |c| c.susceptible as f32);
-            let cur_i = self.point(idx, origin, size, total_f, |c| c.infected as f32);
-            let cur_r = self.point(idx, origin, size, total_f, |c| c.recovered as f32);
-            draw_line(
-                prev_s.x,
-                prev_s.y,
-                cur_s.x,
-                cur_s.y,
-                2.0,
-                Color::from_rgba(200, 220, 255, 255),
+        let colors = [
+            Color::from_rgba(200, 220, 255, 255),
+            Color::from_rgba(255, 90, 90, 255),
+            Color::from_rgba(120, 220, 140, 255),
+            Color::from_rgba(255, 190, 90, 255),
+        ];
+        let curves = if self.exposed { 4 } else { 3 };
+        let point = |sample: &Sample<SirCounts>| {
+            let c = sample.value;
+            let x = SirGraph::x_of(sample.index, first, span, origin, size);
+            [c.susceptible, c.infected, c.recovered, c.exposed]
+                .map(|v| Vec2f::new(x, origin.y + size.y - v as f32 / total_f * size.y))
+        };
+        let mut prev = point(samples[0]);
+        for &sample in &samples[1..] {
+            let cur = point(sample);
+            for ((p, c), color) in prev.iter().zip(&cur).zip(colors).take(curves) {
+                draw_line(p.x, p.y, c.x, c.y, 2.0, color);
+            }
+            prev = cur;
+        }
+    }
+}
+
+/// Share of the graph width given to the forecast while it is shown.
+const FORECAST_SHARE: f32 = 0.3;
+/// Simulated seconds the forecast looks ahead.
+const FORECAST_HORIZON: f32 = 60.0;
+
+/// Faint projected S/I/R curves in the forecast strip at `origin`, which
+/// spans `FORECAST_HORIZON` seconds from now.
+fn draw_forecast(forecast: &Forecast, origin: Vec2f, size: Vec2f) {
+    const POINTS: usize = 40;
+    let faint = |r, g, b| Color::from_rgba(r, g, b, 110);
+    let colors = [
+        faint(200, 220, 255),
+        faint(255, 90, 90),
+        faint(120, 220, 140),
+    ];
+    let point = |k: usize, c: usize| {
+        let t = k as f32 / POINTS as f32;
+        let v = forecast.at(t * forecast.horizon)[c].clamp(0.0, 1.0);
+        (origin.x + t * size.x, origin.y + size.y - v * size.y)
+    };
+    for (c, &color) in colors.iter().enumerate() {
+        for k in 1..=POINTS {
+            let (x0, y0) = point(k - 1, c);
+            let (x1, y1) = point(k, c);
+            draw_line(x0, y0, x1, y1, 1.5, color);
+        }
+    }
+    // Dashed "now" divider between the history and the projection.
+    let mut y = origin.y;
+    while y < origin.y + size.y {
+        let end = (y + 4.0).min(origin.y + size.y);
+        draw_line(
+            origin.x,
+            y,
+            origin.x,
+            end,
+            1.0,
+            Color::from_rgba(150, 160, 180, 160),
+        );
+        y += 8.0;
+    }
+    draw_text(
+        &format!("+{:.0}s", forecast.horizon),
+        origin.x + size.x - 26.0,
+        origin.y + 10.0,
+        14.0,
+        Color::from_rgba(150, 160, 180, 200),
+    );
+}
+
+/// Ticks for infections and a background tone that rises with prevalence.
+struct Audio {
+    tick: Sound,
+    tones: Vec<Sound>,
+    sonifier: Sonifier,
+    /// Length of the infection log already ticked for.
+    logged: usize,
+    playing: Option<usize>,
+}
+
+impl Audio {
+    async fn load(sim: &Simulation) -> Option<Self> {
+        let tick = load_sound_from_bytes(&sonify::wav(&sonify::tick()))
+            .await
+            .ok()?;
+        let mut tones = Vec::with_capacity(sonify::TONE_PITCHES.len());
+        for freq in sonify::TONE_PITCHES {
+            tones.push(
+                load_sound_from_bytes(&sonify::wav(&sonify::tone(freq)))
+                    .await
+                    .ok()?,
             );
-            draw_line(
-                prev_i.x,
-                prev_i.y,
-                cur_i.x,
-                cur_i.y,
-                2.0,
-                Color::from_rgba(255, 90, 90, 255),
+        }
+        Some(Self {
+            tick,
+            tones,
+            sonifier: Sonifier::default(),
+            logged: sim.infection_log().len(),
+            playing: None,
+        })
+    }
+
+    fn update(&mut self, sim: &Simulation, dt: f32) {
+        let logged = sim.infection_log().len();
+        // A shorter log means a new run; its seeded cases are not news.
+        let new = logged.saturating_sub(self.logged);
+        self.logged = logged;
+        let counts = sim.counts();
+        let total = counts.total();
+        let prevalence = counts.infected as f32 / total.max(1) as f32;
+        let cue = self.sonifier.update(new, prevalence, dt);
+        if let Some(volume) = cue.tick {
+            play_sound(
+                &self.tick,
+                PlaySoundParams {
+                    looped: false,
+                    volume,
+                },
             );
-            draw_line(
-                prev_r.x,
-                prev_r.y,
-                cur_r.x,
-                cur_r.y,
-                2.0,
-                Color::from_rgba(120, 220, 140, 255),
+        }
+        let (step, volume) = cue.tone;
+        if self.playing != Some(step) {
+            self.silence();
+            play_sound(
+                &self.tones[step],
+                PlaySoundParams {
+                    looped: true,
+                    volume,
+                },
             );
-            prev_s = cur_s;
-            prev_i = cur_i;
-            prev_r = cur_r;
+            self.playing = Some(step);
+        } else {
+            set_sound_volume(&self.tones[step], volume);
+        }
+    }
+
+    fn silence(&mut self) {
+        if let Some(step) = self.playing.take() {
+            stop_sound(&self.tones[step]);
         }
     }
+}
+
+fn spawn_ensemble(cfg: SimConfig, boids: usize, seed: u64) -> Ensemble {
+    Ensemble::spawn(
+        cfg,
+        boids,
+        ENSEMBLE_REPLICAS,
+        seed,
+        ENSEMBLE_STEPS,
+        ENSEMBLE_DT,
+    )
+}
+
+fn draw_summary(summary: &EpidemicSummary, origin: Vec2f) {
+    let size = Vec2f::new(316.0, 150.0);
+    draw_rectangle(
+        origin.x,
+        origin.y,
+        size.x,
+        size.y,
+        Color::from_rgba(10, 12, 18, 200),
+    );
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        size.x,
+        size.y,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let text = Color::from_rgba(220, 240, 255, 255);
+    draw_text("Epidemic over", origin.x + 8.0, origin.y + 20.0, 18.0, text);
+    for (i, line) in summary.to_string().lines().enumerate() {
+        draw_text(
+            line,
+            origin.x + 8.0,
+            origin.y + 40.0 + i as f32 * 16.0,
+            14.0,
+            Color::from_rgba(90, 110, 135, 255),
+        );
+    }
+}
+
+/// Boids listed in the risk panel, its width and its row height.
+const RISK_ROWS: usize = 10;
+const RISK_PANEL_WIDTH: f32 = 220.0;
+const RISK_ROW_HEIGHT: f32 = 18.0;
+
+/// The risk panel row under the mouse, if any; the panel's top left is at
+/// `origin`.
+fn risk_row_at(origin: Vec2f) -> Option<usize> {
+    let (mx, my) = mouse_position();
+    let top = origin.y + 28.0;
+    if mx < origin.x || mx > origin.x + RISK_PANEL_WIDTH || my < top {
+        return None;
+    }
+    let row = ((my - top) / RISK_ROW_HEIGHT) as usize;
+    (row < RISK_ROWS).then_some(row)
+}
+
+/// The highest-risk susceptible boids, the followed one highlighted; click
+/// a row to follow that boid.
+fn draw_risk_panel(ranked: &[(usize, f32)], selected: Option<usize>, origin: Vec2f) {
+    let height = 36.0 + RISK_ROWS as f32 * RISK_ROW_HEIGHT;
+    draw_rectangle(
+        origin.x,
+        origin.y,
+        RISK_PANEL_WIDTH,
+        height,
+        Color::from_rgba(10, 12, 18, 200),
+    );
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        RISK_PANEL_WIDTH,
+        height,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    draw_text(
+        &format!("[A] exposure risk, last {RISK_WINDOW:.0}s"),
+        origin.x + 8.0,
+        origin.y + 20.0,
+        16.0,
+        Color::from_rgba(220, 240, 255, 255),
+    );
+    if ranked.is_empty() {
+        draw_text(
+            "no susceptible boid exposed yet",
+            origin.x + 8.0,
+            origin.y + 44.0,
+            14.0,
+            Color::from_rgba(90, 110, 135, 255),
+        );
+    }
+    let hovered = risk_row_at(origin);
+    for (row, &(idx, score)) in ranked.iter().enumerate() {
+        let y = origin.y + 28.0 + row as f32 * RISK_ROW_HEIGHT;
+        if selected == Some(idx) || hovered == Some(row) {
+            draw_rectangle(
+                origin.x + 1.0,
+                y,
+                RISK_PANEL_WIDTH - 2.0,
+                RISK_ROW_HEIGHT,
+                Color::from_rgba(255, 170, 60, 50),
+            );
+        }
+        draw_text(
+            &format!("{:>2}. boid {idx:<6} {score:>6.1} s", row + 1),
+            origin.x + 8.0,
+            y + 13.0,
+            14.0,
+            Color::from_rgba(255, 200, 140, 255),
+        );
+    }
+}
+
+/// Top left of the world menu, below the run panel, its width and its row
+/// height.
+const WORLD_MENU_X: f32 = 16.0;
+const WORLD_MENU_Y: f32 = 290.0;
+const WORLD_MENU_WIDTH: f32 = 340.0;
+const WORLD_ROW_HEIGHT: f32 = 20.0;
+
+/// The world menu row under the mouse: 0 for the current preset, `k + 1`
+/// for `WorldPreset::ALL[k]` while the menu is open.
+fn world_row_at(open: bool) -> Option<usize> {
+    let (mx, my) = mouse_position();
+    if !(WORLD_MENU_X..=WORLD_MENU_X + WORLD_MENU_WIDTH).contains(&mx) || my < WORLD_MENU_Y {
+        return None;
+    }
+    let row = ((my - WORLD_MENU_Y) / WORLD_ROW_HEIGHT) as usize;
+    let rows = if open { WorldPreset::ALL.len() + 1 } else { 1 };
+    (row < rows).then_some(row)
+}
+
+/// Dropdown of the world presets: the current one, and every preset below
+/// it while open. Picking one restarts the run in that world.
+fn draw_world_menu(current: WorldPreset, open: bool) {
+    let rows = if open { WorldPreset::ALL.len() + 1 } else { 1 };
+    let height = rows as f32 * WORLD_ROW_HEIGHT;
+    draw_rectangle(
+        WORLD_MENU_X,
+        WORLD_MENU_Y,
+        WORLD_MENU_WIDTH,
+        height,
+        Color::from_rgba(10, 12, 18, 200),
+    );
+    draw_rectangle_lines(
+        WORLD_MENU_X,
+        WORLD_MENU_Y,
+        WORLD_MENU_WIDTH,
+        height,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let hovered = world_row_at(open);
+    let text = Color::from_rgba(220, 240, 255, 255);
+    draw_text(
+        &format!(
+            "world: {}  {}",
+            current.name(),
+            if open { "^" } else { "v" }
+        ),
+        WORLD_MENU_X + 12.0,
+        WORLD_MENU_Y + 14.0,
+        16.0,
+        text,
+    );
+    if !open {
+        return;
+    }
+    for (k, preset) in WorldPreset::ALL.into_iter().enumerate() {
+        let y = WORLD_MENU_Y + (k + 1) as f32 * WORLD_ROW_HEIGHT;
+        if preset == current || hovered == Some(k + 1) {
+            draw_rectangle(
+                WORLD_MENU_X + 1.0,
+                y,
+                WORLD_MENU_WIDTH - 2.0,
+                WORLD_ROW_HEIGHT,
+                Color::from_rgba(90, 140, 200, 50),
+            );
+        }
+        draw_text(preset.name(), WORLD_MENU_X + 24.0, y + 14.0, 16.0, text);
+    }
+}
+
+/// Random configurations the explorer tries, and how many it shortlists.
+const EXPLORE_CANDIDATES: usize = 40;
+const EXPLORE_SHORTLIST: usize = 5;
+const SHORTLIST_KEYS: [KeyCode; EXPLORE_SHORTLIST] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+];
+
+fn draw_explorer(explorer: &Explorer) {
+    let rows = explorer.shortlist().len().max(1) as f32;
+    let height = 48.0 + rows * 18.0;
+    draw_rectangle(
+        16.0,
+        290.0,
+        340.0,
+        height,
+        Color::from_rgba(10, 12, 18, 180),
+    );
+    draw_rectangle_lines(
+        16.0,
+        290.0,
+        340.0,
+        height,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let text = Color::from_rgba(220, 240, 255, 255);
+    let dim = Color::from_rgba(150, 170, 190, 255);
+    draw_text(
+        &format!(
+            "[P] explorer: {}/{} tried",
+            explorer.completed(),
+            explorer.total()
+        ),
+        28.0,
+        310.0,
+        18.0,
+        text,
+    );
+    if explorer.shortlist().is_empty() {
+        draw_text("simulating...", 28.0, 330.0, 14.0, dim);
+    }
+    for (k, c) in explorer.shortlist().iter().enumerate() {
+        let cfg = &c.config;
+        draw_text(
+            &format!(
+                "[{}] beta {:.2}  r {:.0}  T {:.1}: {:.0}% major, {:.0}% infected",
+                k + 1,
+                cfg.infection_beta,
+                cfg.infection_radius,
+                cfg.infectious_period,
+                c.stats.outbreak_probability * 100.0,
+                c.final_size * 100.0
+            ),
+            28.0,
+            330.0 + k as f32 * 18.0,
+            14.0,
+            text,
+        );
+    }
+    draw_text(
+        "most interesting first; press a number to load",
+        28.0,
+        290.0 + height - 10.0,
+        14.0,
+        dim,
+    );
+}
+
+/// Seconds of recent frames kept while capturing, their rate, and the width
+/// they are downscaled to.
+const CAPTURE_SECONDS: f32 = 10.0;
+const CAPTURE_FPS: f32 = 15.0;
+const CAPTURE_WIDTH: usize = 320;
+
+/// Number keys for the current challenge's tools, in order.
+const TOOL_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+
+/// Challenge brief, objective progress and tools, drawn in place of the knobs.
+fn draw_challenge_panel(challenge: &Challenge, attempt: &Attempt) {
+    let text = Color::from_rgba(220, 240, 255, 255);
+    let dim = Color::from_rgba(90, 110, 135, 255);
+    draw_text(challenge.name, 28.0, 42.0, 20.0, text);
+    draw_text(challenge.brief, 28.0, 62.0, 14.0, dim);
+    draw_text(
+        &format!("Goal: {}", challenge.objective),
+        28.0,
+        84.0,
+        14.0,
+        text,
+    );
+    draw_text(
+        &format!(
+            "t={:.0}s   infected so far {:.1}%   peak {:.1}%",
+            attempt.time(),
+            attempt.attack_rate() * 100.0,
+            attempt.peak_prevalence() * 100.0
+        ),
+        28.0,
+        102.0,
+        14.0,
+        dim,
+    );
+    for (k, tool) in attempt.tools().iter().enumerate() {
+        let left = attempt.uses_left(k);
+        let color = if left > 0 { text } else { dim };
+        draw_text(
+            &format!("[{}] {} ({} left)", k + 1, tool.name, left),
+            28.0,
+            128.0 + k as f32 * 18.0,
+            16.0,
+            color,
+        );
+    }
+    draw_text(
+        "G: next challenge   Enter: retry   L: free play",
+        28.0,
+        214.0,
+        14.0,
+        dim,
+    );
+}
+
+/// Stage progress in place of the knobs, and the current caption in a bar
+/// along the bottom of the screen.
+fn draw_tutorial(tutorial: &Tutorial) {
+    let text = Color::from_rgba(220, 240, 255, 255);
+    let dim = Color::from_rgba(90, 110, 135, 255);
+    let (stage, stages, title) = tutorial.progress();
+    draw_text("Tutorial", 28.0, 42.0, 20.0, text);
+    draw_text(
+        &format!("Stage {stage}/{stages}: {title}"),
+        28.0,
+        66.0,
+        16.0,
+        text,
+    );
+    let hint = if tutorial.finished() {
+        "Done! T: back to free play"
+    } else {
+        "Space: next stage   Enter: start over   T: exit"
+    };
+    draw_text(hint, 28.0, 214.0, 14.0, dim);
 
-    fn point(
-        &self,
-        idx: usize,
-        origin: Vec2f,
-        size: Vec2f,
-        total: f32,
-        f: impl Fn(&SirCounts) -> f32,
-    ) -> Vec2f {
-        let t = idx as f32 / (self.max_len.saturating_sub(1).max(1) as f32);
-        let x = origin.x + t * size.x;
-        let v = f(&self.history[idx]) / total;
-        let y = origin.y + size.y - v * size.y;
-        Vec2f::new(x, y)
+    let caption = tutorial.caption();
+    let dims = measure_text(caption, None, 20, 1.0);
+    let width = dims.width + 32.0;
+    let origin = Vec2f::new((screen_width() - width) / 2.0, screen_height() - 72.0);
+    draw_rectangle(
+        origin.x,
+        origin.y,
+        width,
+        40.0,
+        Color::from_rgba(10, 12, 18, 220),
+    );
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        width,
+        40.0,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    draw_text(caption, origin.x + 16.0, origin.y + 26.0, 20.0, text);
+}
+
+fn draw_challenge_result(result: &ChallengeResult) {
+    let size = Vec2f::new(440.0, 150.0);
+    let origin = Vec2f::new(
+        (screen_width() - size.x) / 2.0,
+        (screen_height() - size.y) / 2.0,
+    );
+    draw_rectangle(
+        origin.x,
+        origin.y,
+        size.x,
+        size.y,
+        Color::from_rgba(10, 12, 18, 230),
+    );
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        size.x,
+        size.y,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let (title, color) = if result.won {
+        ("Challenge complete", Color::from_rgba(120, 220, 140, 255))
+    } else {
+        ("Challenge failed", Color::from_rgba(255, 90, 90, 255))
+    };
+    draw_text(title, origin.x + 12.0, origin.y + 26.0, 22.0, color);
+    for (i, line) in result.to_string().lines().enumerate() {
+        draw_text(
+            line,
+            origin.x + 12.0,
+            origin.y + 50.0 + i as f32 * 16.0,
+            14.0,
+            Color::from_rgba(220, 240, 255, 255),
+        );
+    }
+    draw_text(
+        "Enter: retry   G: next challenge",
+        origin.x + 12.0,
+        origin.y + size.y - 10.0,
+        14.0,
+        Color::from_rgba(90, 110, 135, 255),
+    );
+}
+
+/// World sizes cycled with `L`. `None` keeps the world matched to the window;
+/// larger worlds are viewed through a camera that follows the flock.
+const WORLD_PRESETS: [Option<(f32, f32)>; 3] =
+    [None, Some((4000.0, 4000.0)), Some((10000.0, 10000.0))];
+const MINIMAP_SIZE: f32 = 180.0;
+const MINIMAP_BINS: usize = 48;
+
+/// Zoom at or above which every visible boid passing the filter is labeled.
+const LABEL_ZOOM: f32 = 3.0;
+/// A filter matching at most this many visible boids labels them at any zoom.
+const LABEL_LIMIT: usize = 100;
+
+/// Which boids are drawn at full strength; the rest are dimmed. Cycled with
+/// `V`.
+#[derive(Clone, Copy, PartialEq)]
+enum Filter {
+    All,
+    State(HealthState),
+    /// The selected boid's flock.
+    Flock,
+    /// Boids in the selected boid's transmission chain.
+    Chain,
+}
+
+impl Filter {
+    const CYCLE: [Filter; 7] = [
+        Filter::All,
+        Filter::State(HealthState::Susceptible),
+        Filter::State(HealthState::Exposed),
+        Filter::State(HealthState::Infected),
+        Filter::State(HealthState::Recovered),
+        Filter::Flock,
+        Filter::Chain,
+    ];
+
+    fn next(self) -> Filter {
+        let k = Self::CYCLE.iter().position(|&f| f == self).unwrap_or(0);
+        Self::CYCLE[(k + 1) % Self::CYCLE.len()]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Filter::All => "all",
+            Filter::State(HealthState::Susceptible) => "susceptible",
+            Filter::State(HealthState::Exposed) => "exposed",
+            Filter::State(HealthState::Infected) => "infected",
+            Filter::State(HealthState::Recovered) => "recovered",
+            Filter::Flock => "selected flock",
+            Filter::Chain => "selected chain",
+        }
+    }
+
+    /// Per-boid match. Flock and chain filters match everything until a boid
+    /// is selected.
+    fn matches(self, sim: &Simulation, selected: Option<usize>) -> Vec<bool> {
+        let n = sim.boids.len();
+        match (self, selected) {
+            (Filter::State(state), _) => sim.boids.iter().map(|b| b.state == state).collect(),
+            (Filter::Flock, Some(idx)) => {
+                let flocks = sim.flocks();
+                flocks.iter().map(|&f| f == flocks[idx]).collect()
+            }
+            (Filter::Chain, Some(idx)) => {
+                let mut in_chain = vec![false; n];
+                in_chain[idx] = true;
+                if let Some(event) = sim.boids[idx].infection {
+                    let log = sim.infection_log();
+                    let down = descendants(log, event).into_iter().map(|(_, c)| c);
+                    for e in ancestry(log, event).into_iter().chain(down) {
+                        if let Some(m) = in_chain.get_mut(log[e].boid) {
+                            *m = true;
+                        }
+                    }
+                }
+                in_chain
+            }
+            _ => vec![true; n],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Follow {
+    Centroid,
+    Boid(usize),
+}
+
+/// Flock centroid on the wrapping world: a circular mean per axis, so a flock
+/// straddling an edge is not averaged into the middle of the world.
+fn torus_centroid(boids: &[Boid], world: Vec2f) -> Vec2f {
+    let circular_mean = |values: &mut dyn Iterator<Item = f32>, extent: f32| {
+        let (s, c) = values.fold((0.0f32, 0.0f32), |(s, c), v| {
+            let a = v / extent * 2.0 * PI;
+            (s + a.sin(), c + a.cos())
+        });
+        (s.atan2(c) / (2.0 * PI)).rem_euclid(1.0) * extent
+    };
+    Vec2f::new(
+        circular_mean(&mut boids.iter().map(|b| b.pos.x), world.x),
+        circular_mean(&mut boids.iter().map(|b| b.pos.y), world.y),
+    )
+}
+
+const LAYER_ROW: f32 = 18.0;
+
+/// Layer list in the top right corner, back to front: click a name to show
+/// or hide it, the arrows to move it back or forward within its space.
+fn layers_panel(layers: &mut Layers) {
+    let rows = Layer::ALL.len() as f32;
+    let (w, h) = (190.0, 28.0 + rows * LAYER_ROW);
+    let origin = Vec2f::new(screen_width() - w - 16.0, 16.0);
+    draw_rectangle(origin.x, origin.y, w, h, Color::from_rgba(10, 12, 18, 200));
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        w,
+        h,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let text = Color::from_rgba(220, 240, 255, 255);
+    let dim = Color::from_rgba(100, 115, 135, 255);
+    draw_text(
+        "[K] layers, back to front",
+        origin.x + 8.0,
+        origin.y + 16.0,
+        14.0,
+        dim,
+    );
+
+    let (mx, my) = mouse_position();
+    let clicked = is_mouse_button_pressed(MouseButton::Left);
+    let mut action = None;
+    for (k, (layer, on)) in layers.all().enumerate() {
+        let y = origin.y + 24.0 + k as f32 * LAYER_ROW;
+        let space = if layer.in_world() { "world" } else { "screen" };
+        draw_text(
+            &format!(
+                "{} {} ({space})",
+                if on { "[x]" } else { "[ ]" },
+                layer.name()
+            ),
+            origin.x + 8.0,
+            y + 13.0,
+            14.0,
+            if on { text } else { dim },
+        );
+        draw_text("v  ^", origin.x + w - 40.0, y + 13.0, 16.0, text);
+        if clicked && my >= y && my < y + LAYER_ROW {
+            let x = mx - origin.x;
+            action = match x {
+                x if (0.0..w - 44.0).contains(&x) => Some((layer, 0)),
+                x if (w - 44.0..w - 26.0).contains(&x) => Some((layer, -1)),
+                x if (w - 26.0..w).contains(&x) => Some((layer, 1)),
+                _ => None,
+            };
+        }
+    }
+    match action {
+        Some((layer, 0)) => layers.toggle(layer),
+        Some((layer, -1)) => layers.lower(layer),
+        Some((layer, _)) => layers.raise(layer),
+        None => {}
+    }
+}
+
+const POLICY_ROW: f32 = 20.0;
+const MAX_SIGMA: f32 = 0.5;
+
+/// Live edits to the steering policy: Gaussian noise on the running policy,
+/// and a blend between two stored policies. Slots outlive restarts; the
+/// running policy does not, since every run draws its own from the seed.
+struct PolicyLab {
+    sigma: f32,
+    slots: [Option<Policy>; 2],
+    /// Fraction of the way from slot A to slot B last applied.
+    blend: f32,
+    dragging: Option<usize>,
+    rng: Rng,
+}
+
+impl PolicyLab {
+    /// A lab with the first two of `loaded` in its slots.
+    fn new(seed: u64, loaded: &[Policy]) -> Self {
+        Self {
+            sigma: 0.1,
+            slots: [loaded.first().cloned(), loaded.get(1).cloned()],
+            blend: 0.0,
+            dragging: None,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Put the first policy file dropped on the window into the first empty
+    /// slot, or slot B if both are full.
+    fn load_dropped(&mut self) -> Option<String> {
+        let file = get_dropped_files().into_iter().next()?;
+        let name = file
+            .path
+            .as_ref()
+            .map_or("dropped file".to_string(), |p| p.display().to_string());
+        let text = match (&file.bytes, &file.path) {
+            (Some(bytes), _) => String::from_utf8_lossy(bytes).into_owned(),
+            (None, Some(path)) => match std::fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(err) => return Some(format!("failed to read {name}: {err}")),
+            },
+            (None, None) => return None,
+        };
+        Some(match Policy::parse(&text) {
+            Ok(policy) => {
+                let slot = self.slots.iter().position(Option::is_none).unwrap_or(1);
+                self.slots[slot] = Some(policy);
+                format!("loaded {name} into slot {}", SLOT_NAMES[slot])
+            }
+            Err(err) => format!("{name}: {err}"),
+        })
+    }
+}
+
+const SLOT_NAMES: [&str; 2] = ["A", "B"];
+
+/// Horizontal slider over `0..1` at `y`; returns where the mouse holds it.
+fn policy_slider(origin: Vec2f, w: f32, y: f32, t: f32, held: bool) -> Option<f32> {
+    let (x0, x1) = (origin.x + 70.0, origin.x + w - 12.0);
+    let track = Color::from_rgba(90, 110, 135, 255);
+    draw_line(x0, y + 10.0, x1, y + 10.0, 2.0, track);
+    let knob = x0 + t.clamp(0.0, 1.0) * (x1 - x0);
+    draw_circle(knob, y + 10.0, 5.0, Color::from_rgba(220, 240, 255, 255));
+    held.then(|| ((mouse_position().0 - x0) / (x1 - x0)).clamp(0.0, 1.0))
+}
+
+/// Policy lab below the layer list: noise strength and a button to apply
+/// it, slots A and B with buttons to store the running policy or a fresh
+/// random one, a slider blending A into B, and a button to save the running
+/// policy. Returns a message for the notice line.
+fn policy_panel(lab: &mut PolicyLab, sim: &mut Simulation, top: f32) -> Option<String> {
+    let (w, h) = (260.0, 32.0 + 6.0 * POLICY_ROW);
+    let origin = Vec2f::new(screen_width() - w - 16.0, top);
+    draw_rectangle(origin.x, origin.y, w, h, Color::from_rgba(10, 12, 18, 200));
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        w,
+        h,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let text = Color::from_rgba(220, 240, 255, 255);
+    let dim = Color::from_rgba(100, 115, 135, 255);
+    draw_text(
+        "[J] policy lab, drop a file to load",
+        origin.x + 8.0,
+        origin.y + 16.0,
+        14.0,
+        dim,
+    );
+
+    let (mx, my) = mouse_position();
+    let clicked = is_mouse_button_pressed(MouseButton::Left);
+    if !is_mouse_button_down(MouseButton::Left) {
+        lab.dragging = None;
+    }
+    let row_y = |k: usize| origin.y + 24.0 + k as f32 * POLICY_ROW;
+    let row = (my - origin.y - 24.0) / POLICY_ROW;
+    let hit_row = (mx >= origin.x && mx < origin.x + w && row >= 0.0).then_some(row as usize);
+    if clicked && mx >= origin.x + 60.0 && matches!(hit_row, Some(0) | Some(4)) {
+        lab.dragging = hit_row;
+    }
+    let button = |label: &str, x: f32, k: usize| {
+        draw_text(label, origin.x + x, row_y(k) + 14.0, 14.0, text);
+        let width = measure_text(label, None, 14, 1.0).width;
+        clicked && hit_row == Some(k) && (origin.x + x..origin.x + x + width).contains(&mx)
+    };
+    let mut message = None;
+
+    draw_text(
+        &format!("sigma {:.2}", lab.sigma),
+        origin.x + 8.0,
+        row_y(0) + 14.0,
+        14.0,
+        text,
+    );
+    if let Some(t) = policy_slider(
+        origin,
+        w,
+        row_y(0),
+        lab.sigma / MAX_SIGMA,
+        lab.dragging == Some(0),
+    ) {
+        lab.sigma = t * MAX_SIGMA;
+    }
+    if button("[add noise]", 8.0, 1) {
+        let mut policy = sim.policy().clone();
+        policy.perturb(&mut lab.rng, lab.sigma);
+        message = sim.set_policy(policy).err();
+    }
+    if button("[save running]", 110.0, 1) {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = format!("policy-{stamp}.txt");
+        message = Some(match std::fs::write(&path, sim.policy().to_text()) {
+            Ok(()) => format!("saved {path}"),
+            Err(err) => format!("failed to write {path}: {err}"),
+        });
+    }
+
+    for (slot, name) in SLOT_NAMES.iter().enumerate() {
+        let k = 2 + slot;
+        let status = match &lab.slots[slot] {
+            None => "empty".to_string(),
+            Some(policy) => match policy.distance(sim.policy()) {
+                Some(d) => format!("rms {d:.2} off"),
+                None => format!("{} inputs", policy.input_size()),
+            },
+        };
+        draw_text(
+            &format!("{name} {status}"),
+            origin.x + 8.0,
+            row_y(k) + 14.0,
+            14.0,
+            if lab.slots[slot].is_some() { text } else { dim },
+        );
+        if button("[store]", 130.0, k) {
+            lab.slots[slot] = Some(sim.policy().clone());
+        }
+        if button("[random]", 190.0, k) {
+            let current = sim.policy();
+            let mut policy = Policy::new(current.input_size(), current.hidden_size());
+            policy.randomize(&mut lab.rng, INIT_SCALE);
+            lab.slots[slot] = Some(policy);
+        }
+    }
+
+    draw_text(
+        &format!("A->B {:.2}", lab.blend),
+        origin.x + 8.0,
+        row_y(4) + 14.0,
+        14.0,
+        text,
+    );
+    let blend = policy_slider(origin, w, row_y(4), lab.blend, lab.dragging == Some(4));
+    if let (Some(t), [Some(a), Some(b)]) = (blend, &lab.slots) {
+        match Policy::lerp(a, b, t) {
+            Some(policy) => {
+                lab.blend = t;
+                message = sim.set_policy(policy).err();
+            }
+            None => message = Some("slots A and B have different layer sizes".to_string()),
+        }
+    } else if blend.is_some() {
+        message = Some("store policies in slots A and B to blend them".to_string());
+    }
+    if button("[revert to A]", 8.0, 5)
+        && let Some(a) = lab.slots[0].clone()
+    {
+        lab.blend = 0.0;
+        message = sim.set_policy(a).err();
+    }
+    message
+}
+
+/// Every boid inside `view` as a triangle along its heading, dimmed unless
+/// `shown`.
+fn draw_boids(sim: &Simulation, view: Rect, shown: &[bool]) {
+    for (i, boid) in sim.boids.iter().enumerate() {
+        if !view.contains(vec2(boid.pos.x, boid.pos.y)) {
+            continue;
+        }
+        let alpha = if shown[i] { 255 } else { 40 };
+        draw_boid(boid.pos, boid.vel, boid.state, alpha);
+    }
+}
+
+/// One boid as a triangle along its heading, coloured by health.
+fn draw_boid(pos: Vec2f, vel: Vec2f, state: HealthState, alpha: u8) {
+    let dir = vel.normalize_or_zero();
+    let dir = if dir.length() > 0.0 {
+        dir
+    } else {
+        Vec2f::new(1.0, 0.0)
+    };
+    let perp = Vec2f::new(-dir.y, dir.x);
+    let tip = pos.add(dir.mul(6.0));
+    let left = pos.sub(dir.mul(2.5)).add(perp.mul(3.0));
+    let right = pos.sub(dir.mul(2.5)).sub(perp.mul(3.0));
+
+    let color = match state {
+        HealthState::Susceptible => Color::from_rgba(220, 240, 255, alpha),
+        HealthState::Exposed => Color::from_rgba(255, 190, 90, alpha),
+        HealthState::Infected => Color::from_rgba(255, 90, 90, alpha),
+        HealthState::Recovered => Color::from_rgba(120, 220, 140, alpha),
+    };
+
+    draw_triangle(
+        Vec2::new(tip.x, tip.y),
+        Vec2::new(left.x, left.y),
+        Vec2::new(right.x, right.y),
+        color,
+    );
+}
+
+fn draw_quarantine_rings(sim: &Simulation, view: Rect) {
+    for boid in &sim.boids {
+        if boid.quarantined && view.contains(vec2(boid.pos.x, boid.pos.y)) {
+            draw_circle_lines(
+                boid.pos.x,
+                boid.pos.y,
+                5.0,
+                1.0,
+                Color::from_rgba(255, 200, 80, 200),
+            );
+        }
+    }
+}
+
+/// Index labels in screen space for the selected boid, and for every shown
+/// boid in view when `label_all`.
+fn draw_labels(
+    sim: &Simulation,
+    camera: &Camera2D,
+    view: Rect,
+    shown: &[bool],
+    selected: Option<usize>,
+    label_all: bool,
+) {
+    for (i, boid) in sim.boids.iter().enumerate() {
+        let visible = view.contains(vec2(boid.pos.x, boid.pos.y));
+        let wanted = selected == Some(i) || (label_all && shown[i] && visible);
+        if wanted {
+            let p = camera.world_to_screen(vec2(boid.pos.x, boid.pos.y));
+            draw_text(
+                &i.to_string(),
+                p.x + 6.0,
+                p.y - 6.0,
+                14.0,
+                Color::from_rgba(220, 240, 255, 220),
+            );
+        }
+    }
+}
+
+/// Dashed world-space lines along the internal zone borders of a travel
+/// restriction.
+fn draw_zone_borders(spec: &TravelRestriction, world: Vec2f) {
+    const DASH: f32 = 12.0;
+    let color = Color::from_rgba(255, 200, 80, 110);
+    let (nx, ny) = spec.zones;
+    for i in 1..nx {
+        let x = world.x * i as f32 / nx as f32;
+        let mut y = 0.0;
+        while y < world.y {
+            draw_line(x, y, x, (y + DASH).min(world.y), 1.5, color);
+            y += DASH * 2.0;
+        }
+    }
+    for j in 1..ny {
+        let y = world.y * j as f32 / ny as f32;
+        let mut x = 0.0;
+        while x < world.x {
+            draw_line(x, y, (x + DASH).min(world.x), y, 1.5, color);
+            x += DASH * 2.0;
+        }
+    }
+}
+
+/// Outline and faint fill of every quarantine zone.
+fn draw_quarantine_zones(zones: &[QuarantineZone]) {
+    for zone in zones {
+        let size = zone.max.sub(zone.min);
+        draw_rectangle(
+            zone.min.x,
+            zone.min.y,
+            size.x,
+            size.y,
+            Color::from_rgba(255, 90, 90, 25),
+        );
+        draw_rectangle_lines(
+            zone.min.x,
+            zone.min.y,
+            size.x,
+            size.y,
+            2.0,
+            Color::from_rgba(255, 90, 90, 180),
+        );
+    }
+}
+
+/// Every obstacle of the world preset, filled; an arena's outside is drawn
+/// as a wide band along its edge.
+fn draw_obstacles(obstacles: &[Obstacle]) {
+    let fill = Color::from_rgba(55, 62, 76, 255);
+    for obstacle in obstacles {
+        match *obstacle {
+            Obstacle::Rect { min, max } => {
+                draw_rectangle(min.x, min.y, max.x - min.x, max.y - min.y, fill)
+            }
+            Obstacle::Circle { center, radius } => draw_circle(center.x, center.y, radius, fill),
+            Obstacle::Outside { center, radius } => {
+                const BAND: f32 = 24.0;
+                draw_poly_lines(center.x, center.y, 96, radius + BAND / 2.0, 0.0, BAND, fill)
+            }
+        }
+    }
+}
+
+/// Crowd pressure shaded per cell, up to fully opaque at the
+/// over-compressed threshold, which is also outlined.
+fn draw_pressure(pressure: &CrowdPressure) {
+    let size = pressure.cell_size;
+    for row in 0..pressure.rows {
+        for col in 0..pressure.cols {
+            let p = pressure.at(col, row);
+            if p <= 0.0 {
+                continue;
+            }
+            let (x, y) = (col as f32 * size, row as f32 * size);
+            let alpha = (p / OVERCOMPRESSED).min(1.0) * 140.0;
+            draw_rectangle(
+                x,
+                y,
+                size,
+                size,
+                Color::from_rgba(200, 80, 220, alpha as u8),
+            );
+            if p >= OVERCOMPRESSED {
+                draw_rectangle_lines(x, y, size, size, 1.5, Color::from_rgba(255, 120, 255, 230));
+            }
+        }
+    }
+}
+
+/// The boundary between the home and work patches, with each patch named
+/// at its top.
+fn draw_patches(spec: &Commute, world: Vec2f) {
+    const DASH: f32 = 12.0;
+    let color = Color::from_rgba(160, 220, 140, 120);
+    let (_, split) = spec.bounds(Patch::Home, world);
+    let mut y = 0.0;
+    while y < world.y {
+        draw_line(split, y, split, (y + DASH).min(world.y), 1.5, color);
+        y += DASH * 2.0;
+    }
+    for patch in Patch::ALL {
+        let (left, _) = spec.bounds(patch, world);
+        draw_text(patch.name(), left + 8.0, 20.0, 18.0, color);
+    }
+}
+
+/// Upstream chain of boid `idx` back to its index case and everything it
+/// went on to infect, drawn at the positions where each infection happened.
+/// Returns the number of upstream and downstream infections. Links that
+/// cross a wrapping edge are skipped rather than drawn across the world.
+fn draw_transmission_chain(sim: &Simulation, idx: usize) -> (usize, usize) {
+    let Some(event) = sim.boids.get(idx).and_then(|b| b.infection) else {
+        return (0, 0);
+    };
+    let log = sim.infection_log();
+    let world = sim.config().world_size;
+    let link = |a: Vec2f, b: Vec2f, color: Color| {
+        let d = a.sub(b);
+        if d.x.abs() < world.x / 2.0 && d.y.abs() < world.y / 2.0 {
+            draw_line(a.x, a.y, b.x, b.y, 1.5, color);
+        }
+    };
+
+    let downstream = descendants(log, event);
+    let down_color = Color::from_rgba(255, 120, 200, 200);
+    for &(parent, child) in &downstream {
+        link(log[parent].pos, log[child].pos, down_color);
+        draw_circle(log[child].pos.x, log[child].pos.y, 2.0, down_color);
+    }
+
+    let upstream = ancestry(log, event);
+    let up_color = Color::from_rgba(255, 200, 80, 230);
+    link(sim.boids[idx].pos, log[event].pos, up_color);
+    for pair in upstream.windows(2) {
+        link(log[pair[0]].pos, log[pair[1]].pos, up_color);
+    }
+    for &e in &upstream {
+        draw_circle(log[e].pos.x, log[e].pos.y, 2.5, up_color);
+    }
+    if let Some(&index_case) = upstream.last() {
+        let pos = log[index_case].pos;
+        draw_circle_lines(pos.x, pos.y, 7.0, 1.5, up_color);
+    }
+    (upstream.len() - 1, downstream.len())
+}
+
+/// Whole-world overview in the bottom-right corner: infected boids binned into
+/// a coarse grid, plus the camera's view rectangle and the followed boid.
+fn draw_minimap(sim: &Simulation, view: Rect, follow: Follow) {
+    let world = sim.config().world_size;
+    let scale = MINIMAP_SIZE / world.x.max(world.y);
+    let (w, h) = (world.x * scale, world.y * scale);
+    let origin = Vec2f::new(screen_width() - w - 16.0, screen_height() - h - 16.0);
+    draw_rectangle(origin.x, origin.y, w, h, Color::from_rgba(10, 12, 18, 200));
+
+    let cell = world.x.max(world.y) / MINIMAP_BINS as f32;
+    let (nx, ny) = (
+        (world.x / cell).ceil() as usize,
+        (world.y / cell).ceil() as usize,
+    );
+    let mut bins = vec![0u32; nx * ny];
+    for boid in &sim.boids {
+        if boid.state == HealthState::Infected {
+            let bx = ((boid.pos.x / cell) as usize).min(nx - 1);
+            let by = ((boid.pos.y / cell) as usize).min(ny - 1);
+            bins[by * nx + bx] += 1;
+        }
+    }
+    let max = bins.iter().copied().max().unwrap_or(0).max(1) as f32;
+    for (k, &count) in bins.iter().enumerate() {
+        if count > 0 {
+            let [r, g, b] = colormap(0.3 + 0.7 * count as f32 / max);
+            draw_rectangle(
+                origin.x + (k % nx) as f32 * cell * scale,
+                origin.y + (k / nx) as f32 * cell * scale,
+                cell * scale,
+                cell * scale,
+                Color::from_rgba(r, g, b, 230),
+            );
+        }
+    }
+
+    draw_rectangle_lines(
+        origin.x + view.x * scale,
+        origin.y + view.y * scale,
+        view.w * scale,
+        view.h * scale,
+        1.0,
+        Color::from_rgba(220, 240, 255, 200),
+    );
+    if let Follow::Boid(idx) = follow
+        && let Some(boid) = sim.boids.get(idx)
+    {
+        draw_circle(
+            origin.x + boid.pos.x * scale,
+            origin.y + boid.pos.y * scale,
+            2.5,
+            Color::from_rgba(120, 200, 255, 255),
+        );
+    }
+    draw_rectangle_lines(
+        origin.x,
+        origin.y,
+        w,
+        h,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+}
+
+/// Seed for the next restart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NextSeed {
+    /// One past the current seed, so every restart is a fresh run.
+    Increment,
+    /// The current seed again.
+    Same,
+    Random,
+    Exact(u64),
+}
+
+impl NextSeed {
+    fn resolve(self, current: u64) -> u64 {
+        match self {
+            NextSeed::Increment => current.wrapping_add(1),
+            NextSeed::Same => current,
+            NextSeed::Random => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                now.as_secs().wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ u64::from(now.subsec_nanos())
+            }
+            NextSeed::Exact(seed) => seed,
+        }
+    }
+}
+
+/// Range of the simulation speed, stepped by doubling or halving.
+const MIN_TIME_SCALE: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 8.0;
+
+/// Seed, run timers and the seed keys, under the knob panel. `entry` is the
+/// seed being typed, if any.
+fn draw_run_panel(seed: u64, wall: f32, sim_time: f32, entry: Option<&str>) {
+    let text = Color::from_rgba(220, 240, 255, 255);
+    let dim = Color::from_rgba(150, 170, 190, 255);
+    draw_rectangle(16.0, 232.0, 340.0, 52.0, Color::from_rgba(10, 12, 18, 180));
+    draw_rectangle_lines(
+        16.0,
+        232.0,
+        340.0,
+        52.0,
+        1.0,
+        Color::from_rgba(40, 60, 80, 200),
+    );
+    let clock = |t: f32| format!("{}:{:04.1}", (t / 60.0) as u32, t % 60.0);
+    match entry {
+        Some(digits) => {
+            draw_text(&format!("seed: {digits}_"), 28.0, 254.0, 18.0, text);
+            draw_text("[Enter] run   [Esc] cancel", 28.0, 274.0, 14.0, dim);
+        }
+        None => {
+            draw_text(
+                &format!(
+                    "seed {seed}   run {}   sim {}",
+                    clock(wall),
+                    clock(sim_time)
+                ),
+                28.0,
+                254.0,
+                18.0,
+                text,
+            );
+            draw_text(
+                "[Enter] next  [Z] replay  [X] random  [S] type seed",
+                28.0,
+                274.0,
+                14.0,
+                dim,
+            );
+        }
+    }
+}
+
+/// Height of the replay scrub bar along the bottom of the window.
+const SCRUB_HEIGHT: f32 = 24.0;
+/// Seconds of recording `[Left]`/`[Right]` skip while playing.
+const REPLAY_SKIP: f32 = 5.0;
+
+/// Play `replay` back until the window closes. `[Space]` pauses, `[Left]`
+/// and `[Right]` skip (or step a frame while paused), `[-/+]` change speed,
+/// and clicking or dragging on the bar at the bottom seeks.
+async fn replay_view(mut replay: Replay, name: String) {
+    let world = replay.recording().world();
+    let frames = replay.recording().len();
+    let end = replay.recording().time(frames - 1);
+    let mut playhead = replay.time();
+    let mut paused = false;
+    let mut speed = 1.0f32;
+    loop {
+        let dt = get_frame_time().min(0.05);
+        let (sw, sh) = (screen_width(), screen_height());
+        if is_key_pressed(KeyCode::Space) {
+            paused = !paused;
+        }
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            speed = (speed * 2.0).min(MAX_TIME_SCALE);
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            speed = (speed / 2.0).max(MIN_TIME_SCALE);
+        }
+        let mut target = None;
+        if is_key_pressed(KeyCode::Right) {
+            target = Some(if paused {
+                (replay.index() + 1).min(frames - 1)
+            } else {
+                replay.recording().frame_at(playhead + REPLAY_SKIP)
+            });
+        }
+        if is_key_pressed(KeyCode::Left) {
+            target = Some(if paused {
+                replay.index().saturating_sub(1)
+            } else {
+                replay.recording().frame_at(playhead - REPLAY_SKIP)
+            });
+        }
+        let (mx, my) = mouse_position();
+        if is_mouse_button_down(MouseButton::Left) && my >= sh - SCRUB_HEIGHT {
+            let t = (mx / sw).clamp(0.0, 1.0) * end;
+            target = Some(replay.recording().frame_at(t));
+        }
+        match target {
+            Some(index) => {
+                replay.seek(index);
+                playhead = replay.time();
+            }
+            None if !paused && replay.index() + 1 < frames => {
+                playhead += dt * speed;
+                replay.seek(replay.recording().frame_at(playhead));
+            }
+            None => {}
+        }
+
+        clear_background(Color::from_rgba(8, 10, 14, 255));
+        // Fit the whole recorded world in the window, y pointing down.
+        set_camera(&Camera2D {
+            target: vec2(world.x / 2.0, world.y / 2.0),
+            zoom: vec2(2.0 / world.x, 2.0 / world.y),
+            ..Default::default()
+        });
+        let mut counts = [0; 4];
+        for boid in replay.boids() {
+            draw_boid(boid.pos, boid.vel, boid.state, 255);
+            counts[boid.state as usize] += 1;
+        }
+        set_default_camera();
+
+        draw_rectangle(
+            0.0,
+            sh - SCRUB_HEIGHT,
+            sw,
+            SCRUB_HEIGHT,
+            Color::from_rgba(10, 12, 18, 200),
+        );
+        let at = if end > 0.0 { replay.time() / end } else { 1.0 };
+        draw_rectangle(
+            0.0,
+            sh - SCRUB_HEIGHT,
+            sw * at,
+            SCRUB_HEIGHT,
+            Color::from_rgba(40, 60, 80, 200),
+        );
+        draw_line(
+            sw * at,
+            sh - SCRUB_HEIGHT,
+            sw * at,
+            sh,
+            2.0,
+            Color::from_rgba(120, 200, 255, 255),
+        );
+        let [s, e, i, r] = counts;
+        let lines = [
+            format!(
+                "replay {name}: t {:.1}s / {end:.1}s, frame {} / {frames}",
+                replay.time(),
+                replay.index() + 1
+            ),
+            format!("S {s}  E {e}  I {i}  R {r}"),
+            format!(
+                "[Space] {}  [Left/Right] {}  [-/+] speed {speed}x  click the bar to seek",
+                if paused { "play" } else { "pause" },
+                if paused { "step" } else { "skip 5s" }
+            ),
+        ];
+        for (k, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                16.0,
+                24.0 + k as f32 * 20.0,
+                16.0,
+                Color::from_rgba(220, 240, 255, 255),
+            );
+        }
+        next_frame().await;
+    }
+}
+
+/// Write `recorder` to a timestamped file, returning the notice to show.
+fn save_recording(recorder: &Recorder) -> String {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let path = format!("run-{stamp}.boidrec");
+    match recorder.save(std::path::Path::new(&path)) {
+        Ok(()) => format!(
+            "saved {path} ({} frames), play with --replay {path}",
+            recorder.frames()
+        ),
+        Err(err) => format!("failed to write {path}: {err}"),
+    }
+}
+
+/// Flush a per-tick CSV export, returning the notice to show.
+fn finish_tick_csv(export: TickExport, path: &str) -> String {
+    let rows = export.rows();
+    match export.finish() {
+        Ok(()) => format!("saved {path} ({rows} rows)"),
+        Err(err) => format!("failed to write {path}: {err}"),
+    }
+}
+
+/// Every value given for `flag` on the command line, in order.
+fn flag_values(flag: &str) -> Vec<std::path::PathBuf> {
+    let mut values = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag
+            && let Some(value) = args.next()
+        {
+            values.push(value.into());
+        }
+    }
+    values
+}
+
+/// `--config path`: a TOML or JSON file read at startup and watched for
+/// changes. At most one may be given.
+fn config_path() -> Result<Option<std::path::PathBuf>, String> {
+    let mut paths = flag_values("--config");
+    if paths.len() > 1 {
+        return Err("--config given more than once".into());
+    }
+    Ok(paths.pop())
+}
+
+/// Point the knobs at the live parameters in `cfg`, in knob order.
+fn set_knobs(knobs: &mut [Knob], cfg: &SimConfig) {
+    let values = [
+        cfg.neighbor_radius,
+        cfg.separation_radius,
+        cfg.max_speed,
+        cfg.max_force,
+        cfg.infection_radius,
+        cfg.infection_beta,
+        cfg.infectious_period,
+    ];
+    for (knob, value) in knobs.iter_mut().zip(values) {
+        knob.value = value;
     }
 }
 
 #[macroquad::main("Boids")]
 async fn main() {
-    let cfg = SimConfig {
-        world_size: Vec2f::new(screen_width(), screen_height()),
-        max_speed: 160.0,
-        max_force: 80.0,
-        neighbor_radius: 60.0,
-        separation_radius: 22.0,
-        infection_radius: 18.0,
-        infection_beta: 1.2,
-        infectious_period: 6.0,
-        initial_infected: 8,
+    // `--replay path`: play back a recording instead of running.
+    if let Some(path) = flag_values("--replay").into_iter().next() {
+        match Recording::load(&path).and_then(Replay::new) {
+            Ok(replay) => replay_view(replay, path.display().to_string()).await,
+            Err(err) => eprintln!("{err}"),
+        }
+        return;
+    }
+    let defaults = ConfigFile {
+        boids: 2400,
+        config: SimConfig {
+            world_size: Vec2f::new(screen_width(), screen_height()),
+            ..SimConfig::default()
+        },
+        smoothing: 0.4,
+        // Leaves room for drawing within a 30 FPS frame.
+        step_budget: 0.02,
+        sample_interval: DEFAULT_SAMPLE_INTERVAL,
+    };
+    let mut watcher = match config_path() {
+        Ok(path) => path.map(ConfigWatcher::new),
+        Err(err) => {
+            eprintln!("{err}");
+            return;
+        }
+    };
+    let mut file = match &watcher {
+        Some(watcher) => ConfigFile::load(watcher.path(), defaults).unwrap_or_else(|err| {
+            eprintln!("{err}");
+            defaults
+        }),
+        None => defaults,
     };
-    let mut seed = 1337u32;
-    let mut sim = Simulation::new(2400, cfg, seed);
+    let mut cfg = file.config;
+    let mut seed = 1337u64;
+    let mut sim = Simulation::new(file.boids, cfg, seed);
+    // `--policy path`, repeatable: trained policies from `headless --train`.
+    // The first steers every run; the first two fill the policy lab slots.
+    let loaded = sim
+        .load_policies(&flag_values("--policy"))
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            Vec::new()
+        });
     let mut knobs = vec![
         Knob::new("N Radius", 60.0, 20.0, 140.0, Vec2f::new(70.0, 70.0), 28.0),
         Knob::new("S Radius", 22.0, 5.0, 80.0, Vec2f::new(150.0, 70.0), 28.0),
@@ -202,73 +1861,792 @@ async fn main() {
         Knob::new("Beta", 1.2, 0.0, 5.0, Vec2f::new(150.0, 160.0), 28.0),
         Knob::new("Inf T", 6.0, 1.0, 20.0, Vec2f::new(230.0, 160.0), 28.0),
     ];
+    set_knobs(&mut knobs, &cfg);
+    // What the simulation currently runs with; eases toward the knobs.
+    let mut applied: Vec<f32> = knobs.iter().map(|k| k.value).collect();
 
-    let mut graph = SirGraph::new(360);
+    let mut graph = SirGraph::new(false, file.sample_interval);
+    // Earlier runs' curves, newest first, kept on restart while comparing.
+    let mut ghosts: VecDeque<GhostRun> = VecDeque::new();
+    let mut keep_runs = false;
+    let mut tracker = EpidemicTracker::new(&sim);
+    tracker.set_sample_interval(file.sample_interval);
+    let mut ensemble: Option<Ensemble> = None;
+    let mut explorer: Option<Explorer> = None;
+    let mut contact_rate = ContactRate::new(&sim);
+    let mut show_forecast = false;
+    let mut risk: Option<RiskScores> = None;
+    let mut audio: Option<Audio> = None;
+    let mut sound_on = false;
+    let mut preset = 0;
+    let mut world_menu = false;
+    let mut follow = Follow::Centroid;
+    let mut schedule: Option<ActivitySchedule> = cfg.schedule;
+    let mut commute: Option<Commute> = cfg.commute;
+    let mut disease_model = cfg.disease_model;
+    let all_challenges = challenges();
+    let mut active: Option<usize> = None;
+    let mut attempt: Option<Attempt> = None;
+    let mut touring = false;
+    let mut tutorial: Option<Tutorial> = None;
+    let mut capture: Option<FrameRing> = None;
+    let mut capture_clock = 0.0;
+    let mut recorder: Option<Recorder> = None;
+    let mut tick_csv: Option<(TickExport, String)> = None;
+    let mut notice: Option<(String, f32)> = None;
+    let mut area_tool: Option<AreaAction> = None;
+    let mut drag_start: Option<Vec2f> = None;
+    let mut layers = Layers::default();
+    let mut show_layers = false;
+    let mut policy_lab: Option<PolicyLab> = None;
+    let mut filter = Filter::All;
+    let mut zoom = 1.0f32;
+    let mut budget = FrameBudget::new(file.step_budget);
+    let mut next_seed = NextSeed::Increment;
+    let mut seed_entry: Option<String> = None;
+    let mut run_clock = 0.0f32;
+    let mut paused = false;
+    let mut time_scale = 1.0f32;
 
     loop {
         let dt = get_frame_time().min(0.05);
-        sim.set_world_size(Vec2f::new(screen_width(), screen_height()));
-
-        for knob in &mut knobs {
-            knob.update();
-        }
-
-        let neighbor_radius = knobs[0].value;
-        let separation_radius = knobs[1].value.min(neighbor_radius);
-        let max_speed = knobs[2].value;
-        let max_force = knobs[3].value;
-        let infection_radius = knobs[4].value;
-        let infection_beta = knobs[5].value;
-        let infectious_period = knobs[6].value;
-
-        if is_key_pressed(KeyCode::Enter) {
-            let cfg = SimConfig {
-                world_size: Vec2f::new(screen_width(), screen_height()),
-                max_speed,
-                max_force,
-                neighbor_radius,
-                separation_radius,
-                infection_radius,
-                infection_beta,
-                infectious_period,
-                initial_infected: 8,
+        run_clock += get_frame_time();
+        let mut restart = is_key_pressed(KeyCode::Enter);
+        // Typing a seed takes over the keyboard until Enter or Escape.
+        let typing = seed_entry.is_some();
+        if let Some(entry) = &mut seed_entry {
+            while let Some(c) = get_char_pressed() {
+                if c.is_ascii_digit() && entry.len() < 20 {
+                    entry.push(c);
+                }
+            }
+            if is_key_pressed(KeyCode::Backspace) {
+                entry.pop();
+            }
+            if is_key_pressed(KeyCode::Escape) {
+                seed_entry = None;
+            } else if restart {
+                match entry.parse::<u64>() {
+                    Ok(typed) => next_seed = NextSeed::Exact(typed),
+                    Err(_) if entry.is_empty() => next_seed = NextSeed::Same,
+                    Err(_) => {
+                        notice = Some((format!("seed must be at most {}", u64::MAX), 3.0));
+                        restart = false;
+                    }
+                }
+                if restart {
+                    seed_entry = None;
+                }
+            }
+        } else {
+            if is_key_pressed(KeyCode::S) {
+                // Drop keys typed before the entry opened.
+                while get_char_pressed().is_some() {}
+                seed_entry = Some(String::new());
+            }
+            if is_key_pressed(KeyCode::Z) {
+                next_seed = NextSeed::Same;
+                restart = true;
+            }
+            if is_key_pressed(KeyCode::X) {
+                next_seed = NextSeed::Random;
+                restart = true;
+            }
+        }
+        if !typing && is_key_pressed(KeyCode::L) {
+            // Leaving a challenge returns to free play in the current world.
+            if active.take().is_none() && !touring {
+                preset = (preset + 1) % WORLD_PRESETS.len();
+            }
+            touring = false;
+            follow = Follow::Centroid;
+            restart = true;
+        }
+        // The world menu only applies to free play.
+        let world_row = world_row_at(world_menu).filter(|_| active.is_none() && !touring);
+        if !typing && is_mouse_button_pressed(MouseButton::Left) {
+            match world_row {
+                Some(0) => world_menu = !world_menu,
+                Some(k) => {
+                    let picked = WorldPreset::ALL[k - 1];
+                    cfg.world = picked;
+                    cfg.boundaries = picked.boundaries();
+                    world_menu = false;
+                    restart = true;
+                    notice = Some((format!("world: {}", picked.name()), 3.0));
+                }
+                None => world_menu = false,
+            }
+        }
+        if !typing && is_key_pressed(KeyCode::G) {
+            let next = active.map_or(0, |k| k + 1);
+            active = (next < all_challenges.len()).then_some(next);
+            touring = false;
+            preset = 0;
+            follow = Follow::Centroid;
+            restart = true;
+        }
+        if !typing && is_key_pressed(KeyCode::H) {
+            layers.toggle(Layer::Chain);
+        }
+        if !typing && is_key_pressed(KeyCode::K) {
+            show_layers = !show_layers;
+        }
+        if !typing && is_key_pressed(KeyCode::J) {
+            policy_lab = match policy_lab {
+                Some(_) => None,
+                None => Some(PolicyLab::new(seed, &loaded)),
+            };
+        }
+        if let Some(lab) = &mut policy_lab
+            && let Some(message) = lab.load_dropped()
+        {
+            notice = Some((message, 4.0));
+        }
+        if !typing && is_key_pressed(KeyCode::V) {
+            filter = filter.next();
+        }
+        if !typing && is_key_pressed(KeyCode::I) {
+            layers.toggle(Layer::Labels);
+        }
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            zoom = (zoom * 1.15f32.powf(wheel.signum())).clamp(1.0, 8.0);
+        }
+        if !typing && is_key_pressed(KeyCode::Q) {
+            // Cycle off -> vaccinate -> quarantine -> cull -> zone -> off.
+            area_tool = match area_tool {
+                None => Some(AreaAction::ALL[0]),
+                Some(action) => AreaAction::ALL
+                    .iter()
+                    .position(|&a| a == action)
+                    .and_then(|k| AreaAction::ALL.get(k + 1).copied()),
+            };
+            drag_start = None;
+        }
+        if !typing && is_key_pressed(KeyCode::R) {
+            capture = match capture {
+                Some(_) => None,
+                None => {
+                    let height = (CAPTURE_WIDTH as f32 * screen_height() / screen_width()) as usize;
+                    Some(FrameRing::new(
+                        CAPTURE_WIDTH,
+                        height,
+                        CAPTURE_SECONDS,
+                        CAPTURE_FPS,
+                    ))
+                }
+            };
+        }
+        if !typing
+            && is_key_pressed(KeyCode::F)
+            && let Some(ring) = &capture
+            && !ring.is_empty()
+        {
+            let stamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let path = format!("boids-{stamp}.gif");
+            let message = match ring.save_gif(std::path::Path::new(&path)) {
+                Ok(()) => format!("saved {path} ({} frames)", ring.len()),
+                Err(err) => format!("failed to write {path}: {err}"),
+            };
+            notice = Some((message, 4.0));
+        }
+        if !typing && is_key_pressed(KeyCode::Key0) {
+            recorder = match recorder.take() {
+                Some(done) => {
+                    notice = Some((save_recording(&done), 4.0));
+                    None
+                }
+                None => {
+                    let mut fresh = Recorder::new(sim.config().world_size);
+                    fresh.record(&sim);
+                    Some(fresh)
+                }
+            };
+        }
+        if !typing && is_key_pressed(KeyCode::Key8) {
+            keep_runs = !keep_runs;
+            let message = if keep_runs {
+                format!("restarts keep the last {GHOST_RUNS} runs' curves")
+            } else {
+                ghosts.clear();
+                "earlier runs' curves cleared".to_string()
+            };
+            notice = Some((message, 3.0));
+        }
+        if !typing && is_key_pressed(KeyCode::Key9) {
+            tick_csv = match tick_csv.take() {
+                Some((export, path)) => {
+                    notice = Some((finish_tick_csv(export, &path), 4.0));
+                    None
+                }
+                None => {
+                    let stamp = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |d| d.as_secs());
+                    let path = format!("ticks-{stamp}.csv");
+                    match TickExport::create(std::path::Path::new(&path)) {
+                        Ok(export) => Some((export, path)),
+                        Err(err) => {
+                            notice = Some((format!("failed to create {path}: {err}"), 4.0));
+                            None
+                        }
+                    }
+                }
+            };
+        }
+        if !typing && is_key_pressed(KeyCode::W) {
+            graph.whole_run = !graph.whole_run;
+        }
+        if !typing && is_key_pressed(KeyCode::Y) && !graph.history.is_empty() {
+            let stamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let path = format!("history-{stamp}.csv");
+            let message = match std::fs::write(&path, graph.csv()) {
+                Ok(()) => format!("saved {path} ({} samples)", graph.history.len()),
+                Err(err) => format!("failed to write {path}: {err}"),
             };
-            seed = seed.wrapping_add(1);
-            sim = Simulation::new(2400, cfg, seed);
-            graph = SirGraph::new(360);
+            notice = Some((message, 4.0));
+        }
+        if !typing && is_key_pressed(KeyCode::T) {
+            touring = !touring;
+            active = None;
+            preset = 0;
+            follow = Follow::Centroid;
+            restart = true;
+        }
+        if !typing && is_key_pressed(KeyCode::C) {
+            follow = Follow::Centroid;
         }
+        if !typing && is_key_pressed(KeyCode::N) {
+            schedule = match schedule {
+                Some(_) => None,
+                None => Some(ActivitySchedule::parse("20:0.4").expect("valid schedule")),
+            };
+        }
+        if !typing && is_key_pressed(KeyCode::U) {
+            commute = match commute {
+                Some(_) => None,
+                None => Some(Commute::default()),
+            };
+        }
+        if !typing && is_key_pressed(KeyCode::D) {
+            let at = DiseaseModel::ALL.iter().position(|&m| m == disease_model);
+            disease_model = DiseaseModel::ALL[at.map_or(0, |k| (k + 1) % DiseaseModel::ALL.len())];
+            restart = true;
+            notice = Some((format!("disease model: {}", disease_model.name()), 3.0));
+        }
+        if !typing && is_key_pressed(KeyCode::B) {
+            let message = if sim.travel_restriction().is_some() {
+                sim.lift_travel_restriction();
+                "travel restriction lifted".to_string()
+            } else {
+                let spec = sim.config().travel_restriction.unwrap_or_default();
+                match sim.restrict_travel(spec) {
+                    Ok(()) => format!(
+                        "travel restricted to {}x{} zones",
+                        spec.zones.0, spec.zones.1
+                    ),
+                    Err(err) => err,
+                }
+            };
+            notice = Some((message, 3.0));
+        }
+        if !typing && is_key_pressed(KeyCode::P) {
+            explorer = match explorer {
+                Some(_) => None,
+                None => Some(Explorer::spawn(
+                    *sim.config(),
+                    file.boids,
+                    EXPLORE_CANDIDATES,
+                    EXPLORE_SHORTLIST,
+                    seed,
+                )),
+            };
+        }
+        if let Some(explorer) = &mut explorer {
+            explorer.poll();
+            // Challenge tools share the number keys.
+            let picked = SHORTLIST_KEYS
+                .iter()
+                .zip(explorer.shortlist())
+                .find(|(key, _)| !typing && attempt.is_none() && is_key_pressed(**key));
+            if let Some((_, candidate)) = picked {
+                set_knobs(&mut knobs, &candidate.config);
+                touring = false;
+                restart = true;
+                notice = Some((
+                    format!(
+                        "loaded candidate: {:.0}% of previews took off",
+                        candidate.stats.outbreak_probability * 100.0
+                    ),
+                    3.0,
+                ));
+            }
+        }
+        // Live parameters from a saved config file apply at once, through
+        // the knobs; the rest waits for the next restart.
+        if let Some(watcher) = &mut watcher
+            && watcher.poll(dt)
+        {
+            let message = match ConfigFile::load(watcher.path(), defaults) {
+                Ok(next) => {
+                    let pending = file.needs_restart(&next);
+                    // Challenges and the tutorial bring their own population.
+                    let resized = next.boids != file.boids && active.is_none() && !touring;
+                    if resized {
+                        sim.resize(next.boids);
+                    }
+                    file = next;
+                    cfg = next.config;
+                    schedule = cfg.schedule;
+                    commute = cfg.commute;
+                    disease_model = cfg.disease_model;
+                    budget.budget = file.step_budget;
+                    set_knobs(&mut knobs, &cfg);
+                    if pending {
+                        format!(
+                            "reloaded {}; press Enter to restart with the new setup, movement or sampling",
+                            watcher.path().display()
+                        )
+                    } else if resized {
+                        format!(
+                            "reloaded {}; population now {}",
+                            watcher.path().display(),
+                            file.boids
+                        )
+                    } else {
+                        format!("reloaded {}", watcher.path().display())
+                    }
+                }
+                Err(err) => err,
+            };
+            notice = Some((message, 5.0));
+        }
+        let world = match WORLD_PRESETS[preset] {
+            Some((w, h)) => Vec2f::new(w, h),
+            None => Vec2f::new(screen_width(), screen_height()),
+        };
+        sim.set_world_size(world);
 
-        sim.set_motion_params(neighbor_radius, separation_radius, max_speed, max_force);
-        sim.set_infection_params(infection_radius, infection_beta, infectious_period);
-        sim.step(dt);
-        let counts = sim.counts();
-        graph.push(counts);
+        // Challenges and the tutorial set their own parameters.
+        let scripted = active.is_some() || touring;
+        if !scripted {
+            for knob in &mut knobs {
+                knob.update();
+            }
+        }
 
-        clear_background(Color::from_rgba(8, 10, 14, 255));
+        // Ease toward the knobs so a quick drag of Max Spd doesn't blow the
+        // flock apart; a restart starts from the targets.
+        let ease = if restart || file.smoothing <= 0.0 {
+            1.0
+        } else {
+            1.0 - (-dt / file.smoothing).exp()
+        };
+        for (value, knob) in applied.iter_mut().zip(&knobs) {
+            *value += (knob.value - *value) * ease;
+        }
 
-        for boid in &sim.boids {
-            let dir = boid.vel.normalize();
-            let dir = if dir.length() > 0.0 {
-                dir
-            } else {
-                Vec2f::new(1.0, 0.0)
+        let neighbor_radius = applied[0];
+        let separation_radius = applied[1];
+        let max_speed = applied[2];
+        let max_force = applied[3];
+        let infection_radius = applied[4];
+        let infection_beta = applied[5];
+        let infectious_period = applied[6];
+
+        let live_cfg = SimConfig {
+            world_size: world,
+            max_speed,
+            max_force,
+            neighbor_radius,
+            separation_radius,
+            infection_radius,
+            infection_beta,
+            infectious_period,
+            schedule,
+            commute,
+            disease_model,
+            ..cfg
+        };
+
+        if restart {
+            // A recording covers one run.
+            if let Some(done) = recorder.take() {
+                notice = Some((save_recording(&done), 4.0));
+            }
+            if let Some((export, path)) = tick_csv.take() {
+                notice = Some((finish_tick_csv(export, &path), 4.0));
+            }
+            seed = std::mem::replace(&mut next_seed, NextSeed::Increment).resolve(seed);
+            let previous_boids = sim.boids.len();
+            // On stdout too, so a run worth keeping can be reproduced later.
+            println!("restart: seed {seed}");
+            run_clock = 0.0;
+            tutorial = None;
+            sim = match active {
+                Some(k) => all_challenges[k].simulation(world, seed),
+                None if touring => {
+                    let (walkthrough, first) = Tutorial::start(world, seed);
+                    tutorial = Some(walkthrough);
+                    first
+                }
+                None => {
+                    let mut sim = Simulation::new(file.boids, live_cfg, seed);
+                    if let Some(policy) = loaded.first() {
+                        let _ = sim.set_policy(policy.clone());
+                    }
+                    sim
+                }
             };
-            let perp = Vec2f::new(-dir.y, dir.x);
-            let tip = boid.pos.add(dir.mul(6.0));
-            let left = boid.pos.sub(dir.mul(2.5)).add(perp.mul(3.0));
-            let right = boid.pos.sub(dir.mul(2.5)).sub(perp.mul(3.0));
-
-            let color = match boid.state {
-                HealthState::Susceptible => Color::from_rgba(220, 240, 255, 255),
-                HealthState::Infected => Color::from_rgba(255, 90, 90, 255),
-                HealthState::Recovered => Color::from_rgba(120, 220, 140, 255),
+            attempt = active.map(|k| Attempt::new(&all_challenges[k], &sim));
+            let fresh = SirGraph::new(graph.whole_run, file.sample_interval);
+            let previous = std::mem::replace(&mut graph, fresh);
+            if keep_runs && !previous.history.is_empty() {
+                ghosts.push_front(GhostRun {
+                    history: previous.history,
+                    total: previous_boids,
+                });
+                ghosts.truncate(GHOST_RUNS);
+            }
+            tracker = EpidemicTracker::new(&sim);
+            tracker.set_sample_interval(file.sample_interval);
+            contact_rate = ContactRate::new(&sim);
+            if risk.is_some() {
+                risk = Some(RiskScores::new());
+            }
+            if ensemble.is_some() {
+                ensemble = Some(spawn_ensemble(*sim.config(), file.boids, seed));
+            }
+        }
+
+        if !typing && is_key_pressed(KeyCode::E) {
+            ensemble = match ensemble {
+                Some(_) => None,
+                None => Some(spawn_ensemble(*sim.config(), file.boids, seed)),
+            };
+        }
+        if let Some(ensemble) = &mut ensemble {
+            ensemble.poll();
+        }
+        if !typing && is_key_pressed(KeyCode::O) {
+            show_forecast = !show_forecast;
+        }
+        if !typing && is_key_pressed(KeyCode::A) {
+            risk = match risk {
+                Some(_) => None,
+                None => Some(RiskScores::new()),
+            };
+        }
+        if !typing && is_key_pressed(KeyCode::M) {
+            sound_on = !sound_on;
+            if sound_on && audio.is_none() {
+                audio = Audio::load(&sim).await;
+            }
+            let message = match (&mut audio, sound_on) {
+                (None, _) => {
+                    sound_on = false;
+                    "[M] sound unavailable"
+                }
+                (Some(audio), false) => {
+                    audio.silence();
+                    "[M] sound off"
+                }
+                (Some(_), true) => "[M] sound on",
+            };
+            notice = Some((message.to_owned(), 2.0));
+        }
+
+        if let Some(attempt) = &mut attempt {
+            for (k, &key) in TOOL_KEYS.iter().enumerate() {
+                if !typing && is_key_pressed(key) {
+                    attempt.use_tool(k, &mut sim);
+                }
+            }
+        } else if !scripted {
+            sim.set_motion_params(neighbor_radius, separation_radius, max_speed, max_force);
+            sim.set_infection_params(infection_radius, infection_beta, infectious_period);
+            sim.set_schedule(schedule);
+            sim.set_commute(commute);
+        }
+        let mut new_stage = false;
+        if let Some(tutorial) = &mut tutorial
+            && is_key_pressed(KeyCode::Space)
+        {
+            tutorial.skip(&mut sim);
+            new_stage = true;
+        } else if !typing && is_key_pressed(KeyCode::Space) {
+            paused = !paused;
+        }
+        if !typing && (is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd)) {
+            time_scale = (time_scale * 2.0).min(MAX_TIME_SCALE);
+        }
+        if !typing && (is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract)) {
+            time_scale = (time_scale / 2.0).max(MIN_TIME_SCALE);
+        }
+        // The simulation freezes on the results screen.
+        let finished = attempt.as_ref().is_some_and(|a| a.result().is_some());
+        if !finished {
+            sim.set_quality(budget.quality());
+            let started = std::time::Instant::now();
+            let ticks = if !paused {
+                sim.advance(dt * time_scale)
+            } else if !typing && is_key_pressed(KeyCode::Period) {
+                sim.step(TICK);
+                1
+            } else {
+                0
             };
+            if ticks > 0 {
+                let stepped = ticks as f32 * TICK;
+                budget.record(started.elapsed().as_secs_f32(), dt);
+                tracker.record(&sim, stepped);
+                contact_rate.record(&sim, stepped);
+                if let Some(attempt) = &mut attempt {
+                    attempt.record(&sim, stepped);
+                }
+                if let Some(tutorial) = &mut tutorial {
+                    new_stage |= tutorial.update(&mut sim, stepped);
+                }
+                graph.push(&sim);
+                if let Some(recorder) = &mut recorder {
+                    recorder.record(&sim);
+                }
+                if let Some((export, path)) = &mut tick_csv
+                    && let Err(err) = export.record(&sim)
+                {
+                    notice = Some((format!("failed to write {path}: {err}"), 4.0));
+                    tick_csv = None;
+                }
+                if let Some(risk) = &mut risk {
+                    risk.record(&sim, stepped);
+                }
+            }
+        }
+        if sound_on && let Some(audio) = &mut audio {
+            audio.update(&sim, dt);
+        }
+        if new_stage {
+            graph = SirGraph::new(graph.whole_run, file.sample_interval);
+            tracker = EpidemicTracker::new(&sim);
+            tracker.set_sample_interval(file.sample_interval);
+            contact_rate = ContactRate::new(&sim);
+            if risk.is_some() {
+                risk = Some(RiskScores::new());
+            }
+            if ensemble.is_some() {
+                ensemble = Some(spawn_ensemble(*sim.config(), file.boids, seed));
+            }
+        }
+
+        clear_background(Color::from_rgba(8, 10, 14, 255));
 
-            draw_triangle(
-                Vec2::new(tip.x, tip.y),
-                Vec2::new(left.x, left.y),
-                Vec2::new(right.x, right.y),
-                color,
+        let large_world = WORLD_PRESETS[preset].is_some();
+        let (sw, sh) = (screen_width(), screen_height());
+        let focus = match follow {
+            Follow::Boid(idx) if idx < sim.boids.len() => sim.boids[idx].pos,
+            _ => torus_centroid(&sim.boids, world),
+        };
+        let zoomed = zoom > 1.0;
+        let (vw, vh) = (sw / zoom, sh / zoom);
+        let view = if large_world || zoomed {
+            Rect::new(focus.x - vw / 2.0, focus.y - vh / 2.0, vw, vh)
+        } else {
+            Rect::new(0.0, 0.0, sw, sh)
+        };
+        // Positive y zoom keeps macroquad's screen convention of y pointing down.
+        let camera = Camera2D {
+            target: vec2(view.x + vw / 2.0, view.y + vh / 2.0),
+            zoom: vec2(2.0 / vw, 2.0 / vh),
+            ..Default::default()
+        };
+        set_camera(&camera);
+        let mut renderer = MacroquadRenderer {
+            view,
+            overlay_at: Vec2f::new(sw / 2.0 - 70.0, 44.0),
+            graph_origin: Vec2f::default(),
+            graph_size: Vec2f::default(),
+            whole_run: graph.whole_run,
+            exposed: sim.config().disease_model == DiseaseModel::Seir,
+        };
+
+        let ranked = risk
+            .as_ref()
+            .map(|r| r.ranked(&sim, RISK_ROWS))
+            .unwrap_or_default();
+        let risk_origin = Vec2f::new(sw - RISK_PANEL_WIDTH - 16.0, 48.0);
+        let risk_row = risk.is_some().then(|| risk_row_at(risk_origin)).flatten();
+        if let Some(row) = risk_row
+            && is_mouse_button_pressed(MouseButton::Left)
+            && let Some(&(idx, _)) = ranked.get(row)
+        {
+            follow = Follow::Boid(idx);
+        }
+        let over_panels = (mouse_position().1 < 320.0 && mouse_position().0 < 700.0)
+            || risk_row.is_some()
+            || world_row_at(world_menu).is_some();
+        // Challenges only allow their own tools.
+        let area_action = area_tool.filter(|_| active.is_none());
+        // Clicking a boid selects it; large or zoomed views also follow it.
+        if area_action.is_none()
+            && is_mouse_button_pressed(MouseButton::Left)
+            && !over_panels
+            && !knobs.iter().any(|k| k.dragging)
+        {
+            let m = camera.screen_to_world(mouse_position().into());
+            let picked = sim
+                .boids
+                .iter()
+                .enumerate()
+                .map(|(i, b)| (b.pos.sub(Vec2f::new(m.x, m.y)).length(), i))
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((dist, idx)) = picked
+                && dist < 20.0
+            {
+                follow = Follow::Boid(idx);
+            }
+        }
+
+        let selected = match follow {
+            Follow::Boid(idx) if idx < sim.boids.len() => Some(idx),
+            _ => None,
+        };
+        let graph_origin = Vec2f::new(380.0, 24.0);
+        let graph_size = Vec2f::new(300.0, 120.0);
+        // The history shares the graph with the projection while it is shown.
+        let history_size = if show_forecast {
+            Vec2f::new(graph_size.x * (1.0 - FORECAST_SHARE), graph_size.y)
+        } else {
+            graph_size
+        };
+        // Hovering the graph picks out who was infected at that time.
+        let (mx, my) = mouse_position();
+        let brushed = (my >= graph_origin.y && my <= graph_origin.y + graph_size.y)
+            .then(|| graph.sample_at(mx, graph_origin, history_size))
+            .flatten();
+        let shown = match brushed {
+            Some((time, _)) => {
+                let mut infected = graph.states.infected_at(time);
+                infected.resize(sim.boids.len(), false);
+                infected
+            }
+            None => filter.matches(&sim, selected),
+        };
+        let mut chain_size = None;
+        let mut pressure = None;
+        for layer in layers.visible().filter(|l| l.in_world()) {
+            match layer {
+                Layer::Zones => {
+                    if let Some(spec) = sim.travel_restriction() {
+                        draw_zone_borders(spec, sim.config().world_size);
+                    }
+                    if let Some(spec) = sim.config().commute {
+                        draw_patches(&spec, sim.config().world_size);
+                    }
+                    draw_quarantine_zones(sim.quarantine_zones());
+                    draw_obstacles(&sim.obstacles());
+                }
+                Layer::Pressure => {
+                    let grid = crowd_pressure(&sim, sim.config().neighbor_radius);
+                    draw_pressure(&grid);
+                    pressure = Some(grid);
+                }
+                Layer::Boids => renderer.draw_boids(&sim, &shown),
+                Layer::Quarantine => draw_quarantine_rings(&sim, view),
+                Layer::Chain => {
+                    if let Some(idx) = selected {
+                        chain_size = Some(draw_transmission_chain(&sim, idx));
+                    }
+                }
+                Layer::Selection => {
+                    if let Some(boid) = selected.map(|idx| &sim.boids[idx]) {
+                        draw_circle_lines(
+                            boid.pos.x,
+                            boid.pos.y,
+                            12.0,
+                            1.0,
+                            Color::from_rgba(120, 200, 255, 220),
+                        );
+                    }
+                }
+                Layer::Night | Layer::Labels | Layer::Minimap => {}
+            }
+        }
+        if let Some(action) = area_action {
+            let m = camera.screen_to_world(mouse_position().into());
+            let mouse = Vec2f::new(m.x, m.y);
+            if is_mouse_button_pressed(MouseButton::Left) && !over_panels {
+                drag_start = Some(mouse);
+            }
+            if let Some(start) = drag_start {
+                let min = Vec2f::new(start.x.min(mouse.x), start.y.min(mouse.y));
+                let max = Vec2f::new(start.x.max(mouse.x), start.y.max(mouse.y));
+                draw_rectangle_lines(
+                    min.x,
+                    min.y,
+                    max.x - min.x,
+                    max.y - min.y,
+                    1.5,
+                    Color::from_rgba(255, 200, 80, 220),
+                );
+                if is_mouse_button_released(MouseButton::Left) {
+                    let affected = sim.apply_in_area(min, max, action);
+                    notice = Some((format!("{}: {affected} boids", action.name()), 3.0));
+                    drag_start = None;
+                }
+            }
+            if action == AreaAction::Zone
+                && is_mouse_button_pressed(MouseButton::Right)
+                && !sim.quarantine_zones().is_empty()
+            {
+                sim.clear_quarantine_zones();
+                notice = Some(("quarantine zones cleared".to_string(), 3.0));
+            }
+        }
+        for &(idx, _) in &ranked {
+            let pos = sim.boids[idx].pos;
+            draw_circle_lines(pos.x, pos.y, 9.0, 1.0, Color::from_rgba(255, 170, 60, 200));
+        }
+        set_default_camera();
+
+        for layer in layers.visible().filter(|l| !l.in_world()) {
+            match layer {
+                Layer::Night if sim.is_night() => {
+                    draw_rectangle(0.0, 0.0, sw, sh, Color::from_rgba(0, 0, 30, 110));
+                }
+                Layer::Labels => {
+                    let visible = |b: &Boid| view.contains(vec2(b.pos.x, b.pos.y));
+                    let matching = (0..sim.boids.len())
+                        .filter(|&i| shown[i] && visible(&sim.boids[i]))
+                        .count();
+                    let label_all =
+                        zoom >= LABEL_ZOOM || (filter != Filter::All && matching <= LABEL_LIMIT);
+                    draw_labels(&sim, &camera, view, &shown, selected, label_all);
+                }
+                Layer::Minimap if large_world || zoomed => draw_minimap(&sim, view, follow),
+                _ => {}
+            }
+        }
+        // Status lines stack up from above the notice line, each on its own
+        // row, so any combination of them stays readable.
+        let mut status_row = sh - 36.0;
+        let mut status_line = |text: &str, color: Color| {
+            draw_text(text, 16.0, status_row, 16.0, color);
+            status_row -= 20.0;
+        };
+        let show_labels = layers.is_shown(Layer::Labels);
+        if filter != Filter::All || show_labels || zoomed {
+            status_line(
+                &format!(
+                    "[V] filter: {}   [I] labels {}   zoom {zoom:.1}x",
+                    filter.name(),
+                    if show_labels { "on" } else { "off" }
+                ),
+                Color::from_rgba(220, 240, 255, 255),
             );
         }
 
@@ -281,12 +2659,36 @@ async fn main() {
             1.0,
             Color::from_rgba(40, 60, 80, 200),
         );
-        for knob in &knobs {
-            knob.draw();
+        match (active, &attempt, &tutorial) {
+            (Some(k), Some(attempt), _) => draw_challenge_panel(&all_challenges[k], attempt),
+            (_, _, Some(tutorial)) => draw_tutorial(tutorial),
+            _ => {
+                for knob in &knobs {
+                    knob.draw();
+                }
+            }
+        }
+        draw_run_panel(seed, run_clock, sim.time(), seed_entry.as_deref());
+        if !scripted {
+            draw_world_menu(sim.config().world, world_menu);
+        }
+        if let Some(explorer) = &explorer {
+            draw_explorer(explorer);
+        }
+        if show_layers {
+            layers_panel(&mut layers);
+        }
+        if let Some(lab) = &mut policy_lab {
+            let top = if show_layers {
+                44.0 + Layer::ALL.len() as f32 * LAYER_ROW
+            } else {
+                16.0
+            };
+            if let Some(message) = policy_panel(lab, &mut sim, top) {
+                notice = Some((message, 4.0));
+            }
         }
 
-        let graph_origin = Vec2f::new(380.0, 24.0);
-        let graph_size = Vec2f::new(300.0, 120.0);
         draw_rectangle(
             graph_origin.x - 8.0,
             graph_origin.y - 8.0,
@@ -302,7 +2704,214 @@ async fn main() {
             1.0,
             Color::from_rgba(40, 60, 80, 200),
         );
-        graph.draw(graph_origin, graph_size, sim.boids.len());
+        let forecast = show_forecast.then(|| {
+            Forecast::project(
+                sim.counts(),
+                contact_rate.estimate(&sim),
+                sim.config().infectious_period,
+                FORECAST_HORIZON,
+            )
+        });
+        if let Some(ensemble) = &ensemble {
+            graph.draw_band(ensemble, graph_origin, history_size);
+            let status = format!("ensemble {}/{}", ensemble.completed(), ensemble.replicas());
+            draw_text(
+                &status,
+                graph_origin.x,
+                graph_origin.y + graph_size.y + 4.0,
+                14.0,
+                Color::from_rgba(255, 170, 120, 220),
+            );
+        }
+        graph.draw_ghosts(&ghosts, graph_origin, history_size);
+        renderer.graph_origin = graph_origin;
+        renderer.graph_size = history_size;
+        renderer.draw_graph(&graph.history, sim.boids.len());
+        if let Some((time, x)) = brushed {
+            draw_line(
+                x,
+                graph_origin.y,
+                x,
+                graph_origin.y + graph_size.y,
+                1.0,
+                Color::from_rgba(255, 220, 120, 220),
+            );
+            let carrying = shown.iter().filter(|&&s| s).count();
+            draw_text(
+                &format!("t {time:.1}s: {carrying} infected, highlighted"),
+                graph_origin.x,
+                graph_origin.y + graph_size.y + 32.0,
+                14.0,
+                Color::from_rgba(255, 220, 120, 220),
+            );
+        }
+        let span = if graph.whole_run {
+            format!("[W] whole run, {:.0}s  [Y] save", sim.time())
+        } else {
+            "[W] recent  [Y] save".to_string()
+        };
+        draw_text(
+            &span,
+            graph_origin.x,
+            graph_origin.y + 10.0,
+            14.0,
+            Color::from_rgba(150, 160, 180, 200),
+        );
+        if let Some(forecast) = &forecast {
+            draw_forecast(
+                forecast,
+                Vec2f::new(graph_origin.x + history_size.x, graph_origin.y),
+                Vec2f::new(graph_size.x - history_size.x, graph_size.y),
+            );
+            let (peak_in, peak) = forecast.peak();
+            let source = if contact_rate.observed().is_some() {
+                "observed"
+            } else {
+                "mean-field"
+            };
+            draw_text(
+                &format!(
+                    "[O] forecast: peak {:.0}% in {peak_in:.0}s, final {:.0}% ({source})",
+                    peak * 100.0,
+                    forecast.final_recovered() * 100.0
+                ),
+                graph_origin.x,
+                graph_origin.y + graph_size.y + 18.0,
+                14.0,
+                Color::from_rgba(150, 160, 180, 220),
+            );
+        }
+
+        if risk.is_some() {
+            draw_risk_panel(&ranked, selected, risk_origin);
+        }
+        if let Some(summary) = tracker.summary() {
+            draw_summary(summary, Vec2f::new(graph_origin.x - 8.0, 168.0));
+        }
+        if let Some(result) = attempt.as_ref().and_then(Attempt::result) {
+            draw_challenge_result(result);
+        }
+
+        if let Some((upstream, downstream)) = chain_size {
+            status_line(
+                &format!("[H] chain: {upstream} upstream, {downstream} downstream"),
+                Color::from_rgba(255, 200, 80, 255),
+            );
+        }
+        if let Some(degraded) = budget.degraded() {
+            status_line(
+                &format!(
+                    "step {:.1} ms (budget {:.0} ms): {degraded}",
+                    budget.average() * 1000.0,
+                    budget.budget * 1000.0
+                ),
+                Color::from_rgba(255, 170, 120, 255),
+            );
+        }
+        if let (Some(spec), Some(counts)) = (sim.config().commute, sim.patch_counts()) {
+            let mut line = format!(
+                "[U] commuting, {}:",
+                if spec.is_work_time(sim.time()) {
+                    "work hours"
+                } else {
+                    "home hours"
+                }
+            );
+            for (patch, c) in Patch::ALL.into_iter().zip(counts) {
+                let total = c.total().max(1);
+                line.push_str(&format!(
+                    "  {} {} boids, {:.0}% infected",
+                    patch.name(),
+                    total,
+                    c.infected as f32 / total as f32 * 100.0
+                ));
+            }
+            status_line(&line, Color::from_rgba(180, 220, 160, 255));
+        }
+        if let Some(pressure) = &pressure {
+            status_line(
+                &format!(
+                    "pressure: max {:.1}, {} over-compressed cells",
+                    pressure.max(),
+                    pressure.hotspots(OVERCOMPRESSED).len()
+                ),
+                Color::from_rgba(240, 160, 255, 255),
+            );
+        }
+        if paused {
+            renderer.draw_overlay(&["[Space] paused  [.] step".to_string()]);
+        } else if time_scale != 1.0 {
+            renderer.draw_overlay(&[format!("[-/+] speed {time_scale}x")]);
+        }
+        if let Some(action) = area_action {
+            let hint = match action {
+                AreaAction::Zone => "  [right-click] clear zones",
+                _ => "",
+            };
+            draw_text(
+                &format!("[Q] drag to {}{hint}", action.name()),
+                sw / 2.0 - 70.0,
+                24.0,
+                16.0,
+                Color::from_rgba(255, 200, 80, 255),
+            );
+        }
+
+        // Grab the finished frame before drawing capture status over it.
+        if let Some(ring) = &mut capture {
+            capture_clock += dt;
+            if capture_clock >= 1.0 / CAPTURE_FPS {
+                capture_clock = 0.0;
+                let image = get_screen_data();
+                ring.push_rgba(
+                    &image.bytes,
+                    image.width as usize,
+                    image.height as usize,
+                    true,
+                );
+            }
+            draw_circle(sw - 24.0, 24.0, 6.0, Color::from_rgba(255, 60, 60, 255));
+            draw_text(
+                &format!("{:.0}s [F] save", ring.len() as f32 / CAPTURE_FPS),
+                sw - 120.0,
+                29.0,
+                16.0,
+                Color::from_rgba(220, 240, 255, 255),
+            );
+        }
+        if let Some(recorder) = &recorder {
+            draw_circle(sw - 24.0, 48.0, 6.0, Color::from_rgba(255, 60, 60, 255));
+            draw_text(
+                &format!("{} frames [0] stop", recorder.frames()),
+                sw - 120.0,
+                53.0,
+                16.0,
+                Color::from_rgba(220, 240, 255, 255),
+            );
+        }
+        if let Some((export, _)) = &tick_csv {
+            draw_circle(sw - 24.0, 72.0, 6.0, Color::from_rgba(120, 220, 140, 255));
+            draw_text(
+                &format!("{} rows [9] stop", export.rows()),
+                sw - 120.0,
+                77.0,
+                16.0,
+                Color::from_rgba(220, 240, 255, 255),
+            );
+        }
+        if let Some((message, left)) = &mut notice {
+            draw_text(
+                message,
+                16.0,
+                sh - 16.0,
+                16.0,
+                Color::from_rgba(220, 240, 255, 255),
+            );
+            *left -= dt;
+            if *left <= 0.0 {
+                notice = None;
+            }
+        }
 
         next_frame().await;
     }
@@ -0,0 +1,128 @@
+use std::fmt;
+
+/// Autocorrelation peaks below this are treated as noise around an
+/// equilibrium rather than a cycle.
+pub const OSCILLATION_THRESHOLD: f32 = 0.3;
+
+/// Long-run behaviour of the infected series after the transient.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dynamics {
+    /// Infections died out.
+    Extinct,
+    /// Prevalence fluctuates around a steady endemic level.
+    Equilibrium,
+    /// Prevalence cycles with a detectable period.
+    Oscillating,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OscillationReport {
+    /// Samples analysed, after discarding the first half as transient.
+    pub samples: usize,
+    /// Mean infected fraction over the analysed window.
+    pub mean: f32,
+    /// Standard deviation over mean; 0 for a flat series.
+    pub coefficient_of_variation: f32,
+    /// Lag of the dominant autocorrelation peak, in seconds.
+    pub period: Option<f32>,
+    /// Autocorrelation at `period`.
+    pub peak_autocorrelation: f32,
+    pub dynamics: Dynamics,
+}
+
+/// Sample autocorrelation of `series` for lags `0..=max_lag`.
+pub fn autocorrelation(series: &[f32], max_lag: usize) -> Vec<f32> {
+    let n = series.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let mean = series.iter().sum::<f32>() / n as f32;
+    let var: f32 = series.iter().map(|x| (x - mean) * (x - mean)).sum();
+    (0..=max_lag.min(n - 1))
+        .map(|lag| {
+            if var <= 0.0 {
+                return 0.0;
+            }
+            let cov: f32 = series
+                .iter()
+                .zip(&series[lag..])
+                .map(|(a, b)| (a - mean) * (b - mean))
+                .sum();
+            cov / var
+        })
+        .collect()
+}
+
+/// Classify the infected-fraction `series`, sampled every `sample_dt`
+/// seconds. The first half is discarded so the initial epidemic wave does not
+/// register as a cycle.
+pub fn analyze(series: &[f32], sample_dt: f32) -> OscillationReport {
+    let window = &series[series.len() / 2..];
+    let n = window.len();
+    let mean = if n == 0 {
+        0.0
+    } else {
+        window.iter().sum::<f32>() / n as f32
+    };
+    let sd = if n == 0 {
+        0.0
+    } else {
+        (window.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / n as f32).sqrt()
+    };
+    let mut report = OscillationReport {
+        samples: n,
+        mean,
+        coefficient_of_variation: if mean > 0.0 { sd / mean } else { 0.0 },
+        period: None,
+        peak_autocorrelation: 0.0,
+        dynamics: Dynamics::Equilibrium,
+    };
+    if window.last().is_none_or(|&last| last <= 0.0) {
+        report.dynamics = Dynamics::Extinct;
+        return report;
+    }
+
+    // The dominant period is the highest autocorrelation peak after the
+    // function first drops below zero.
+    let acf = autocorrelation(window, n / 2);
+    let Some(start) = acf.iter().position(|&r| r < 0.0) else {
+        return report;
+    };
+    let peak = (start + 1..acf.len().saturating_sub(1))
+        .filter(|&k| acf[k] >= acf[k - 1] && acf[k] >= acf[k + 1])
+        .max_by(|&a, &b| acf[a].total_cmp(&acf[b]));
+    if let Some(lag) = peak {
+        report.period = Some(lag as f32 * sample_dt);
+        report.peak_autocorrelation = acf[lag];
+        if acf[lag] >= OSCILLATION_THRESHOLD {
+            report.dynamics = Dynamics::Oscillating;
+        }
+    }
+    report
+}
+
+impl fmt::Display for OscillationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dynamics = match self.dynamics {
+            Dynamics::Extinct => "extinct",
+            Dynamics::Equilibrium => "endemic equilibrium",
+            Dynamics::Oscillating => "oscillating",
+        };
+        writeln!(f, "long-run dynamics: {dynamics}")?;
+        writeln!(
+            f,
+            "endemic level:     {:.1}% infected (CV {:.2}, {} samples)",
+            self.mean * 100.0,
+            self.coefficient_of_variation,
+            self.samples
+        )?;
+        match self.period {
+            Some(period) => write!(
+                f,
+                "dominant period:   {period:.1}s (autocorrelation {:.2})",
+                self.peak_autocorrelation
+            ),
+            None => write!(f, "dominant period:   none"),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt::Write as _;
+use std::mem::size_of;
+use std::path::Path;
+
+use crate::sim::{Rng, Vec2f};
+
+/// Weights of a fresh policy are drawn from `-INIT_SCALE..INIT_SCALE`.
+pub const INIT_SCALE: f32 = 0.6;
+
+/// The steering network: one tanh hidden layer mapping a boid's features to
+/// a steering direction in the unit square.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Policy {
+    input_size: usize,
+    hidden_size: usize,
+    w1: Vec<f32>,
+    b1: Vec<f32>,
+    w2: Vec<f32>,
+    b2: Vec<f32>,
+}
+
+impl Policy {
+    /// A policy with every weight zero.
+    pub fn new(input_size: usize, hidden_size: usize) -> Self {
+        Self {
+            input_size,
+            hidden_size,
+            w1: vec![0.0; input_size * hidden_size],
+            b1: vec![0.0; hidden_size],
+            w2: vec![0.0; hidden_size * 2],
+            b2: vec![0.0; 2],
+        }
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        (self.w1.capacity() + self.b1.capacity() + self.w2.capacity() + self.b2.capacity())
+            * size_of::<f32>()
+    }
+
+    fn params_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.w1
+            .iter_mut()
+            .chain(&mut self.b1)
+            .chain(&mut self.w2)
+            .chain(&mut self.b2)
+    }
+
+    fn params(&self) -> impl Iterator<Item = &f32> {
+        self.w1
+            .iter()
+            .chain(&self.b1)
+            .chain(&self.w2)
+            .chain(&self.b2)
+    }
+
+    /// Every weight and bias in one vector, in the order `to_text` writes
+    /// them.
+    pub fn weights(&self) -> Vec<f32> {
+        self.params().copied().collect()
+    }
+
+    /// A policy of the same layer sizes with `weights` in the order
+    /// `weights` returns them, or `None` if there are not exactly as many.
+    pub fn with_weights(&self, weights: &[f32]) -> Option<Policy> {
+        if weights.len() != self.params().count() {
+            return None;
+        }
+        let mut out = self.clone();
+        for (w, &value) in out.params_mut().zip(weights) {
+            *w = value;
+        }
+        Some(out)
+    }
+
+    /// Draw every weight uniformly from `-scale..scale`.
+    pub fn randomize(&mut self, rng: &mut Rng, scale: f32) {
+        for w in self.params_mut() {
+            *w = (rng.next_f32() * 2.0 - 1.0) * scale;
+        }
+    }
+
+    /// Add independent normal noise with standard deviation `sigma` to every
+    /// weight.
+    pub fn perturb(&mut self, rng: &mut Rng, sigma: f32) {
+        for w in self.params_mut() {
+            // Box-Muller normal from two uniform draws.
+            let (u, v) = (rng.next_f32(), rng.next_f32());
+            let normal = (-2.0 * (1.0 - u).max(f32::MIN_POSITIVE).ln()).sqrt()
+                * (std::f32::consts::TAU * v).cos();
+            *w += normal * sigma;
+        }
+    }
+
+    /// The policy a fraction `t` of the way from `a` to `b`, weight by
+    /// weight, or `None` if their layer sizes differ.
+    pub fn lerp(a: &Policy, b: &Policy, t: f32) -> Option<Policy> {
+        if (a.input_size, a.hidden_size) != (b.input_size, b.hidden_size) {
+            return None;
+        }
+        let mut out = a.clone();
+        for (w, &to) in out.params_mut().zip(b.params()) {
+            *w += (to - *w) * t;
+        }
+        Some(out)
+    }
+
+    /// Root mean square difference between the weights of `self` and
+    /// `other`, or `None` if their layer sizes differ.
+    pub fn distance(&self, other: &Policy) -> Option<f32> {
+        if (self.input_size, self.hidden_size) != (other.input_size, other.hidden_size) {
+            return None;
+        }
+        let (sum, count) = self
+            .params()
+            .zip(other.params())
+            .fold((0.0, 0usize), |(sum, n), (a, b)| {
+                (sum + (a - b) * (a - b), n + 1)
+            });
+        Some((sum / count.max(1) as f32).sqrt())
+    }
+
+    pub fn forward(&self, input: &[f32]) -> Vec2f {
+        let hidden: Vec<f32> = self
+            .w1
+            .chunks_exact(self.input_size)
+            .zip(&self.b1)
+            .map(|(row, b)| {
+                let acc = row.iter().zip(input).fold(*b, |acc, (w, x)| acc + w * x);
+                acc.tanh()
+            })
+            .collect();
+
+        let mut out = [0.0; 2];
+        for ((o, row), b) in out
+            .iter_mut()
+            .zip(self.w2.chunks_exact(self.hidden_size))
+            .zip(&self.b2)
+        {
+            let acc = row.iter().zip(&hidden).fold(*b, |acc, (w, h)| acc + w * h);
+            *o = acc.tanh();
+        }
+        Vec2f::new(out[0], out[1])
+    }
+
+    /// The policy as text: a `policy <inputs> <hidden>` header, then one
+    /// line each for the hidden weights, hidden biases, output weights and
+    /// output biases.
+    pub fn to_text(&self) -> String {
+        let mut out = format!("policy {} {}\n", self.input_size, self.hidden_size);
+        for layer in [&self.w1, &self.b1, &self.w2, &self.b2] {
+            let line: Vec<String> = layer.iter().map(|w| w.to_string()).collect();
+            let _ = writeln!(out, "{}", line.join(" "));
+        }
+        out
+    }
+
+    /// Write the policy to `path` in the `to_text` format.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_text())
+            .map_err(|err| format!("failed to write {}: {err}", path.display()))
+    }
+
+    /// Read a policy file written by `save`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        Self::parse(&text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Read a policy written by `to_text`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<&str> = lines
+            .next()
+            .ok_or("empty policy file")?
+            .split_whitespace()
+            .collect();
+        let [tag, inputs, hidden] = header[..] else {
+            return Err("expected a `policy <inputs> <hidden>` header".to_string());
+        };
+        if tag != "policy" {
+            return Err("expected a `policy <inputs> <hidden>` header".to_string());
+        }
+        let size = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| format!("bad layer size `{s}`"))
+        };
+        let mut policy = Policy::new(size(inputs)?, size(hidden)?);
+        let names = [
+            "hidden weights",
+            "hidden biases",
+            "output weights",
+            "output biases",
+        ];
+        for (name, layer) in names.into_iter().zip([
+            &mut policy.w1,
+            &mut policy.b1,
+            &mut policy.w2,
+            &mut policy.b2,
+        ]) {
+            let values = lines
+                .next()
+                .ok_or_else(|| format!("missing {name}"))?
+                .split_whitespace()
+                .map(|s| s.parse::<f32>().map_err(|_| format!("bad weight `{s}`")))
+                .collect::<Result<Vec<f32>, String>>()?;
+            if values.len() != layer.len() {
+                return Err(format!(
+                    "expected {} {name}, found {}",
+                    layer.len(),
+                    values.len()
+                ));
+            }
+            *layer = values;
+        }
+        Ok(policy)
+    }
+}
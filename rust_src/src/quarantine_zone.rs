@@ -0,0 +1,87 @@
+use crate::sim::Vec2f;
+
+/// Name of the tag value holding the quarantine zone an infected boid is
+/// confined to: 1-based, 0 for none.
+pub const CONFINED_TAG: &str = "confined";
+
+/// Width of the band along each wall over which the push builds up.
+const MARGIN: f32 = 30.0;
+/// Cap on the push, as a multiple of `STRENGTH`, for confined boids well
+/// outside their zone.
+const MAX_PUSH: f32 = 3.0;
+/// Wall push at full depth, as a multiple of `max_force`.
+const STRENGTH: f32 = 4.0;
+
+/// A box in world coordinates that keeps infected boids in and everyone
+/// else out. An infected boid found inside is confined to it until it
+/// recovers; boids outside are turned away from its walls. Zones do not wrap
+/// around the world edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuarantineZone {
+    pub min: Vec2f,
+    pub max: Vec2f,
+}
+
+impl QuarantineZone {
+    /// The box spanned by corners `a` and `b`, in any order.
+    pub fn new(a: Vec2f, b: Vec2f) -> Self {
+        Self {
+            min: Vec2f::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Vec2f::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    /// Parse the corners as `x0,y0,x1,y1`, e.g. `100,100,300,250`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let values = spec
+            .split(',')
+            .map(|s| s.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid zone {spec}: expected x0,y0,x1,y1"))?;
+        let [x0, y0, x1, y1] = values[..] else {
+            return Err(format!("invalid zone {spec}: expected x0,y0,x1,y1"));
+        };
+        Ok(Self::new(Vec2f::new(x0, y0), Vec2f::new(x1, y1)))
+    }
+
+    pub fn contains(&self, pos: Vec2f) -> bool {
+        pos.x >= self.min.x && pos.x <= self.max.x && pos.y >= self.min.y && pos.y <= self.max.y
+    }
+
+    /// Acceleration keeping a boid at `pos` inside, given the simulation's
+    /// `max_force`. Zero in the interior; builds up over the band inside
+    /// each wall and keeps growing past it.
+    pub fn confine(&self, pos: Vec2f, max_force: f32) -> Vec2f {
+        let axis = |p: f32, lo: f32, hi: f32| {
+            let half = (hi - lo) / 2.0;
+            let u = p - (lo + half);
+            let inner = (half - MARGIN).max(0.0);
+            let depth = if u.abs() <= inner {
+                0.0
+            } else {
+                ((u.abs() - inner) / MARGIN).min(MAX_PUSH)
+            };
+            -u.signum() * depth
+        };
+        Vec2f::new(
+            axis(pos.x, self.min.x, self.max.x),
+            axis(pos.y, self.min.y, self.max.y),
+        )
+        .mul(STRENGTH * max_force)
+    }
+
+    /// Acceleration turning a boid at `pos` away from the zone: strongest
+    /// at the walls, fading to zero `MARGIN` out, and zero inside.
+    pub fn repel(&self, pos: Vec2f, max_force: f32) -> Vec2f {
+        let nearest = Vec2f::new(
+            pos.x.clamp(self.min.x, self.max.x),
+            pos.y.clamp(self.min.y, self.max.y),
+        );
+        let away = pos.sub(nearest);
+        let dist = away.length();
+        if dist <= 0.0 || dist >= MARGIN {
+            return Vec2f::default();
+        }
+        away.mul((1.0 - dist / MARGIN) * STRENGTH * max_force / dist)
+    }
+}
@@ -0,0 +1,460 @@
+use std::io;
+use std::path::Path;
+
+use crate::sim::{HealthState, Simulation, Vec2f};
+
+const MAGIC: &[u8; 8] = b"BOIDREC1";
+/// Positions are stored in 1/16 world units, velocities in 1/16 units per
+/// second: well below a pixel, and small enough that a tick's change fits
+/// in a byte or two.
+const SCALE: f32 = 16.0;
+/// Frames between full frames, bounding how far a seek has to decode.
+const KEYFRAME_EVERY: usize = 120;
+
+const KEY: u8 = 0;
+const DELTA: u8 = 1;
+
+/// One boid of a recorded frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordedBoid {
+    pub pos: Vec2f,
+    pub vel: Vec2f,
+    pub state: HealthState,
+}
+
+/// Position and velocity in fixed point, as written.
+type Quantized = [i32; 4];
+
+fn quantize(pos: Vec2f, vel: Vec2f) -> Quantized {
+    [pos.x, pos.y, vel.x, vel.y].map(|v| (v * SCALE).round() as i32)
+}
+
+fn state_code(state: HealthState) -> u8 {
+    match state {
+        HealthState::Susceptible => 0,
+        HealthState::Exposed => 1,
+        HealthState::Infected => 2,
+        HealthState::Recovered => 3,
+    }
+}
+
+fn state_from(code: u8) -> Result<HealthState, String> {
+    match code {
+        0 => Ok(HealthState::Susceptible),
+        1 => Ok(HealthState::Exposed),
+        2 => Ok(HealthState::Infected),
+        3 => Ok(HealthState::Recovered),
+        _ => Err(format!("bad health state {code}")),
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+fn put_signed(out: &mut Vec<u8>, v: i32) {
+    put_varint(out, ((v << 1) ^ (v >> 31)) as u32 as u64);
+}
+
+/// Captures a run tick by tick into the compact binary format `Recording`
+/// reads. Every `KEYFRAME_EVERY`th frame, and any frame where the
+/// population changed, holds every boid in full; the rest hold only the
+/// change in each boid's position and velocity since the frame before and
+/// the boids whose health changed.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    bytes: Vec<u8>,
+    prev: Vec<Quantized>,
+    prev_states: Vec<HealthState>,
+    since_key: usize,
+    frames: usize,
+}
+
+impl Recorder {
+    /// An empty recording of a world of `world` size.
+    pub fn new(world: Vec2f) -> Self {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(world.x.to_le_bytes());
+        bytes.extend(world.y.to_le_bytes());
+        Self {
+            bytes,
+            prev: Vec::new(),
+            prev_states: Vec::new(),
+            since_key: 0,
+            frames: 0,
+        }
+    }
+
+    /// Append the current state of `sim` as the next frame.
+    pub fn record(&mut self, sim: &Simulation) {
+        let now: Vec<Quantized> = sim.boids.iter().map(|b| quantize(b.pos, b.vel)).collect();
+        let states: Vec<HealthState> = sim.boids.iter().map(|b| b.state).collect();
+        let key =
+            self.frames == 0 || self.since_key >= KEYFRAME_EVERY || now.len() != self.prev.len();
+        let out = &mut self.bytes;
+        out.push(if key { KEY } else { DELTA });
+        out.extend(sim.time().to_le_bytes());
+        if key {
+            put_varint(out, now.len() as u64);
+            for (q, &state) in now.iter().zip(&states) {
+                for v in q {
+                    put_signed(out, *v);
+                }
+                out.push(state_code(state));
+            }
+            self.since_key = 0;
+        } else {
+            for (q, p) in now.iter().zip(&self.prev) {
+                for k in 0..4 {
+                    put_signed(out, q[k].wrapping_sub(p[k]));
+                }
+            }
+            let changed: Vec<usize> = (0..states.len())
+                .filter(|&i| states[i] != self.prev_states[i])
+                .collect();
+            put_varint(out, changed.len() as u64);
+            let mut last = 0;
+            for i in changed {
+                put_varint(out, (i - last) as u64);
+                out.push(state_code(states[i]));
+                last = i;
+            }
+        }
+        self.since_key += 1;
+        self.frames += 1;
+        self.prev = now;
+        self.prev_states = states;
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, &self.bytes)
+    }
+}
+
+/// Reads through the encoded bytes, failing on truncation.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.bytes.get(self.at).ok_or("recording is truncated")?;
+        self.at += 1;
+        Ok(b)
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        let raw = self
+            .bytes
+            .get(self.at..self.at + 4)
+            .ok_or("recording is truncated")?;
+        self.at += 4;
+        Ok(f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err("bad varint in recording".to_string())
+    }
+
+    fn signed(&mut self) -> Result<i32, String> {
+        let v = self.varint()? as u32;
+        Ok((v >> 1) as i32 ^ -((v & 1) as i32))
+    }
+
+    /// Decode the frame at the cursor onto `boids`, which must hold the
+    /// frame before it unless this is a full frame.
+    fn frame(&mut self, boids: &mut Vec<(Quantized, HealthState)>) -> Result<(bool, f32), String> {
+        let kind = self.byte()?;
+        let time = self.f32()?;
+        match kind {
+            KEY => {
+                let n = self.varint()? as usize;
+                boids.clear();
+                for _ in 0..n {
+                    let mut q = [0; 4];
+                    for v in &mut q {
+                        *v = self.signed()?;
+                    }
+                    boids.push((q, state_from(self.byte()?)?));
+                }
+            }
+            DELTA => {
+                for (q, _) in boids.iter_mut() {
+                    for v in q.iter_mut() {
+                        *v = v.wrapping_add(self.signed()?);
+                    }
+                }
+                let changed = self.varint()?;
+                let mut i = 0;
+                for _ in 0..changed {
+                    i += self.varint()? as usize;
+                    let state = state_from(self.byte()?)?;
+                    boids
+                        .get_mut(i)
+                        .ok_or("state change past the population")?
+                        .1 = state;
+                }
+            }
+            _ => return Err(format!("bad frame kind {kind}")),
+        }
+        Ok((kind == KEY, time))
+    }
+}
+
+/// Where a frame starts and the full frame it is decoded from.
+#[derive(Clone, Copy, Debug)]
+struct FrameEntry {
+    offset: usize,
+    time: f32,
+    keyframe: usize,
+}
+
+/// A recorded run, indexed for seeking. Frames are decoded on demand.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    world: Vec2f,
+    bytes: Vec<u8>,
+    frames: Vec<FrameEntry>,
+}
+
+impl Recording {
+    pub fn decode(bytes: Vec<u8>) -> Result<Self, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("not a boids recording".to_string());
+        }
+        let mut reader = Reader {
+            bytes: &bytes,
+            at: MAGIC.len(),
+        };
+        let world = Vec2f::new(reader.f32()?, reader.f32()?);
+        let mut frames = Vec::new();
+        let mut boids = Vec::new();
+        let mut keyframe = 0;
+        while reader.at < bytes.len() {
+            let offset = reader.at;
+            if frames.is_empty() && bytes[offset] != KEY {
+                return Err("recording does not start with a full frame".to_string());
+            }
+            let (key, time) = reader.frame(&mut boids)?;
+            if key {
+                keyframe = frames.len();
+            }
+            frames.push(FrameEntry {
+                offset,
+                time,
+                keyframe,
+            });
+        }
+        Ok(Self {
+            world,
+            bytes,
+            frames,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        Self::decode(bytes).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn world(&self) -> Vec2f {
+        self.world
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Simulated time of frame `index`.
+    pub fn time(&self, index: usize) -> f32 {
+        self.frames[index].time
+    }
+
+    /// The last frame at or before `time`, or the first frame.
+    pub fn frame_at(&self, time: f32) -> usize {
+        self.frames
+            .partition_point(|f| f.time <= time)
+            .saturating_sub(1)
+    }
+}
+
+/// A playback position in a `Recording` and the boids of that frame.
+/// Stepping forward decodes one frame; seeking elsewhere decodes from the
+/// nearest full frame.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    recording: Recording,
+    index: usize,
+    boids: Vec<(Quantized, HealthState)>,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Result<Self, String> {
+        if recording.is_empty() {
+            return Err("recording has no frames".to_string());
+        }
+        let mut replay = Self {
+            recording,
+            index: 0,
+            boids: Vec::new(),
+        };
+        replay.decode_range(0, 0);
+        Ok(replay)
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn time(&self) -> f32 {
+        self.recording.time(self.index)
+    }
+
+    /// Move to frame `index`, clamped to the recording.
+    pub fn seek(&mut self, index: usize) {
+        let index = index.min(self.recording.len() - 1);
+        let keyframe = self.recording.frames[index].keyframe;
+        if index > self.index && keyframe <= self.index {
+            self.decode_range(self.index + 1, index);
+        } else if index != self.index {
+            self.decode_range(keyframe, index);
+        }
+        self.index = index;
+    }
+
+    /// Decode frames `from..=to` in order onto the current boids. The
+    /// recording was checked end to end when it was loaded.
+    fn decode_range(&mut self, from: usize, to: usize) {
+        let mut reader = Reader {
+            bytes: &self.recording.bytes,
+            at: self.recording.frames[from].offset,
+        };
+        for _ in from..=to {
+            reader
+                .frame(&mut self.boids)
+                .expect("recording validated on load");
+        }
+    }
+
+    /// Every boid of the current frame.
+    pub fn boids(&self) -> impl Iterator<Item = RecordedBoid> + '_ {
+        self.boids.iter().map(|&(q, state)| {
+            let [px, py, vx, vy] = q.map(|v| v as f32 / SCALE);
+            RecordedBoid {
+                pos: Vec2f::new(px, py),
+                vel: Vec2f::new(vx, vy),
+                state,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimConfig;
+
+    /// `sim`'s boids as a replay shows them after quantization.
+    fn quantized(sim: &Simulation) -> Vec<RecordedBoid> {
+        sim.boids
+            .iter()
+            .map(|b| {
+                let [px, py, vx, vy] = quantize(b.pos, b.vel).map(|v| v as f32 / SCALE);
+                RecordedBoid {
+                    pos: Vec2f::new(px, py),
+                    vel: Vec2f::new(vx, vy),
+                    state: b.state,
+                }
+            })
+            .collect()
+    }
+
+    /// A run past two keyframe intervals, with a boid added partway.
+    fn recorded() -> (Vec<u8>, Vec<Vec<RecordedBoid>>) {
+        let mut sim = Simulation::new(30, SimConfig::default(), 4);
+        let mut recorder = Recorder::new(sim.config().world_size);
+        let mut expected = Vec::new();
+        for step in 0..2 * KEYFRAME_EVERY + 30 {
+            if step == 150 {
+                sim.spawn_boid(
+                    Vec2f::new(10.0, 10.0),
+                    Vec2f::new(1.0, 0.0),
+                    HealthState::Infected,
+                );
+            }
+            sim.step(1.0 / 30.0);
+            recorder.record(&sim);
+            expected.push(quantized(&sim));
+        }
+        (recorder.bytes().to_vec(), expected)
+    }
+
+    fn frame(replay: &Replay) -> Vec<RecordedBoid> {
+        replay.boids().collect()
+    }
+
+    #[test]
+    fn recordings_decode_to_the_quantized_run() {
+        let (bytes, expected) = recorded();
+        let mut replay = Replay::new(Recording::decode(bytes).unwrap()).unwrap();
+        assert_eq!(replay.recording().len(), expected.len());
+        for (i, boids) in expected.iter().enumerate() {
+            replay.seek(i);
+            assert_eq!(&frame(&replay), boids, "frame {i}");
+        }
+        assert!(expected.iter().any(|boids| boids.len() == 31));
+    }
+
+    #[test]
+    fn seeking_back_matches_decoding_forward() {
+        let (bytes, expected) = recorded();
+        let mut replay = Replay::new(Recording::decode(bytes).unwrap()).unwrap();
+        for index in [200, 130, KEYFRAME_EVERY, 5, 160, 149] {
+            replay.seek(index);
+            assert_eq!(replay.index(), index);
+            assert_eq!(frame(&replay), expected[index], "frame {index}");
+        }
+    }
+
+    #[test]
+    fn truncated_recordings_are_rejected() {
+        let (bytes, _) = recorded();
+        assert!(Recording::decode(bytes[..bytes.len() - 1].to_vec()).is_err());
+        assert!(Recording::decode(bytes[..MAGIC.len() + 2].to_vec()).is_err());
+        // Cuts on a frame boundary leave a shorter valid recording; anywhere
+        // else must fail cleanly rather than panic.
+        for cut in (MAGIC.len()..bytes.len()).step_by(97) {
+            let _ = Recording::decode(bytes[..cut].to_vec());
+        }
+    }
+}
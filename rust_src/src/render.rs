@@ -0,0 +1,80 @@
+use crate::history::History;
+use crate::sim::{Simulation, SirCounts};
+
+/// What a frame of the application draws. The live view implements it with
+/// macroquad; `NullRenderer` only counts what it is given, so frame code
+/// written against it also runs headless, in tests and CI.
+pub trait Renderer {
+    /// The boids of `sim`, dimmed where `shown` is false.
+    fn draw_boids(&mut self, sim: &Simulation, shown: &[bool]);
+    /// Status and hint lines over the world.
+    fn draw_overlay(&mut self, lines: &[String]);
+    /// Population curves through `history`, scaled to `total` boids.
+    fn draw_graph(&mut self, history: &History<SirCounts>, total: usize);
+}
+
+/// A renderer that draws nothing and tallies its calls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NullRenderer {
+    pub frames: usize,
+    /// Boids passed to `draw_boids`, summed over frames.
+    pub boids: usize,
+    /// Of those, the ones drawn dimmed.
+    pub dimmed: usize,
+    pub overlay_lines: usize,
+    /// Samples the latest graph held.
+    pub graph_samples: usize,
+}
+
+impl Renderer for NullRenderer {
+    fn draw_boids(&mut self, sim: &Simulation, shown: &[bool]) {
+        assert_eq!(shown.len(), sim.boids.len(), "one shown flag per boid");
+        self.frames += 1;
+        self.boids += sim.boids.len();
+        self.dimmed += shown.iter().filter(|&&s| !s).count();
+    }
+
+    fn draw_overlay(&mut self, lines: &[String]) {
+        self.overlay_lines += lines.len();
+    }
+
+    fn draw_graph(&mut self, history: &History<SirCounts>, _total: usize) {
+        self.graph_samples = history.len();
+    }
+}
+
+/// Draw one frame of `sim` through `renderer`: every boid, then `overlay`,
+/// then the graph of `history`.
+pub fn draw_frame(
+    renderer: &mut impl Renderer,
+    sim: &Simulation,
+    history: &History<SirCounts>,
+    overlay: &[String],
+) {
+    renderer.draw_boids(sim, &vec![true; sim.boids.len()]);
+    renderer.draw_overlay(overlay);
+    renderer.draw_graph(history, sim.boids.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{SimConfig, TICK};
+
+    #[test]
+    fn null_renderer_runs_the_frame_loop() {
+        let mut sim = Simulation::new(50, SimConfig::default(), 7);
+        let mut history = History::new(8);
+        let mut renderer = NullRenderer::default();
+        for _ in 0..20 {
+            sim.advance(TICK);
+            history.push(sim.time(), sim.counts());
+            draw_frame(&mut renderer, &sim, &history, &["status".to_string()]);
+        }
+        assert_eq!(renderer.frames, 20);
+        assert_eq!(renderer.boids, 20 * 50);
+        assert_eq!(renderer.dimmed, 0);
+        assert_eq!(renderer.overlay_lines, 20);
+        assert_eq!(renderer.graph_samples, history.len());
+    }
+}
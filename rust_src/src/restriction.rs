@@ -0,0 +1,111 @@
+use boid_math::torus_delta;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::Vec2f;
+
+/// Name of the tag value holding the zone each boid is confined to while a
+/// travel restriction is on: 1-based, 0 for unconfined.
+pub const ZONE_TAG: &str = "zone";
+
+/// Width of the band inside each zone border over which the push back
+/// builds up.
+const MARGIN: f32 = 30.0;
+/// Cap on the push, as a multiple of `strength`, for boids well outside
+/// their zone.
+const MAX_PUSH: f32 = 3.0;
+
+/// Confines boids to the zone of an `nx` by `ny` grid they are in when the
+/// restriction starts, with soft walls that push them back from the zone
+/// borders. Starts when prevalence reaches `threshold`, or by hand.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TravelRestriction {
+    pub zones: (usize, usize),
+    /// Infected fraction at which the restriction starts on its own; `None`
+    /// for manual only. It triggers at most once per run.
+    pub threshold: Option<f32>,
+    /// Seconds it stays on; 0 until lifted by hand.
+    pub duration: f32,
+    /// Wall push at full depth, as a multiple of `max_force`.
+    pub strength: f32,
+}
+
+impl Default for TravelRestriction {
+    fn default() -> Self {
+        Self {
+            zones: (2, 2),
+            threshold: None,
+            duration: 0.0,
+            strength: 4.0,
+        }
+    }
+}
+
+impl TravelRestriction {
+    /// Parse `NxM[:threshold[:duration[:strength]]]`, e.g. `2x2:0.05:30`;
+    /// `manual` as the threshold leaves starting it to the user.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec.split(':');
+        let grid = parts.next().unwrap_or_default();
+        let (nx, ny) = grid
+            .split_once('x')
+            .and_then(|(x, y)| Some((x.parse::<usize>().ok()?, y.parse::<usize>().ok()?)))
+            .filter(|&(x, y)| x > 0 && y > 0)
+            .ok_or_else(|| format!("expected zones as NxM, got {grid}"))?;
+        let num = |s: &str| s.parse::<f32>().map_err(|_| format!("invalid number: {s}"));
+        let mut out = Self {
+            zones: (nx, ny),
+            ..Self::default()
+        };
+        if let Some(threshold) = parts.next().filter(|&s| s != "manual") {
+            out.threshold = Some(num(threshold)?.clamp(0.0, 1.0));
+        }
+        if let Some(duration) = parts.next() {
+            out.duration = num(duration)?.max(0.0);
+        }
+        if let Some(strength) = parts.next() {
+            out.strength = num(strength)?.max(0.0);
+        }
+        Ok(out)
+    }
+
+    pub fn zone_count(&self) -> usize {
+        self.zones.0 * self.zones.1
+    }
+
+    /// Index of the zone holding `pos`.
+    pub fn zone_of(&self, pos: Vec2f, world: Vec2f) -> usize {
+        let (nx, ny) = self.zones;
+        let cell = |v: f32, extent: f32, n: usize| {
+            ((v.rem_euclid(extent) / extent * n as f32) as usize).min(n - 1)
+        };
+        cell(pos.y, world.y, ny) * nx + cell(pos.x, world.x, nx)
+    }
+
+    /// Acceleration keeping a boid at `pos` inside `zone`, given the
+    /// simulation's `max_force`. Zero away from the borders; axes with a
+    /// single zone have no border.
+    pub fn push(&self, pos: Vec2f, zone: usize, world: Vec2f, max_force: f32) -> Vec2f {
+        let (nx, ny) = self.zones;
+        let size = Vec2f::new(world.x / nx as f32, world.y / ny as f32);
+        let center = Vec2f::new(
+            ((zone % nx) as f32 + 0.5) * size.x,
+            ((zone / nx) as f32 + 0.5) * size.y,
+        );
+        let local = torus_delta(center, pos, world);
+        let axis = |u: f32, half: f32, n: usize| {
+            let inner = (half - MARGIN).max(0.0);
+            let depth = if n < 2 || u.abs() <= inner {
+                0.0
+            } else {
+                ((u.abs() - inner) / MARGIN).min(MAX_PUSH)
+            };
+            -u.signum() * depth
+        };
+        Vec2f::new(
+            axis(local.x, size.x / 2.0, nx),
+            axis(local.y, size.y / 2.0, ny),
+        )
+        .mul(self.strength * max_force)
+    }
+}
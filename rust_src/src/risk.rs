@@ -0,0 +1,67 @@
+use crate::sim::{HealthState, Simulation};
+
+/// Seconds over which exposure fades out of a risk score.
+pub const RISK_WINDOW: f32 = 30.0;
+
+/// Rolling infection risk per boid: seconds spent within the infection
+/// radius of infectious boids, counted once per infectious neighbor, with
+/// older exposure decaying over `RISK_WINDOW` so boids that leave the
+/// outbreak rank lower again. Quarantined boids neither give nor take
+/// exposure, as in the model itself.
+///
+/// Scores are kept by boid index, like `ContactNetwork`.
+#[derive(Clone, Debug, Default)]
+pub struct RiskScores {
+    scores: Vec<f32>,
+}
+
+impl RiskScores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decay every score by `dt`, then add `dt` for each infectious boid
+    /// currently within infection radius.
+    pub fn record(&mut self, sim: &Simulation, dt: f32) {
+        self.scores.resize(sim.boids.len(), 0.0);
+        let keep = (-dt / RISK_WINDOW).exp();
+        for score in &mut self.scores {
+            *score *= keep;
+        }
+        let infectious = |i: usize| {
+            let b = &sim.boids[i];
+            b.state == HealthState::Infected && !b.quarantined
+        };
+        for (a, b) in sim.contact_pairs(sim.config().infection_radius) {
+            if sim.boids[a].quarantined || sim.boids[b].quarantined {
+                continue;
+            }
+            if infectious(b) {
+                self.scores[a] += dt;
+            }
+            if infectious(a) {
+                self.scores[b] += dt;
+            }
+        }
+    }
+
+    pub fn score(&self, index: usize) -> f32 {
+        self.scores.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Up to `n` susceptible boids with a positive score, highest first, as
+    /// `(index, score)`.
+    pub fn ranked(&self, sim: &Simulation, n: usize) -> Vec<(usize, f32)> {
+        let mut ranked: Vec<(usize, f32)> = sim
+            .boids
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.state == HealthState::Susceptible)
+            .map(|(i, _)| (i, self.score(i)))
+            .filter(|&(_, s)| s > 0.0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked.truncate(n);
+        ranked
+    }
+}
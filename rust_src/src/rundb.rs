@@ -0,0 +1,495 @@
+use std::fmt;
+use std::path::Path;
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, params_from_iter};
+
+use crate::immunization::Immunization;
+use crate::sim::{Boundaries, Boundary, MovementModel, SimConfig, SirCounts};
+use crate::stats::EpidemicSummary;
+
+/// Columns of the `runs` table and their SQL types. Stores written before a
+/// column was added get it on open, empty for the runs already in them.
+pub const COLUMNS: [(&str, &str); 24] = [
+    ("timestamp", "INTEGER"),
+    ("boids", "INTEGER"),
+    ("steps", "INTEGER"),
+    ("seed", "INTEGER"),
+    ("initial_infected", "INTEGER"),
+    ("infection_radius", "REAL"),
+    ("infection_beta", "REAL"),
+    ("infectious_period", "REAL"),
+    ("movement", "TEXT"),
+    ("boundary", "TEXT"),
+    ("immunize", "TEXT"),
+    ("day_night", "TEXT"),
+    ("venues", "INTEGER"),
+    ("time", "REAL"),
+    ("susceptible", "INTEGER"),
+    ("infected", "INTEGER"),
+    ("recovered", "INTEGER"),
+    ("attack_rate", "REAL"),
+    ("peak_prevalence", "REAL"),
+    ("peak_time", "REAL"),
+    ("duration", "REAL"),
+    ("mean_field_r0", "REAL"),
+    ("growth_r0", "REAL"),
+    ("offspring_r0", "REAL"),
+];
+
+/// One finished headless run: what was simulated and how it turned out.
+pub struct RunRecord<'a> {
+    /// Seconds since the Unix epoch when the run finished.
+    pub timestamp: u64,
+    pub boids: usize,
+    /// The step limit the run was given.
+    pub steps: usize,
+    pub seed: u64,
+    pub config: &'a SimConfig,
+    pub venues: usize,
+    /// Simulated time at the end of the run.
+    pub time: f32,
+    pub counts: SirCounts,
+    /// `None` when the epidemic was still active at the end.
+    pub summary: Option<&'a EpidemicSummary>,
+}
+
+/// `v` through its shortest decimal form, so `infection_beta=0.3` matches a
+/// run given 0.3 rather than the nearest f32.
+fn real(v: f32) -> Value {
+    Value::Real(v.to_string().parse().unwrap_or(f64::from(v)))
+}
+
+fn integer(v: usize) -> Value {
+    Value::Integer(v as i64)
+}
+
+impl RunRecord<'_> {
+    /// Values in `COLUMNS` order; `NULL` for missing estimates.
+    fn values(&self) -> Vec<Value> {
+        let cfg = self.config;
+        let opt = |v: Option<f32>| v.map_or(Value::Null, real);
+        let summary = |f: fn(&EpidemicSummary) -> f32| opt(self.summary.map(f));
+        vec![
+            // SQLite integers are signed 64-bit; seeds keep their bits.
+            Value::Integer(self.timestamp as i64),
+            integer(self.boids),
+            integer(self.steps),
+            Value::Integer(self.seed as i64),
+            integer(cfg.initial_infected),
+            real(cfg.infection_radius),
+            real(cfg.infection_beta),
+            real(cfg.infectious_period),
+            Value::Text(movement_spec(cfg.movement)),
+            Value::Text(boundaries_spec(cfg.boundaries)),
+            Value::Text(immunization_spec(cfg.immunization)),
+            Value::Text(cfg.schedule.map_or("off".into(), |s| {
+                format!("{}:{}", s.day_length, s.night_fraction)
+            })),
+            integer(self.venues),
+            real(self.time),
+            integer(self.counts.susceptible),
+            integer(self.counts.infected),
+            integer(self.counts.recovered),
+            summary(|s| s.final_attack_rate),
+            summary(|s| s.peak_prevalence),
+            summary(|s| s.peak_time),
+            summary(|s| s.duration),
+            summary(|s| s.mean_field_r0),
+            opt(self.summary.and_then(|s| s.growth_r0)),
+            opt(self.summary.and_then(|s| s.offspring_r0)),
+        ]
+    }
+}
+
+/// What `RunStore::query` lists: the runs passing every filter, optionally
+/// sorted and cut to `limit`, showing `columns` (every column if empty).
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub filters: Vec<Filter>,
+    /// Column to sort by, and whether descending. Missing values sort last.
+    pub sort: Option<(String, bool)>,
+    pub limit: Option<usize>,
+    pub columns: Vec<String>,
+}
+
+/// An SQLite database of finished runs, one row per run in table `runs`.
+pub struct RunStore {
+    conn: Connection,
+    /// Every column of `runs` but the row id, in table order.
+    columns: Vec<String>,
+}
+
+impl RunStore {
+    /// Open the store at `path`, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|err| format!("failed to open {}: {err}", path.display()))?;
+        Self::with_connection(conn).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Create `runs` if it is missing and add any column it lacks.
+    fn with_connection(conn: Connection) -> Result<Self, String> {
+        let sql_err = |err: rusqlite::Error| err.to_string();
+        let defs: Vec<String> = COLUMNS
+            .iter()
+            .map(|(name, ty)| format!("{name} {ty}"))
+            .collect();
+        conn.execute(
+            &format!(
+                "CREATE TABLE IF NOT EXISTS runs (id INTEGER PRIMARY KEY, {})",
+                defs.join(", ")
+            ),
+            [],
+        )
+        .map_err(sql_err)?;
+        let mut store = Self {
+            conn,
+            columns: Vec::new(),
+        };
+        store.columns = store.table_columns().map_err(sql_err)?;
+        for (name, ty) in COLUMNS {
+            if !store.columns.iter().any(|c| c == name) {
+                store
+                    .conn
+                    .execute(&format!("ALTER TABLE runs ADD COLUMN {name} {ty}"), [])
+                    .map_err(sql_err)?;
+            }
+        }
+        store.columns = store.table_columns().map_err(sql_err)?;
+        Ok(store)
+    }
+
+    fn table_columns(&self) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name FROM pragma_table_info('runs')")?;
+        let names = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        names
+            .filter(|name| !matches!(name, Ok(n) if n == "id"))
+            .collect()
+    }
+
+    /// Every column of the store, including any added by newer builds.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// `name` quoted for SQL, if the store has such a column.
+    fn column(&self, name: &str) -> Result<String, String> {
+        self.columns
+            .iter()
+            .find(|c| *c == name)
+            .map(|c| format!("\"{c}\""))
+            .ok_or_else(|| format!("unknown column: {name}"))
+    }
+
+    pub fn insert(&self, record: &RunRecord) -> Result<(), String> {
+        let names: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+        let sql = format!(
+            "INSERT INTO runs ({}) VALUES ({})",
+            names.join(", "),
+            vec!["?"; names.len()].join(", ")
+        );
+        self.conn
+            .execute(&sql, params_from_iter(record.values()))
+            .map(|_| ())
+            .map_err(|err| format!("failed to record run: {err}"))
+    }
+
+    pub fn query(&self, query: &Query) -> Result<RunTable, String> {
+        let columns = if query.columns.is_empty() {
+            self.columns.clone()
+        } else {
+            query.columns.clone()
+        };
+        let selected = columns
+            .iter()
+            .map(|c| self.column(c))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut sql = format!("SELECT {} FROM runs", selected.join(", "));
+        let mut params = Vec::new();
+        for (k, filter) in query.filters.iter().enumerate() {
+            sql.push_str(if k == 0 { " WHERE " } else { " AND " });
+            sql.push_str(&format!(
+                "{} {} ?",
+                self.column(&filter.column)?,
+                filter.op.sql()
+            ));
+            params.push(filter.param());
+        }
+        match &query.sort {
+            Some((column, descending)) => {
+                let column = self.column(column)?;
+                let dir = if *descending { "DESC" } else { "ASC" };
+                sql.push_str(&format!(" ORDER BY {column} IS NULL, {column} {dir}, id"));
+            }
+            None => sql.push_str(" ORDER BY id"),
+        }
+        if let Some(n) = query.limit {
+            sql.push_str(&format!(" LIMIT {n}"));
+        }
+        let sql_err = |err: rusqlite::Error| format!("query failed: {err}");
+        let mut stmt = self.conn.prepare(&sql).map_err(sql_err)?;
+        let rows = stmt
+            .query_map(params_from_iter(params), |row| {
+                (0..columns.len())
+                    .map(|k| row.get::<_, Value>(k).map(cell))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(sql_err)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(sql_err)?;
+        Ok(RunTable { columns, rows })
+    }
+}
+
+/// A stored value as `query` prints it. Reals were stored from f32, so they
+/// print at that precision.
+fn cell(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(v) => v.to_string(),
+        Value::Real(v) => (v as f32).to_string(),
+        Value::Text(s) => s,
+        Value::Blob(b) => format!("<{} bytes>", b.len()),
+    }
+}
+
+/// Runs read back from a store, ready to print.
+#[derive(Clone, Debug, Default)]
+pub struct RunTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl fmt::Display for RunTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|k| {
+                self.rows
+                    .iter()
+                    .map(|r| r[k].len())
+                    .chain([self.columns[k].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for row in std::iter::once(&self.columns).chain(&self.rows) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &w)| format!("{cell:<w$}"))
+                .collect();
+            writeln!(f, "{}", cells.join("  ").trim_end())?;
+        }
+        let n = self.rows.len();
+        write!(f, "{n} run{}", if n == 1 { "" } else { "s" })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn sql(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            // Unlike `!=`, also true for runs missing the value.
+            Op::Ne => "IS NOT",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+}
+
+/// A condition on one column, e.g. `seed=7`, `attack_rate>=0.5` or
+/// `movement!=flocking`. Values that parse as numbers compare as numbers;
+/// runs missing the value only satisfy `!=`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    pub column: String,
+    op: Op,
+    value: String,
+}
+
+impl Filter {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        // Two-character operators first so `<=` is not read as `<`.
+        for (token, op) in [
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("=", Op::Eq),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ] {
+            if let Some((column, value)) = spec.split_once(token)
+                && !column.trim().is_empty()
+            {
+                return Ok(Self {
+                    column: column.trim().to_owned(),
+                    op,
+                    value: value.trim().to_owned(),
+                });
+            }
+        }
+        Err(format!(
+            "expected COLUMN OP VALUE with OP one of = != < <= > >=, got {spec}"
+        ))
+    }
+
+    /// The value bound for the `?` in the condition.
+    fn param(&self) -> Value {
+        if let Ok(v) = self.value.parse::<i64>() {
+            Value::Integer(v)
+        } else if let Ok(v) = self.value.parse::<f64>() {
+            Value::Real(v)
+        } else {
+            Value::Text(self.value.clone())
+        }
+    }
+}
+
+/// The `--movement` spec that parses back to `movement`.
+fn movement_spec(movement: MovementModel) -> String {
+    match movement {
+        MovementModel::Flocking => "flocking".into(),
+        MovementModel::Brownian => "brownian".into(),
+        MovementModel::CorrelatedWalk { turn_sd } => format!("crw:{turn_sd}"),
+        MovementModel::LevyFlight { exponent, .. } => format!("levy:{exponent}"),
+    }
+}
+
+fn boundaries_spec(b: Boundaries) -> String {
+    let name = |b: Boundary| match b {
+        Boundary::Wrap => "wrap",
+        Boundary::Reflect => "reflect",
+        Boundary::Remove => "remove",
+        Boundary::Respawn => "respawn",
+    };
+    if b == Boundaries::uniform(b.left) {
+        name(b.left).into()
+    } else {
+        [b.left, b.right, b.top, b.bottom].map(name).join(",")
+    }
+}
+
+fn immunization_spec(immunization: Immunization) -> String {
+    match immunization {
+        Immunization::None => "none".into(),
+        Immunization::Random(f) => format!("random:{f}"),
+        Immunization::HighestDegree { fraction, burn_in } => {
+            format!("degree:{fraction}:{burn_in}")
+        }
+        Immunization::Ring(f) => format!("ring:{f}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_parse_the_longest_operator() {
+        let f = Filter::parse("attack_rate <= 0.5").unwrap();
+        assert_eq!(
+            (f.column.as_str(), f.op, f.value.as_str()),
+            ("attack_rate", Op::Le, "0.5")
+        );
+        assert_eq!(Filter::parse("movement!=crw:0.3").unwrap().op, Op::Ne);
+        assert_eq!(Filter::parse("seed=7").unwrap().param(), Value::Integer(7));
+        assert_eq!(
+            Filter::parse("beta>0.25").unwrap().param(),
+            Value::Real(0.25)
+        );
+        assert!(Filter::parse("seed").is_err());
+        assert!(Filter::parse("=7").is_err());
+    }
+
+    fn store_with_runs() -> RunStore {
+        let store = RunStore::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        for (seed, beta) in [(1, 0.3), (2, 0.6), (3, 0.9)] {
+            let config = SimConfig {
+                infection_beta: beta,
+                ..SimConfig::default()
+            };
+            store
+                .insert(&RunRecord {
+                    timestamp: 0,
+                    boids: 100,
+                    steps: 10,
+                    seed,
+                    config: &config,
+                    venues: 0,
+                    time: 1.0,
+                    counts: SirCounts::default(),
+                    summary: None,
+                })
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn queries_filter_and_sort_in_sql() {
+        let store = store_with_runs();
+        let table = store
+            .query(&Query {
+                filters: vec![
+                    Filter::parse("infection_beta>=0.3").unwrap(),
+                    Filter::parse("seed!=2").unwrap(),
+                ],
+                sort: Some(("seed".into(), true)),
+                columns: vec!["seed".into(), "infection_beta".into()],
+                ..Query::default()
+            })
+            .unwrap();
+        assert_eq!(table.rows, [["3", "0.9"], ["1", "0.3"]]);
+        let missing = store
+            .query(&Query {
+                filters: vec![Filter::parse("attack_rate>0").unwrap()],
+                ..Query::default()
+            })
+            .unwrap();
+        assert!(missing.rows.is_empty());
+        assert!(
+            store
+                .query(&Query {
+                    columns: vec!["seed; DROP TABLE runs".into()],
+                    ..Query::default()
+                })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn older_stores_gain_new_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE runs (id INTEGER PRIMARY KEY, seed INTEGER)",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO runs (seed) VALUES (9)", [])
+            .unwrap();
+        let store = RunStore::with_connection(conn).unwrap();
+        assert_eq!(store.columns().len(), COLUMNS.len());
+        let table = store
+            .query(&Query {
+                columns: vec!["seed".into(), "offspring_r0".into()],
+                ..Query::default()
+            })
+            .unwrap();
+        assert_eq!(table.rows[0], ["9", ""]);
+    }
+}
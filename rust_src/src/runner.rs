@@ -0,0 +1,85 @@
+use crate::sim::{SimConfig, Simulation};
+use crate::stats::EpidemicTracker;
+
+/// Final size below which a replicate counts as a minor outbreak that faded
+/// out stochastically rather than a major epidemic.
+pub const MINOR_OUTBREAK_THRESHOLD: f32 = 0.1;
+
+/// Outcome of one replicate run to extinction or to the step cap.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RunOutcome {
+    /// Peak infected fraction.
+    pub peak_prevalence: f32,
+    pub peak_time: f32,
+    /// Fraction ever infected, seeded cases included.
+    pub final_size: f32,
+    pub duration: f32,
+    /// Whether infections died out before the step cap.
+    pub extinct: bool,
+    /// Empirical herd-immunity threshold: 1 minus the susceptible fraction
+    /// at peak prevalence.
+    pub herd_immunity_threshold: f32,
+}
+
+impl RunOutcome {
+    pub fn is_major(&self) -> bool {
+        self.final_size >= MINOR_OUTBREAK_THRESHOLD
+    }
+}
+
+/// Run a fresh simulation with fixed steps of `dt` until infections die out
+/// or `max_steps` is reached.
+pub fn run_to_end(
+    cfg: SimConfig,
+    boids: usize,
+    seed: u64,
+    max_steps: usize,
+    dt: f32,
+) -> RunOutcome {
+    run_sim_to_end(Simulation::new(boids, cfg, seed), max_steps, dt)
+}
+
+/// Like `run_to_end` for a simulation the caller has already set up, e.g.
+/// with part of the population immunized.
+pub fn run_sim_to_end(mut sim: Simulation, max_steps: usize, dt: f32) -> RunOutcome {
+    run_in_place(&mut sim, max_steps, dt)
+}
+
+/// Like `run_sim_to_end`, leaving `sim` in its final state for the caller.
+pub fn run_in_place(sim: &mut Simulation, max_steps: usize, dt: f32) -> RunOutcome {
+    let mut tracker = EpidemicTracker::new(sim);
+    for _ in 0..max_steps {
+        sim.step(dt);
+        tracker.record(sim, dt);
+        if tracker.summary().is_some() {
+            break;
+        }
+    }
+    outcome_of(sim, &tracker)
+}
+
+/// Summarize a run observed by `tracker`, whether or not it has ended.
+pub fn outcome_of(sim: &Simulation, tracker: &EpidemicTracker) -> RunOutcome {
+    match tracker.summary() {
+        Some(s) => RunOutcome {
+            peak_prevalence: s.peak_prevalence,
+            peak_time: s.peak_time,
+            final_size: s.final_attack_rate,
+            duration: s.duration,
+            extinct: true,
+            herd_immunity_threshold: s.herd_immunity_threshold,
+        },
+        None => {
+            let population = sim.boids.len().max(1) as f32;
+            let c = sim.counts();
+            RunOutcome {
+                peak_prevalence: tracker.peak_infected() as f32 / population,
+                peak_time: tracker.peak_time(),
+                final_size: (c.infected + c.recovered) as f32 / population,
+                duration: tracker.time(),
+                extinct: false,
+                herd_immunity_threshold: 1.0 - tracker.susceptible_at_peak(),
+            }
+        }
+    }
+}
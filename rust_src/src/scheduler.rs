@@ -0,0 +1,182 @@
+use std::fmt::{self, Write as _};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::runner::RunOutcome;
+
+/// Shortest gap between writes of completed results to the checkpoint file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+/// Jobs handed to the thread pool at a time, per thread; progress and
+/// checkpoints update between batches.
+const BATCH_PER_THREAD: usize = 4;
+const BAR_WIDTH: usize = 30;
+
+/// Replicate `replicate` of parameter point `point`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Job {
+    pub point: usize,
+    pub replicate: usize,
+}
+
+/// Every (point, replicate) pair in rounds: round `r` runs each point once,
+/// point `p` taking replicate `(p + r) % replicates`. After any number of
+/// whole rounds every point has the same number of replicates, and each
+/// round spreads the replicate seeds across points like the rows of a Latin
+/// square, so a job killed part-way leaves balanced, usable results.
+pub fn latin_schedule(points: usize, replicates: usize) -> Vec<Job> {
+    let mut jobs = Vec::with_capacity(points * replicates);
+    for round in 0..replicates {
+        for point in 0..points {
+            jobs.push(Job {
+                point,
+                replicate: (point + round) % replicates,
+            });
+        }
+    }
+    jobs
+}
+
+/// Jobs done out of a total, with an ETA from the average rate so far.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            done: 0,
+            total,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.done as f32 / self.total.max(1) as f32
+    }
+
+    /// Time left at the rate so far; `None` before anything has finished.
+    pub fn eta(&self) -> Option<Duration> {
+        (self.done > 0).then(|| {
+            let per_job = self.started.elapsed().as_secs_f32() / self.done as f32;
+            Duration::from_secs_f32(per_job * (self.total - self.done) as f32)
+        })
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let filled = (self.fraction() * BAR_WIDTH as f32) as usize;
+        write!(
+            f,
+            "[{}{}] {:>3.0}% {}/{} runs",
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            self.fraction() * 100.0,
+            self.done,
+            self.total
+        )?;
+        match self.eta() {
+            Some(eta) => {
+                let secs = eta.as_secs();
+                write!(f, "  ETA {}m{:02}s", secs / 60, secs % 60)
+            }
+            None => write!(f, "  ETA --"),
+        }
+    }
+}
+
+/// Runs the replicates of a sweep in Latin-square order on the rayon pool,
+/// reporting progress and periodically appending completed results to a
+/// checkpoint CSV.
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    /// Where completed runs are written, one row each, as they finish.
+    pub checkpoint: Option<PathBuf>,
+    /// Draw a progress bar on stderr.
+    pub progress: bool,
+}
+
+impl Scheduler {
+    /// Run `run` for every replicate of every point and return the outcomes
+    /// indexed `point * replicates + replicate`.
+    pub fn run<F>(
+        &self,
+        points: usize,
+        replicates: usize,
+        run: F,
+    ) -> Result<Vec<RunOutcome>, String>
+    where
+        F: Fn(Job) -> RunOutcome + Sync,
+    {
+        let jobs = latin_schedule(points, replicates);
+        let mut outcomes = vec![RunOutcome::default(); jobs.len()];
+        let mut progress = Progress::new(jobs.len());
+        let mut checkpoint = self.open_checkpoint()?;
+        let mut pending = String::new();
+        let mut last_flush = Instant::now();
+        let batch = rayon::current_num_threads() * BATCH_PER_THREAD;
+        for chunk in jobs.chunks(batch.max(1)) {
+            let done: Vec<(Job, RunOutcome)> =
+                chunk.par_iter().map(|&job| (job, run(job))).collect();
+            for (job, outcome) in done {
+                outcomes[job.point * replicates + job.replicate] = outcome;
+                if checkpoint.is_none() {
+                    continue;
+                }
+                let _ = writeln!(
+                    pending,
+                    "{},{},{:.4},{:.3},{:.4},{:.3},{}",
+                    job.point,
+                    job.replicate,
+                    outcome.peak_prevalence,
+                    outcome.peak_time,
+                    outcome.final_size,
+                    outcome.duration,
+                    outcome.extinct
+                );
+            }
+            progress.done += chunk.len();
+            let finished = progress.done == progress.total;
+            if let (Some(file), Some(path)) = (&mut checkpoint, &self.checkpoint)
+                && (finished || last_flush.elapsed() >= FLUSH_INTERVAL)
+            {
+                file.write_all(pending.as_bytes())
+                    .and_then(|()| file.flush())
+                    .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+                pending.clear();
+                last_flush = Instant::now();
+            }
+            if self.progress {
+                eprint!("\r{progress}");
+            }
+        }
+        if self.progress {
+            eprintln!();
+        }
+        Ok(outcomes)
+    }
+
+    fn open_checkpoint(&self) -> Result<Option<File>, String> {
+        let Some(path) = &self.checkpoint else {
+            return Ok(None);
+        };
+        let err = |err: io::Error| format!("failed to write {}: {err}", path.display());
+        std::fs::write(
+            path,
+            "point,replicate,peak_prevalence,peak_time,final_size,duration,extinct\n",
+        )
+        .map_err(err)?;
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map(Some)
+            .map_err(err)
+    }
+}
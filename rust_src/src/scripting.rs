@@ -0,0 +1,312 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope};
+
+use crate::sensitivity::Param;
+use crate::sim::{Simulation, Target, Vec2f};
+
+/// Operations one hook call may run before it is stopped with an error.
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+/// How deep hook functions may call each other.
+pub const MAX_CALL_LEVELS: usize = 64;
+
+/// A Rhai script hooked into the step loop. Every hook is optional:
+///
+/// - `fn steer(boid)` returns `[ax, ay]`, an extra acceleration for one boid.
+///   `boid` has `index`, `x`, `y`, `vx`, `vy` and `state` (`"S"`, `"I"`,
+///   `"R"`).
+/// - `fn on_step(stats)` returns a map of parameter changes keyed by
+///   parameter name, e.g. `#{ max_speed: 80.0 }`, or `()` for none.
+/// - `fn metrics(stats)` returns a map of custom numeric metrics.
+///
+/// `stats` has `time`, `population`, `susceptible`, `infected`, `recovered`
+/// and every parameter by name. Hooks can also change the population
+/// through `Simulation::infect`, `cure` and `immunize`, applied once the hook
+/// returns: `infect(i)`, `infect_region(x0, y0, x1, y1)`, `cure(i)`,
+/// `immunize(fraction)` and `immunize_region(x0, y0, x1, y1, fraction)`.
+/// Hooks share a persistent `this` map for their
+/// own state, so a trigger can fire once:
+///
+/// ```text
+/// fn on_step(s) {
+///     if this.slowed != true && s.infected > 0.2 * s.population {
+///         this.slowed = true;
+///         #{ max_speed: s.max_speed / 2.0 }
+///     }
+/// }
+/// ```
+///
+/// A hook call that runs more than `MAX_OPERATIONS` operations or nests
+/// calls deeper than `MAX_CALL_LEVELS` fails with an error, so a runaway
+/// loop or recursion stops the run instead of hanging it.
+pub struct Script {
+    engine: Engine,
+    queued: Rc<RefCell<Vec<Mutation>>>,
+    ast: AST,
+    state: Dynamic,
+    has_steer: bool,
+    has_on_step: bool,
+    has_metrics: bool,
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        let queued = Rc::new(RefCell::new(Vec::new()));
+        register_mutations(&mut engine, &queued);
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        let defines = |name: &str| ast.iter_functions().any(|f| f.name == name);
+        Ok(Self {
+            has_steer: defines("steer"),
+            has_on_step: defines("on_step"),
+            has_metrics: defines("metrics"),
+            engine,
+            queued,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::compile(&source)
+    }
+
+    fn call(&mut self, name: &str, arg: Dynamic) -> Result<Dynamic, String> {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, (arg,))
+            .map_err(|err| match *err {
+                EvalAltResult::ErrorTooManyOperations(_) => {
+                    format!("{name}: stopped after {MAX_OPERATIONS} operations (endless loop?)")
+                }
+                EvalAltResult::ErrorStackOverflow(_) => {
+                    format!(
+                        "{name}: calls nested deeper than {MAX_CALL_LEVELS} (endless recursion?)"
+                    )
+                }
+                err => format!("{name}: {err}"),
+            })
+    }
+
+    /// Run `on_step`, apply the parameter changes it returns, then step `sim`
+    /// with the forces from `steer`.
+    pub fn step(&mut self, sim: &mut Simulation, dt: f32) -> Result<(), String> {
+        if self.has_on_step {
+            let changes = self.call("on_step", stats(sim).into())?;
+            if let Some(changes) = changes.try_cast::<Map>() {
+                apply_changes(sim, &changes)?;
+            }
+        }
+        let mut forces = Vec::new();
+        if self.has_steer {
+            forces.reserve(sim.boids.len());
+            for (i, b) in sim.boids.iter().enumerate() {
+                let mut boid = Map::new();
+                boid.insert("index".into(), (i as rhai::INT).into());
+                boid.insert("x".into(), (b.pos.x as rhai::FLOAT).into());
+                boid.insert("y".into(), (b.pos.y as rhai::FLOAT).into());
+                boid.insert("vx".into(), (b.vel.x as rhai::FLOAT).into());
+                boid.insert("vy".into(), (b.vel.y as rhai::FLOAT).into());
+                boid.insert("state".into(), b.state.letter().into());
+                let force = self.call("steer", boid.into())?;
+                forces.push(to_vec(&force).ok_or("steer: expected [ax, ay]")?);
+            }
+        }
+        self.apply_queued(sim);
+        sim.step_with_forces(dt, &forces);
+        Ok(())
+    }
+
+    fn apply_queued(&mut self, sim: &mut Simulation) {
+        for mutation in self.queued.borrow_mut().drain(..) {
+            match mutation {
+                Mutation::Infect(target) => {
+                    sim.infect(target);
+                }
+                Mutation::Cure(index) => {
+                    sim.cure(index);
+                }
+                Mutation::Immunize(target, fraction) => {
+                    sim.immunize(target, fraction);
+                }
+            }
+        }
+    }
+
+    /// Custom metrics for the current state, sorted by name; empty without a
+    /// `metrics` hook.
+    pub fn metrics(&mut self, sim: &Simulation) -> Result<Vec<(String, f32)>, String> {
+        if !self.has_metrics {
+            return Ok(Vec::new());
+        }
+        let map = self
+            .call("metrics", stats(sim).into())?
+            .try_cast::<Map>()
+            .ok_or("metrics: expected a map")?;
+        let mut out: Vec<(String, f32)> = map
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), to_f32(&v).unwrap_or(f32::NAN)))
+            .collect();
+        out.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(out)
+    }
+}
+
+/// A population change requested by a script, applied after the hook.
+#[derive(Clone, Copy, Debug)]
+enum Mutation {
+    Infect(Target),
+    Cure(usize),
+    Immunize(Target, f32),
+}
+
+/// Make the mutation functions callable from scripts, queueing onto
+/// `queued`. Arguments take integers or floats alike.
+fn register_mutations(engine: &mut Engine, queued: &Rc<RefCell<Vec<Mutation>>>) {
+    type Args = (Dynamic, Dynamic, Dynamic, Dynamic);
+    fn region((x0, y0, x1, y1): Args) -> Result<Target, Box<rhai::EvalAltResult>> {
+        let coord = |v: &Dynamic| to_f32(v).ok_or("expected a number");
+        Ok(Target::Region {
+            min: Vec2f::new(coord(&x0)?, coord(&y0)?),
+            max: Vec2f::new(coord(&x1)?, coord(&y1)?),
+        })
+    }
+    let index = |v: rhai::INT| usize::try_from(v).map_err(|_| format!("invalid boid index: {v}"));
+    let q = Rc::clone(queued);
+    engine.register_fn("infect", move |i: rhai::INT| {
+        q.borrow_mut()
+            .push(Mutation::Infect(Target::Boid(index(i)?)));
+        Ok::<_, Box<rhai::EvalAltResult>>(())
+    });
+    let q = Rc::clone(queued);
+    engine.register_fn(
+        "infect_region",
+        move |x0: Dynamic, y0: Dynamic, x1: Dynamic, y1: Dynamic| {
+            q.borrow_mut()
+                .push(Mutation::Infect(region((x0, y0, x1, y1))?));
+            Ok::<_, Box<rhai::EvalAltResult>>(())
+        },
+    );
+    let q = Rc::clone(queued);
+    engine.register_fn("cure", move |i: rhai::INT| {
+        q.borrow_mut().push(Mutation::Cure(index(i)?));
+        Ok::<_, Box<rhai::EvalAltResult>>(())
+    });
+    let q = Rc::clone(queued);
+    engine.register_fn("immunize", move |fraction: Dynamic| {
+        let fraction = to_f32(&fraction).ok_or("expected a fraction")?;
+        q.borrow_mut()
+            .push(Mutation::Immunize(Target::All, fraction));
+        Ok::<_, Box<rhai::EvalAltResult>>(())
+    });
+    let q = Rc::clone(queued);
+    engine.register_fn(
+        "immunize_region",
+        move |x0: Dynamic, y0: Dynamic, x1: Dynamic, y1: Dynamic, fraction: Dynamic| {
+            let fraction = to_f32(&fraction).ok_or("expected a fraction")?;
+            q.borrow_mut()
+                .push(Mutation::Immunize(region((x0, y0, x1, y1))?, fraction));
+            Ok::<_, Box<rhai::EvalAltResult>>(())
+        },
+    );
+}
+
+fn stats(sim: &Simulation) -> Map {
+    let c = sim.counts();
+    let cfg = sim.config();
+    let mut map = Map::new();
+    map.insert("time".into(), (sim.time() as rhai::FLOAT).into());
+    map.insert("population".into(), (sim.boids.len() as rhai::INT).into());
+    map.insert("susceptible".into(), (c.susceptible as rhai::INT).into());
+    map.insert("exposed".into(), (c.exposed as rhai::INT).into());
+    map.insert("infected".into(), (c.infected as rhai::INT).into());
+    map.insert("recovered".into(), (c.recovered as rhai::INT).into());
+    for (param, value) in [
+        (Param::NeighborRadius, cfg.neighbor_radius),
+        (Param::SeparationRadius, cfg.separation_radius),
+        (Param::MaxSpeed, cfg.max_speed),
+        (Param::MaxForce, cfg.max_force),
+        (Param::InfectionRadius, cfg.infection_radius),
+        (Param::InfectionBeta, cfg.infection_beta),
+        (Param::InfectiousPeriod, cfg.infectious_period),
+        (Param::IncubationPeriod, cfg.incubation_period),
+    ] {
+        map.insert(param.name().into(), (value as rhai::FLOAT).into());
+    }
+    map
+}
+
+fn apply_changes(sim: &mut Simulation, changes: &Map) -> Result<(), String> {
+    let mut cfg = *sim.config();
+    for (name, value) in changes {
+        let param =
+            Param::from_name(name).ok_or_else(|| format!("on_step: unknown parameter {name}"))?;
+        let value = to_f32(value).ok_or_else(|| format!("on_step: {name} is not a number"))?;
+        param.apply(&mut cfg, value);
+    }
+    sim.set_motion_params(
+        cfg.neighbor_radius,
+        cfg.separation_radius,
+        cfg.max_speed,
+        cfg.max_force,
+    );
+    sim.set_infection_params(
+        cfg.infection_radius,
+        cfg.infection_beta,
+        cfg.infectious_period,
+    );
+    sim.set_incubation_period(cfg.incubation_period);
+    Ok(())
+}
+
+fn to_f32(value: &Dynamic) -> Option<f32> {
+    value
+        .as_float()
+        .ok()
+        .or_else(|| value.as_int().ok().map(|i| i as rhai::FLOAT))
+        .map(|v| v as f32)
+}
+
+fn to_vec(value: &Dynamic) -> Option<Vec2f> {
+    let array = value.clone().try_cast::<rhai::Array>()?;
+    match array.as_slice() {
+        [x, y] => Some(Vec2f::new(to_f32(x)?, to_f32(y)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::SimConfig;
+
+    #[test]
+    fn runaway_hooks_fail_instead_of_hanging() {
+        let mut sim = Simulation::new(20, SimConfig::default(), 1);
+        let mut looping = Script::compile("fn on_step(s) { loop {} }").unwrap();
+        let err = looping.step(&mut sim, 0.1).unwrap_err();
+        assert!(err.contains("operations"), "{err}");
+
+        let mut recursing = Script::compile("fn f(n) { f(n + 1) } fn metrics(s) { f(0) }").unwrap();
+        let err = recursing.metrics(&sim).unwrap_err();
+        assert!(err.contains("nested"), "{err}");
+    }
+
+    #[test]
+    fn on_step_changes_every_parameter_it_reports() {
+        let mut sim = Simulation::new(20, SimConfig::default(), 1);
+        let mut script =
+            Script::compile("fn on_step(s) { #{ incubation_period: 5.0, infection_beta: 0.5 } }")
+                .unwrap();
+        script.step(&mut sim, 0.1).unwrap();
+        assert_eq!(sim.config().incubation_period, 5.0);
+        assert_eq!(sim.config().infection_beta, 0.5);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::runner::{RunOutcome, run_to_end};
+use crate::scheduler::Scheduler;
+use crate::sim::{Rng, SimConfig};
+
+/// A `SimConfig` field that sweeps and sensitivity analyses can vary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Param {
+    NeighborRadius,
+    SeparationRadius,
+    MaxSpeed,
+    MaxForce,
+    InfectionRadius,
+    InfectionBeta,
+    InfectiousPeriod,
+    IncubationPeriod,
+}
+
+impl Param {
+    pub const ALL: [Param; 8] = [
+        Param::NeighborRadius,
+        Param::SeparationRadius,
+        Param::MaxSpeed,
+        Param::MaxForce,
+        Param::InfectionRadius,
+        Param::InfectionBeta,
+        Param::InfectiousPeriod,
+        Param::IncubationPeriod,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::NeighborRadius => "neighbor_radius",
+            Param::SeparationRadius => "separation_radius",
+            Param::MaxSpeed => "max_speed",
+            Param::MaxForce => "max_force",
+            Param::InfectionRadius => "infection_radius",
+            Param::InfectionBeta => "infection_beta",
+            Param::InfectiousPeriod => "infectious_period",
+            Param::IncubationPeriod => "incubation_period",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Param> {
+        Param::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    pub fn apply(self, cfg: &mut SimConfig, value: f32) {
+        match self {
+            Param::NeighborRadius => cfg.neighbor_radius = value,
+            Param::SeparationRadius => cfg.separation_radius = value,
+            Param::MaxSpeed => cfg.max_speed = value,
+            Param::MaxForce => cfg.max_force = value,
+            Param::InfectionRadius => cfg.infection_radius = value,
+            Param::InfectionBeta => cfg.infection_beta = value,
+            Param::InfectiousPeriod => cfg.infectious_period = value,
+            Param::IncubationPeriod => cfg.incubation_period = value,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ParamRange {
+    pub param: Param,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ParamRange {
+    /// Parse `name=min:max`, e.g. `infection_beta=0.2:3.0`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, range) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=min:max, got {spec}"))?;
+        let param = Param::from_name(name).ok_or_else(|| format!("unknown parameter: {name}"))?;
+        let (min, max) = range
+            .split_once(':')
+            .ok_or_else(|| format!("expected min:max, got {range}"))?;
+        let parse = |s: &str| s.parse::<f32>().map_err(|_| format!("invalid number: {s}"));
+        Ok(Self {
+            param,
+            min: parse(min)?,
+            max: parse(max)?,
+        })
+    }
+
+    pub fn lerp(&self, t: f32) -> f32 {
+        self.min + t * (self.max - self.min)
+    }
+}
+
+/// Settings shared by every replicate of an analysis.
+#[derive(Clone, Copy, Debug)]
+pub struct RunSettings {
+    pub base: SimConfig,
+    pub boids: usize,
+    pub replicates: usize,
+    pub seed: u64,
+    pub max_steps: usize,
+    pub dt: f32,
+}
+
+impl RunSettings {
+    /// Mean outcome over `replicates` seeds. Replicate `r` of any parameter
+    /// point uses seed `seed + r`, so points are compared on matched seeds.
+    pub fn mean_outcome(&self, cfg: SimConfig) -> RunOutcome {
+        let runs: Vec<RunOutcome> = (0..self.replicates.max(1))
+            .map(|r| self.replicate(cfg, r))
+            .collect();
+        mean_of(&runs)
+    }
+
+    /// Replicate `r` of `cfg`, run on seed `seed + r`.
+    pub fn replicate(&self, cfg: SimConfig, r: usize) -> RunOutcome {
+        run_to_end(
+            cfg,
+            self.boids,
+            self.seed.wrapping_add(r as u64),
+            self.max_steps,
+            self.dt,
+        )
+    }
+}
+
+/// Mean peak, timing, final size and duration of `runs`, summed in order.
+pub fn mean_of(runs: &[RunOutcome]) -> RunOutcome {
+    let n = runs.len().max(1) as f32;
+    let mut mean = RunOutcome::default();
+    for o in runs {
+        mean.peak_prevalence += o.peak_prevalence / n;
+        mean.peak_time += o.peak_time / n;
+        mean.final_size += o.final_size / n;
+        mean.duration += o.duration / n;
+    }
+    mean
+}
+
+pub struct Sample {
+    pub values: Vec<f32>,
+    pub outcome: RunOutcome,
+}
+
+/// Partial rank correlation coefficients of each parameter with each outcome.
+pub struct SensitivityIndex {
+    pub param: Param,
+    pub peak_prevalence: f32,
+    pub final_size: f32,
+}
+
+pub struct SensitivityResult {
+    pub samples: Vec<Sample>,
+    pub indices: Vec<SensitivityIndex>,
+}
+
+/// `n` stratified points in the unit hypercube of dimension `dims`: each
+/// coordinate visits every one of the `n` strata exactly once.
+pub fn latin_hypercube(n: usize, dims: usize, rng: &mut Rng) -> Vec<Vec<f32>> {
+    let mut points = vec![vec![0.0; dims]; n];
+    for d in 0..dims {
+        let mut strata: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            strata.swap(i, rng.next_index(i + 1));
+        }
+        for (point, stratum) in points.iter_mut().zip(strata) {
+            point[d] = (stratum as f32 + rng.next_f32()) / n as f32;
+        }
+    }
+    points
+}
+
+/// Latin hypercube sensitivity analysis with PRCC indices, running the
+/// replicates through `scheduler`.
+pub fn run_sensitivity(
+    ranges: &[ParamRange],
+    samples: usize,
+    settings: &RunSettings,
+    scheduler: &Scheduler,
+) -> Result<SensitivityResult, String> {
+    let mut rng = Rng::new(settings.seed ^ 0x5e45_17f1);
+    let points = latin_hypercube(samples, ranges.len(), &mut rng);
+    let configs: Vec<(SimConfig, Vec<f32>)> = points
+        .iter()
+        .map(|unit| {
+            let mut cfg = settings.base;
+            let values = ranges
+                .iter()
+                .zip(unit)
+                .map(|(range, &t)| {
+                    let v = range.lerp(t);
+                    range.param.apply(&mut cfg, v);
+                    v
+                })
+                .collect();
+            (cfg, values)
+        })
+        .collect();
+    let n = settings.replicates.max(1);
+    let outcomes = scheduler.run(configs.len(), n, |job| {
+        settings.replicate(configs[job.point].0, job.replicate)
+    })?;
+    let samples: Vec<Sample> = configs
+        .into_iter()
+        .zip(outcomes.chunks(n))
+        .map(|((_, values), runs)| Sample {
+            values,
+            outcome: mean_of(runs),
+        })
+        .collect();
+
+    let column = |d: usize| samples.iter().map(|s| s.values[d]).collect::<Vec<_>>();
+    let inputs: Vec<Vec<f32>> = (0..ranges.len()).map(column).collect();
+    let peak: Vec<f32> = samples.iter().map(|s| s.outcome.peak_prevalence).collect();
+    let size: Vec<f32> = samples.iter().map(|s| s.outcome.final_size).collect();
+    let indices = ranges
+        .iter()
+        .enumerate()
+        .map(|(d, range)| SensitivityIndex {
+            param: range.param,
+            peak_prevalence: prcc(&inputs, d, &peak),
+            final_size: prcc(&inputs, d, &size),
+        })
+        .collect();
+    Ok(SensitivityResult { samples, indices })
+}
+
+/// Partial rank correlation of `inputs[target]` with `output`, controlling for
+/// the other inputs. Returns NaN when the design is too small or degenerate.
+pub fn prcc(inputs: &[Vec<f32>], target: usize, output: &[f32]) -> f32 {
+    let ranked: Vec<Vec<f32>> = inputs.iter().map(|c| ranks(c)).collect();
+    let controls: Vec<&[f32]> = ranked
+        .iter()
+        .enumerate()
+        .filter(|&(d, _)| d != target)
+        .map(|(_, c)| c.as_slice())
+        .collect();
+    let (Some(rx), Some(ry)) = (
+        residuals(&ranked[target], &controls),
+        residuals(&ranks(output), &controls),
+    ) else {
+        return f32::NAN;
+    };
+    pearson(&rx, &ry)
+}
+
+/// Average ranks (ties share the mean of their positions), starting at 1.
+fn ranks(values: &[f32]) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut out = vec![0.0; values.len()];
+    let mut i = 0;
+    while i < order.len() {
+        let mut j = i;
+        while j + 1 < order.len() && values[order[j + 1]] == values[order[i]] {
+            j += 1;
+        }
+        let rank = (i + j) as f32 / 2.0 + 1.0;
+        for &k in &order[i..=j] {
+            out[k] = rank;
+        }
+        i = j + 1;
+    }
+    out
+}
+
+/// Residuals of an ordinary least-squares fit of `y` on `xs` plus intercept.
+fn residuals(y: &[f32], xs: &[&[f32]]) -> Option<Vec<f32>> {
+    let n = y.len();
+    let k = xs.len() + 1;
+    if n <= k {
+        return None;
+    }
+    let row = |i: usize| std::iter::once(1.0f64).chain(xs.iter().map(move |x| x[i] as f64));
+    // Normal equations (X^T X) b = X^T y, augmented.
+    let mut a = vec![vec![0.0f64; k + 1]; k];
+    for (i, &yi) in y.iter().enumerate() {
+        let r: Vec<f64> = row(i).collect();
+        for (p, a_row) in a.iter_mut().enumerate() {
+            for (q, rq) in r.iter().enumerate() {
+                a_row[q] += r[p] * rq;
+            }
+            a_row[k] += r[p] * yi as f64;
+        }
+    }
+    // Gaussian elimination with partial pivoting.
+    for col in 0..k {
+        let pivot = (col..k).max_by(|&p, &q| a[p][col].abs().total_cmp(&a[q][col].abs()))?;
+        if a[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for (r, a_row) in a.iter_mut().enumerate() {
+            if r != col {
+                let factor = a_row[col] / pivot_row[col];
+                for (c, p) in a_row.iter_mut().zip(&pivot_row).skip(col) {
+                    *c -= factor * p;
+                }
+            }
+        }
+    }
+    let beta: Vec<f64> = (0..k).map(|p| a[p][k] / a[p][p]).collect();
+    Some(
+        (0..n)
+            .map(|i| {
+                let fit: f64 = row(i).zip(&beta).map(|(x, b)| x * b).sum();
+                (y[i] as f64 - fit) as f32
+            })
+            .collect(),
+    )
+}
+
+fn pearson(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let ma = a.iter().sum::<f32>() / n;
+    let mb = b.iter().sum::<f32>() / n;
+    let mut cov = 0.0;
+    let mut va = 0.0;
+    let mut vb = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - ma) * (y - mb);
+        va += (x - ma) * (x - ma);
+        vb += (y - mb) * (y - mb);
+    }
+    if va <= 0.0 || vb <= 0.0 {
+        return f32::NAN;
+    }
+    cov / (va.sqrt() * vb.sqrt())
+}
@@ -1,124 +1,326 @@
-use std::collections::HashMap;
+use std::fmt;
+use std::mem::size_of;
+use std::ops::Range;
+use std::path::PathBuf;
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct Vec2f {
-    pub x: f32,
-    pub y: f32,
-}
-
-impl Vec2f {
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x, y }
-    }
+/// The shared vector type, under the name used throughout this crate; plain
+/// `Vec2` would clash with macroquad's in the renderer.
+pub use boid_math::Vec2 as Vec2f;
+use boid_math::params::{
+    Adjustment, MIN_INFECTION_RADIUS, MIN_PERCEPTION_RADIUS, Radii, clamp_separation,
+};
+pub use boid_math::rng::Rng;
+use boid_math::spatial::Grid;
+use boid_math::torus_delta;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-    pub fn add(self, other: Vec2f) -> Vec2f {
-        Vec2f::new(self.x + other.x, self.y + other.y)
-    }
+use crate::commute::{COMMUTER_TAG, Commute, Patch};
+use crate::household::{HOUSEHOLD_TAG, Households};
+use crate::immunization::{self, Immunization};
+use crate::policy::{INIT_SCALE, Policy};
+use crate::quarantine_zone::{CONFINED_TAG, QuarantineZone};
+use crate::restriction::{TravelRestriction, ZONE_TAG};
+use crate::tags::{Flag, TagRegistry, Tags, Value};
+use crate::venue::{Venue, shared_venue};
+use crate::world::{Obstacle, WorldPreset};
 
-    pub fn sub(self, other: Vec2f) -> Vec2f {
-        Vec2f::new(self.x - other.x, self.y - other.y)
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HealthState {
+    Susceptible,
+    /// Infected but not yet infectious; only reached under `DiseaseModel::Seir`.
+    Exposed,
+    Infected,
+    Recovered,
+}
 
-    pub fn mul(self, s: f32) -> Vec2f {
-        Vec2f::new(self.x * s, self.y * s)
+impl HealthState {
+    /// One-letter compartment name, as in the trajectory CSV.
+    pub fn letter(self) -> &'static str {
+        match self {
+            HealthState::Susceptible => "S",
+            HealthState::Exposed => "E",
+            HealthState::Infected => "I",
+            HealthState::Recovered => "R",
+        }
     }
+}
 
-    pub fn div(self, s: f32) -> Vec2f {
-        Vec2f::new(self.x / s, self.y / s)
-    }
+/// Which compartments a boid passes through once infected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiseaseModel {
+    /// Infected, then recovered and immune for good.
+    #[default]
+    Sir,
+    /// Infected, then susceptible again.
+    Sis,
+    /// Exposed for `incubation_period`, then infected, then recovered.
+    Seir,
+}
 
-    pub fn length(self) -> f32 {
-        (self.x * self.x + self.y * self.y).sqrt()
-    }
+impl DiseaseModel {
+    pub const ALL: [DiseaseModel; 3] = [DiseaseModel::Sir, DiseaseModel::Sis, DiseaseModel::Seir];
 
-    pub fn normalize(self) -> Vec2f {
-        let len = self.length();
-        if len > 0.0 {
-            self.div(len)
-        } else {
-            Vec2f::default()
+    /// Parse `sir`, `sis` or `seir`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec {
+            "sir" => Ok(DiseaseModel::Sir),
+            "sis" => Ok(DiseaseModel::Sis),
+            "seir" => Ok(DiseaseModel::Seir),
+            _ => Err(format!("unknown disease model: {spec}")),
         }
     }
 
-    pub fn limit(self, max: f32) -> Vec2f {
-        let len = self.length();
-        if len > max { self.mul(max / len) } else { self }
+    pub fn name(self) -> &'static str {
+        match self {
+            DiseaseModel::Sir => "sir",
+            DiseaseModel::Sis => "sis",
+            DiseaseModel::Seir => "seir",
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum HealthState {
-    Susceptible,
-    Infected,
-    Recovered,
-}
-
 #[derive(Clone, Copy, Debug)]
 pub struct Boid {
     pub pos: Vec2f,
     pub vel: Vec2f,
     pub state: HealthState,
     pub infected_time: f32,
+    /// Index into `Simulation::infection_log` of this boid's most recent
+    /// infection, if it has ever been infected.
+    pub infection: Option<usize>,
+    /// Distance left in the current straight flight under `LevyFlight`.
+    pub flight_left: f32,
+    /// Where the boid returns at night under an `ActivitySchedule`.
+    pub home: Vec2f,
+    /// Isolated in place: does not move, and can neither infect nor be
+    /// infected. Infections already under way still run their course.
+    pub quarantined: bool,
+    /// Metadata for features outside the core model, named through
+    /// `Simulation::tag_registry_mut`.
+    pub tags: Tags,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct CellKey {
-    x: i32,
-    y: i32,
+/// One node of the transmission tree.
+#[derive(Clone, Copy, Debug)]
+pub struct InfectionEvent {
+    /// Boid index at the time of infection.
+    pub boid: usize,
+    /// Log index of the infector's infection; `None` for seeded cases.
+    pub infector: Option<usize>,
+    pub time: f32,
+    /// When the case became infectious (the onset used for serial intervals).
+    /// Equal to `time` while infection is immediately infectious.
+    pub onset: f32,
+    /// Where the infectee was when infected.
+    pub pos: Vec2f,
+    /// Index into `Simulation::venues` of the venue infector and infectee
+    /// shared, if any.
+    pub venue: Option<usize>,
+    /// Transmitted between housemates rather than in the community.
+    pub household: bool,
 }
 
-struct SpatialHash {
-    cell_size: f32,
-    buckets: HashMap<CellKey, Vec<usize>>,
+/// How a step advances positions from velocities. Steering forces are sensed
+/// once per step either way, so both hold the acceleration fixed over it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Update the velocity, then move with the new one. First order: the
+    /// path drifts with the step size, so different frame rates give
+    /// visibly different trajectories.
+    #[default]
+    #[serde(rename = "euler")]
+    SemiImplicitEuler,
+    /// Heun's method: move with the mean of the velocities before and after
+    /// the update, i.e. `v dt + a dt^2 / 2` under constant acceleration.
+    /// Second order in position, so paths depend much less on `dt`.
+    #[serde(rename = "rk2", alias = "heun")]
+    Rk2,
 }
 
-impl SpatialHash {
-    fn new(cell_size: f32) -> Self {
-        Self {
-            cell_size,
-            buckets: HashMap::new(),
+impl Integrator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::SemiImplicitEuler => "euler",
+            Integrator::Rk2 => "rk2",
         }
     }
 
-    fn clear(&mut self) {
-        self.buckets.clear();
+    /// Parse `euler` or `rk2`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec {
+            "euler" => Ok(Integrator::SemiImplicitEuler),
+            "rk2" | "heun" => Ok(Integrator::Rk2),
+            _ => Err(format!(
+                "unknown integrator: {spec} (expected euler or rk2)"
+            )),
+        }
     }
+}
+
+/// How boids move. The null models ignore neighbours entirely and move at
+/// `max_speed`, so contact parameters stay comparable with flocking.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementModel {
+    /// Steering from the neural policy.
+    #[default]
+    Flocking,
+    /// A fresh uniformly random heading every step.
+    Brownian,
+    /// The heading drifts by a normal turn with standard deviation
+    /// `turn_sd` radians per square-root second.
+    #[serde(rename = "crw")]
+    CorrelatedWalk { turn_sd: f32 },
+    /// Straight flights in random directions with Pareto-distributed lengths
+    /// `P(l) ~ l^-exponent` for `l >= min_flight`; `exponent` in (1, 3].
+    #[serde(rename = "levy")]
+    LevyFlight { exponent: f32, min_flight: f32 },
+}
 
-    fn set_cell_size(&mut self, cell_size: f32) {
-        self.cell_size = cell_size.max(1.0);
+impl MovementModel {
+    /// Parse `flocking`, `brownian`, `crw[:turn_sd]` or `levy[:exponent]`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (
+                name,
+                Some(
+                    arg.parse::<f32>()
+                        .map_err(|_| format!("invalid number: {arg}"))?,
+                ),
+            ),
+            None => (spec, None),
+        };
+        match name {
+            "flocking" => Ok(MovementModel::Flocking),
+            "brownian" => Ok(MovementModel::Brownian),
+            "crw" => Ok(MovementModel::CorrelatedWalk {
+                turn_sd: arg.unwrap_or(1.0),
+            }),
+            "levy" => Ok(MovementModel::LevyFlight {
+                exponent: arg.unwrap_or(2.0).clamp(1.05, 3.0),
+                min_flight: 10.0,
+            }),
+            _ => Err(format!("unknown movement model: {name}")),
+        }
     }
+}
 
-    fn cell_key(&self, pos: Vec2f) -> CellKey {
-        CellKey {
-            x: (pos.x / self.cell_size).floor() as i32,
-            y: (pos.y / self.cell_size).floor() as i32,
+/// Day/night cycle on the simulation clock. By day boids move under the
+/// configured movement model; by night they slow down and drift back toward
+/// their home anchors, so mixing becomes periodic.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivitySchedule {
+    /// Length of a full day in seconds.
+    pub day_length: f32,
+    /// Share of each day that is night, at the end of the day.
+    pub night_fraction: f32,
+    /// Night speed as a fraction of `max_speed`.
+    pub night_speed: f32,
+}
+
+impl ActivitySchedule {
+    /// Parse `day_length[:night_fraction]`, e.g. `20:0.4`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec
+            .split(':')
+            .map(|s| s.parse::<f32>().map_err(|_| format!("invalid number: {s}")));
+        let day_length = parts.next().transpose()?.unwrap_or(20.0);
+        let night_fraction = parts.next().transpose()?.unwrap_or(0.4);
+        Ok(Self {
+            day_length: day_length.max(0.1),
+            night_fraction: night_fraction.clamp(0.0, 1.0),
+            night_speed: 0.25,
+        })
+    }
+
+    pub fn is_night(&self, time: f32) -> bool {
+        (time / self.day_length).fract() >= 1.0 - self.night_fraction
+    }
+}
+
+/// What happens to a boid that crosses one edge of the world.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Boundary {
+    /// Re-enter from the opposite edge, whatever that edge's own setting.
+    #[default]
+    Wrap,
+    /// Bounce back in, mirroring the velocity component across the edge.
+    Reflect,
+    /// Leave the simulation for good.
+    Remove,
+    /// Leave, replaced by a new susceptible boid entering from the opposite
+    /// edge, e.g. to model a migration corridor.
+    Respawn,
+}
+
+impl Boundary {
+    pub fn from_name(name: &str) -> Option<Boundary> {
+        match name {
+            "wrap" => Some(Boundary::Wrap),
+            "reflect" => Some(Boundary::Reflect),
+            "remove" => Some(Boundary::Remove),
+            "respawn" => Some(Boundary::Respawn),
+            _ => None,
         }
     }
+}
+
+/// Per-edge boundary behaviour. `top` is `y = 0`, matching screen coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Boundaries {
+    pub left: Boundary,
+    pub right: Boundary,
+    pub top: Boundary,
+    pub bottom: Boundary,
+}
 
-    fn insert(&mut self, idx: usize, pos: Vec2f) {
-        let key = self.cell_key(pos);
-        self.buckets.entry(key).or_default().push(idx);
+impl Boundaries {
+    /// Whether every edge wraps, making the world a torus.
+    pub fn wraps(&self) -> bool {
+        [self.left, self.right, self.top, self.bottom]
+            .iter()
+            .all(|&b| b == Boundary::Wrap)
     }
 
-    fn for_each_neighbor(&self, pos: Vec2f, mut f: impl FnMut(usize)) {
-        let key = self.cell_key(pos);
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                let nk = CellKey {
-                    x: key.x + dx,
-                    y: key.y + dy,
-                };
-                if let Some(items) = self.buckets.get(&nk) {
-                    for &idx in items {
-                        f(idx);
-                    }
-                }
-            }
+    pub fn uniform(boundary: Boundary) -> Self {
+        Self {
+            left: boundary,
+            right: boundary,
+            top: boundary,
+            bottom: boundary,
+        }
+    }
+
+    /// Parse one mode for every edge, or four comma-separated modes in
+    /// `left,right,top,bottom` order, e.g. `respawn,remove,reflect,reflect`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let modes = spec
+            .split(',')
+            .map(|name| {
+                Boundary::from_name(name).ok_or_else(|| format!("unknown boundary: {name}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match modes[..] {
+            [all] => Ok(Self::uniform(all)),
+            [left, right, top, bottom] => Ok(Self {
+                left,
+                right,
+                top,
+                bottom,
+            }),
+            _ => Err(format!("expected 1 or 4 boundary modes, got {spec}")),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Fields missing when deserializing keep their `Default` values.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SimConfig {
     pub world_size: Vec2f,
     pub max_speed: f32,
@@ -128,62 +330,443 @@ pub struct SimConfig {
     pub infection_radius: f32,
     pub infection_beta: f32,
     pub infectious_period: f32,
+    pub disease_model: DiseaseModel,
+    /// Seconds from exposure to becoming infectious under SEIR.
+    pub incubation_period: f32,
     pub initial_infected: usize,
+    /// Seconds of infection-free flocking before the initial infections are
+    /// seeded, so they land in a formed flock rather than the uniform
+    /// starting scatter.
+    pub warmup: f32,
+    /// Applied once, right after the initial infections are seeded.
+    pub immunization: Immunization,
+    pub boundaries: Boundaries,
+    /// Obstacles and spawn area. Its boundaries are not applied here; see
+    /// `WorldPreset::boundaries`.
+    pub world: WorldPreset,
+    pub movement: MovementModel,
+    pub integrator: Integrator,
+    pub schedule: Option<ActivitySchedule>,
+    pub households: Option<Households>,
+    pub travel_restriction: Option<TravelRestriction>,
+    /// Two-patch commuting between a home and a work patch.
+    pub commute: Option<Commute>,
+}
+
+/// Shortest infectious or incubation period, in seconds.
+const MIN_PERIOD: f32 = 0.1;
+
+impl SimConfig {
+    /// The torus the world forms when every edge wraps, for neighbour
+    /// lookups across the seams.
+    pub fn torus(&self) -> Option<Vec2f> {
+        self.boundaries.wraps().then_some(self.world_size)
+    }
+
+    /// Displacement from `from` to `to`, the short way round when the world
+    /// is a torus and straight across otherwise.
+    pub fn offset(&self, from: Vec2f, to: Vec2f) -> Vec2f {
+        match self.torus() {
+            Some(world) => torus_delta(from, to, world),
+            None => to.sub(from),
+        }
+    }
+
+    /// This config as `Simulation` runs it, and every value that changed.
+    /// Radii follow the rules shared with leah's apps
+    /// (`boid_math::params::Radii`); disease periods have a floor.
+    pub fn normalized(mut self) -> (SimConfig, Vec<Adjustment>) {
+        let (radii, mut adjustments) = Radii {
+            perception: self.neighbor_radius,
+            separation: self.separation_radius,
+            infection: self.infection_radius,
+        }
+        .normalize();
+        self.neighbor_radius = radii.perception;
+        self.separation_radius = radii.separation;
+        self.infection_radius = radii.infection;
+        for (name, period) in [
+            ("infectious period", &mut self.infectious_period),
+            ("incubation period", &mut self.incubation_period),
+        ] {
+            let used = period.max(MIN_PERIOD);
+            if used != *period {
+                adjustments.push(Adjustment {
+                    name,
+                    requested: *period,
+                    used,
+                });
+                *period = used;
+            }
+        }
+        (self, adjustments)
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            world_size: Vec2f::new(1280.0, 720.0),
+            max_speed: 160.0,
+            max_force: 80.0,
+            neighbor_radius: 60.0,
+            separation_radius: 22.0,
+            infection_radius: 18.0,
+            infection_beta: 1.2,
+            infectious_period: 6.0,
+            disease_model: DiseaseModel::Sir,
+            incubation_period: 3.0,
+            initial_infected: 8,
+            warmup: 0.0,
+            immunization: Immunization::None,
+            boundaries: Boundaries::uniform(Boundary::Wrap),
+            world: WorldPreset::OpenField,
+            movement: MovementModel::Flocking,
+            integrator: Integrator::SemiImplicitEuler,
+            schedule: None,
+            households: None,
+            travel_restriction: None,
+            commute: None,
+        }
+    }
+}
+
+/// Cumulative state transitions since the simulation was created. Seeded
+/// infections are not transitions and are not counted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Transitions {
+    pub infections: usize,
+    /// Infections of boids never infected before; fewer than `infections`
+    /// only when recovery can lead back to susceptible, as under SIS.
+    pub first_infections: usize,
+    /// Exposed boids that became infectious.
+    pub onsets: usize,
+    pub recoveries: usize,
+    /// Boids that entered through a respawning edge.
+    pub arrivals: usize,
+    /// Boids that left through a removing or respawning edge.
+    pub departures: usize,
+    /// Boids removed by `AreaAction::Cull`.
+    pub culled: usize,
+    /// Boids added by `Simulation::spawn_boid` or `Simulation::resize`.
+    pub spawned: usize,
+    /// Boids taken out by `Simulation::remove_boid` or `Simulation::resize`.
+    pub removed: usize,
+}
+
+/// Intervention applied to every boid in a region of the world.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AreaAction {
+    /// Susceptible boids become `Recovered`.
+    Vaccinate,
+    /// Susceptible boids become seeded cases.
+    Infect,
+    /// Boids stop moving and drop out of transmission.
+    Quarantine,
+    /// Boids are removed from the simulation.
+    Cull,
+    /// The area becomes a `QuarantineZone`.
+    Zone,
+}
+
+impl AreaAction {
+    pub const ALL: [AreaAction; 5] = [
+        AreaAction::Vaccinate,
+        AreaAction::Infect,
+        AreaAction::Quarantine,
+        AreaAction::Cull,
+        AreaAction::Zone,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AreaAction::Vaccinate => "vaccinate",
+            AreaAction::Infect => "infect",
+            AreaAction::Quarantine => "quarantine",
+            AreaAction::Cull => "cull",
+            AreaAction::Zone => "quarantine zone",
+        }
+    }
+}
+
+/// The boids `Simulation::infect` and `Simulation::immunize` act on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    /// One boid by index.
+    Boid(usize),
+    /// Every boid inside the axis-aligned box from `min` to `max`.
+    Region { min: Vec2f, max: Vec2f },
+    /// The whole population.
+    All,
+}
+
+impl Target {
+    fn includes(&self, index: usize, boid: &Boid) -> bool {
+        match *self {
+            Target::Boid(i) => i == index,
+            Target::Region { min, max } => {
+                boid.pos.x >= min.x
+                    && boid.pos.x <= max.x
+                    && boid.pos.y >= min.y
+                    && boid.pos.y <= max.y
+            }
+            Target::All => true,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct SirCounts {
     pub susceptible: usize,
+    pub exposed: usize,
     pub infected: usize,
     pub recovered: usize,
 }
 
-const FEATURE_SIZE: usize = 14;
+impl SirCounts {
+    pub fn total(&self) -> usize {
+        self.susceptible + self.exposed + self.infected + self.recovered
+    }
+
+    /// Count `state` in.
+    pub fn add(&mut self, state: HealthState) {
+        match state {
+            HealthState::Susceptible => self.susceptible += 1,
+            HealthState::Exposed => self.exposed += 1,
+            HealthState::Infected => self.infected += 1,
+            HealthState::Recovered => self.recovered += 1,
+        }
+    }
+}
+
+/// Step size of the warm-up phase.
+const WARMUP_DT: f32 = 1.0 / 60.0;
+/// Step size of `advance`.
+pub const TICK: f32 = 1.0 / 60.0;
+/// Most ticks one `advance` call takes; time beyond that is dropped rather
+/// than caught up, so a stalled frame can't snowball.
+const MAX_TICKS: usize = 32;
+
+const FEATURE_SIZE: usize = 17;
 const HIDDEN_SIZE: usize = 16;
 
+/// Heap bytes held by a simulation, broken down by what holds them.
+/// Counts allocated capacity, not just the live elements.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemoryFootprint {
+    pub boids: usize,
+    /// Spatial hash: the bucket table and the boid indices in each bucket.
+    pub grid: usize,
+    pub infection_log: usize,
+    /// Steering policy weights.
+    pub policy: usize,
+}
+
+impl MemoryFootprint {
+    pub fn total(&self) -> usize {
+        self.boids + self.grid + self.infection_log + self.policy
+    }
+
+    /// Expected footprint of a `boids`-strong run of `cfg` once every boid
+    /// has been infected once, i.e. a single epidemic wave that reaches
+    /// everyone. Use it to size very large runs before launching them:
+    /// everything scales linearly with `boids` except the grid table, which
+    /// is bounded by the number of cells covering the world. Runs with
+    /// reinfection (respawning edges) grow the infection log past this by
+    /// one event per extra infection.
+    pub fn estimate(boids: usize, cfg: &SimConfig) -> Self {
+        let cell = cfg.neighbor_radius.max(cfg.infection_radius).max(1.0);
+        let cells = ((cfg.world_size.x / cell).ceil() * (cfg.world_size.y / cell).ceil()) as usize;
+        Self {
+            boids: boids * size_of::<Boid>(),
+            grid: Grid::<usize>::estimate_heap_bytes(cells.min(boids), boids),
+            // Grown one push at a time, so capacity is the next power of two.
+            infection_log: boids.next_power_of_two() * size_of::<InfectionEvent>(),
+            policy: Policy::new(FEATURE_SIZE, HIDDEN_SIZE).heap_bytes(),
+        }
+    }
+}
+
+impl fmt::Display for MemoryFootprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+        write!(
+            f,
+            "boids {:.2} MiB, grid {:.2} MiB, infection log {:.2} MiB, policy {:.2} MiB, total {:.2} MiB",
+            mib(self.boids),
+            mib(self.grid),
+            mib(self.infection_log),
+            mib(self.policy),
+            mib(self.total())
+        )
+    }
+}
+
+/// Shortcuts that trade steering fidelity for speed. The default is full
+/// quality; infection contacts are always checked against every neighbour.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quality {
+    /// Flocking features use at most this many neighbours per boid.
+    pub neighbor_cap: Option<usize>,
+    /// Each boid re-evaluates the steering policy every this many steps
+    /// (staggered across boids) and reuses its last steering in between.
+    pub policy_interval: u32,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self {
+            neighbor_cap: None,
+            policy_interval: 1,
+        }
+    }
+}
+
 pub struct Simulation {
     pub boids: Vec<Boid>,
-    grid: SpatialHash,
+    grid: Grid<usize>,
     cfg: SimConfig,
-    rng: Lcg,
-    policy: NnPolicy,
+    rng: Rng,
+    policy: Policy,
+    seed: u64,
+    step_index: u64,
+    time: f32,
+    transitions: Transitions,
+    infection_log: Vec<InfectionEvent>,
+    venues: Vec<Venue>,
+    quarantine_zones: Vec<QuarantineZone>,
+    quality: Quality,
+    /// Last policy output per boid, reused while `quality.policy_interval > 1`.
+    steering: Vec<Vec2f>,
+    /// A step started by `step_partial` and not yet finalized.
+    pending: Option<PendingStep>,
+    tag_registry: TagRegistry,
+    /// The travel restriction in force and when it started.
+    restriction: Option<(TravelRestriction, f32)>,
+    /// Start and, once lifted, end time of every travel restriction so far.
+    restriction_periods: Vec<(f32, Option<f32>)>,
+    /// Time handed to `advance` not yet covered by a whole tick.
+    accumulator: f32,
+    /// Boids from this index on are halo copies lent by a neighbouring tile:
+    /// they move and catch infections like the rest, but their transitions
+    /// are counted by the tile that owns them.
+    owned: usize,
+}
+
+/// Per-boid results of the sensing phase, held until `finalize_step`.
+struct PendingStep {
+    dt: f32,
+    night: bool,
+    flocking: bool,
+    sensed: Vec<bool>,
+    accelerations: Vec<Vec2f>,
+    /// Per boid: `Some(infector boid)` if it gets infected this step.
+    newly_infected: Vec<Option<usize>>,
+    infection_venue: Vec<Option<usize>>,
+    household_case: Vec<bool>,
+    /// Members of each household, by id - 1, when households are on.
+    housemates: Vec<Vec<usize>>,
+}
+
+/// Boids per rayon task when sensing: enough to outweigh the scheduling
+/// cost of a task.
+const SENSE_CHUNK: usize = 64;
+
+/// What sensing one boid decided. Sensing only reads the simulation, so
+/// boids are sensed in parallel and the results written back afterwards.
+struct Sensed {
+    /// The policy's new steering, if it was evaluated this step.
+    steering: Option<Vec2f>,
+    infector: Option<usize>,
+    venue: Option<usize>,
+    household: bool,
 }
 
 impl Simulation {
-    pub fn new(count: usize, mut cfg: SimConfig, seed: u32) -> Self {
-        let mut boids = Vec::with_capacity(count);
-        let mut rng = Lcg::new(seed);
-        for _ in 0..count {
-            let pos = Vec2f::new(
-                rng.next_f32() * cfg.world_size.x,
-                rng.next_f32() * cfg.world_size.y,
-            );
-            let angle = rng.next_f32() * std::f32::consts::TAU;
-            let speed = cfg.max_speed * (0.3 + 0.7 * rng.next_f32());
-            let vel = Vec2f::new(angle.cos(), angle.sin()).mul(speed);
-            boids.push(Boid {
-                pos,
-                vel,
-                state: HealthState::Susceptible,
-                infected_time: 0.0,
-            });
-        }
-        cfg.neighbor_radius = cfg.neighbor_radius.max(1.0);
-        cfg.separation_radius = cfg.separation_radius.min(cfg.neighbor_radius).max(0.5);
-        cfg.infection_radius = cfg.infection_radius.max(1.0);
-        cfg.infectious_period = cfg.infectious_period.max(0.1);
+    pub fn new(count: usize, cfg: SimConfig, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let boids = (0..count).map(|_| random_boid(&mut rng, &cfg)).collect();
+        let (cfg, _) = cfg.normalized();
         let mut sim = Self {
             boids,
-            grid: SpatialHash::new(cfg.neighbor_radius.max(cfg.infection_radius)),
+            grid: neighbor_grid(&cfg, cfg.neighbor_radius.max(cfg.infection_radius)),
             cfg,
             rng,
-            policy: NnPolicy::new(FEATURE_SIZE, HIDDEN_SIZE),
+            policy: Policy::new(FEATURE_SIZE, HIDDEN_SIZE),
+            seed,
+            step_index: 0,
+            time: 0.0,
+            transitions: Transitions::default(),
+            infection_log: Vec::new(),
+            venues: Vec::new(),
+            quarantine_zones: Vec::new(),
+            quality: Quality::default(),
+            steering: Vec::new(),
+            pending: None,
+            tag_registry: TagRegistry::default(),
+            restriction: None,
+            restriction_periods: Vec::new(),
+            accumulator: 0.0,
+            owned: usize::MAX,
         };
-        sim.policy.randomize(&mut sim.rng, 0.6);
+        sim.policy.randomize(&mut sim.rng, INIT_SCALE);
+        if let Some(commute) = cfg.commute {
+            sim.start_at_home(commute);
+            sim.assign_commuters(commute);
+        }
+        if let Some(households) = cfg.households {
+            sim.assign_households(households);
+        }
+        sim.warm_up();
         sim.seed_infections();
+        immunization::apply(&mut sim);
         sim
     }
 
+    /// Run `cfg.warmup` seconds of plain motion, then restart the clock and
+    /// the transition counts so the run proper begins at time 0. The step
+    /// counter keeps going, so the random streams keyed on it don't repeat.
+    fn warm_up(&mut self) {
+        if self.cfg.warmup <= 0.0 {
+            return;
+        }
+        let steps = (self.cfg.warmup / WARMUP_DT).round() as usize;
+        for _ in 0..steps {
+            self.step(WARMUP_DT);
+        }
+        self.time = 0.0;
+        self.transitions = Transitions::default();
+    }
+
+    /// A simulation of `boids` under the same config, steering policy and
+    /// clock as `self`, with its own random streams drawn from `seed`. No
+    /// infections are seeded and the infection log starts empty, so the
+    /// boids' `infection` indices should be cleared by the caller.
+    pub fn with_boids(&self, boids: Vec<Boid>, seed: u64) -> Simulation {
+        Simulation {
+            boids,
+            grid: neighbor_grid(&self.cfg, self.grid.cell_size()),
+            cfg: self.cfg,
+            rng: Rng::new(seed),
+            policy: self.policy.clone(),
+            seed,
+            step_index: self.step_index,
+            time: self.time,
+            transitions: Transitions::default(),
+            infection_log: Vec::new(),
+            venues: self.venues.clone(),
+            quarantine_zones: self.quarantine_zones.clone(),
+            quality: self.quality,
+            steering: Vec::new(),
+            pending: None,
+            tag_registry: self.tag_registry.clone(),
+            restriction: self.restriction,
+            restriction_periods: Vec::new(),
+            accumulator: 0.0,
+            owned: usize::MAX,
+        }
+    }
+
     pub fn set_motion_params(
         &mut self,
         neighbor_radius: f32,
@@ -191,10 +774,8 @@ impl Simulation {
         max_speed: f32,
         max_force: f32,
     ) {
-        self.cfg.neighbor_radius = neighbor_radius.max(1.0);
-        self.cfg.separation_radius = separation_radius
-            .min(self.cfg.neighbor_radius)
-            .max(0.5);
+        self.cfg.neighbor_radius = neighbor_radius.max(MIN_PERCEPTION_RADIUS);
+        self.cfg.separation_radius = clamp_separation(separation_radius, self.cfg.neighbor_radius);
         self.cfg.max_speed = max_speed.max(1.0);
         self.cfg.max_force = max_force.max(1.0);
         self.grid
@@ -207,68 +788,990 @@ impl Simulation {
         infection_beta: f32,
         infectious_period: f32,
     ) {
-        self.cfg.infection_radius = infection_radius.max(1.0);
+        self.cfg.infection_radius = infection_radius.max(MIN_INFECTION_RADIUS);
         self.cfg.infection_beta = infection_beta.max(0.0);
-        self.cfg.infectious_period = infectious_period.max(0.1);
+        self.cfg.infectious_period = infectious_period.max(MIN_PERIOD);
         self.grid
             .set_cell_size(self.cfg.neighbor_radius.max(self.cfg.infection_radius));
     }
 
+    /// Seconds from exposure to becoming infectious under SEIR. Boids already
+    /// incubating compare their time so far against the new period.
+    pub fn set_incubation_period(&mut self, incubation_period: f32) {
+        self.cfg.incubation_period = incubation_period.max(MIN_PERIOD);
+    }
+
+    /// Grow or shrink the population to `count` without restarting the run.
+    /// Surviving boids keep their positions, health states and order; new
+    /// boids are susceptible and placed uniformly at random, removed boids
+    /// are chosen at random and taken out as by `remove_boid`.
+    pub fn resize(&mut self, count: usize) {
+        while self.boids.len() > count {
+            let idx = self.rng.next_index(self.boids.len());
+            self.remove_boid(idx);
+        }
+        while self.boids.len() < count {
+            let boid = random_boid(&mut self.rng, &self.cfg);
+            self.boids.push(boid);
+            self.transitions.spawned += 1;
+        }
+    }
+
+    /// Take out the boid at `idx`, e.g. when it dies or is eaten. The boids
+    /// after it move down one index, as with removal at an edge; infection
+    /// log entries keep the index each boid had when it was infected.
+    pub fn remove_boid(&mut self, idx: usize) -> Option<Boid> {
+        if idx >= self.boids.len() {
+            return None;
+        }
+        self.transitions.removed += 1;
+        // Cached steering is per index, so it has to shift with the boids.
+        if idx < self.steering.len() {
+            self.steering.remove(idx);
+        }
+        Some(self.boids.remove(idx))
+    }
+
+    /// Add a boid at `pos` moving at `vel` and return its index. An
+    /// infected boid starts a fresh infectious period and is logged as a
+    /// seeded case, since nobody in the simulation infected it. The spatial
+    /// grid is rebuilt at the start of every step, so the boid interacts
+    /// from the next step on.
+    pub fn spawn_boid(&mut self, pos: Vec2f, vel: Vec2f, state: HealthState) -> usize {
+        let idx = self.boids.len();
+        let mut boid = Boid {
+            pos,
+            vel: vel.limit(self.cfg.max_speed),
+            state,
+            infected_time: 0.0,
+            infection: None,
+            flight_left: 0.0,
+            home: pos,
+            quarantined: false,
+            tags: Tags::default(),
+        };
+        if matches!(state, HealthState::Exposed | HealthState::Infected) {
+            boid.infection = Some(self.infection_log.len());
+            self.infection_log.push(InfectionEvent {
+                boid: idx,
+                infector: None,
+                time: self.time,
+                onset: self.time,
+                pos,
+                venue: None,
+                household: false,
+            });
+        }
+        self.boids.push(boid);
+        self.transitions.spawned += 1;
+        idx
+    }
+
     pub fn set_world_size(&mut self, size: Vec2f) {
         self.cfg.world_size = size;
     }
 
-    pub fn step(&mut self, dt: f32) {
-        self.rebuild_grid();
-        let mut accelerations = vec![Vec2f::default(); self.boids.len()];
-        let mut newly_infected = vec![false; self.boids.len()];
-        let infect_p = 1.0 - (-self.cfg.infection_beta * dt).exp();
+    pub fn set_schedule(&mut self, schedule: Option<ActivitySchedule>) {
+        self.cfg.schedule = schedule;
+    }
 
-        for i in 0..self.boids.len() {
-            let (inputs, infected_contact) = self.features_for(i);
-            let accel = self.policy.forward(&inputs).mul(self.cfg.max_force);
-            accelerations[i] = accel.limit(self.cfg.max_force);
-            if self.boids[i].state == HealthState::Susceptible
-                && infected_contact
-                && self.rng.next_f32() < infect_p
-            {
-                newly_infected[i] = true;
+    /// Start or stop commuting. Boids stay where they are; commuters are
+    /// picked again when the commuter share changes.
+    pub fn set_commute(&mut self, commute: Option<Commute>) {
+        let previous = self.cfg.commute.map(|c| c.commuters);
+        self.cfg.commute = commute;
+        if let Some(commute) = commute
+            && previous != Some(commute.commuters)
+        {
+            self.assign_commuters(commute);
+        }
+    }
+
+    /// Squeeze the starting scatter into the home patch and make it home.
+    fn start_at_home(&mut self, commute: Commute) {
+        let world = self.cfg.world_size;
+        let (_, right) = commute.bounds(Patch::Home, world);
+        for boid in &mut self.boids {
+            boid.pos.x = (boid.pos.x / world.x * right).clamp(0.0, right - 0.01);
+            boid.home = boid.pos;
+        }
+    }
+
+    /// Flag each boid as a commuter with probability `commute.commuters`,
+    /// from its own random stream so the others are unchanged. Boids added
+    /// later stay home.
+    fn assign_commuters(&mut self, commute: Commute) {
+        let Ok(flag) = self.tag_registry.flag(COMMUTER_TAG) else {
+            return;
+        };
+        for (i, boid) in self.boids.iter_mut().enumerate() {
+            boid.tags.set(
+                flag,
+                hash_unit(self.seed ^ COMMUTE_STREAM, 0, i) < commute.commuters,
+            );
+        }
+    }
+
+    /// Health counts of the boids in each patch, in `Patch::ALL` order, while
+    /// commuting is on.
+    pub fn patch_counts(&self) -> Option<[SirCounts; 2]> {
+        let commute = self.cfg.commute?;
+        let mut counts = [SirCounts::default(); 2];
+        for boid in &self.boids {
+            let c = &mut counts[match commute.patch_of(boid.pos, self.cfg.world_size) {
+                Patch::Home => 0,
+                Patch::Work => 1,
+            }];
+            c.add(boid.state);
+        }
+        Some(counts)
+    }
+
+    /// Confine every boid to the zone of `spec` it is in now, until lifted.
+    /// Boids that arrive later are not confined.
+    pub fn restrict_travel(&mut self, spec: TravelRestriction) -> Result<(), String> {
+        let tag = self.tag_registry.value(ZONE_TAG)?;
+        let world = self.cfg.world_size;
+        for boid in &mut self.boids {
+            boid.tags
+                .set_value(tag, (spec.zone_of(boid.pos, world) + 1) as f32);
+        }
+        self.restriction = Some((spec, self.time));
+        self.restriction_periods.push((self.time, None));
+        Ok(())
+    }
+
+    pub fn lift_travel_restriction(&mut self) {
+        if self.restriction.take().is_none() {
+            return;
+        }
+        if let Some(tag) = self.tag_registry.find_value(ZONE_TAG) {
+            for boid in &mut self.boids {
+                boid.tags.set_value(tag, 0.0);
             }
         }
+        if let Some((_, end)) = self.restriction_periods.last_mut() {
+            *end = Some(self.time);
+        }
+    }
+
+    /// The travel restriction in force, if any.
+    pub fn travel_restriction(&self) -> Option<&TravelRestriction> {
+        self.restriction.as_ref().map(|(spec, _)| spec)
+    }
+
+    /// Start and, once lifted, end time of each travel restriction so far.
+    pub fn restriction_periods(&self) -> &[(f32, Option<f32>)] {
+        &self.restriction_periods
+    }
+
+    /// Lift an expired restriction, or start the configured one when
+    /// prevalence first reaches its threshold.
+    fn update_restriction(&mut self) {
+        if let Some((spec, since)) = self.restriction {
+            if spec.duration > 0.0 && self.time - since >= spec.duration {
+                self.lift_travel_restriction();
+            }
+            return;
+        }
+        let Some(spec) = self.cfg.travel_restriction else {
+            return;
+        };
+        let Some(threshold) = spec.threshold else {
+            return;
+        };
+        if !self.restriction_periods.is_empty() || self.boids.is_empty() {
+            return;
+        }
+        let infected = self
+            .boids
+            .iter()
+            .filter(|b| b.state == HealthState::Infected)
+            .count();
+        if infected as f32 >= threshold * self.boids.len() as f32 {
+            // Only fails when every tag value is taken by other features.
+            let _ = self.restrict_travel(spec);
+        }
+    }
+
+    /// Regions with their own transmission multiplier. Where venues overlap,
+    /// the first one containing both boids applies.
+    pub fn set_venues(&mut self, venues: Vec<Venue>) {
+        self.venues = venues;
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) {
+        self.quality = Quality {
+            policy_interval: quality.policy_interval.max(1),
+            ..quality
+        };
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    pub fn venues(&self) -> &[Venue] {
+        &self.venues
+    }
+
+    /// Strategy used by `immunization::apply`, for immunizing mid-run.
+    pub fn set_immunization(&mut self, strategy: Immunization) {
+        self.cfg.immunization = strategy;
+    }
+
+    /// Whether the activity schedule currently has boids resting at home.
+    pub fn is_night(&self) -> bool {
+        self.cfg.schedule.is_some_and(|s| s.is_night(self.time))
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.step_with_forces(dt, &[]);
+    }
+
+    /// `step` with the boids from index `owned` on being halo copies, whose
+    /// transitions are neither counted nor logged here.
+    pub(crate) fn step_with_halo(&mut self, dt: f32, owned: usize) {
+        self.owned = owned;
+        self.step(dt);
+        self.owned = usize::MAX;
+    }
+
+    /// Move the simulation `elapsed` seconds forward in fixed steps of
+    /// `TICK`, carrying the remainder over to the next call, so a run takes
+    /// the same steps whatever the frame rate. Returns the steps taken.
+    pub fn advance(&mut self, elapsed: f32) -> usize {
+        self.accumulator += elapsed.max(0.0);
+        let ticks = ((self.accumulator / TICK) as usize).min(MAX_TICKS);
+        self.accumulator = if ticks == MAX_TICKS {
+            0.0
+        } else {
+            self.accumulator - ticks as f32 * TICK
+        };
+        for _ in 0..ticks {
+            self.step(TICK);
+        }
+        ticks
+    }
+
+    /// Step with an extra acceleration per boid on top of the movement model,
+    /// e.g. from a script. Boids past the end of `forces` get none.
+    pub fn step_with_forces(&mut self, dt: f32, forces: &[Vec2f]) {
+        self.step_partial(0..self.boids.len(), dt);
+        self.finalize_step(forces);
+    }
+
+    /// Sense and roll infections for the boids in `range` as part of the
+    /// current step, starting one if none is pending. Spreading the ranges of
+    /// one step over several frames and then calling `finalize_step` gives
+    /// exactly the same result as `step`, since positions only change in the
+    /// finalize phase and infection rolls do not depend on visiting order.
+    /// `dt` is taken from the call that starts the step; the population must
+    /// not change until it is finalized.
+    pub fn step_partial(&mut self, range: Range<usize>, dt: f32) {
+        let mut pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => self.begin_step(dt),
+        };
+        let end = range.end.min(pending.sensed.len());
+        self.sense_range(range.start.min(end)..end, &mut pending);
+        self.pending = Some(pending);
+    }
+
+    /// Whether a step started by `step_partial` is waiting to be finalized.
+    pub fn step_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Finish the pending step: sense any boids no chunk covered, then move
+    /// every boid and resolve infections and recoveries. Extra accelerations
+    /// in `forces` are applied as in `step_with_forces`. Does nothing when no
+    /// step is pending.
+    pub fn finalize_step(&mut self, forces: &[Vec2f]) {
+        let Some(mut pending) = self.pending.take() else {
+            return;
+        };
+        if pending.sensed.len() != self.boids.len() {
+            // A resize mid-step invalidates the per-boid results.
+            pending = self.begin_step(pending.dt);
+        }
+        self.sense_range(0..self.boids.len(), &mut pending);
+        let PendingStep {
+            dt,
+            night,
+            flocking,
+            accelerations,
+            newly_infected,
+            infection_venue,
+            household_case,
+            ..
+        } = pending;
+        self.time += dt;
 
-        for (boid, accel) in self.boids.iter_mut().zip(accelerations) {
-            boid.vel = boid.vel.add(accel.mul(dt)).limit(self.cfg.max_speed);
-            boid.pos = boid.pos.add(boid.vel.mul(dt));
-            boid.pos = wrap_position(boid.pos, self.cfg.world_size);
+        let restriction = self.restriction.zip(self.tag_registry.find_value(ZONE_TAG));
+        let commute = self
+            .cfg
+            .commute
+            .map(|c| (c, self.tag_registry.find_flag(COMMUTER_TAG)));
+        let confined = self
+            .tag_registry
+            .find_value(CONFINED_TAG)
+            .filter(|_| !self.quarantine_zones.is_empty());
+        let obstacles = self.obstacles();
+        let mut leaving = Vec::new();
+        for (i, (boid, accel)) in self.boids.iter_mut().zip(accelerations).enumerate() {
+            if boid.quarantined {
+                boid.vel = Vec2f::default();
+                continue;
+            }
+            let start_vel = boid.vel;
+            if night {
+                steer_home(boid, &self.cfg, dt);
+            } else if flocking {
+                boid.vel = boid.vel.add(accel.mul(dt)).limit(self.cfg.max_speed);
+            } else {
+                let u = [
+                    hash_unit(self.seed ^ MOVEMENT_STREAM, self.step_index, i),
+                    hash_unit(
+                        self.seed ^ MOVEMENT_STREAM.rotate_left(16),
+                        self.step_index,
+                        i,
+                    ),
+                ];
+                random_walk(boid, &self.cfg, u, dt);
+            }
+            if let Some(force) = forces.get(i) {
+                boid.vel = boid.vel.add(force.mul(dt)).limit(self.cfg.max_speed);
+            }
+            if let Some(((spec, _), tag)) = restriction {
+                let zone = boid.tags.get(tag) as usize;
+                if zone > 0 {
+                    let push =
+                        spec.push(boid.pos, zone - 1, self.cfg.world_size, self.cfg.max_force);
+                    boid.vel = boid.vel.add(push.mul(dt)).limit(self.cfg.max_speed);
+                }
+            }
+            if let Some((spec, flag)) = commute {
+                let commuter = flag.is_some_and(|f| boid.tags.has(f));
+                let target = spec.target(commuter, self.time);
+                let push = spec.push(boid.pos, target, self.cfg.world_size, self.cfg.max_force);
+                boid.vel = boid.vel.add(push.mul(dt)).limit(self.cfg.max_speed);
+            }
+            if let Some(tag) = confined {
+                let zones = &self.quarantine_zones;
+                // Infected boids are held by the first zone they are found
+                // in until they stop being infected.
+                let zone = match boid.tags.get(tag) as usize {
+                    _ if boid.state != HealthState::Infected => 0,
+                    0 => zones
+                        .iter()
+                        .position(|z| z.contains(boid.pos))
+                        .map_or(0, |k| k + 1),
+                    k => k,
+                };
+                boid.tags.set_value(tag, zone as f32);
+                let push = match zones.get(zone.wrapping_sub(1)) {
+                    Some(z) => z.confine(boid.pos, self.cfg.max_force),
+                    None => zones.iter().fold(Vec2f::default(), |acc, z| {
+                        acc.add(z.repel(boid.pos, self.cfg.max_force))
+                    }),
+                };
+                boid.vel = boid.vel.add(push.mul(dt)).limit(self.cfg.max_speed);
+            }
+            if !obstacles.is_empty() {
+                let push = obstacles.iter().fold(Vec2f::default(), |acc, o| {
+                    acc.add(o.repel(boid.pos, self.cfg.max_force))
+                });
+                boid.vel = boid.vel.add(push.mul(dt)).limit(self.cfg.max_speed);
+            }
+            let move_vel = match self.cfg.integrator {
+                Integrator::SemiImplicitEuler => boid.vel,
+                Integrator::Rk2 => start_vel.add(boid.vel).mul(0.5),
+            };
+            boid.pos = boid.pos.add(move_vel.mul(dt));
+            if !apply_boundaries(boid, &self.cfg) {
+                leaving.push(i);
+            }
+            for obstacle in &obstacles {
+                obstacle.collide(&mut boid.pos, &mut boid.vel);
+            }
         }
 
+        // Resolve infectors before any state changes so every new case points
+        // at the infection that was active during this step.
+        let infectors: Vec<Option<Option<usize>>> = newly_infected
+            .iter()
+            .map(|src| src.map(|j| self.boids[j].infection))
+            .collect();
         for (i, boid) in self.boids.iter_mut().enumerate() {
-            if newly_infected[i] {
-                boid.state = HealthState::Infected;
+            let owned = i < self.owned;
+            if let Some(infector) = infectors[i] {
+                boid.state = match self.cfg.disease_model {
+                    DiseaseModel::Seir => HealthState::Exposed,
+                    DiseaseModel::Sir | DiseaseModel::Sis => HealthState::Infected,
+                };
                 boid.infected_time = 0.0;
+                if owned {
+                    if boid.infection.is_none() {
+                        self.transitions.first_infections += 1;
+                    }
+                    boid.infection = Some(self.infection_log.len());
+                    self.infection_log.push(InfectionEvent {
+                        boid: i,
+                        infector,
+                        time: self.time,
+                        onset: self.time,
+                        pos: boid.pos,
+                        venue: infection_venue[i],
+                        household: household_case[i],
+                    });
+                    self.transitions.infections += 1;
+                }
             }
-            if boid.state == HealthState::Infected {
+            // `infected_time` is the time spent in the current state.
+            if boid.state == HealthState::Exposed {
+                boid.infected_time += dt;
+                if boid.infected_time >= self.cfg.incubation_period {
+                    boid.state = HealthState::Infected;
+                    boid.infected_time = 0.0;
+                    if let Some(event) = boid.infection.and_then(|k| self.infection_log.get_mut(k))
+                    {
+                        event.onset = self.time;
+                    }
+                    if owned {
+                        self.transitions.onsets += 1;
+                    }
+                }
+            } else if boid.state == HealthState::Infected {
                 boid.infected_time += dt;
                 if boid.infected_time >= self.cfg.infectious_period {
-                    boid.state = HealthState::Recovered;
+                    boid.state = match self.cfg.disease_model {
+                        DiseaseModel::Sis => HealthState::Susceptible,
+                        DiseaseModel::Sir | DiseaseModel::Seir => HealthState::Recovered,
+                    };
+                    if owned {
+                        self.transitions.recoveries += 1;
+                    }
+                }
+            }
+        }
+        if !leaving.is_empty() {
+            self.handle_leaving(&leaving);
+        }
+        self.update_restriction();
+        self.step_index += 1;
+    }
+
+    fn begin_step(&mut self, dt: f32) -> PendingStep {
+        self.rebuild_grid();
+        // Boids added or removed since the last step keep a stale or zero
+        // steering until their next evaluation.
+        self.steering.resize(self.boids.len(), Vec2f::default());
+        let night = self.is_night();
+        let n = self.boids.len();
+        PendingStep {
+            dt,
+            night,
+            flocking: self.cfg.movement == MovementModel::Flocking && !night,
+            sensed: vec![false; n],
+            accelerations: vec![Vec2f::default(); n],
+            newly_infected: vec![None; n],
+            infection_venue: vec![None; n],
+            household_case: vec![false; n],
+            housemates: self.housemates(),
+        }
+    }
+
+    /// Members of each household, indexed by id - 1; empty when households
+    /// are off.
+    fn housemates(&self) -> Vec<Vec<usize>> {
+        let Some(tag) = self.household_tag() else {
+            return Vec::new();
+        };
+        let mut housemates: Vec<Vec<usize>> = Vec::new();
+        for (i, boid) in self.boids.iter().enumerate() {
+            let id = boid.tags.get(tag) as usize;
+            if id > 0 {
+                if housemates.len() < id {
+                    housemates.resize_with(id, Vec::new);
                 }
+                housemates[id - 1].push(i);
             }
         }
+        housemates
+    }
+
+    fn household_tag(&self) -> Option<Value> {
+        self.cfg.households?;
+        self.tag_registry.find_value(HOUSEHOLD_TAG)
+    }
+
+    /// Group the boids into households of `households.size` in index order.
+    /// Each household's home anchor is its first member's starting position,
+    /// and everyone starts the run at home. Boids added later belong to no
+    /// household.
+    fn assign_households(&mut self, households: Households) {
+        let Ok(tag) = self.tag_registry.value(HOUSEHOLD_TAG) else {
+            return;
+        };
+        let size = households.size.max(1);
+        for start in (0..self.boids.len()).step_by(size) {
+            let home = self.boids[start].home;
+            let end = (start + size).min(self.boids.len());
+            for (k, boid) in self.boids[start..end].iter_mut().enumerate() {
+                boid.tags.set_value(tag, (start / size + 1) as f32);
+                boid.home = home;
+                // Spread round the anchor so housemates do not overlap.
+                let angle = k as f32 / size as f32 * std::f32::consts::TAU;
+                let pos = home.add(Vec2f::new(angle.cos(), angle.sin()).mul(4.0));
+                let world = self.cfg.world_size;
+                boid.pos = Vec2f::new(
+                    pos.x.clamp(0.0, world.x - 0.01),
+                    pos.y.clamp(0.0, world.y - 0.01),
+                );
+            }
+        }
+    }
+
+    /// Sense the boids in `range` not yet sensed this step, in parallel, and
+    /// record the results in `pending`. Every boid reads only the state at
+    /// the start of the step, so the result is the same however the work is
+    /// split.
+    fn sense_range(&mut self, range: Range<usize>, pending: &mut PendingStep) {
+        let todo: Vec<usize> = range.filter(|&i| !pending.sensed[i]).collect();
+        let results: Vec<Sensed> = {
+            let (sim, pending) = (&*self, &*pending);
+            todo.par_iter()
+                .with_min_len(SENSE_CHUNK)
+                .map(|&i| sim.sense(i, pending))
+                .collect()
+        };
+        for (&i, sensed) in todo.iter().zip(results) {
+            pending.sensed[i] = true;
+            if let Some(steering) = sensed.steering {
+                self.steering[i] = steering;
+            }
+            if pending.flocking {
+                pending.accelerations[i] = self.steering[i];
+            }
+            pending.newly_infected[i] = sensed.infector;
+            pending.infection_venue[i] = sensed.venue;
+            pending.household_case[i] = sensed.household;
+        }
+    }
+
+    /// Steering and the infection roll for boid `i`, from positions at the
+    /// start of the step.
+    fn sense(&self, i: usize, pending: &PendingStep) -> Sensed {
+        let interval = u64::from(self.quality.policy_interval.max(1));
+        let evaluate = pending.flocking && (self.step_index + i as u64).is_multiple_of(interval);
+        let (steering, infected_contact) = if evaluate {
+            let (inputs, infected_contact) = self.features_for(i);
+            let accel = self.policy.forward(&inputs).mul(self.cfg.max_force);
+            (Some(accel.limit(self.cfg.max_force)), infected_contact)
+        } else {
+            (None, self.infected_contact(i))
+        };
+        let mut sensed = Sensed {
+            steering,
+            infector: None,
+            venue: None,
+            household: false,
+        };
+        // Counter-based roll: the outcome depends only on (seed, step, boid),
+        // never on the order in which boids are visited or the thread.
+        if self.boids[i].state == HealthState::Susceptible
+            && !self.boids[i].quarantined
+            && let Some(j) = infected_contact
+        {
+            let venue = shared_venue(&self.venues, self.boids[i].pos, self.boids[j].pos);
+            let multiplier = venue.map_or(1.0, |k| self.venues[k].multiplier);
+            let infect_p = 1.0 - (-self.cfg.infection_beta * multiplier * pending.dt).exp();
+            if hash_unit(self.seed, self.step_index, i) < infect_p {
+                sensed.infector = infected_contact;
+                sensed.venue = venue;
+            }
+        }
+        if sensed.infector.is_none()
+            && let Some(closest) = self.sense_household(i, pending)
+        {
+            sensed.infector = Some(closest);
+            sensed.household = true;
+        }
+        sensed
+    }
+
+    /// The within-household roll for boid `i`: every infectious housemate at
+    /// night, and those within the household radius by day, each add
+    /// `households.beta` to the rate. Returns the closest of them if the
+    /// boid is infected.
+    fn sense_household(&self, i: usize, pending: &PendingStep) -> Option<usize> {
+        let (Some(households), Some(tag)) = (self.cfg.households, self.household_tag()) else {
+            return None;
+        };
+        let boid = self.boids[i];
+        let id = boid.tags.get(tag) as usize;
+        if id == 0
+            || id > pending.housemates.len()
+            || boid.state != HealthState::Susceptible
+            || boid.quarantined
+        {
+            return None;
+        }
+        let mut sources = 0;
+        let mut closest = None;
+        let mut closest_dist = f32::INFINITY;
+        for &j in &pending.housemates[id - 1] {
+            let other = self.boids[j];
+            if j == i || other.state != HealthState::Infected || other.quarantined {
+                continue;
+            }
+            let dist = self.grid.offset(boid.pos, other.pos).length();
+            if !pending.night && dist >= households.radius {
+                continue;
+            }
+            sources += 1;
+            if dist < closest_dist {
+                closest = Some(j);
+                closest_dist = dist;
+            }
+        }
+        let infect_p = 1.0 - (-households.beta * sources as f32 * pending.dt).exp();
+        if sources > 0 && hash_unit(self.seed ^ HOUSEHOLD_STREAM, self.step_index, i) < infect_p {
+            closest
+        } else {
+            None
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn step_index(&self) -> u64 {
+        self.step_index
+    }
+
+    pub fn transitions(&self) -> Transitions {
+        self.transitions
+    }
+
+    /// Simulated seconds since the simulation was created.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Every infection so far, seeded cases first, in the order they happened.
+    pub fn infection_log(&self) -> &[InfectionEvent] {
+        &self.infection_log
+    }
+
+    /// Heap memory currently held, for comparison with
+    /// `MemoryFootprint::estimate`.
+    pub fn memory_footprint(&self) -> MemoryFootprint {
+        MemoryFootprint {
+            boids: self.boids.capacity() * size_of::<Boid>(),
+            grid: self.grid.heap_bytes(),
+            infection_log: self.infection_log.capacity() * size_of::<InfectionEvent>(),
+            policy: self.policy.heap_bytes(),
+        }
+    }
+
+    /// FNV-1a hash over the exact bit patterns of every boid's position,
+    /// velocity and health state. Two runs are bit-identical iff their hashes
+    /// match at every step.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        let mut mix = |word: u32| {
+            for byte in word.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+        for boid in &self.boids {
+            mix(boid.pos.x.to_bits());
+            mix(boid.pos.y.to_bits());
+            mix(boid.vel.x.to_bits());
+            mix(boid.vel.y.to_bits());
+            mix(boid.state as u32);
+            mix(boid.infected_time.to_bits());
+        }
+        hash
     }
 
     pub fn counts(&self) -> SirCounts {
         let mut counts = SirCounts::default();
         for boid in &self.boids {
-            match boid.state {
-                HealthState::Susceptible => counts.susceptible += 1,
-                HealthState::Infected => counts.infected += 1,
-                HealthState::Recovered => counts.recovered += 1,
-            }
+            counts.add(boid.state);
         }
         counts
     }
 
-    fn features_for(&self, idx: usize) -> ([f32; FEATURE_SIZE], bool) {
+    /// All unordered pairs `(i, j)` with `i < j` whose boids are closer than
+    /// `radius`. Uses a private grid built from current positions, so it is
+    /// exact regardless of where in the step it is called.
+    pub fn contact_pairs(&self, radius: f32) -> Vec<(usize, usize)> {
+        let mut grid = neighbor_grid(&self.cfg, radius);
+        for (i, b) in self.boids.iter().enumerate() {
+            grid.insert(b.pos, i);
+        }
+        let mut pairs = Vec::new();
+        for (i, boid) in self.boids.iter().enumerate() {
+            pairs.extend(
+                grid.within(boid.pos, radius)
+                    .filter(|&(&j, _, _)| i < j)
+                    .map(|(&j, _, _)| (i, j)),
+            );
+        }
+        pairs
+    }
+
+    /// Flock id per boid: boids within the neighbor radius of each other,
+    /// directly or through a chain of others, share an id.
+    pub fn flocks(&self) -> Vec<usize> {
+        fn root(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+        let mut parent: Vec<usize> = (0..self.boids.len()).collect();
+        for (a, b) in self.contact_pairs(self.cfg.neighbor_radius) {
+            let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+            parent[ra] = rb;
+        }
+        (0..self.boids.len())
+            .map(|i| root(&mut parent, i))
+            .collect()
+    }
+
+    pub fn config(&self) -> &SimConfig {
+        &self.cfg
+    }
+
+    /// The steering policy every boid follows.
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Steer with `policy` from the next policy evaluation on. Fails if it
+    /// takes a different number of inputs than the features computed here.
+    pub fn set_policy(&mut self, policy: Policy) -> Result<(), String> {
+        if policy.input_size() != FEATURE_SIZE {
+            return Err(format!(
+                "policy takes {} inputs, the simulation provides {FEATURE_SIZE}",
+                policy.input_size()
+            ));
+        }
+        self.policy = policy;
+        Ok(())
+    }
+
+    /// Load every policy file in `paths` and steer with the first. Fails
+    /// without changing anything if a file can't be read or doesn't fit
+    /// this simulation.
+    pub fn load_policies(&mut self, paths: &[PathBuf]) -> Result<Vec<Policy>, String> {
+        let policies = paths
+            .iter()
+            .map(|path| {
+                let policy = Policy::load(path)?;
+                if policy.input_size() != FEATURE_SIZE {
+                    return Err(format!(
+                        "{}: policy takes {} inputs, the simulation provides {FEATURE_SIZE}",
+                        path.display(),
+                        policy.input_size()
+                    ));
+                }
+                Ok(policy)
+            })
+            .collect::<Result<Vec<Policy>, String>>()?;
+        if let Some(first) = policies.first() {
+            self.policy = first.clone();
+        }
+        Ok(policies)
+    }
+
+    /// Names of the flags and values stored in each boid's `tags`.
+    pub fn tag_registry(&self) -> &TagRegistry {
+        &self.tag_registry
+    }
+
+    pub fn tag_registry_mut(&mut self) -> &mut TagRegistry {
+        &mut self.tag_registry
+    }
+
+    /// Indices of the boids with `flag` set.
+    pub fn tagged(&self, flag: Flag) -> impl Iterator<Item = usize> + '_ {
+        self.boids
+            .iter()
+            .enumerate()
+            .filter(move |(_, b)| b.tags.has(flag))
+            .map(|(i, _)| i)
+    }
+
+    /// Move each susceptible boid to `Recovered` with probability `fraction`
+    /// and return how many were immunized.
+    pub fn immunize_random(&mut self, fraction: f32) -> usize {
+        self.immunize(Target::All, fraction)
+    }
+
+    /// Infect the susceptible boids in `target` as seeded cases: infectious
+    /// at once whatever the disease model, and logged with no infector.
+    /// Returns how many were infected.
+    pub fn infect(&mut self, target: Target) -> usize {
+        let mut infected = 0;
+        for (i, boid) in self.boids.iter_mut().enumerate() {
+            if boid.state != HealthState::Susceptible || !target.includes(i, boid) {
+                continue;
+            }
+            boid.state = HealthState::Infected;
+            boid.infected_time = 0.0;
+            boid.infection = Some(self.infection_log.len());
+            self.infection_log.push(InfectionEvent {
+                boid: i,
+                infector: None,
+                time: self.time,
+                onset: self.time,
+                pos: boid.pos,
+                venue: None,
+                household: false,
+            });
+            infected += 1;
+        }
+        infected
+    }
+
+    /// End boid `index`'s infection, exposed or infectious, as if it had run
+    /// its course: susceptible again under SIS, recovered otherwise. Returns
+    /// whether it was infected.
+    pub fn cure(&mut self, index: usize) -> bool {
+        let model = self.cfg.disease_model;
+        let Some(boid) = self.boids.get_mut(index) else {
+            return false;
+        };
+        if !matches!(boid.state, HealthState::Exposed | HealthState::Infected) {
+            return false;
+        }
+        boid.state = match model {
+            DiseaseModel::Sis => HealthState::Susceptible,
+            DiseaseModel::Sir | DiseaseModel::Seir => HealthState::Recovered,
+        };
+        boid.infected_time = 0.0;
+        self.transitions.recoveries += 1;
+        true
+    }
+
+    /// Move each susceptible boid in `target` to `Recovered` with
+    /// probability `fraction` and return how many were immunized. Draws
+    /// come from a stream of their own derived from the seed and the time,
+    /// so motion matches an unimmunized run with the same seed.
+    pub fn immunize(&mut self, target: Target, fraction: f32) -> usize {
+        let mut rng = Rng::new(self.seed ^ 0x1a2b_3c4d ^ u64::from(self.time.to_bits()));
+        let mut immunized = 0;
+        for (i, boid) in self.boids.iter_mut().enumerate() {
+            if boid.state == HealthState::Susceptible
+                && target.includes(i, boid)
+                && rng.next_f32() < fraction
+            {
+                boid.state = HealthState::Recovered;
+                immunized += 1;
+            }
+        }
+        immunized
+    }
+
+    /// Apply `action` to every boid inside the axis-aligned box from `min` to
+    /// `max` and return how many were affected. Vaccination only counts
+    /// boids that were susceptible, a zone the infected boids it confines.
+    pub fn apply_in_area(&mut self, min: Vec2f, max: Vec2f, action: AreaAction) -> usize {
+        let region = Target::Region { min, max };
+        let inside = |b: &Boid| region.includes(0, b);
+        match action {
+            AreaAction::Vaccinate => self.immunize(region, 1.0),
+            AreaAction::Infect => self.infect(region),
+            AreaAction::Quarantine => {
+                let mut affected = 0;
+                for boid in self.boids.iter_mut().filter(|b| inside(b)) {
+                    boid.quarantined = true;
+                    boid.vel = Vec2f::default();
+                    affected += 1;
+                }
+                affected
+            }
+            AreaAction::Cull => {
+                let before = self.boids.len();
+                self.boids.retain(|b| !inside(b));
+                let affected = before - self.boids.len();
+                self.transitions.culled += affected;
+                affected
+            }
+            AreaAction::Zone => self
+                .add_quarantine_zone(QuarantineZone::new(min, max))
+                .unwrap_or(0),
+        }
+    }
+
+    /// Add a zone and confine the infected boids already in it. Returns how
+    /// many were confined.
+    pub fn add_quarantine_zone(&mut self, zone: QuarantineZone) -> Result<usize, String> {
+        let tag = self.tag_registry.value(CONFINED_TAG)?;
+        self.quarantine_zones.push(zone);
+        let k = self.quarantine_zones.len();
+        let mut confined = 0;
+        for boid in &mut self.boids {
+            if boid.state == HealthState::Infected
+                && boid.tags.get(tag) == 0.0
+                && zone.contains(boid.pos)
+            {
+                boid.tags.set_value(tag, k as f32);
+                confined += 1;
+            }
+        }
+        Ok(confined)
+    }
+
+    /// Remove every quarantine zone and release the boids confined to them.
+    pub fn clear_quarantine_zones(&mut self) {
+        self.quarantine_zones.clear();
+        if let Some(tag) = self.tag_registry.find_value(CONFINED_TAG) {
+            for boid in &mut self.boids {
+                boid.tags.set_value(tag, 0.0);
+            }
+        }
+    }
+
+    pub fn quarantine_zones(&self) -> &[QuarantineZone] {
+        &self.quarantine_zones
+    }
+
+    /// The obstacles of `cfg.world` at the current world size.
+    pub fn obstacles(&self) -> Vec<Obstacle> {
+        self.cfg.world.obstacles(self.cfg.world_size)
+    }
+
+    /// The closest infectious boid within the infection radius of `idx`.
+    fn infected_contact(&self, idx: usize) -> Option<usize> {
+        let pos = self.boids[idx].pos;
+        let mut contact = None;
+        let mut contact_dist = f32::INFINITY;
+        for (_, &j) in self.grid.candidates(pos) {
+            let other = self.boids[j];
+            if idx == j || other.state != HealthState::Infected || other.quarantined {
+                continue;
+            }
+            let dist = self.grid.offset(pos, other.pos).length();
+            if dist < self.cfg.infection_radius && dist < contact_dist {
+                contact = Some(j);
+                contact_dist = dist;
+            }
+        }
+        contact
+    }
+
+    /// Policy inputs for boid `idx`, plus the nearest infected boid within the
+    /// infection radius (the attributed infector if `idx` gets infected).
+    fn features_for(&self, idx: usize) -> ([f32; FEATURE_SIZE], Option<usize>) {
         let boid = self.boids[idx];
         let mut align_sum = Vec2f::default();
         let mut cohesion_sum = Vec2f::default();
@@ -278,18 +1781,28 @@ impl Simulation {
         let mut infected_count = 0;
         let mut nearest_infected_dist = f32::INFINITY;
         let mut nearest_infected_dir = Vec2f::default();
-        let mut infected_contact = false;
+        let mut infected_contact = None;
+        let mut infected_contact_dist = f32::INFINITY;
+        let cap = self.quality.neighbor_cap.unwrap_or(usize::MAX);
+        let wrapped = self.cfg.torus().is_some();
 
-        self.grid.for_each_neighbor(boid.pos, |j| {
+        for (_, &j) in self.grid.candidates(boid.pos) {
             if idx == j {
-                return;
+                continue;
             }
             let other = self.boids[j];
-            let offset = other.pos.sub(boid.pos);
+            let offset = self.grid.offset(boid.pos, other.pos);
             let dist = offset.length();
-            if dist < self.cfg.neighbor_radius {
+            if dist < self.cfg.neighbor_radius && count < cap {
                 align_sum = align_sum.add(other.vel);
-                cohesion_sum = cohesion_sum.add(other.pos);
+                // The neighbour's image nearest this boid, so flocks hold
+                // together across a wrapping edge.
+                let seen = if wrapped {
+                    boid.pos.add(offset)
+                } else {
+                    other.pos
+                };
+                cohesion_sum = cohesion_sum.add(seen);
                 count += 1;
                 if dist < self.cfg.separation_radius && dist > 0.0 {
                     separation_sum = separation_sum.sub(offset.div(dist));
@@ -303,10 +1816,15 @@ impl Simulation {
                     }
                 }
             }
-            if other.state == HealthState::Infected && dist < self.cfg.infection_radius {
-                infected_contact = true;
+            if other.state == HealthState::Infected
+                && !other.quarantined
+                && dist < self.cfg.infection_radius
+                && dist < infected_contact_dist
+            {
+                infected_contact = Some(j);
+                infected_contact_dist = dist;
             }
-        });
+        }
 
         let mut inputs = [0.0; FEATURE_SIZE];
         let speed = boid.vel.length();
@@ -345,118 +1863,494 @@ impl Simulation {
             inputs[13] = infected_count as f32 / count as f32;
         }
 
+        if let Some((dir, dist)) = self.nearest_wall(boid.pos) {
+            inputs[14] = dir.x;
+            inputs[15] = dir.y;
+            inputs[16] = (dist / self.cfg.neighbor_radius).clamp(0.0, 1.0);
+        } else {
+            inputs[16] = 1.0;
+        }
+
         (inputs, infected_contact)
     }
 
+    /// Direction towards and distance to the closest edge that does not
+    /// wrap, if one is within the neighbour radius. Wrapping edges are open
+    /// space as far as a boid can tell.
+    fn nearest_wall(&self, pos: Vec2f) -> Option<(Vec2f, f32)> {
+        let b = self.cfg.boundaries;
+        let size = self.cfg.world_size;
+        [
+            (b.left, Vec2f::new(-1.0, 0.0), pos.x),
+            (b.right, Vec2f::new(1.0, 0.0), size.x - pos.x),
+            (b.top, Vec2f::new(0.0, -1.0), pos.y),
+            (b.bottom, Vec2f::new(0.0, 1.0), size.y - pos.y),
+        ]
+        .into_iter()
+        .filter(|&(edge, _, dist)| edge != Boundary::Wrap && dist < self.cfg.neighbor_radius)
+        .map(|(_, dir, dist)| (dir, dist.max(0.0)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
     fn rebuild_grid(&mut self) {
         self.grid.clear();
+        // The world size or boundaries may have changed since the last step.
+        self.grid.set_wrap(self.cfg.torus());
         for (i, b) in self.boids.iter().enumerate() {
-            self.grid.insert(i, b.pos);
+            self.grid.insert(b.pos, i);
+        }
+    }
+
+    /// Respawn or remove the boids at `leaving`, which crossed an absorbing
+    /// edge this step. Runs after infections are resolved, so indices held
+    /// during the step stay valid.
+    fn handle_leaving(&mut self, leaving: &[usize]) {
+        let size = self.cfg.world_size;
+        let b = self.cfg.boundaries;
+        let mut removed = vec![false; self.boids.len()];
+        for &i in leaving {
+            let pos = self.boids[i].pos;
+            let along_x = self.rng.next_f32() * size.x;
+            let along_y = self.rng.next_f32() * size.y;
+            // Edge crossed, plus where and in which direction a replacement
+            // enters: through the opposite edge, heading inward.
+            let (edge, entry, inward) = if pos.x < 0.0 {
+                (
+                    b.left,
+                    Vec2f::new(size.x - 0.01, along_y),
+                    Vec2f::new(-1.0, 0.0),
+                )
+            } else if pos.x >= size.x {
+                (b.right, Vec2f::new(0.0, along_y), Vec2f::new(1.0, 0.0))
+            } else if pos.y < 0.0 {
+                (
+                    b.top,
+                    Vec2f::new(along_x, size.y - 0.01),
+                    Vec2f::new(0.0, -1.0),
+                )
+            } else {
+                (b.bottom, Vec2f::new(along_x, 0.0), Vec2f::new(0.0, 1.0))
+            };
+            let owned = i < self.owned;
+            if owned {
+                self.transitions.departures += 1;
+            }
+            if edge == Boundary::Respawn {
+                if owned {
+                    self.transitions.arrivals += 1;
+                }
+                let mut boid = random_boid(&mut self.rng, &self.cfg);
+                boid.pos = entry;
+                if inward.x != 0.0 {
+                    boid.vel.x = boid.vel.x.abs() * inward.x;
+                } else {
+                    boid.vel.y = boid.vel.y.abs() * inward.y;
+                }
+                self.boids[i] = boid;
+            } else {
+                removed[i] = true;
+            }
         }
+        let mut keep = removed.into_iter().map(|r| !r);
+        self.boids.retain(|_| keep.next().unwrap_or(true));
     }
 
     fn seed_infections(&mut self) {
         let count = self.cfg.initial_infected.min(self.boids.len());
         for _ in 0..count {
-            let idx = (self.rng.next_f32() * self.boids.len() as f32) as usize;
+            let idx = self.rng.next_index(self.boids.len());
             let boid = &mut self.boids[idx];
+            if boid.state == HealthState::Infected {
+                continue;
+            }
             boid.state = HealthState::Infected;
             boid.infected_time = 0.0;
+            boid.infection = Some(self.infection_log.len());
+            self.infection_log.push(InfectionEvent {
+                boid: idx,
+                infector: None,
+                time: 0.0,
+                onset: 0.0,
+                pos: boid.pos,
+                venue: None,
+                household: false,
+            });
         }
     }
 }
 
-fn wrap_position(pos: Vec2f, size: Vec2f) -> Vec2f {
-    let mut x = pos.x;
-    let mut y = pos.y;
-    if x < 0.0 {
-        x += size.x;
-    } else if x >= size.x {
-        x -= size.x;
+/// Draws allowed per boid to land outside every obstacle before the last
+/// draw is kept anyway and pushed out by the first step.
+const SPAWN_TRIES: usize = 32;
+
+fn random_boid(rng: &mut Rng, cfg: &SimConfig) -> Boid {
+    let (min, max) = cfg.world.spawn_area(cfg.world_size);
+    let obstacles = cfg.world.obstacles(cfg.world_size);
+    let mut pos = Vec2f::default();
+    for _ in 0..SPAWN_TRIES {
+        pos = Vec2f::new(
+            min.x + rng.next_f32() * (max.x - min.x),
+            min.y + rng.next_f32() * (max.y - min.y),
+        );
+        if !obstacles.iter().any(|o| o.contains(pos)) {
+            break;
+        }
     }
-    if y < 0.0 {
-        y += size.y;
-    } else if y >= size.y {
-        y -= size.y;
+    let angle = rng.next_f32() * std::f32::consts::TAU;
+    let speed = cfg.max_speed * (0.3 + 0.7 * rng.next_f32());
+    let vel = Vec2f::new(angle.cos(), angle.sin()).mul(speed);
+    Boid {
+        pos,
+        vel,
+        state: HealthState::Susceptible,
+        infected_time: 0.0,
+        infection: None,
+        flight_left: 0.0,
+        home: pos,
+        quarantined: false,
+        tags: Tags::default(),
     }
-    Vec2f::new(x, y)
 }
 
-struct NnPolicy {
-    input_size: usize,
-    hidden_size: usize,
-    w1: Vec<f32>,
-    b1: Vec<f32>,
-    w2: Vec<f32>,
-    b2: Vec<f32>,
+/// A neighbour grid of `cell_size` over `cfg`'s world, wrapping when every
+/// edge does.
+fn neighbor_grid(cfg: &SimConfig, cell_size: f32) -> Grid<usize> {
+    match cfg.torus() {
+        Some(world) => Grid::wrapping(cell_size, world),
+        None => Grid::new(cell_size),
+    }
 }
 
-impl NnPolicy {
-    fn new(input_size: usize, hidden_size: usize) -> Self {
-        Self {
-            input_size,
-            hidden_size,
-            w1: vec![0.0; input_size * hidden_size],
-            b1: vec![0.0; hidden_size],
-            w2: vec![0.0; hidden_size * 2],
-            b2: vec![0.0; 2],
+/// Wrap or reflect `boid` at the edges it crossed. Returns `false` if it
+/// crossed an absorbing edge and must be removed or respawned.
+fn apply_boundaries(boid: &mut Boid, cfg: &SimConfig) -> bool {
+    let size = cfg.world_size;
+    let b = cfg.boundaries;
+    let (pos, vel) = (&mut boid.pos, &mut boid.vel);
+    for (coord, v, extent, low, high) in [
+        (&mut pos.x, &mut vel.x, size.x, b.left, b.right),
+        (&mut pos.y, &mut vel.y, size.y, b.top, b.bottom),
+    ] {
+        let edge = if *coord < 0.0 {
+            low
+        } else if *coord >= extent {
+            high
+        } else {
+            continue;
+        };
+        match edge {
+            Boundary::Wrap if *coord < 0.0 => *coord += extent,
+            Boundary::Wrap => *coord -= extent,
+            Boundary::Reflect => {
+                *coord = if *coord < 0.0 {
+                    -*coord
+                } else {
+                    2.0 * extent - *coord
+                }
+                .clamp(0.0, extent - 0.01);
+                *v = -*v;
+            }
+            Boundary::Remove | Boundary::Respawn => return false,
         }
     }
+    true
+}
+
+/// Seek the boid's home anchor at the schedule's night speed, taking the
+/// short way around a wrapping world.
+fn steer_home(boid: &mut Boid, cfg: &SimConfig, dt: f32) {
+    let night_speed = cfg.max_speed * cfg.schedule.map_or(1.0, |s| s.night_speed);
+    let to_home = torus_delta(boid.pos, boid.home, cfg.world_size);
+    // Slow down over the last stretch so boids settle rather than orbit home.
+    const ARRIVE_RADIUS: f32 = 40.0;
+    let desired = to_home
+        .normalize_or_zero()
+        .mul(night_speed * (to_home.length() / ARRIVE_RADIUS).min(1.0));
+    let steer = desired.sub(boid.vel).limit(cfg.max_force);
+    boid.vel = boid.vel.add(steer.mul(dt)).limit(night_speed);
+}
+
+/// Salt separating movement draws from the infection rolls.
+const MOVEMENT_STREAM: u64 = 0x6d6f_7665;
+/// Salt separating within-household rolls from community ones.
+const HOUSEHOLD_STREAM: u64 = 0x686f_6d65;
+/// Salt for picking commuters.
+const COMMUTE_STREAM: u64 = 0x636f_6d6d;
 
-    fn randomize(&mut self, rng: &mut Lcg, scale: f32) {
-        for w in &mut self.w1 {
-            *w = (rng.next_f32() * 2.0 - 1.0) * scale;
+/// Set the velocity of `boid` under a null movement model from two uniform
+/// draws `u`.
+fn random_walk(boid: &mut Boid, cfg: &SimConfig, u: [f32; 2], dt: f32) {
+    let speed = cfg.max_speed;
+    let heading = |angle: f32| Vec2f::new(angle.cos(), angle.sin()).mul(speed);
+    match cfg.movement {
+        MovementModel::Flocking => {}
+        MovementModel::Brownian => boid.vel = heading(u[0] * std::f32::consts::TAU),
+        MovementModel::CorrelatedWalk { turn_sd } => {
+            // Box-Muller normal from the two draws.
+            let normal = (-2.0 * (1.0 - u[0]).ln()).sqrt() * (std::f32::consts::TAU * u[1]).cos();
+            let angle = boid.vel.y.atan2(boid.vel.x) + normal * turn_sd * dt.sqrt();
+            boid.vel = heading(angle);
         }
-        for b in &mut self.b1 {
-            *b = (rng.next_f32() * 2.0 - 1.0) * scale;
+        MovementModel::LevyFlight {
+            exponent,
+            min_flight,
+        } => {
+            if boid.flight_left <= 0.0 {
+                let max_flight = cfg.world_size.length();
+                boid.flight_left =
+                    (min_flight * (1.0 - u[1]).powf(-1.0 / (exponent - 1.0))).min(max_flight);
+                boid.vel = heading(u[0] * std::f32::consts::TAU);
+            }
+            boid.vel = boid.vel.normalize_or_zero().mul(speed);
+            boid.flight_left -= speed * dt;
         }
-        for w in &mut self.w2 {
-            *w = (rng.next_f32() * 2.0 - 1.0) * scale;
+    }
+}
+
+/// Stateless counter-based uniform in [0, 1): a SplitMix64 finalizer over
+/// (seed, step, index). Used for per-boid rolls that must not depend on
+/// iteration order or thread count.
+fn hash_unit(seed: u64, step: u64, index: usize) -> f32 {
+    let mut z = seed
+        ^ step.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (index as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy(n: usize, model: DiseaseModel) -> Simulation {
+        let cfg = SimConfig {
+            initial_infected: 0,
+            disease_model: model,
+            ..SimConfig::default()
+        };
+        Simulation::new(n, cfg, 3)
+    }
+
+    #[test]
+    fn neighbours_are_found_across_wrapping_edges_only() {
+        let mut sim = healthy(2, DiseaseModel::Sir);
+        let world = sim.config().world_size;
+        sim.boids[0].pos = Vec2f::new(1.0, 1.0);
+        sim.boids[1].pos = Vec2f::new(world.x - 1.0, world.y - 1.0);
+        assert!(sim.config().boundaries.wraps());
+        assert_eq!(sim.contact_pairs(5.0), [(0, 1)]);
+
+        let cfg = SimConfig {
+            initial_infected: 0,
+            boundaries: Boundaries::uniform(Boundary::Reflect),
+            ..SimConfig::default()
+        };
+        let mut sim = Simulation::new(2, cfg, 3);
+        sim.boids[0].pos = Vec2f::new(1.0, 1.0);
+        sim.boids[1].pos = Vec2f::new(world.x - 1.0, world.y - 1.0);
+        assert!(sim.contact_pairs(5.0).is_empty());
+    }
+
+    #[test]
+    fn infect_one_boid_logs_a_seeded_case() {
+        let mut sim = healthy(50, DiseaseModel::Seir);
+        assert_eq!(sim.infect(Target::Boid(7)), 1);
+        assert_eq!(sim.boids[7].state, HealthState::Infected);
+        assert_eq!(sim.infection_log().len(), 1);
+        assert_eq!(sim.infection_log()[0].boid, 7);
+        assert_eq!(sim.infection_log()[0].infector, None);
+        // Already infected, and out of range.
+        assert_eq!(sim.infect(Target::Boid(7)), 0);
+        assert_eq!(sim.infect(Target::Boid(50)), 0);
+    }
+
+    #[test]
+    fn infect_region_only_reaches_susceptibles_inside() {
+        let mut sim = healthy(400, DiseaseModel::Sir);
+        let (min, max) = (Vec2f::new(0.0, 0.0), Vec2f::new(300.0, 200.0));
+        sim.boids[0].pos = Vec2f::new(10.0, 10.0);
+        sim.boids[0].state = HealthState::Recovered;
+        let inside =
+            |b: &Boid| b.pos.x >= min.x && b.pos.x <= max.x && b.pos.y >= min.y && b.pos.y <= max.y;
+        let expected = sim
+            .boids
+            .iter()
+            .filter(|b| inside(b) && b.state == HealthState::Susceptible)
+            .count();
+        assert!(expected > 0);
+        assert_eq!(sim.infect(Target::Region { min, max }), expected);
+        for boid in &sim.boids {
+            let infected = boid.state == HealthState::Infected;
+            assert_eq!(infected, inside(boid) && boid.infection.is_some());
+        }
+        assert_eq!(sim.boids[0].state, HealthState::Recovered);
+    }
+
+    #[test]
+    fn cure_follows_the_disease_model() {
+        for (model, after) in [
+            (DiseaseModel::Sir, HealthState::Recovered),
+            (DiseaseModel::Sis, HealthState::Susceptible),
+            (DiseaseModel::Seir, HealthState::Recovered),
+        ] {
+            let mut sim = healthy(10, model);
+            sim.infect(Target::Boid(2));
+            assert!(sim.cure(2));
+            assert_eq!(sim.boids[2].state, after);
+            assert_eq!(sim.transitions().recoveries, 1);
+            assert!(!sim.cure(3));
+            assert!(!sim.cure(10));
         }
-        for b in &mut self.b2 {
-            *b = (rng.next_f32() * 2.0 - 1.0) * scale;
+    }
+
+    #[test]
+    fn immunize_takes_about_the_fraction_and_leaves_motion_alone() {
+        let mut sim = healthy(2000, DiseaseModel::Sir);
+        let mut control = healthy(2000, DiseaseModel::Sir);
+        let immunized = sim.immunize(Target::All, 0.25);
+        assert!((400..600).contains(&immunized), "{immunized}");
+        assert_eq!(sim.counts().recovered, immunized);
+        assert_eq!(sim.immunize(Target::All, 0.0), 0);
+        for _ in 0..10 {
+            sim.step(1.0 / 60.0);
+            control.step(1.0 / 60.0);
+        }
+        for (a, b) in sim.boids.iter().zip(&control.boids) {
+            assert_eq!(a.pos, b.pos);
         }
     }
 
-    fn forward(&self, input: &[f32; FEATURE_SIZE]) -> Vec2f {
-        let mut hidden = vec![0.0; self.hidden_size];
-        for h in 0..self.hidden_size {
-            let mut acc = self.b1[h];
-            let row = h * self.input_size;
-            for i in 0..self.input_size {
-                acc += self.w1[row + i] * input[i];
+    #[test]
+    fn same_seed_gives_bit_identical_runs() {
+        let run = |seed| {
+            let mut sim = Simulation::new(300, SimConfig::default(), seed);
+            for _ in 0..200 {
+                sim.step(1.0 / 60.0);
             }
-            hidden[h] = acc.tanh();
-        }
+            sim.boids
+                .iter()
+                .map(|b| (b.pos.x.to_bits(), b.pos.y.to_bits(), b.state))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(u64::MAX - 9), run(u64::MAX - 9));
+        assert_ne!(run(1), run(2));
+    }
 
-        let mut out = [0.0; 2];
-        for o in 0..2 {
-            let mut acc = self.b2[o];
-            let row = o * self.hidden_size;
-            for h in 0..self.hidden_size {
-                acc += self.w2[row + h] * hidden[h];
+    #[test]
+    fn sensing_in_slices_matches_a_whole_step() {
+        let state = |sim: &Simulation| {
+            sim.boids
+                .iter()
+                .map(|b| (b.pos.x.to_bits(), b.pos.y.to_bits(), b.state))
+                .collect::<Vec<_>>()
+        };
+        let mut whole = Simulation::new(500, SimConfig::default(), 11);
+        let mut sliced = Simulation::new(500, SimConfig::default(), 11);
+        for _ in 0..60 {
+            whole.step(1.0 / 60.0);
+            // Uneven, overlapping slices, smaller than a rayon task and larger.
+            for range in [0..7, 3..300, 450..900] {
+                sliced.step_partial(range, 1.0 / 60.0);
             }
-            out[o] = acc.tanh();
+            sliced.finalize_step(&[]);
         }
-        Vec2f::new(out[0], out[1])
+        assert_eq!(state(&whole), state(&sliced));
     }
-}
 
-struct Lcg {
-    state: u32,
-}
+    #[test]
+    fn removing_and_spawning_boids_keeps_indices_consistent() {
+        let mut sim = healthy(4, DiseaseModel::Sir);
+        sim.step(0.1);
+        let steering = sim.steering.clone();
+        let after: Vec<Vec2f> = sim.boids[2..].iter().map(|b| b.pos).collect();
 
-impl Lcg {
-    fn new(seed: u32) -> Self {
-        Self { state: seed }
+        assert!(sim.remove_boid(1).is_some());
+        assert!(sim.remove_boid(9).is_none());
+        assert_eq!(sim.boids.len(), 3);
+        let shifted: Vec<Vec2f> = sim.boids[1..].iter().map(|b| b.pos).collect();
+        assert_eq!(shifted, after);
+        assert_eq!(sim.steering[1..], steering[2..]);
+
+        let idx = sim.spawn_boid(
+            Vec2f::new(5.0, 5.0),
+            Vec2f::default(),
+            HealthState::Infected,
+        );
+        assert_eq!(idx, 3);
+        let event = sim.boids[idx]
+            .infection
+            .map(|k| sim.infection_log[k])
+            .unwrap();
+        assert_eq!((event.boid, event.infector), (idx, None));
+        sim.spawn_boid(
+            Vec2f::new(6.0, 6.0),
+            Vec2f::default(),
+            HealthState::Susceptible,
+        );
+        assert_eq!(sim.infection_log.len(), 1);
+
+        let t = sim.transitions();
+        assert_eq!((t.removed, t.spawned), (1, 2));
+        assert_eq!(sim.counts().infected, 1);
     }
 
-    fn next_u32(&mut self) -> u32 {
-        self.state = self.state.wrapping_mul(1664525).wrapping_add(1013904223);
-        self.state
+    #[test]
+    fn resizing_keeps_the_survivors() {
+        let mut sim = healthy(20, DiseaseModel::Sir);
+        for (i, boid) in sim.boids.iter_mut().enumerate() {
+            boid.pos = Vec2f::new(10.0 * i as f32, 5.0);
+            if i % 3 == 0 {
+                boid.state = HealthState::Infected;
+            }
+        }
+        let before = sim.boids.clone();
+        let survivor = |b: &Boid| {
+            let i = (b.pos.x / 10.0) as usize;
+            (i, b.state == before[i].state)
+        };
+
+        sim.resize(12);
+        let kept: Vec<(usize, bool)> = sim.boids.iter().map(survivor).collect();
+        assert_eq!(kept.len(), 12);
+        assert!(kept.windows(2).all(|w| w[0].0 < w[1].0), "order kept");
+        assert!(kept.iter().all(|&(_, same)| same), "states kept");
+
+        sim.resize(15);
+        assert_eq!(
+            sim.boids[..12].iter().map(survivor).collect::<Vec<_>>(),
+            kept
+        );
+        assert!(
+            sim.boids[12..]
+                .iter()
+                .all(|b| b.state == HealthState::Susceptible)
+        );
+        let t = sim.transitions();
+        assert_eq!((t.removed, t.spawned), (8, 3));
     }
 
-    fn next_f32(&mut self) -> f32 {
-        (self.next_u32() as f32) / (u32::MAX as f32)
+    #[test]
+    fn reinfections_are_not_first_infections() {
+        let cfg = SimConfig {
+            world_size: Vec2f::new(200.0, 200.0),
+            disease_model: DiseaseModel::Sis,
+            infection_beta: 50.0,
+            infection_radius: 60.0,
+            infectious_period: 1.0,
+            initial_infected: 5,
+            ..SimConfig::default()
+        };
+        let mut sim = Simulation::new(60, cfg, 2);
+        let ever_infected =
+            |sim: &Simulation| sim.boids.iter().filter(|b| b.infection.is_some()).count();
+        let seeded = ever_infected(&sim);
+        for _ in 0..100 {
+            sim.step(0.1);
+        }
+        let t = sim.transitions();
+        assert!(t.infections > 60, "{t:?}");
+        assert_eq!(seeded + t.first_infections, ever_infected(&sim));
     }
 }
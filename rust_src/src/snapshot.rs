@@ -0,0 +1,107 @@
+use std::io;
+use std::path::Path;
+
+use crate::heatmap::write_rgb_png;
+use crate::runner::run_in_place;
+use crate::sensitivity::RunSettings;
+use crate::sim::{HealthState, SimConfig, Simulation};
+
+const BACKGROUND: [u8; 3] = [10, 12, 18];
+/// Between tiles of a contact sheet.
+const GUTTER: usize = 2;
+const GUTTER_COLOR: [u8; 3] = [40, 60, 80];
+
+/// An 8-bit RGB image, rows from the top.
+#[derive(Clone, Debug)]
+pub struct RgbImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    pub fn new(width: usize, height: usize, fill: [u8; 3]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: fill.repeat(width * height),
+        }
+    }
+
+    fn put(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let at = (y * self.width + x) * 3;
+            self.pixels[at..at + 3].copy_from_slice(&rgb);
+        }
+    }
+
+    /// Copy `other` in with its top-left corner at `(x, y)`, clipped.
+    fn blit(&mut self, other: &RgbImage, x: usize, y: usize) {
+        for row in 0..other.height {
+            for col in 0..other.width {
+                let at = (row * other.width + col) * 3;
+                let rgb = [other.pixels[at], other.pixels[at + 1], other.pixels[at + 2]];
+                self.put(x + col, y + row, rgb);
+            }
+        }
+    }
+
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        write_rgb_png(path, self.width, self.height, &self.pixels)
+    }
+}
+
+/// The boids of `sim` as 2x2 dots in their health colors, scaled so the
+/// world is `width` pixels wide.
+pub fn render_state(sim: &Simulation, width: usize) -> RgbImage {
+    let world = sim.config().world_size;
+    let scale = width as f32 / world.x.max(1.0);
+    let height = ((world.y * scale).round() as usize).max(1);
+    let mut image = RgbImage::new(width, height, BACKGROUND);
+    for boid in &sim.boids {
+        let rgb = match boid.state {
+            HealthState::Susceptible => [220, 240, 255],
+            HealthState::Exposed => [255, 190, 90],
+            HealthState::Infected => [255, 90, 90],
+            HealthState::Recovered => [120, 220, 140],
+        };
+        let x = (boid.pos.x.rem_euclid(world.x) * scale) as usize;
+        let y = (boid.pos.y.rem_euclid(world.y) * scale) as usize;
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            image.put(x + dx, y + dy, rgb);
+        }
+    }
+    image
+}
+
+/// Run replicate `r` of `cfg` as `settings.replicate` does and render where
+/// it ended. Runs are deterministic, so this is the state behind that
+/// replicate's outcome.
+pub fn final_state(settings: &RunSettings, cfg: SimConfig, r: usize, width: usize) -> RgbImage {
+    let mut sim = Simulation::new(settings.boids, cfg, settings.seed.wrapping_add(r as u64));
+    run_in_place(&mut sim, settings.max_steps, settings.dt);
+    render_state(&sim, width)
+}
+
+/// `tiles` laid out `columns` to a row, first tile at the top left, with a
+/// thin gutter around each. Tiles are placed on a grid of the largest tile.
+pub fn contact_sheet(tiles: &[RgbImage], columns: usize) -> RgbImage {
+    let columns = columns.clamp(1, tiles.len().max(1));
+    let rows = tiles.len().div_ceil(columns);
+    let tile_w = tiles.iter().map(|t| t.width).max().unwrap_or(0);
+    let tile_h = tiles.iter().map(|t| t.height).max().unwrap_or(0);
+    let mut sheet = RgbImage::new(
+        columns * (tile_w + GUTTER) + GUTTER,
+        rows * (tile_h + GUTTER) + GUTTER,
+        GUTTER_COLOR,
+    );
+    for (k, tile) in tiles.iter().enumerate() {
+        let (col, row) = (k % columns, k / columns);
+        sheet.blit(
+            tile,
+            GUTTER + col * (tile_w + GUTTER),
+            GUTTER + row * (tile_h + GUTTER),
+        );
+    }
+    sheet
+}
@@ -0,0 +1,102 @@
+use std::f32::consts::TAU;
+
+pub const SAMPLE_RATE: u32 = 22_050;
+
+/// Background tone pitches from low to high prevalence: an A minor
+/// pentatonic scale, so stepping between them never sounds out of key.
+pub const TONE_PITCHES: [f32; 8] = [220.0, 261.6, 293.7, 329.6, 392.0, 440.0, 523.3, 587.3];
+
+/// Shortest gap between ticks, so a burst of infections reads as a rattle
+/// rather than a buzz.
+const TICK_GAP: f32 = 0.05;
+
+/// Encode mono samples in [-1, 1] as a 16-bit PCM WAV file.
+pub fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    out.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for &s in samples {
+        out.extend_from_slice(&((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    out
+}
+
+/// A 30 ms click: a high sine with a fast exponential decay.
+pub fn tick() -> Vec<f32> {
+    let n = (SAMPLE_RATE as f32 * 0.03) as usize;
+    (0..n)
+        .map(|k| {
+            let t = k as f32 / SAMPLE_RATE as f32;
+            (TAU * 1800.0 * t).sin() * (-t * 150.0).exp() * 0.8
+        })
+        .collect()
+}
+
+/// About one second of a soft sine at `freq`, trimmed to a whole number of
+/// cycles so it loops without a click.
+pub fn tone(freq: f32) -> Vec<f32> {
+    let cycles = freq.round().max(1.0);
+    let n = (SAMPLE_RATE as f32 * cycles / freq).round() as usize;
+    (0..n)
+        .map(|k| {
+            let phase = k as f32 / n as f32 * cycles;
+            // A touch of the octave above so low pitches stay audible on
+            // small speakers.
+            (TAU * phase).sin() * 0.5 + (TAU * 2.0 * phase).sin() * 0.15
+        })
+        .collect()
+}
+
+/// What to play this frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cue {
+    /// Volume of a tick to play now, if any.
+    pub tick: Option<f32>,
+    /// Index into `TONE_PITCHES` and volume of the background tone; volume
+    /// 0 when nobody is infected.
+    pub tone: (usize, f32),
+}
+
+/// Turns infections and prevalence into sound cues.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sonifier {
+    /// Infections not yet ticked for.
+    queued: usize,
+    since_tick: f32,
+}
+
+impl Sonifier {
+    /// `new_infections` since the last call, the infected fraction of the
+    /// population, and the frame time.
+    pub fn update(&mut self, new_infections: usize, prevalence: f32, dt: f32) -> Cue {
+        self.queued += new_infections;
+        self.since_tick += dt;
+        let tick = (self.queued > 0 && self.since_tick >= TICK_GAP).then(|| {
+            // Louder when several infections are waiting on one tick.
+            let volume = (0.25 + 0.1 * self.queued as f32).min(0.8);
+            self.queued = 0;
+            self.since_tick = 0.0;
+            volume
+        });
+        let p = prevalence.clamp(0.0, 1.0);
+        // Most outbreaks peak well below full prevalence, so spread the
+        // scale over the square root.
+        let step = ((p.sqrt() * TONE_PITCHES.len() as f32) as usize).min(TONE_PITCHES.len() - 1);
+        let volume = if p > 0.0 { 0.05 + 0.25 * p.sqrt() } else { 0.0 };
+        Cue {
+            tick,
+            tone: (step, volume),
+        }
+    }
+}
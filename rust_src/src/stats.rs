@@ -0,0 +1,420 @@
+use std::fmt;
+
+use crate::history::{DEFAULT_SAMPLE_INTERVAL, Sampler};
+use crate::sim::{HealthState, SimConfig, Simulation, Transitions, Vec2f};
+use crate::transmission::seed_offspring_mean;
+
+/// Observes a run step by step and produces an `EpidemicSummary` once no
+/// infected boids remain.
+pub struct EpidemicTracker {
+    time: f32,
+    initial_population: usize,
+    initial_infected: usize,
+    baseline: Transitions,
+    peak_infected: usize,
+    peak_time: f32,
+    susceptible_at_peak: f32,
+    /// `(time, infected)` once per sample interval, for the early growth
+    /// fit.
+    curve: Vec<(f32, usize)>,
+    sampler: Sampler,
+    summary: Option<EpidemicSummary>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EpidemicSummary {
+    /// Boids present at any point of the run: the initial population plus
+    /// arrivals through open boundaries.
+    pub population: usize,
+    /// Fraction of the population ever infected, seeded cases included.
+    pub final_attack_rate: f32,
+    pub peak_infected: usize,
+    /// `peak_infected` as a fraction of the population.
+    pub peak_prevalence: f32,
+    pub peak_time: f32,
+    /// Time from the start of the run until the last infection cleared.
+    pub duration: f32,
+    pub infections: usize,
+    pub recoveries: usize,
+    /// Susceptible fraction when prevalence peaked, i.e. when the effective
+    /// reproduction number crossed 1.
+    pub susceptible_at_peak: f32,
+    /// Empirical herd-immunity threshold, `1 - susceptible_at_peak`.
+    pub herd_immunity_threshold: f32,
+    /// Mean-field basic reproduction number from the run's parameters.
+    pub mean_field_r0: f32,
+    /// R0 from the exponential growth rate of the early infected curve.
+    pub growth_r0: Option<f32>,
+    /// R0 as the mean offspring of the seeded cases in the transmission tree.
+    pub offspring_r0: Option<f32>,
+}
+
+impl EpidemicSummary {
+    /// Mean-field herd-immunity prediction `1 - 1/R0`, or 0 when R0 <= 1.
+    pub fn predicted_herd_immunity_threshold(&self) -> f32 {
+        herd_immunity_threshold(self.mean_field_r0)
+    }
+}
+
+impl EpidemicTracker {
+    pub fn new(sim: &Simulation) -> Self {
+        let counts = sim.counts();
+        Self {
+            time: 0.0,
+            initial_population: sim.boids.len(),
+            initial_infected: counts.infected,
+            baseline: sim.transitions(),
+            peak_infected: counts.infected,
+            peak_time: 0.0,
+            susceptible_at_peak: counts.susceptible as f32 / sim.boids.len().max(1) as f32,
+            curve: vec![(0.0, counts.infected)],
+            sampler: Self::sampler_after(DEFAULT_SAMPLE_INTERVAL, 0.0),
+            summary: None,
+        }
+    }
+
+    /// Sample the infected curve every `interval` simulated seconds from
+    /// now on; 0 samples every step.
+    pub fn set_sample_interval(&mut self, interval: f32) {
+        self.sampler = Self::sampler_after(interval, self.time);
+    }
+
+    /// A sampler whose first sample is due one interval after `time`.
+    fn sampler_after(interval: f32, time: f32) -> Sampler {
+        let mut sampler = Sampler::new(interval);
+        sampler.due(time);
+        sampler
+    }
+
+    /// Record the state after a step of length `dt`. Does nothing once the
+    /// epidemic has ended.
+    pub fn record(&mut self, sim: &Simulation, dt: f32) {
+        if self.summary.is_some() {
+            return;
+        }
+        self.time += dt;
+        let counts = sim.counts();
+        let population = sim.boids.len().max(1);
+        if self.sampler.due(self.time) {
+            self.curve.push((self.time, counts.infected));
+        }
+        if counts.infected > self.peak_infected {
+            self.peak_infected = counts.infected;
+            self.peak_time = self.time;
+            self.susceptible_at_peak = counts.susceptible as f32 / population as f32;
+        }
+        if counts.infected + counts.exposed == 0 {
+            let transitions = sim.transitions();
+            let infections = transitions.infections - self.baseline.infections;
+            // Distinct boids, so reinfections under SIS can't push the attack
+            // rate past 1.
+            let ever_infected = self.initial_infected + transitions.first_infections
+                - self.baseline.first_infections;
+            // Everyone present at some point, so open boundaries and
+            // resizing don't inflate the attack rate as boids leave.
+            let ever_present = self.initial_population + transitions.arrivals
+                - self.baseline.arrivals
+                + transitions.spawned
+                - self.baseline.spawned;
+            self.summary = Some(EpidemicSummary {
+                population: ever_present,
+                final_attack_rate: ever_infected as f32 / ever_present.max(1) as f32,
+                peak_infected: self.peak_infected,
+                peak_prevalence: self.peak_infected as f32 / ever_present.max(1) as f32,
+                peak_time: self.peak_time,
+                duration: self.time,
+                infections,
+                recoveries: transitions.recoveries - self.baseline.recoveries,
+                susceptible_at_peak: self.susceptible_at_peak,
+                herd_immunity_threshold: 1.0 - self.susceptible_at_peak,
+                mean_field_r0: mean_field_r0(sim.config(), self.initial_population),
+                growth_r0: growth_rate_r0(&self.curve, sim.config().infectious_period),
+                offspring_r0: seed_offspring_mean(sim.infection_log()),
+            });
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn peak_infected(&self) -> usize {
+        self.peak_infected
+    }
+
+    pub fn peak_time(&self) -> f32 {
+        self.peak_time
+    }
+
+    pub fn susceptible_at_peak(&self) -> f32 {
+        self.susceptible_at_peak
+    }
+
+    /// `Some` once infections have died out.
+    pub fn summary(&self) -> Option<&EpidemicSummary> {
+        self.summary.as_ref()
+    }
+}
+
+impl fmt::Display for EpidemicSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "population:        {}", self.population)?;
+        writeln!(
+            f,
+            "final attack rate: {:.1}%",
+            self.final_attack_rate * 100.0
+        )?;
+        writeln!(
+            f,
+            "peak prevalence:   {:.1}% ({} infected) at t={:.1}s",
+            self.peak_prevalence * 100.0,
+            self.peak_infected,
+            self.peak_time
+        )?;
+        writeln!(f, "duration:          {:.1}s", self.duration)?;
+        writeln!(
+            f,
+            "transitions:       {} infections, {} recoveries",
+            self.infections, self.recoveries
+        )?;
+        writeln!(
+            f,
+            "herd immunity:     {:.1}% observed vs {:.1}% mean-field (R0 {:.2})",
+            self.herd_immunity_threshold * 100.0,
+            self.predicted_herd_immunity_threshold() * 100.0,
+            self.mean_field_r0
+        )?;
+        let estimate = |r0: Option<f32>| r0.map_or("n/a".to_string(), |r| format!("{r:.2}"));
+        write!(
+            f,
+            "R0 estimates:      {} early growth, {} seed offspring",
+            estimate(self.growth_r0),
+            estimate(self.offspring_r0)
+        )
+    }
+}
+
+/// Mean-field R0 for a uniformly mixed population: each infected boid meets
+/// `density * pi * r^2` others at any moment and infects each at rate `beta`
+/// for `infectious_period` seconds.
+pub fn mean_field_r0(cfg: &SimConfig, population: usize) -> f32 {
+    let area = (cfg.world_size.x * cfg.world_size.y).max(1.0);
+    let contacts = population as f32 / area * std::f32::consts::PI * cfg.infection_radius.powi(2);
+    cfg.infection_beta * cfg.infectious_period * contacts
+}
+
+/// R0 from a log-linear fit to the `(time, infected)` curve up to the point
+/// where prevalence first reaches half its peak, before susceptible depletion
+/// bends the curve. With a fixed infectious period `T` the generation
+/// interval is uniform on `[0, T]`, giving `R0 = rT / (1 - e^(-rT))`. `None`
+/// when the window is too short or infections never grew.
+pub fn growth_rate_r0(curve: &[(f32, usize)], infectious_period: f32) -> Option<f32> {
+    let peak = curve.iter().map(|&(_, i)| i).max()?;
+    let end = curve.iter().position(|&(_, i)| 2 * i >= peak)?;
+    let points: Vec<(f32, f32)> = curve[..=end]
+        .iter()
+        .filter(|&&(_, i)| i > 0)
+        .map(|&(t, i)| (t, (i as f32).ln()))
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+    let n = points.len() as f32;
+    let mt = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let ml = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let sxx: f32 = points.iter().map(|p| (p.0 - mt) * (p.0 - mt)).sum();
+    let sxy: f32 = points.iter().map(|p| (p.0 - mt) * (p.1 - ml)).sum();
+    if sxx <= 0.0 {
+        return None;
+    }
+    let rt = sxy / sxx * infectious_period;
+    if rt <= 0.0 {
+        return None;
+    }
+    Some(rt / (1.0 - (-rt).exp()))
+}
+
+/// `1 - 1/R0`, clamped to 0 for R0 <= 1.
+pub fn herd_immunity_threshold(r0: f32) -> f32 {
+    if r0 > 1.0 { 1.0 - 1.0 / r0 } else { 0.0 }
+}
+
+/// Nearest-neighbor clustering of the infected subpopulation compared with a
+/// well-mixed (complete spatial randomness) null.
+#[derive(Clone, Copy, Debug)]
+pub struct InfectedClustering {
+    pub infected: usize,
+    /// Mean distance from each infected boid to its nearest infected boid,
+    /// measured the short way round on a wrapping world.
+    pub mean_nn_distance: f32,
+    /// Expected mean nearest-neighbor distance for the same number of points
+    /// placed uniformly at random: `0.5 / sqrt(density)`.
+    pub expected_nn_distance: f32,
+    /// Clark-Evans ratio `mean / expected`: below 1 infections are clustered,
+    /// around 1 they are well mixed, above 1 they are dispersed.
+    pub ratio: f32,
+}
+
+/// `None` when fewer than two boids are infected.
+pub fn infected_clustering(sim: &Simulation) -> Option<InfectedClustering> {
+    let cfg = sim.config();
+    let world = cfg.world_size;
+    let infected: Vec<Vec2f> = sim
+        .boids
+        .iter()
+        .filter(|b| b.state == HealthState::Infected)
+        .map(|b| b.pos)
+        .collect();
+    if infected.len() < 2 {
+        return None;
+    }
+
+    let total: f32 = infected
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            infected
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| i != j)
+                .map(|(_, b)| cfg.offset(*a, *b).length())
+                .fold(f32::INFINITY, f32::min)
+        })
+        .sum();
+    let mean = total / infected.len() as f32;
+    let density = infected.len() as f32 / (world.x * world.y);
+    let expected = 0.5 / density.sqrt();
+    Some(InfectedClustering {
+        infected: infected.len(),
+        mean_nn_distance: mean,
+        expected_nn_distance: expected,
+        ratio: mean / expected,
+    })
+}
+
+/// Cell pressure at which a region counts as over-compressed: e.g. four
+/// boids each pressed half way into the separation radius of two others.
+pub const OVERCOMPRESSED: f32 = 4.0;
+
+/// Local crowd pressure on a grid of square cells over the world. Boids
+/// closer than the separation radius push on each other like soft bodies,
+/// with a force growing linearly from 0 at the radius to 1 at contact; a
+/// cell's pressure is the sum of those force magnitudes over the boids in
+/// it. High pressure marks regions where separation isn't keeping the flock
+/// apart, which are also where contacts are densest, so it doubles as a map
+/// of transmission-risk hotspots.
+#[derive(Clone, Debug)]
+pub struct CrowdPressure {
+    pub cell_size: f32,
+    pub cols: usize,
+    pub rows: usize,
+    /// Row-major, row 0 at the top of the world.
+    pub cells: Vec<f32>,
+}
+
+impl CrowdPressure {
+    pub fn at(&self, col: usize, row: usize) -> f32 {
+        self.cells[row * self.cols + col]
+    }
+
+    pub fn total(&self) -> f32 {
+        self.cells.iter().sum()
+    }
+
+    pub fn max(&self) -> f32 {
+        self.cells.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// Cells at or above `threshold` as `(col, row, pressure)`, highest
+    /// first.
+    pub fn hotspots(&self, threshold: f32) -> Vec<(usize, usize, f32)> {
+        let mut hot: Vec<(usize, usize, f32)> = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|&(_, &p)| p >= threshold)
+            .map(|(k, &p)| (k % self.cols, k / self.cols, p))
+            .collect();
+        hot.sort_by(|a, b| b.2.total_cmp(&a.2));
+        hot
+    }
+}
+
+/// Crowd pressure of `sim` right now, on cells of `cell_size`. Edge cells
+/// are clipped to the world.
+pub fn crowd_pressure(sim: &Simulation, cell_size: f32) -> CrowdPressure {
+    let world = sim.config().world_size;
+    let radius = sim.config().separation_radius;
+    let cell_size = cell_size.max(1.0);
+    let cols = ((world.x / cell_size).ceil() as usize).max(1);
+    let rows = ((world.y / cell_size).ceil() as usize).max(1);
+    let mut cells = vec![0.0; cols * rows];
+    let mut add = |pos: Vec2f, force: f32| {
+        let col = ((pos.x.rem_euclid(world.x) / cell_size) as usize).min(cols - 1);
+        let row = ((pos.y.rem_euclid(world.y) / cell_size) as usize).min(rows - 1);
+        cells[row * cols + col] += force;
+    };
+    for (i, j) in sim.contact_pairs(radius) {
+        let (a, b) = (sim.boids[i].pos, sim.boids[j].pos);
+        let force = (1.0 - sim.config().offset(a, b).length() / radius).max(0.0);
+        add(a, force);
+        add(b, force);
+    }
+    CrowdPressure {
+        cell_size,
+        cols,
+        rows,
+        cells,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{Boundaries, Boundary};
+
+    #[test]
+    fn clustering_measures_across_edges_only_when_they_wrap() {
+        for (boundary, expected) in [(Boundary::Wrap, 2.0), (Boundary::Reflect, 398.0)] {
+            let cfg = SimConfig {
+                world_size: Vec2f::new(400.0, 300.0),
+                boundaries: Boundaries::uniform(boundary),
+                initial_infected: 0,
+                ..SimConfig::default()
+            };
+            let mut sim = Simulation::new(2, cfg, 1);
+            sim.boids[0].pos = Vec2f::new(1.0, 150.0);
+            sim.boids[1].pos = Vec2f::new(399.0, 150.0);
+            for boid in &mut sim.boids {
+                boid.state = HealthState::Infected;
+            }
+            let clustering = infected_clustering(&sim).unwrap();
+            assert!(
+                (clustering.mean_nn_distance - expected).abs() < 1e-3,
+                "{boundary:?}: {}",
+                clustering.mean_nn_distance
+            );
+        }
+    }
+
+    #[test]
+    fn crowd_pressure_uses_straight_distances_between_walls() {
+        let cfg = SimConfig {
+            world_size: Vec2f::new(30.0, 30.0),
+            boundaries: Boundaries::uniform(Boundary::Reflect),
+            separation_radius: 22.0,
+            initial_infected: 0,
+            ..SimConfig::default()
+        };
+        let mut sim = Simulation::new(2, cfg, 1);
+        let radius = sim.config().separation_radius;
+        sim.boids[0].pos = Vec2f::new(2.0, 15.0);
+        sim.boids[1].pos = Vec2f::new(22.0, 15.0);
+        let pressure = crowd_pressure(&sim, 10.0);
+        let total: f32 = pressure.cells.iter().sum();
+        assert!(
+            (total - 2.0 * (1.0 - 20.0 / radius)).abs() < 1e-4,
+            "{total}"
+        );
+    }
+}
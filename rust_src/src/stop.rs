@@ -0,0 +1,90 @@
+use std::fmt;
+
+use crate::sim::Simulation;
+
+/// Why a run ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    MaxSteps,
+    MaxTime,
+    /// Nobody is infected any more.
+    Extinct,
+    /// Nobody is left to infect.
+    SusceptibleExhausted,
+}
+
+impl StopReason {
+    pub fn name(self) -> &'static str {
+        match self {
+            StopReason::MaxSteps => "step limit reached",
+            StopReason::MaxTime => "time limit reached",
+            StopReason::Extinct => "infections extinct",
+            StopReason::SusceptibleExhausted => "susceptibles exhausted",
+        }
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// When a run ends: as soon as any enabled condition holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StopConditions {
+    pub max_steps: usize,
+    /// Simulated seconds; `None` for no limit.
+    pub max_time: Option<f32>,
+    pub extinct: bool,
+    pub exhausted: bool,
+}
+
+impl Default for StopConditions {
+    fn default() -> Self {
+        Self {
+            max_steps: 600,
+            max_time: None,
+            extinct: true,
+            exhausted: false,
+        }
+    }
+}
+
+impl StopConditions {
+    /// Set the state-based conditions from a comma-separated list of
+    /// `extinct` and `exhausted`, or `none` to run to the limits.
+    pub fn parse_events(&mut self, spec: &str) -> Result<(), String> {
+        self.extinct = false;
+        self.exhausted = false;
+        for event in spec.split(',').map(str::trim) {
+            match event {
+                "extinct" => self.extinct = true,
+                "exhausted" => self.exhausted = true,
+                "none" => {}
+                other => {
+                    return Err(format!(
+                        "unknown stop condition: {other} (expected extinct, exhausted or none)"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The first condition `sim` meets after `steps` steps, if any.
+    pub fn check(&self, sim: &Simulation, steps: usize) -> Option<StopReason> {
+        let counts = sim.counts();
+        if self.extinct && counts.infected == 0 {
+            Some(StopReason::Extinct)
+        } else if self.exhausted && counts.susceptible == 0 {
+            Some(StopReason::SusceptibleExhausted)
+        } else if self.max_time.is_some_and(|t| sim.time() >= t) {
+            Some(StopReason::MaxTime)
+        } else if steps >= self.max_steps {
+            Some(StopReason::MaxSteps)
+        } else {
+            None
+        }
+    }
+}
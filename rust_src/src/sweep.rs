@@ -0,0 +1,134 @@
+use std::fmt::Write as _;
+
+use crate::runner::RunOutcome;
+use crate::scheduler::Scheduler;
+use crate::sensitivity::{ParamRange, RunSettings};
+
+/// Outcomes at one point of a 2D parameter grid.
+#[derive(Clone, Copy, Debug)]
+pub struct PhaseCell {
+    pub x: f32,
+    pub y: f32,
+    /// Mean final attack rate over replicates.
+    pub attack_rate: f32,
+    /// Fraction of replicates whose infections died out before the step
+    /// cap.
+    pub extinction_probability: f32,
+    /// Replicates still infectious at the step cap, which count towards
+    /// neither extinction nor the lack of it.
+    pub unfinished: usize,
+}
+
+impl PhaseCell {
+    /// Aggregate the replicates `runs` at grid point (`x`, `y`).
+    pub fn from_runs(x: f32, y: f32, runs: &[RunOutcome]) -> Self {
+        let n = runs.len().max(1) as f32;
+        let attack: f32 = runs.iter().map(|o| o.final_size).sum();
+        let extinct = runs.iter().filter(|o| o.extinct).count();
+        PhaseCell {
+            x,
+            y,
+            attack_rate: attack / n,
+            extinction_probability: extinct as f32 / n,
+            unfinished: runs.len() - extinct,
+        }
+    }
+}
+
+/// Row-major (y outer, x inner) grid of outcomes.
+pub struct PhaseDiagram {
+    pub x: ParamRange,
+    pub y: ParamRange,
+    pub nx: usize,
+    pub ny: usize,
+    pub cells: Vec<PhaseCell>,
+}
+
+/// Grid coordinate `i` of `n` evenly spaced points spanning the whole range.
+pub(crate) fn grid_value(range: &ParamRange, i: usize, n: usize) -> f32 {
+    range.lerp(i as f32 / n.saturating_sub(1).max(1) as f32)
+}
+
+/// Sweep a `nx` by `ny` grid of `x` and `y` values, running the replicates
+/// through `scheduler`.
+pub fn run_phase_diagram(
+    x: ParamRange,
+    y: ParamRange,
+    nx: usize,
+    ny: usize,
+    settings: &RunSettings,
+    scheduler: &Scheduler,
+) -> Result<PhaseDiagram, String> {
+    let point = |k: usize| (grid_value(&x, k % nx, nx), grid_value(&y, k / nx, ny));
+    let n = settings.replicates.max(1);
+    let outcomes = scheduler.run(nx * ny, n, |job| {
+        let (xv, yv) = point(job.point);
+        let mut cfg = settings.base;
+        x.param.apply(&mut cfg, xv);
+        y.param.apply(&mut cfg, yv);
+        settings.replicate(cfg, job.replicate)
+    })?;
+    let cells = outcomes
+        .chunks(n)
+        .enumerate()
+        .map(|(k, runs)| {
+            let (xv, yv) = point(k);
+            PhaseCell::from_runs(xv, yv, runs)
+        })
+        .collect();
+    Ok(PhaseDiagram {
+        x,
+        y,
+        nx,
+        ny,
+        cells,
+    })
+}
+
+impl PhaseDiagram {
+    pub fn to_csv(&self) -> String {
+        let mut out = format!(
+            "{},{},attack_rate,extinction_probability,unfinished\n",
+            self.x.param.name(),
+            self.y.param.name()
+        );
+        for c in &self.cells {
+            let _ = writeln!(
+                out,
+                "{:.4},{:.4},{:.4},{:.4},{}",
+                c.x, c.y, c.attack_rate, c.extinction_probability, c.unfinished
+            );
+        }
+        out
+    }
+
+    pub fn attack_rates(&self) -> Vec<f32> {
+        self.cells.iter().map(|c| c.attack_rate).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_runs_that_died_out_count_as_extinct() {
+        let run = |final_size, extinct| RunOutcome {
+            final_size,
+            extinct,
+            ..Default::default()
+        };
+        // A small outbreak still spreading at the cap is not extinct, and a
+        // large one that burned out is.
+        let runs = [
+            run(0.02, false),
+            run(0.05, true),
+            run(0.8, true),
+            run(0.6, false),
+        ];
+        let cell = PhaseCell::from_runs(1.0, 2.0, &runs);
+        assert_eq!(cell.extinction_probability, 0.5);
+        assert_eq!(cell.unfinished, 2);
+        assert!((cell.attack_rate - 0.3675).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,95 @@
+/// Number of distinct flags a simulation can name.
+pub const MAX_FLAGS: usize = 64;
+/// Number of distinct numeric values a simulation can name.
+pub const MAX_VALUES: usize = 4;
+
+/// A named on/off property, e.g. `vaccinated` or `team_red`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flag(u8);
+
+/// A named number, e.g. `household` or `compliance`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Value(u8);
+
+/// Per-boid metadata for features outside the core model. Stored inline in
+/// `Boid`, so it moves with the boid when others are removed, and new boids
+/// (spawned, respawned or added by a resize) start with everything clear.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tags {
+    flags: u64,
+    values: [f32; MAX_VALUES],
+}
+
+impl Tags {
+    pub fn has(&self, flag: Flag) -> bool {
+        self.flags & (1 << flag.0) != 0
+    }
+
+    pub fn set(&mut self, flag: Flag, on: bool) {
+        if on {
+            self.flags |= 1 << flag.0;
+        } else {
+            self.flags &= !(1 << flag.0);
+        }
+    }
+
+    /// The value, 0 until set.
+    pub fn get(&self, value: Value) -> f32 {
+        self.values[value.0 as usize]
+    }
+
+    pub fn set_value(&mut self, value: Value, v: f32) {
+        self.values[value.0 as usize] = v;
+    }
+}
+
+/// Names of the flags and values in use, handed out on first request so
+/// independent features can claim their own without coordinating.
+#[derive(Clone, Debug, Default)]
+pub struct TagRegistry {
+    flags: Vec<String>,
+    values: Vec<String>,
+}
+
+impl TagRegistry {
+    /// The flag called `name`, registering it if new.
+    pub fn flag(&mut self, name: &str) -> Result<Flag, String> {
+        claim(&mut self.flags, name, MAX_FLAGS, "flags").map(Flag)
+    }
+
+    /// The value called `name`, registering it if new.
+    pub fn value(&mut self, name: &str) -> Result<Value, String> {
+        claim(&mut self.values, name, MAX_VALUES, "values").map(Value)
+    }
+
+    pub fn find_flag(&self, name: &str) -> Option<Flag> {
+        position(&self.flags, name).map(Flag)
+    }
+
+    pub fn find_value(&self, name: &str) -> Option<Value> {
+        position(&self.values, name).map(Value)
+    }
+
+    pub fn flag_names(&self) -> &[String] {
+        &self.flags
+    }
+
+    pub fn value_names(&self) -> &[String] {
+        &self.values
+    }
+}
+
+fn position(names: &[String], name: &str) -> Option<u8> {
+    names.iter().position(|n| n == name).map(|k| k as u8)
+}
+
+fn claim(names: &mut Vec<String>, name: &str, max: usize, kind: &str) -> Result<u8, String> {
+    if let Some(k) = position(names, name) {
+        return Ok(k);
+    }
+    if names.len() == max {
+        return Err(format!("cannot add {name}: all {max} {kind} are in use"));
+    }
+    names.push(name.to_owned());
+    Ok((names.len() - 1) as u8)
+}
@@ -0,0 +1,258 @@
+use rayon::prelude::*;
+
+use crate::sim::{Boid, Boundary, SimConfig, Simulation, SirCounts, Transitions, Vec2f};
+
+/// A simulation split into an `nx x ny` grid of tiles, each stepped as its
+/// own `Simulation` on a rayon worker thread; all tiles live in one process.
+/// Before every step each tile receives copies of the boids within
+/// interaction range of its edges from the neighbouring tiles (the halo),
+/// across wrapping world edges too, so flocking and infection see across
+/// tile borders; afterwards halos are dropped and boids that moved out of
+/// their tile migrate to the new owner. A boid's transitions are counted
+/// only by the tile that owns it, never by the tiles holding its halo copy.
+///
+/// Only SIR counts and transitions are aggregated. Each tile keeps its own
+/// infection log and boids lose their log index when they change tile, so
+/// transmission trees are not available in this mode. Results match a single
+/// `Simulation` statistically, not bit for bit: random draws are per tile.
+pub struct TiledSimulation {
+    tiles: Vec<Simulation>,
+    nx: usize,
+    ny: usize,
+    tile_size: Vec2f,
+    halo_boids: usize,
+}
+
+impl TiledSimulation {
+    pub fn new(
+        count: usize,
+        cfg: SimConfig,
+        seed: u64,
+        nx: usize,
+        ny: usize,
+    ) -> Result<Self, String> {
+        Self::split(Simulation::new(count, cfg, seed), nx, ny)
+    }
+
+    /// Distribute the boids of `sim` over the tiles.
+    pub fn split(sim: Simulation, nx: usize, ny: usize) -> Result<Self, String> {
+        let b = sim.config().boundaries;
+        if [b.left, b.right, b.top, b.bottom].contains(&Boundary::Remove) {
+            // Removal reorders a tile's boids, mixing owned boids and halo.
+            return Err("tiled runs do not support removing boundaries".into());
+        }
+        let (nx, ny) = (nx.max(1), ny.max(1));
+        let world = sim.config().world_size;
+        let mut tiled = Self {
+            tiles: Vec::with_capacity(nx * ny),
+            nx,
+            ny,
+            tile_size: Vec2f::new(world.x / nx as f32, world.y / ny as f32),
+            halo_boids: 0,
+        };
+        let mut owned = vec![Vec::new(); nx * ny];
+        for boid in &sim.boids {
+            owned[tiled.tile_of(boid.pos)].push(Boid {
+                infection: None,
+                ..*boid
+            });
+        }
+        for (k, boids) in owned.into_iter().enumerate() {
+            let seed = sim.seed() ^ (k as u64 + 1).wrapping_mul(0x9e37_79b9);
+            tiled.tiles.push(sim.with_boids(boids, seed));
+        }
+        Ok(tiled)
+    }
+
+    fn tile_of(&self, pos: Vec2f) -> usize {
+        let tx = ((pos.x / self.tile_size.x) as usize).min(self.nx - 1);
+        let ty = ((pos.y / self.tile_size.y) as usize).min(self.ny - 1);
+        ty * self.nx + tx
+    }
+
+    /// Tile columns (or rows) whose extent overlaps `[v - reach, v + reach]`,
+    /// taken modulo `n` on a wrapping world and clipped to it otherwise.
+    fn span(v: f32, reach: f32, size: f32, n: usize, wrap: bool) -> Vec<usize> {
+        let lo = ((v - reach) / size).floor() as i64;
+        let hi = ((v + reach) / size).floor() as i64;
+        if wrap {
+            let count = (hi - lo + 1).min(n as i64);
+            (lo..lo + count)
+                .map(|t| t.rem_euclid(n as i64) as usize)
+                .collect()
+        } else {
+            (lo.max(0)..=hi.min(n as i64 - 1))
+                .map(|t| t as usize)
+                .collect()
+        }
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        let cfg = *self.tiles[0].config();
+        let reach = cfg.neighbor_radius.max(cfg.infection_radius);
+        // Tiles look neighbours up across wrapping edges like the single
+        // simulation does, so halos have to cross them as well.
+        let wrap = cfg.torus().is_some();
+
+        let mut halos = vec![Vec::new(); self.tiles.len()];
+        for (k, tile) in self.tiles.iter().enumerate() {
+            for boid in &tile.boids {
+                let xs = Self::span(boid.pos.x, reach, self.tile_size.x, self.nx, wrap);
+                for ty in Self::span(boid.pos.y, reach, self.tile_size.y, self.ny, wrap) {
+                    for &tx in &xs {
+                        let dest = ty * self.nx + tx;
+                        if dest != k {
+                            halos[dest].push(Boid {
+                                infection: None,
+                                ..*boid
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        self.halo_boids = halos.iter().map(Vec::len).sum();
+
+        self.tiles
+            .par_iter_mut()
+            .zip(halos)
+            .for_each(|(tile, halo)| {
+                let owned = tile.boids.len();
+                tile.boids.extend(halo);
+                tile.step_with_halo(dt, owned);
+                tile.boids.truncate(owned);
+            });
+
+        // Migration to the tile each boid now sits in.
+        let mut arriving = vec![Vec::new(); self.tiles.len()];
+        for k in 0..self.tiles.len() {
+            let mut kept = Vec::with_capacity(self.tiles[k].boids.len());
+            for boid in std::mem::take(&mut self.tiles[k].boids) {
+                let dest = self.tile_of(boid.pos);
+                if dest == k {
+                    kept.push(boid);
+                } else {
+                    arriving[dest].push(Boid {
+                        infection: None,
+                        ..boid
+                    });
+                }
+            }
+            self.tiles[k].boids = kept;
+        }
+        for (tile, boids) in self.tiles.iter_mut().zip(arriving) {
+            tile.boids.extend(boids);
+        }
+    }
+
+    /// SIR counts over all tiles.
+    pub fn counts(&self) -> SirCounts {
+        self.tiles
+            .iter()
+            .fold(SirCounts::default(), |mut sum, tile| {
+                let c = tile.counts();
+                sum.susceptible += c.susceptible;
+                sum.exposed += c.exposed;
+                sum.infected += c.infected;
+                sum.recovered += c.recovered;
+                sum
+            })
+    }
+
+    /// Transitions summed over all tiles.
+    pub fn transitions(&self) -> Transitions {
+        self.tiles
+            .iter()
+            .fold(Transitions::default(), |mut sum, tile| {
+                let t = tile.transitions();
+                sum.infections += t.infections;
+                sum.first_infections += t.first_infections;
+                sum.onsets += t.onsets;
+                sum.recoveries += t.recoveries;
+                sum.departures += t.departures;
+                sum.arrivals += t.arrivals;
+                sum
+            })
+    }
+
+    /// Counts per tile, row-major.
+    pub fn tile_counts(&self) -> Vec<SirCounts> {
+        self.tiles.iter().map(Simulation::counts).collect()
+    }
+
+    pub fn population(&self) -> usize {
+        self.tiles.iter().map(|t| t.boids.len()).sum()
+    }
+
+    pub fn time(&self) -> f32 {
+        self.tiles[0].time()
+    }
+
+    /// Boid copies sent between tiles in the last halo exchange.
+    pub fn halo_boids(&self) -> usize {
+        self.halo_boids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{DiseaseModel, HealthState};
+
+    /// Boids on a lattice 40 apart within a 50 infection radius, slow enough
+    /// that no pair crosses it during the run, so only lattice neighbours
+    /// (including those across the wrapping edges) ever meet. With a beta
+    /// that makes every contact infect, both runs spread one hop per step.
+    fn lattice() -> Simulation {
+        let cfg = SimConfig {
+            world_size: Vec2f::new(320.0, 240.0),
+            neighbor_radius: 50.0,
+            infection_radius: 50.0,
+            max_speed: 1.0,
+            max_force: 1.0,
+            infection_beta: 1e6,
+            infectious_period: 1000.0,
+            initial_infected: 0,
+            ..SimConfig::default()
+        };
+        let mut sim = Simulation::new(8 * 6, cfg, 7);
+        for (i, boid) in sim.boids.iter_mut().enumerate() {
+            boid.pos = Vec2f::new(20.0 + 40.0 * (i % 8) as f32, 20.0 + 40.0 * (i / 8) as f32);
+            boid.vel = Vec2f::default();
+        }
+        sim.boids[0].state = HealthState::Infected;
+        sim
+    }
+
+    #[test]
+    fn tiled_run_matches_a_single_simulation_on_a_wrapped_world() {
+        let mut single = lattice();
+        let mut tiled = TiledSimulation::split(lattice(), 2, 2).unwrap();
+        for _ in 0..20 {
+            single.step(0.1);
+            tiled.step(0.1);
+            assert_eq!(tiled.counts().infected, single.counts().infected);
+            assert_eq!(
+                tiled.transitions().infections,
+                single.transitions().infections
+            );
+        }
+        // Reached the far corner through the seams: 4 + 3 hops on the torus.
+        assert_eq!(single.counts().infected, 48);
+    }
+
+    #[test]
+    fn counts_include_incubating_boids() {
+        let cfg = SimConfig {
+            disease_model: DiseaseModel::Seir,
+            ..SimConfig::default()
+        };
+        let mut tiled = TiledSimulation::new(200, cfg, 4, 2, 2).unwrap();
+        for _ in 0..10 {
+            tiled.step(0.1);
+        }
+        let c = tiled.counts();
+        assert!(c.exposed > 0);
+        assert_eq!(c.total(), tiled.population());
+    }
+}
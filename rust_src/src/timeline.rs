@@ -0,0 +1,51 @@
+use crate::sim::{HealthState, Simulation};
+
+/// Every boid's health state over a run, kept as the times it changed, so
+/// the state of the population at any earlier time can be looked up.
+/// Memory grows with transitions rather than frames. Boids are tracked by
+/// index: a respawned boid continues its slot's record, and removals shift
+/// later boids onto earlier records.
+#[derive(Clone, Debug, Default)]
+pub struct StateTimeline {
+    /// Per boid, `(time, state)` at each recorded change, oldest first.
+    changes: Vec<Vec<(f32, HealthState)>>,
+}
+
+impl StateTimeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note every boid whose state differs from its last record.
+    pub fn record(&mut self, sim: &Simulation) {
+        let time = sim.time();
+        self.changes.truncate(sim.boids.len());
+        self.changes.resize_with(sim.boids.len(), Vec::new);
+        for (changes, boid) in self.changes.iter_mut().zip(&sim.boids) {
+            if changes.last().is_none_or(|&(_, state)| state != boid.state) {
+                changes.push((time, boid.state));
+            }
+        }
+    }
+
+    /// The state boid `index` was in at `time`; `None` before its first
+    /// record.
+    pub fn state_at(&self, index: usize, time: f32) -> Option<HealthState> {
+        let changes = self.changes.get(index)?;
+        let after = changes.partition_point(|&(t, _)| t <= time);
+        after.checked_sub(1).map(|k| changes[k].1)
+    }
+
+    /// Per boid, whether it carried the infection, exposed or infectious,
+    /// at `time`.
+    pub fn infected_at(&self, time: f32) -> Vec<bool> {
+        (0..self.changes.len())
+            .map(|i| {
+                matches!(
+                    self.state_at(i, time),
+                    Some(HealthState::Exposed | HealthState::Infected)
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,68 @@
+use crate::sim::Simulation;
+
+/// Chooses each step's `dt` so that no boid moves more than `max_travel`
+/// of the smallest interaction radius in one step, so contacts are not
+/// stepped over. The step shrinks at once when boids speed up and grows
+/// back by at most `growth` per step when they calm down.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveDt {
+    pub min_dt: f32,
+    pub max_dt: f32,
+    /// Largest step, as a fraction of the smallest of the separation and
+    /// infection radii, that the fastest boid may cover.
+    pub max_travel: f32,
+    /// Largest factor by which `dt` may grow from one step to the next.
+    pub growth: f32,
+    dt: f32,
+}
+
+impl AdaptiveDt {
+    pub fn new(min_dt: f32, max_dt: f32, max_travel: f32) -> Self {
+        let min_dt = min_dt.max(1e-5);
+        Self {
+            min_dt,
+            max_dt: max_dt.max(min_dt),
+            max_travel: max_travel.max(0.01),
+            growth: 1.1,
+            dt: min_dt,
+        }
+    }
+
+    /// `min_dt:max_dt[:max_travel]`, e.g. `0.005:0.05:0.25`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts = spec
+            .split(':')
+            .map(|s| s.parse::<f32>().map_err(|_| format!("invalid number: {s}")));
+        let (Some(min_dt), Some(max_dt)) = (parts.next(), parts.next()) else {
+            return Err(format!("expected min_dt:max_dt[:max_travel], got {spec}"));
+        };
+        let max_travel = parts.next().transpose()?.unwrap_or(0.25);
+        Ok(Self::new(min_dt?, max_dt?, max_travel))
+    }
+
+    /// The step to take next from `sim`'s current state.
+    pub fn next_dt(&mut self, sim: &Simulation) -> f32 {
+        let cfg = sim.config();
+        let radius = cfg.separation_radius.min(cfg.infection_radius);
+        let fastest = sim
+            .boids
+            .iter()
+            .filter(|b| !b.quarantined)
+            .map(|b| b.vel.length())
+            .fold(0.0f32, f32::max);
+        let limit = if fastest > 0.0 {
+            self.max_travel * radius / fastest
+        } else {
+            self.max_dt
+        };
+        self.dt = limit
+            .min(self.dt * self.growth)
+            .clamp(self.min_dt, self.max_dt);
+        self.dt
+    }
+
+    /// The most recently chosen step.
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+}
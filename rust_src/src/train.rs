@@ -0,0 +1,182 @@
+use rayon::prelude::*;
+
+use crate::policy::Policy;
+use crate::runner::run_sim_to_end;
+use crate::sensitivity::RunSettings;
+use crate::sim::{Rng, Simulation};
+
+/// Salt separating the candidate noise from the evaluation seeds.
+const TRAIN_STREAM: u64 = 0x6365_6d21;
+
+/// Settings of a cross-entropy method (CEM) search over steering policies.
+/// Each iteration samples `candidates` policies around the current mean,
+/// scores them on the same seeds, and moves the mean to the average of the
+/// best `elite_fraction` of them.
+#[derive(Clone, Copy, Debug)]
+pub struct CemSettings {
+    pub iterations: usize,
+    pub candidates: usize,
+    pub elite_fraction: f32,
+    /// Standard deviation of the noise candidates are sampled with in the
+    /// first iteration.
+    pub sigma: f32,
+    /// Factor `sigma` is multiplied by after every iteration.
+    pub sigma_decay: f32,
+    /// Stop once the best score has not improved by more than `tolerance`
+    /// for this many iterations; 0 always runs every iteration.
+    pub patience: usize,
+    pub tolerance: f32,
+}
+
+impl Default for CemSettings {
+    fn default() -> Self {
+        Self {
+            iterations: 20,
+            candidates: 24,
+            elite_fraction: 0.2,
+            sigma: 0.3,
+            sigma_decay: 0.9,
+            patience: 5,
+            tolerance: 1e-3,
+        }
+    }
+}
+
+impl CemSettings {
+    /// Candidates the next mean is fitted to, at least one.
+    pub fn elites(&self) -> usize {
+        ((self.candidates as f32 * self.elite_fraction).ceil() as usize)
+            .clamp(1, self.candidates.max(1))
+    }
+}
+
+/// Scores of one CEM iteration.
+#[derive(Clone, Copy, Debug)]
+pub struct IterationLog {
+    /// From 0.
+    pub iteration: usize,
+    pub mean_score: f32,
+    /// Best candidate of this iteration.
+    pub best_score: f32,
+    /// Mean of the elites, which becomes the next sampling mean.
+    pub elite_score: f32,
+    /// Noise this iteration's candidates were sampled with.
+    pub sigma: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct CemResult {
+    /// Best candidate seen in any iteration.
+    pub best: Policy,
+    pub best_score: f32,
+    pub log: Vec<IterationLog>,
+    /// Whether training stopped on the plateau rule before `iterations`.
+    pub stopped_early: bool,
+}
+
+/// Score of `policy`: the mean fraction of the population never infected
+/// over `settings.replicates` runs from `seed`, `seed + 1`, ...
+pub fn evaluate(policy: &Policy, settings: &RunSettings, seed: u64) -> Result<f32, String> {
+    let replicates = settings.replicates.max(1);
+    let mut spared = 0.0;
+    for r in 0..replicates {
+        let mut sim = Simulation::new(settings.boids, settings.base, seed.wrapping_add(r as u64));
+        sim.set_policy(policy.clone())?;
+        spared += 1.0 - run_sim_to_end(sim, settings.max_steps, settings.dt).final_size;
+    }
+    Ok(spared / replicates as f32)
+}
+
+/// Seed of the noise for candidate `k` of `iteration`. Each candidate draws
+/// from its own stream, so the candidates do not depend on the order they
+/// are evaluated in.
+fn candidate_seed(seed: u64, iteration: usize, k: usize) -> u64 {
+    let mut z = seed
+        ^ TRAIN_STREAM
+        ^ (iteration as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (k as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Train a steering policy with CEM, starting from `initial`. Candidates
+/// are scored by `evaluate` on `run`, each on `run.replicates` seeds, and
+/// the candidates of an iteration are evaluated in parallel on the rayon
+/// pool. The result is the same for any number of threads.
+/// `on_iteration` sees each iteration's scores as soon as it is done.
+pub fn train(
+    initial: &Policy,
+    run: &RunSettings,
+    settings: &CemSettings,
+    mut on_iteration: impl FnMut(&IterationLog),
+) -> Result<CemResult, String> {
+    let mut mean = initial.weights();
+    let mut sigma = settings.sigma;
+    let mut best = initial.clone();
+    let mut best_score = f32::NEG_INFINITY;
+    let mut since_improvement = 0;
+    let mut log = Vec::new();
+    let mut stopped_early = false;
+
+    for iteration in 0..settings.iterations {
+        let center = initial
+            .with_weights(&mean)
+            .expect("mean has the policy's size");
+        // Every candidate of an iteration runs on the same seeds, so they
+        // are ranked on their steering rather than on luck.
+        let seed = run
+            .seed
+            .wrapping_add((iteration * run.replicates.max(1)) as u64);
+        let mut scored = (0..settings.candidates.max(1))
+            .into_par_iter()
+            .map(|k| {
+                let mut rng = Rng::new(candidate_seed(run.seed, iteration, k));
+                let mut candidate = center.clone();
+                candidate.perturb(&mut rng, sigma);
+                Ok((evaluate(&candidate, run, seed)?, candidate))
+            })
+            .collect::<Result<Vec<(f32, Policy)>, String>>()?;
+        // Stable, so ties keep candidate order.
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let elites = &scored[..settings.elites().min(scored.len())];
+        mean = vec![0.0; mean.len()];
+        for (_, policy) in elites {
+            for (m, w) in mean.iter_mut().zip(policy.weights()) {
+                *m += w / elites.len() as f32;
+            }
+        }
+        let entry = IterationLog {
+            iteration,
+            mean_score: scored.iter().map(|(s, _)| s).sum::<f32>() / scored.len() as f32,
+            best_score: scored[0].0,
+            elite_score: elites.iter().map(|(s, _)| s).sum::<f32>() / elites.len() as f32,
+            sigma,
+        };
+        on_iteration(&entry);
+        log.push(entry);
+
+        if scored[0].0 > best_score + settings.tolerance {
+            since_improvement = 0;
+        } else {
+            since_improvement += 1;
+        }
+        if scored[0].0 > best_score {
+            best_score = scored[0].0;
+            best = scored[0].1.clone();
+        }
+        if settings.patience > 0 && since_improvement >= settings.patience {
+            stopped_early = iteration + 1 < settings.iterations;
+            break;
+        }
+        sigma *= settings.sigma_decay;
+    }
+
+    Ok(CemResult {
+        best,
+        best_score,
+        log,
+        stopped_early,
+    })
+}
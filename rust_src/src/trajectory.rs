@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use crate::sim::{HealthState, Rng, Simulation};
+use crate::tags::Value;
+
+/// Name of the tag value holding each tracked boid's trajectory id: 1-based,
+/// 0 for untracked.
+pub const TRACK_TAG: &str = "track";
+
+/// Which boids to record.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TrackSelection {
+    /// Everyone infected when tracking starts: the seeded cases on a fresh
+    /// run.
+    IndexCases,
+    /// These indices, as of when tracking starts.
+    Boids(Vec<usize>),
+    /// This many boids picked at random.
+    Random(usize),
+}
+
+impl TrackSelection {
+    /// Parse `index-cases`, `random:N` or a comma-separated index list such
+    /// as `0,17,42`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if spec == "index-cases" {
+            return Ok(Self::IndexCases);
+        }
+        if let Some(n) = spec.strip_prefix("random:") {
+            return n
+                .parse()
+                .map(Self::Random)
+                .map_err(|_| format!("invalid count: {n}"));
+        }
+        spec.split(',')
+            .map(|s| {
+                s.trim()
+                    .parse()
+                    .map_err(|_| format!("invalid boid index: {s}"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::Boids)
+    }
+}
+
+/// Full-resolution trajectories of a few marked boids, one CSV row per
+/// tracked boid per recorded step. The mark lives in the boid's tags, so it
+/// follows the boid when others are removed, and the cost of recording is a
+/// tag check per boid rather than a row per boid.
+#[derive(Clone, Debug)]
+pub struct TrajectoryRecorder {
+    value: Value,
+    tracked: usize,
+    step: usize,
+    csv: String,
+}
+
+impl TrajectoryRecorder {
+    /// Mark the boids in `selection` and record their starting positions.
+    pub fn mark(sim: &mut Simulation, selection: &TrackSelection) -> Result<Self, String> {
+        let value = sim.tag_registry_mut().value(TRACK_TAG)?;
+        let n = sim.boids.len();
+        let chosen: Vec<usize> = match selection {
+            TrackSelection::IndexCases => (0..n)
+                .filter(|&i| sim.boids[i].state == HealthState::Infected)
+                .collect(),
+            TrackSelection::Boids(indices) => {
+                if let Some(&bad) = indices.iter().find(|&&i| i >= n) {
+                    return Err(format!("boid {bad} out of range (population {n})"));
+                }
+                indices.clone()
+            }
+            TrackSelection::Random(count) => {
+                let mut rng = Rng::new(sim.seed() ^ 0x7a3c_15e9);
+                let mut picked = Vec::new();
+                while picked.len() < (*count).min(n) {
+                    let i = rng.next_index(n);
+                    if !picked.contains(&i) {
+                        picked.push(i);
+                    }
+                }
+                picked
+            }
+        };
+        let mut id = 0;
+        for i in chosen {
+            let tags = &mut sim.boids[i].tags;
+            if tags.get(value) == 0.0 {
+                id += 1;
+                tags.set_value(value, id as f32);
+            }
+        }
+        let mut recorder = Self {
+            value,
+            tracked: id,
+            step: 0,
+            csv: String::from("track,step,time,x,y,vx,vy,state\n"),
+        };
+        recorder.write_rows(sim);
+        Ok(recorder)
+    }
+
+    /// Number of boids marked.
+    pub fn tracked(&self) -> usize {
+        self.tracked
+    }
+
+    /// Record the step that just advanced `sim`. Tracked boids that have
+    /// been removed simply stop appearing.
+    pub fn record(&mut self, sim: &Simulation) {
+        self.step += 1;
+        self.write_rows(sim);
+    }
+
+    pub fn csv(&self) -> &str {
+        &self.csv
+    }
+
+    fn write_rows(&mut self, sim: &Simulation) {
+        for boid in &sim.boids {
+            let id = boid.tags.get(self.value);
+            if id == 0.0 {
+                continue;
+            }
+            let state = boid.state.letter();
+            let _ = writeln!(
+                self.csv,
+                "{id},{},{:.4},{:.3},{:.3},{:.3},{:.3},{state}",
+                self.step,
+                sim.time(),
+                boid.pos.x,
+                boid.pos.y,
+                boid.vel.x,
+                boid.vel.y
+            );
+        }
+    }
+}
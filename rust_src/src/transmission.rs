@@ -0,0 +1,138 @@
+use std::fmt::Write as _;
+
+use crate::sim::InfectionEvent;
+use boid_math::ensemble::quantile;
+
+/// One infector -> infectee link of the transmission tree.
+#[derive(Clone, Copy, Debug)]
+pub struct TransmissionPair {
+    pub infector: usize,
+    pub infectee: usize,
+    /// Infection-to-infection delay.
+    pub generation_interval: f32,
+    /// Onset-to-onset delay.
+    pub serial_interval: f32,
+}
+
+/// All attributed transmissions in `log` (seeded cases have no infector and
+/// contribute none).
+pub fn transmission_pairs(log: &[InfectionEvent]) -> Vec<TransmissionPair> {
+    log.iter()
+        .enumerate()
+        .filter_map(|(infectee, event)| {
+            let infector = event.infector?;
+            let source = &log[infector];
+            Some(TransmissionPair {
+                infector,
+                infectee,
+                generation_interval: event.time - source.time,
+                serial_interval: event.onset - source.onset,
+            })
+        })
+        .collect()
+}
+
+pub fn generation_intervals(log: &[InfectionEvent]) -> Vec<f32> {
+    transmission_pairs(log)
+        .iter()
+        .map(|p| p.generation_interval)
+        .collect()
+}
+
+pub fn serial_intervals(log: &[InfectionEvent]) -> Vec<f32> {
+    transmission_pairs(log)
+        .iter()
+        .map(|p| p.serial_interval)
+        .collect()
+}
+
+/// Mean and quantiles of an interval distribution.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IntervalStats {
+    pub count: usize,
+    pub mean: f32,
+    pub p10: f32,
+    pub median: f32,
+    pub p90: f32,
+}
+
+impl IntervalStats {
+    pub fn from_values(values: &[f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        Self {
+            count: sorted.len(),
+            mean: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            p10: quantile(&sorted, 0.1),
+            median: quantile(&sorted, 0.5),
+            p90: quantile(&sorted, 0.9),
+        }
+    }
+}
+
+/// `infector_event,infectee_event,infection_time,generation_interval,serial_interval`
+/// CSV, one row per attributed transmission. Events are indices into the
+/// infection log.
+pub fn intervals_csv(log: &[InfectionEvent]) -> String {
+    let mut out = String::from(
+        "infector_event,infectee_event,infection_time,generation_interval,serial_interval\n",
+    );
+    for p in transmission_pairs(log) {
+        let _ = writeln!(
+            out,
+            "{},{},{:.4},{:.4},{:.4}",
+            p.infector, p.infectee, log[p.infectee].time, p.generation_interval, p.serial_interval
+        );
+    }
+    out
+}
+
+/// Mean number of cases infected directly by the seeded cases. The seeds are
+/// the first generation of the tree and spread into an almost fully
+/// susceptible population, so this is a direct estimate of R0. `None` when
+/// the log has no seeded cases.
+pub fn seed_offspring_mean(log: &[InfectionEvent]) -> Option<f32> {
+    let seeds = log.iter().filter(|e| e.infector.is_none()).count();
+    if seeds == 0 {
+        return None;
+    }
+    let offspring = log
+        .iter()
+        .filter_map(|e| e.infector)
+        .filter(|&i| log[i].infector.is_none())
+        .count();
+    Some(offspring as f32 / seeds as f32)
+}
+
+/// Log indices from `event` back to its index case: `event` first, the
+/// seeded case last.
+pub fn ancestry(log: &[InfectionEvent], event: usize) -> Vec<usize> {
+    let mut chain = vec![event];
+    while let Some(parent) = log.get(*chain.last().unwrap()).and_then(|e| e.infector) {
+        chain.push(parent);
+    }
+    chain
+}
+
+/// Every infection descended from `event`, excluding it, as
+/// `(infector, infectee)` log-index links in breadth-first order.
+pub fn descendants(log: &[InfectionEvent], event: usize) -> Vec<(usize, usize)> {
+    let mut children = vec![Vec::new(); log.len()];
+    for (k, e) in log.iter().enumerate() {
+        if let Some(parent) = e.infector {
+            children[parent].push(k);
+        }
+    }
+    let mut links = Vec::new();
+    let mut queue = std::collections::VecDeque::from([event]);
+    while let Some(parent) = queue.pop_front() {
+        for &child in children.get(parent).into_iter().flatten() {
+            links.push((parent, child));
+            queue.push_back(child);
+        }
+    }
+    links
+}
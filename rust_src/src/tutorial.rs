@@ -0,0 +1,207 @@
+use crate::challenge;
+use crate::immunization::Immunization;
+use crate::intervention::Intervention;
+use crate::sensitivity::Param;
+use crate::sim::{SimConfig, Simulation, Vec2f};
+
+/// A caption shown from `at` seconds into its stage, optionally changing the
+/// running simulation at the same moment.
+#[derive(Clone, Copy, Debug)]
+pub struct Cue {
+    pub at: f32,
+    pub caption: &'static str,
+    pub intervention: Option<Intervention>,
+}
+
+/// One scenario of the walkthrough. Each stage starts a fresh simulation
+/// from `config`.
+#[derive(Clone, Debug)]
+pub struct Stage {
+    pub title: &'static str,
+    pub config: SimConfig,
+    pub duration: f32,
+    /// Ascending by `at`.
+    pub cues: Vec<Cue>,
+}
+
+const fn say(at: f32, caption: &'static str) -> Cue {
+    Cue {
+        at,
+        caption,
+        intervention: None,
+    }
+}
+
+/// The built-in walkthrough: pure flocking, then infection, then distancing
+/// and vaccination against the same outbreak.
+pub fn stages() -> Vec<Stage> {
+    let outbreak = SimConfig {
+        infection_beta: 0.3,
+        infection_radius: 12.0,
+        ..SimConfig::default()
+    };
+    vec![
+        Stage {
+            title: "Flocking",
+            config: SimConfig {
+                infection_beta: 0.0,
+                initial_infected: 0,
+                ..SimConfig::default()
+            },
+            duration: 21.0,
+            cues: vec![
+                say(
+                    0.0,
+                    "Each boid steers by three local rules and nothing else.",
+                ),
+                say(7.0, "Separation keeps it apart from close neighbours."),
+                say(
+                    14.0,
+                    "Alignment and cohesion match their heading and pull it in.",
+                ),
+            ],
+        },
+        Stage {
+            title: "Infection",
+            config: outbreak,
+            duration: 36.0,
+            cues: vec![
+                say(
+                    0.0,
+                    "Red boids are infected and spread it to anyone in range.",
+                ),
+                say(
+                    12.0,
+                    "Flocks keep boids close, so infection races through them.",
+                ),
+                say(24.0, "Green boids have recovered and are immune."),
+            ],
+        },
+        Stage {
+            title: "Distancing",
+            config: outbreak,
+            duration: 36.0,
+            cues: vec![
+                say(0.0, "The same outbreak again, this time with distancing."),
+                Cue {
+                    at: 4.0,
+                    caption: "Boids now keep twice the separation from each other.",
+                    intervention: Some(Intervention::Set(Param::SeparationRadius, 44.0)),
+                },
+                say(20.0, "Fewer close contacts: the infected curve flattens."),
+            ],
+        },
+        Stage {
+            title: "Vaccination",
+            config: outbreak,
+            duration: 36.0,
+            cues: vec![
+                say(0.0, "Once more, without distancing."),
+                Cue {
+                    at: 4.0,
+                    caption: "Vaccinate 40% of the susceptible boids (they turn green).",
+                    intervention: Some(Intervention::Immunize(Immunization::Random(0.4))),
+                },
+                say(
+                    20.0,
+                    "Immune boids break chains of transmission for everyone.",
+                ),
+            ],
+        },
+    ]
+}
+
+/// Steps through `stages()`, firing cues as time passes and restarting the
+/// simulation at each stage boundary. Stays on the last stage once done.
+pub struct Tutorial {
+    stages: Vec<Stage>,
+    stage: usize,
+    time: f32,
+    next_cue: usize,
+    caption: &'static str,
+    world_size: Vec2f,
+    seed: u64,
+}
+
+impl Tutorial {
+    /// The walkthrough and the simulation for its first stage.
+    pub fn start(world_size: Vec2f, seed: u64) -> (Self, Simulation) {
+        let mut tutorial = Self {
+            stages: stages(),
+            stage: 0,
+            time: 0.0,
+            next_cue: 0,
+            caption: "",
+            world_size,
+            seed,
+        };
+        let mut sim = tutorial.stage_simulation();
+        tutorial.fire_cues(&mut sim);
+        (tutorial, sim)
+    }
+
+    fn stage_simulation(&self) -> Simulation {
+        let cfg = SimConfig {
+            world_size: self.world_size,
+            ..self.stages[self.stage].config
+        };
+        Simulation::new(2400, cfg, self.seed)
+    }
+
+    fn fire_cues(&mut self, sim: &mut Simulation) {
+        let cues = &self.stages[self.stage].cues;
+        while let Some(cue) = cues.get(self.next_cue).filter(|c| c.at <= self.time) {
+            self.caption = cue.caption;
+            if let Some(intervention) = cue.intervention {
+                challenge::apply(intervention, sim);
+            }
+            self.next_cue += 1;
+        }
+    }
+
+    /// Advance by `dt` after `sim` has stepped. Returns true when a new
+    /// stage replaced `sim`, so per-run state built on it must be reset.
+    pub fn update(&mut self, sim: &mut Simulation, dt: f32) -> bool {
+        self.time += dt;
+        if self.time >= self.stages[self.stage].duration && !self.is_last_stage() {
+            self.skip(sim);
+            return true;
+        }
+        self.fire_cues(sim);
+        false
+    }
+
+    /// Jump to the next stage, if any, replacing `sim`.
+    pub fn skip(&mut self, sim: &mut Simulation) {
+        if self.is_last_stage() {
+            return;
+        }
+        self.stage += 1;
+        self.time = 0.0;
+        self.next_cue = 0;
+        *sim = self.stage_simulation();
+        self.fire_cues(sim);
+    }
+
+    fn is_last_stage(&self) -> bool {
+        self.stage + 1 == self.stages.len()
+    }
+
+    /// Whether the last stage has run its course.
+    pub fn finished(&self) -> bool {
+        self.is_last_stage() && self.time >= self.stages[self.stage].duration
+    }
+
+    pub fn caption(&self) -> &'static str {
+        self.caption
+    }
+
+    /// 1-based stage number, the number of stages and the stage title.
+    pub fn progress(&self) -> (usize, usize, &'static str) {
+        (
+            self.stage + 1,
+            self.stages.len(),
+            self.stages[self.stage].title,
+        )
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+
+use crate::sim::{InfectionEvent, Vec2f};
+
+#[derive(Clone, Debug)]
+pub enum VenueShape {
+    Circle {
+        center: Vec2f,
+        radius: f32,
+    },
+    /// Vertices in order; the last connects back to the first.
+    Polygon(Vec<Vec2f>),
+}
+
+impl VenueShape {
+    pub fn contains(&self, p: Vec2f) -> bool {
+        match self {
+            VenueShape::Circle { center, radius } => p.sub(*center).length() <= *radius,
+            VenueShape::Polygon(vertices) => {
+                // Even-odd ray casting towards +x.
+                let mut inside = false;
+                let n = vertices.len();
+                for k in 0..n {
+                    let (a, b) = (vertices[k], vertices[(k + n - 1) % n]);
+                    if (a.y > p.y) != (b.y > p.y)
+                        && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+}
+
+/// A region where transmission runs at `multiplier` times the base rate
+/// between two boids that are both inside it, e.g. a crowded indoor market
+/// (above 1) or a park (below 1).
+#[derive(Clone, Debug)]
+pub struct Venue {
+    pub name: String,
+    pub shape: VenueShape,
+    pub multiplier: f32,
+}
+
+impl Venue {
+    /// `name:circle:x,y,r:multiplier` or
+    /// `name:polygon:x1,y1;x2,y2;x3,y3[;...]:multiplier`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let [name, kind, geometry, multiplier] = parts[..] else {
+            return Err(format!(
+                "expected name:shape:geometry:multiplier, got {spec}"
+            ));
+        };
+        let num = |s: &str| {
+            s.trim()
+                .parse::<f32>()
+                .map_err(|_| format!("invalid number: {s}"))
+        };
+        let point = |s: &str| -> Result<Vec2f, String> {
+            let (x, y) = s
+                .split_once(',')
+                .ok_or_else(|| format!("expected x,y, got {s}"))?;
+            Ok(Vec2f::new(num(x)?, num(y)?))
+        };
+        let shape = match kind {
+            "circle" => {
+                let values = geometry
+                    .split(',')
+                    .map(num)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let [x, y, radius] = values[..] else {
+                    return Err(format!("expected x,y,r, got {geometry}"));
+                };
+                VenueShape::Circle {
+                    center: Vec2f::new(x, y),
+                    radius,
+                }
+            }
+            "polygon" => {
+                let vertices = geometry
+                    .split(';')
+                    .map(point)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if vertices.len() < 3 {
+                    return Err(format!(
+                        "a polygon needs at least 3 vertices, got {geometry}"
+                    ));
+                }
+                VenueShape::Polygon(vertices)
+            }
+            other => {
+                return Err(format!(
+                    "unknown venue shape: {other} (expected circle or polygon)"
+                ));
+            }
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            shape,
+            multiplier: num(multiplier)?.max(0.0),
+        })
+    }
+}
+
+/// The first of `venues` containing both `a` and `b`.
+pub fn shared_venue(venues: &[Venue], a: Vec2f, b: Vec2f) -> Option<usize> {
+    venues
+        .iter()
+        .position(|v| v.shape.contains(a) && v.shape.contains(b))
+}
+
+/// Transmissions attributed to each venue, plus those outside any venue.
+#[derive(Clone, Debug, Default)]
+pub struct VenueIncidence {
+    pub names: Vec<String>,
+    pub counts: Vec<usize>,
+    pub outside: usize,
+}
+
+impl VenueIncidence {
+    /// Count the transmitted (non-seeded) cases in `log` by venue.
+    pub fn from_log(venues: &[Venue], log: &[InfectionEvent]) -> Self {
+        let mut incidence = Self {
+            names: venues.iter().map(|v| v.name.clone()).collect(),
+            counts: vec![0; venues.len()],
+            outside: 0,
+        };
+        for event in log.iter().filter(|e| e.infector.is_some()) {
+            match event.venue {
+                Some(k) => incidence.counts[k] += 1,
+                None => incidence.outside += 1,
+            }
+        }
+        incidence
+    }
+}
+
+impl fmt::Display for VenueIncidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = (self.counts.iter().sum::<usize>() + self.outside).max(1) as f32;
+        for (name, &count) in self.names.iter().zip(&self.counts) {
+            let label = format!("venue {name}");
+            writeln!(
+                f,
+                "{label:<18} {count:>6} infections ({:.1}%)",
+                count as f32 / total * 100.0
+            )?;
+        }
+        write!(
+            f,
+            "{:<18} {:>6} infections ({:.1}%)",
+            "outside venues",
+            self.outside,
+            self.outside as f32 / total * 100.0
+        )
+    }
+}
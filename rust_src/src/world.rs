@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{Boundaries, Boundary, Vec2f};
+
+/// Distance from an obstacle over which boids start turning away.
+const MARGIN: f32 = 30.0;
+/// Turn-away at the surface, as a multiple of `max_force`.
+const STRENGTH: f32 = 4.0;
+/// How far outside the surface `Obstacle::collide` puts a boid.
+const SKIN: f32 = 0.01;
+/// Interior walls, as a fraction of the shorter world side. At the default
+/// window size this is thicker than the default infection radius, so rooms
+/// only share infections through their doorways.
+const WALL: f32 = 0.03;
+/// Width of doorways and of the corridor's bottleneck, same units.
+const DOOR: f32 = 0.08;
+
+/// Solid geometry boids cannot enter. Obstacles do not wrap around the
+/// world edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Obstacle {
+    /// An axis-aligned box.
+    Rect { min: Vec2f, max: Vec2f },
+    /// A disc.
+    Circle { center: Vec2f, radius: f32 },
+    /// Everything farther than `radius` from `center`: a round arena.
+    Outside { center: Vec2f, radius: f32 },
+}
+
+impl Obstacle {
+    /// Signed distance from `pos` to the surface, negative inside, and the
+    /// outward unit normal there.
+    fn surface(&self, pos: Vec2f) -> (f32, Vec2f) {
+        match *self {
+            Obstacle::Rect { min, max } => {
+                let center = min.add(max).mul(0.5);
+                let half = max.sub(min).mul(0.5);
+                let u = pos.sub(center);
+                let q = Vec2f::new(u.x.abs() - half.x, u.y.abs() - half.y);
+                let sign = Vec2f::new(u.x.signum(), u.y.signum());
+                if q.x > 0.0 || q.y > 0.0 {
+                    let out = Vec2f::new(q.x.max(0.0) * sign.x, q.y.max(0.0) * sign.y);
+                    let dist = out.length();
+                    (dist, out.div(dist))
+                } else if q.x > q.y {
+                    (q.x, Vec2f::new(sign.x, 0.0))
+                } else {
+                    (q.y, Vec2f::new(0.0, sign.y))
+                }
+            }
+            Obstacle::Circle { center, radius } => {
+                let d = pos.sub(center);
+                (d.length() - radius, d.normalize_or_zero())
+            }
+            Obstacle::Outside { center, radius } => {
+                let d = pos.sub(center);
+                (radius - d.length(), d.normalize_or_zero().mul(-1.0))
+            }
+        }
+    }
+
+    pub fn contains(&self, pos: Vec2f) -> bool {
+        self.surface(pos).0 < 0.0
+    }
+
+    /// Acceleration turning a boid at `pos` away, given the simulation's
+    /// `max_force`: strongest at the surface, fading to zero `MARGIN` out.
+    pub fn repel(&self, pos: Vec2f, max_force: f32) -> Vec2f {
+        let (dist, normal) = self.surface(pos);
+        if dist >= MARGIN {
+            return Vec2f::default();
+        }
+        let depth = (1.0 - dist.max(0.0) / MARGIN).min(1.0);
+        normal.mul(depth * STRENGTH * max_force)
+    }
+
+    /// Move a boid that ended up inside back onto the surface, reflecting
+    /// the part of its velocity heading further in.
+    pub fn collide(&self, pos: &mut Vec2f, vel: &mut Vec2f) {
+        let (dist, normal) = self.surface(*pos);
+        if dist >= 0.0 {
+            return;
+        }
+        // Just past the surface, so round-off doesn't leave it inside.
+        *pos = pos.sub(normal.mul(dist - SKIN));
+        let into = vel.x * normal.x + vel.y * normal.y;
+        if into < 0.0 {
+            *vel = vel.sub(normal.mul(2.0 * into));
+        }
+    }
+}
+
+/// Built-in world geometry: edge behaviour, obstacles and where boids are
+/// first placed, laid out relative to the world size so every preset works
+/// at any window or world size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorldPreset {
+    /// No obstacles, wrapping edges.
+    #[default]
+    #[serde(rename = "open")]
+    OpenField,
+    /// Four rooms joined by doorways in the walls between them.
+    #[serde(rename = "rooms")]
+    FourRooms,
+    /// A thick wall across the middle with one narrow gap; every boid
+    /// starts on the left.
+    #[serde(rename = "corridor")]
+    Corridor,
+    /// A round track around a central disc.
+    #[serde(rename = "ring")]
+    RingWorld,
+}
+
+impl WorldPreset {
+    pub const ALL: [WorldPreset; 4] = [
+        WorldPreset::OpenField,
+        WorldPreset::FourRooms,
+        WorldPreset::Corridor,
+        WorldPreset::RingWorld,
+    ];
+
+    /// Parse `open`, `rooms`, `corridor` or `ring`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        match spec {
+            "open" => Ok(WorldPreset::OpenField),
+            "rooms" => Ok(WorldPreset::FourRooms),
+            "corridor" => Ok(WorldPreset::Corridor),
+            "ring" => Ok(WorldPreset::RingWorld),
+            _ => Err(format!("unknown world: {spec}")),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WorldPreset::OpenField => "open field",
+            WorldPreset::FourRooms => "four rooms",
+            WorldPreset::Corridor => "corridor with bottleneck",
+            WorldPreset::RingWorld => "ring world",
+        }
+    }
+
+    /// The edge behaviour the preset is designed around. Only the open
+    /// field wraps; walled worlds keep boids in.
+    pub fn boundaries(self) -> Boundaries {
+        match self {
+            WorldPreset::OpenField => Boundaries::uniform(Boundary::Wrap),
+            _ => Boundaries::uniform(Boundary::Reflect),
+        }
+    }
+
+    /// The obstacles of the preset in a world of `size`.
+    pub fn obstacles(self, size: Vec2f) -> Vec<Obstacle> {
+        let unit = size.x.min(size.y);
+        let (wall, door) = (WALL * unit, DOOR * unit);
+        let rect = |x0: f32, y0: f32, x1: f32, y1: f32| Obstacle::Rect {
+            min: Vec2f::new(x0, y0),
+            max: Vec2f::new(x1, y1),
+        };
+        let center = size.mul(0.5);
+        match self {
+            WorldPreset::OpenField => Vec::new(),
+            WorldPreset::FourRooms => {
+                // One wall down and one across the middle, each with a door
+                // into every room it separates.
+                let mut walls = Vec::new();
+                let (x0, x1) = (center.x - wall / 2.0, center.x + wall / 2.0);
+                let (y0, y1) = (center.y - wall / 2.0, center.y + wall / 2.0);
+                let stops = |extent: f32| {
+                    let (a, b) = (extent / 4.0, extent * 3.0 / 4.0);
+                    [
+                        (0.0, a - door / 2.0),
+                        (a + door / 2.0, b - door / 2.0),
+                        (b + door / 2.0, extent),
+                    ]
+                };
+                for (from, to) in stops(size.y) {
+                    walls.push(rect(x0, from, x1, to));
+                }
+                for (from, to) in stops(size.x) {
+                    walls.push(rect(from, y0, to, y1));
+                }
+                walls
+            }
+            WorldPreset::Corridor => {
+                let thick = size.x * 0.15;
+                let (x0, x1) = (center.x - thick / 2.0, center.x + thick / 2.0);
+                vec![
+                    rect(x0, 0.0, x1, center.y - door / 2.0),
+                    rect(x0, center.y + door / 2.0, x1, size.y),
+                ]
+            }
+            WorldPreset::RingWorld => vec![
+                Obstacle::Circle {
+                    center,
+                    radius: unit * 0.18,
+                },
+                Obstacle::Outside {
+                    center,
+                    radius: unit * 0.48,
+                },
+            ],
+        }
+    }
+
+    /// Corners of the box boids are first placed in, in a world of `size`.
+    /// Positions inside an obstacle are drawn again.
+    pub fn spawn_area(self, size: Vec2f) -> (Vec2f, Vec2f) {
+        match self {
+            WorldPreset::Corridor => (Vec2f::default(), Vec2f::new(size.x * 0.3, size.y)),
+            _ => (Vec2f::default(), size),
+        }
+    }
+}